@@ -0,0 +1,96 @@
+// SPDX-License-Identifier: GPL-3.0-only
+
+//! Key bindings for actions only the compositor knows about.
+//!
+//! These complement the shared shortcuts of `com.system76.CosmicSettings.Shortcuts`.
+//! Shared shortcuts are matched first, so they win if both bind the same keys.
+
+use serde::{Deserialize, Serialize};
+
+/// A key binding for an action only the compositor knows about
+#[derive(Debug, Clone, PartialEq, Eq, Serialize, Deserialize)]
+pub struct Binding {
+    #[serde(default)]
+    pub modifiers: Modifiers,
+    /// Name of the keysym, e.g. `"Return"` or `"j"`
+    pub key: String,
+    pub action: CompAction,
+}
+
+#[derive(Debug, Clone, Copy, Default, PartialEq, Eq, Serialize, Deserialize)]
+#[serde(default)]
+pub struct Modifiers {
+    pub ctrl: bool,
+    pub alt: bool,
+    pub shift: bool,
+    pub logo: bool,
+}
+
+/// Actions provided by the compositor in addition to the shared shortcut actions
+#[derive(Debug, Clone, PartialEq, Eq, Hash, Serialize, Deserialize)]
+pub enum CompAction {
+    /// Toggle the master-stack layout on the active workspace
+    ToggleMasterStack,
+    /// Toggle the scrollable columns layout on the active workspace
+    ToggleColumns,
+    /// Move the focused window into the master area
+    Promote,
+    /// Move the focused window out of the master area
+    Demote,
+    /// Rotate windows forward through the master and stack areas
+    RotateNext,
+    /// Rotate windows backward through the master and stack areas
+    RotatePrev,
+    IncreaseMasterCount,
+    DecreaseMasterCount,
+    GrowMaster,
+    ShrinkMaster,
+    /// Rotate the focused tiling subtree by 90 degrees
+    RotateTreeClockwise,
+    RotateTreeCounterClockwise,
+    /// Mirror the focused tiling subtree, swapping left and right
+    MirrorTreeHorizontally,
+    /// Mirror the focused tiling subtree, swapping top and bottom
+    MirrorTreeVertically,
+    /// Give all windows of the focused tiling group the same size
+    EqualizeSizes,
+    /// Swap the focused window with its neighbor, without changing the tiling tree
+    SwapWithNeighbor(Direction),
+    /// Apply the named layout template to the active workspace.
+    ///
+    /// Existing windows fill the slots of the template in order. With `keep_slots`,
+    /// unfilled slots are kept as placeholders, which new windows fill in order.
+    /// Turns off the master-stack or columns layout of the workspace.
+    ApplyTemplate {
+        name: String,
+        keep_slots: bool,
+    },
+    /// Hide the focused window in the named scratchpad
+    SendToScratchpad(String),
+    /// Show the window of the named scratchpad on the focused output, or hide it again
+    ToggleScratchpad(String),
+    /// Keep the focused floating window above the other floating windows, or stop doing so
+    ToggleKeepAbove,
+    /// Keep the focused floating window below the other floating windows, or stop doing so
+    ToggleKeepBelow,
+    /// Make the focused window more opaque
+    IncreaseOpacity,
+    /// Make the focused window more translucent
+    DecreaseOpacity,
+    /// Make the focused window fully opaque again
+    ResetOpacity,
+    /// Toggle grouping new windows into stacks with windows of the same application,
+    /// on the active workspace
+    ToggleAutoStack,
+    /// Enable or disable the night light color temperature filter
+    ToggleNightLight,
+}
+
+/// Serialized like the direction of the shared shortcut actions
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Hash, Serialize, Deserialize)]
+pub enum Direction {
+    Left,
+    Right,
+    Up,
+    Down,
+}
@@ -6,9 +6,14 @@ use std::collections::HashMap;
 
 use crate::input::TouchpadOverride;
 
+pub mod bindings;
 pub mod input;
+pub mod night_light;
 #[cfg(feature = "output")]
 pub mod output;
+pub mod snap_zones;
+pub mod templates;
+pub mod window_rules;
 pub mod workspace;
 
 #[derive(Debug, Deserialize, Serialize, Copy, Clone, PartialEq, Eq, PartialOrd, Ord, Hash)]
@@ -66,6 +71,71 @@ impl Default for AppearanceConfig {
     }
 }
 
+/// Where newly mapped floating windows are placed
+#[derive(Debug, Default, Clone, Copy, PartialEq, Eq, Hash, Serialize, Deserialize)]
+pub enum FloatingPlacement {
+    /// Cascade from the last placed window
+    #[default]
+    Cascade,
+    /// Center of the output
+    Centered,
+    /// Centered under the cursor
+    UnderCursor,
+    /// Position overlapping the least with existing windows
+    Smart,
+}
+
+#[derive(Debug, Default, Clone, Copy, PartialEq, Eq, Hash, Serialize, Deserialize)]
+pub struct FloatingPlacementConfig {
+    pub strategy: FloatingPlacement,
+    /// Center dialogs on their parent window instead of using `strategy`
+    pub center_dialogs_on_parent: bool,
+}
+
+#[derive(Debug, Clone, Copy, PartialEq, Serialize, Deserialize)]
+#[serde(default)]
+pub struct MasterStackConfig {
+    /// Initial number of windows in the master area
+    pub master_count: usize,
+    /// Initial share of the output width taken by the master area
+    pub master_ratio: f32,
+}
+
+impl Default for MasterStackConfig {
+    fn default() -> Self {
+        Self {
+            master_count: 1,
+            master_ratio: 0.55,
+        }
+    }
+}
+
+#[derive(Debug, Clone, Copy, PartialEq, Serialize, Deserialize)]
+#[serde(default)]
+pub struct ColumnsConfig {
+    /// Width of new columns, as share of the output width
+    pub column_width: f32,
+}
+
+impl Default for ColumnsConfig {
+    fn default() -> Self {
+        Self { column_width: 0.5 }
+    }
+}
+
+/// What happens to tiled windows, which don't fit their minimum size even after
+/// taking space from their neighbors
+#[derive(Debug, Default, Clone, Copy, PartialEq, Eq, Hash, Serialize, Deserialize)]
+pub enum TilingOverflow {
+    /// Keep the window tiled, cut off and marked by an indicator
+    #[default]
+    Clip,
+    /// Float the window
+    Float,
+    /// Stack the window with a neighboring window, or float it if there is none
+    Stack,
+}
+
 #[derive(Clone, Debug, PartialEq, CosmicConfigEntry)]
 #[version = 1]
 pub struct CosmicCompConfig {
@@ -99,6 +169,26 @@ pub struct CosmicCompConfig {
     pub edge_snap_threshold: u32,
     pub accessibility_zoom: ZoomConfig,
     pub appearance_settings: AppearanceConfig,
+    /// Rules applied to newly mapped windows
+    pub window_rules: Vec<window_rules::WindowRule>,
+    /// Key bindings for compositor actions, shared shortcuts take precedence over them
+    pub bindings: Vec<bindings::Binding>,
+    /// Defaults for the master-stack layout
+    pub master_stack: MasterStackConfig,
+    /// Defaults for the scrollable columns layout
+    pub columns: ColumnsConfig,
+    /// Named tiling layouts, which can be applied to workspaces
+    pub layout_templates: HashMap<String, templates::TemplateNode>,
+    /// Placement of new floating windows
+    pub floating_placement: FloatingPlacementConfig,
+    /// User-defined snap zones for floating windows
+    pub snap_zones: snap_zones::SnapZonesConfig,
+    /// Modifiers to hold while scrolling over a window to change its opacity, disabled if unset
+    pub opacity_scroll_modifiers: Option<bindings::Modifiers>,
+    /// Handling of tiled windows too large for their tile
+    pub tiling_overflow: TilingOverflow,
+    /// Color temperature filter following a schedule
+    pub night_light: night_light::NightLightConfig,
 }
 
 impl Default for CosmicCompConfig {
@@ -135,6 +225,16 @@ impl Default for CosmicCompConfig {
             edge_snap_threshold: 0,
             accessibility_zoom: ZoomConfig::default(),
             appearance_settings: AppearanceConfig::default(),
+            window_rules: Vec::new(),
+            bindings: Vec::new(),
+            master_stack: Default::default(),
+            columns: Default::default(),
+            layout_templates: HashMap::new(),
+            floating_placement: Default::default(),
+            snap_zones: Default::default(),
+            opacity_scroll_modifiers: None,
+            tiling_overflow: TilingOverflow::default(),
+            night_light: Default::default(),
         }
     }
 }
@@ -0,0 +1,45 @@
+// SPDX-License-Identifier: GPL-3.0-only
+
+use serde::{Deserialize, Serialize};
+
+/// Color temperature filter reducing blue light, applied to all outputs
+#[derive(Debug, Clone, Copy, PartialEq, Serialize, Deserialize)]
+#[serde(default)]
+pub struct NightLightConfig {
+    pub enabled: bool,
+    /// Color temperature in Kelvin while night light is fully active
+    pub temperature: u32,
+    pub schedule: NightLightSchedule,
+    /// Duration of the transition between day and night in minutes
+    pub transition_duration: u32,
+}
+
+impl Default for NightLightConfig {
+    fn default() -> Self {
+        NightLightConfig {
+            enabled: false,
+            temperature: 4000,
+            schedule: NightLightSchedule::default(),
+            transition_duration: 30,
+        }
+    }
+}
+
+#[derive(Debug, Clone, Copy, PartialEq, Serialize, Deserialize)]
+pub enum NightLightSchedule {
+    /// Active whenever night light is enabled
+    Always,
+    /// Active between two times of the day, given as hour and minute in local time
+    Fixed { start: (u8, u8), end: (u8, u8) },
+    /// Active between sunset and sunrise at a location, given in degrees
+    SunsetToSunrise { latitude: f64, longitude: f64 },
+}
+
+impl Default for NightLightSchedule {
+    fn default() -> Self {
+        NightLightSchedule::Fixed {
+            start: (20, 0),
+            end: (6, 0),
+        }
+    }
+}
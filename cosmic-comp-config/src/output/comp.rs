@@ -1,7 +1,11 @@
 // SPDX-License-Identifier: GPL-3.0-only
 
 use serde::{Deserialize, Serialize};
-use std::{collections::HashMap, fs::OpenOptions, path::Path};
+use std::{
+    collections::HashMap,
+    fs::OpenOptions,
+    path::{Path, PathBuf},
+};
 use tracing::{error, warn};
 
 #[derive(Debug, Deserialize, Serialize, Clone, PartialEq)]
@@ -32,6 +36,19 @@ fn default_sync() -> AdaptiveSync {
     AdaptiveSync::Enabled
 }
 
+#[derive(Debug, Default, Deserialize, Serialize, Clone, Copy, PartialEq, Eq)]
+#[serde(rename_all = "lowercase")]
+pub enum ColorSpace {
+    /// Regular SDR output, sRGB primaries and transfer function
+    #[default]
+    Srgb,
+    /// HDR output, BT.2020 primaries with the SMPTE ST 2084 (PQ) transfer function
+    Bt2020Pq,
+}
+
+/// Luminance of SDR content on HDR outputs as recommended by ITU-R BT.2408
+pub const DEFAULT_SDR_WHITE_LEVEL: u32 = 203;
+
 #[derive(Debug, Clone, Deserialize, Serialize)]
 pub struct OutputsConfig {
     pub config: HashMap<Vec<OutputInfo>, Vec<OutputConfig>>,
@@ -51,6 +68,14 @@ pub struct OutputConfig {
     pub max_bpc: Option<u32>,
     #[serde(default)]
     pub xwayland_primary: bool,
+    #[serde(default)]
+    pub color_space: ColorSpace,
+    /// Luminance in nits SDR white is mapped to, if `color_space` is HDR
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub sdr_white_level: Option<u32>,
+    /// ICC profile used to calibrate the output
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub icc_profile: Option<PathBuf>,
 }
 
 impl Default for OutputConfig {
@@ -64,6 +89,9 @@ impl Default for OutputConfig {
             enabled: OutputState::Enabled,
             max_bpc: None,
             xwayland_primary: false,
+            color_space: ColorSpace::Srgb,
+            sdr_white_level: None,
+            icc_profile: None,
         }
     }
 }
@@ -0,0 +1,68 @@
+// SPDX-License-Identifier: GPL-3.0-only
+
+use std::collections::HashMap;
+
+use serde::{Deserialize, Serialize};
+
+use crate::bindings::Modifiers;
+
+/// A zone floating windows can be snapped into, in fractions of the usable area of an output
+#[derive(Debug, Clone, Copy, PartialEq, Serialize, Deserialize)]
+pub struct SnapZone {
+    pub x: f32,
+    pub y: f32,
+    pub width: f32,
+    pub height: f32,
+}
+
+/// User-defined snap zones, shown while moving a floating window with `modifiers` held.
+///
+/// For example thirds with a wider center column, and a grid on one output:
+///
+/// ```ron
+/// (
+///     modifiers: (shift: true),
+///     default: [
+///         (x: 0.0, y: 0.0, width: 0.25, height: 1.0),
+///         (x: 0.25, y: 0.0, width: 0.5, height: 1.0),
+///         (x: 0.75, y: 0.0, width: 0.25, height: 1.0),
+///     ],
+///     outputs: {
+///         "DP-1": [
+///             (x: 0.0, y: 0.0, width: 0.5, height: 0.5),
+///             (x: 0.5, y: 0.0, width: 0.5, height: 0.5),
+///             (x: 0.0, y: 0.5, width: 1.0, height: 0.5),
+///         ],
+///     },
+/// )
+/// ```
+#[derive(Debug, Clone, PartialEq, Serialize, Deserialize)]
+#[serde(default)]
+pub struct SnapZonesConfig {
+    /// Modifiers to hold while moving a window to use the zones
+    pub modifiers: Modifiers,
+    /// Zones of outputs without their own layout
+    pub default: Vec<SnapZone>,
+    /// Zones by output connector name
+    pub outputs: HashMap<String, Vec<SnapZone>>,
+}
+
+impl Default for SnapZonesConfig {
+    fn default() -> Self {
+        Self {
+            modifiers: Modifiers {
+                shift: true,
+                ..Default::default()
+            },
+            default: Vec::new(),
+            outputs: HashMap::new(),
+        }
+    }
+}
+
+impl SnapZonesConfig {
+    /// Zones of the output with the given connector name
+    pub fn zones(&self, output: &str) -> &[SnapZone] {
+        self.outputs.get(output).unwrap_or(&self.default)
+    }
+}
@@ -0,0 +1,35 @@
+// SPDX-License-Identifier: GPL-3.0-only
+
+use serde::{Deserialize, Serialize};
+
+/// A tiling layout, which can be applied to a workspace.
+///
+/// For example a 60/40 split with a stacked right column:
+///
+/// ```ron
+/// Split(
+///     orientation: Vertical,
+///     children: [
+///         (0.6, Slot),
+///         (0.4, Split(orientation: Horizontal, children: [(1.0, Slot), (1.0, Slot)])),
+///     ],
+/// )
+/// ```
+#[derive(Debug, Clone, PartialEq, Serialize, Deserialize)]
+pub enum TemplateNode {
+    /// Space for a single window
+    Slot,
+    /// Children split along `orientation`, each with its size relative to its siblings
+    Split {
+        orientation: TemplateOrientation,
+        children: Vec<(f32, TemplateNode)>,
+    },
+}
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Serialize, Deserialize)]
+pub enum TemplateOrientation {
+    /// Children are stacked on top of each other
+    Horizontal,
+    /// Children are placed side by side
+    Vertical,
+}
@@ -0,0 +1,92 @@
+// SPDX-License-Identifier: GPL-3.0-only
+
+use serde::{Deserialize, Serialize};
+
+/// A rule applied to windows when they are first mapped.
+///
+/// All conditions of `matches` have to hold for the rule to apply.
+/// If multiple rules match a window, their effects are merged in order,
+/// with later rules overriding earlier ones.
+#[derive(Debug, Clone, Default, PartialEq, Serialize, Deserialize)]
+#[serde(default)]
+pub struct WindowRule {
+    pub matches: WindowMatch,
+    pub apply: WindowEffects,
+}
+
+#[derive(Debug, Clone, Default, PartialEq, Serialize, Deserialize)]
+#[serde(default)]
+pub struct WindowMatch {
+    /// Regex matched against the whole app_id (or X11 class)
+    pub app_id: Option<String>,
+    /// Regex matched against the whole title.
+    /// Matching windows of an application share their remembered floating geometry.
+    pub title: Option<String>,
+    /// Whether the window is an X11 window
+    pub xwayland: Option<bool>,
+    /// Regex matched against the app_id of the parent window.
+    /// An empty regex matches windows without a parent.
+    pub parent_app_id: Option<String>,
+    /// Process id of the client
+    pub pid: Option<u32>,
+}
+
+#[derive(Debug, Clone, Default, PartialEq, Serialize, Deserialize)]
+#[serde(default)]
+pub struct WindowEffects {
+    /// Name of the output to open the window on
+    pub output: Option<String>,
+    /// Index of the workspace to open the window on
+    pub workspace: Option<usize>,
+    /// Open the window floating (`true`) or tiled (`false`)
+    pub floating: Option<bool>,
+    /// Initial geometry of floating windows, relative to the output
+    pub geometry: Option<RuleGeometry>,
+    pub sticky: Option<bool>,
+    pub fullscreen: Option<bool>,
+    pub maximized: Option<bool>,
+    /// Regex matched against the whole app_id of an existing window on the target workspace,
+    /// which the new window is then stacked with
+    pub stack_with: Option<String>,
+    pub opacity: Option<f32>,
+    /// Opacity while the window isn't activated, `opacity` is used if unset
+    pub inactive_opacity: Option<f32>,
+    pub no_decorations: Option<bool>,
+    /// Inhibit idle while the window is visible
+    pub inhibit_idle: Option<bool>,
+}
+
+impl WindowEffects {
+    /// Overrides all effects set in `other`
+    pub fn merge(&mut self, other: &WindowEffects) {
+        macro_rules! merge {
+            ($($field:ident),*) => {
+                $(if other.$field.is_some() {
+                    self.$field = other.$field.clone();
+                })*
+            };
+        }
+        merge!(
+            output,
+            workspace,
+            floating,
+            geometry,
+            sticky,
+            fullscreen,
+            maximized,
+            stack_with,
+            opacity,
+            inactive_opacity,
+            no_decorations,
+            inhibit_idle
+        );
+    }
+}
+
+#[derive(Debug, Clone, Copy, Default, PartialEq, Eq, Serialize, Deserialize)]
+pub struct RuleGeometry {
+    pub x: i32,
+    pub y: i32,
+    pub width: i32,
+    pub height: i32,
+}
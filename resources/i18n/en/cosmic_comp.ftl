@@ -20,6 +20,8 @@ window-menu-stack = Create window stack
 window-menu-unstack-all = Unstack windows
 window-menu-unstack = Unstack window
 window-menu-sticky = Sticky window
+window-menu-keep-above = Keep above others
+window-menu-keep-below = Keep below others
 window-menu-close = Close
 window-menu-close-all = Close all windows
 window-menu-resize-edge-top = Top
@@ -0,0 +1,387 @@
+// SPDX-License-Identifier: GPL-3.0-only
+
+use crate::{
+    backend::render,
+    config::ScreenFilter,
+    state::{BackendData, Common},
+    utils::prelude::*,
+};
+use anyhow::{Context, Result};
+use cosmic_comp_config::output::comp::OutputConfig;
+use smithay::{
+    backend::{
+        allocator::Fourcc,
+        renderer::{
+            Bind, Offscreen,
+            damage::{OutputDamageTracker, RenderOutputResult},
+            gles::GlesRenderbuffer,
+            glow::GlowRenderer,
+        },
+    },
+    output::{Mode, Output, PhysicalProperties, Scale, Subpixel},
+    reexports::{
+        calloop::{
+            EventLoop, LoopHandle,
+            timer::{TimeoutAction, Timer},
+        },
+        wayland_protocols::wp::presentation_time::server::wp_presentation_feedback,
+        wayland_server::DisplayHandle,
+    },
+    utils::{Size, Transform},
+    wayland::presentation::Refresh,
+};
+use std::{borrow::BorrowMut, cell::RefCell, time::Duration};
+use tracing::{error, info, warn};
+
+use super::render::{ScreenFilterStorage, init_shaders};
+
+const DEFAULT_SIZE: (i32, i32) = (1920, 1080);
+const DEFAULT_REFRESH: i32 = 60_000;
+
+#[derive(Debug)]
+pub struct HeadlessState {
+    pub renderer: GlowRenderer,
+    surfaces: Vec<Surface>,
+    loop_handle: LoopHandle<'static, State>,
+}
+
+#[derive(Debug)]
+pub struct Surface {
+    output: Output,
+    damage_tracker: OutputDamageTracker,
+    buffer: Option<GlesRenderbuffer>,
+    dirty: bool,
+    pending: bool,
+    screen_filter_state: ScreenFilterStorage,
+}
+
+impl HeadlessState {
+    pub fn add_output(&mut self, mode: Mode) -> Output {
+        let name = format!("HEADLESS-{}", self.surfaces.len());
+        let props = PhysicalProperties {
+            size: (0, 0).into(),
+            subpixel: Subpixel::Unknown,
+            make: "COSMIC".to_string(),
+            model: name.clone(),
+            serial_number: "Unknown".to_string(),
+        };
+        let output = Output::new(name, props);
+        output.add_mode(mode);
+        output.set_preferred(mode);
+        output.change_current_state(
+            Some(mode),
+            Some(Transform::Normal),
+            Some(Scale::Integer(1)),
+            Some((0, 0).into()),
+        );
+        output.user_data().insert_if_missing(|| {
+            RefCell::new(OutputConfig {
+                mode: ((mode.size.w, mode.size.h), Some(mode.refresh as u32)),
+                ..Default::default()
+            })
+        });
+
+        self.surfaces.push(Surface {
+            damage_tracker: OutputDamageTracker::from_output(&output),
+            output: output.clone(),
+            buffer: None,
+            dirty: true,
+            pending: false,
+            screen_filter_state: ScreenFilterStorage::default(),
+        });
+
+        output
+    }
+
+    pub fn schedule_render(&mut self, output: &Output) {
+        if let Some(surface) = self.surfaces.iter_mut().find(|s| s.output == *output) {
+            surface.dirty = true;
+            if !surface.pending {
+                surface.pending = true;
+                let output = output.clone();
+                self.loop_handle.insert_idle(move |state| {
+                    state
+                        .backend
+                        .headless()
+                        .render_output(&output, &mut state.common);
+                });
+            }
+        }
+    }
+
+    fn render_output(&mut self, output: &Output, state: &mut Common) {
+        let Some(surface) = self.surfaces.iter_mut().find(|s| s.output == *output) else {
+            return;
+        };
+
+        if surface.dirty && output.is_enabled() {
+            if let Err(err) = surface.render_output(&mut self.renderer, state) {
+                error!(?err, "Error rendering.");
+            }
+        }
+        surface.dirty = false;
+
+        // There is no vblank to wait for, so emulate one using the refresh rate of the current mode.
+        let refresh = output
+            .current_mode()
+            .map(|mode| Duration::from_secs_f64(1_000.0 / mode.refresh as f64))
+            .unwrap_or(Duration::from_millis(16));
+        let output = output.clone();
+        if let Err(err) = state.event_loop_handle.insert_source(
+            Timer::from_duration(refresh),
+            move |_, _, state| {
+                let headless_state = state.backend.headless();
+                if let Some(surface) = headless_state
+                    .surfaces
+                    .iter_mut()
+                    .find(|s| s.output == output)
+                {
+                    if surface.dirty {
+                        headless_state.render_output(&output, &mut state.common);
+                    } else {
+                        surface.pending = false;
+                    }
+                }
+                TimeoutAction::Drop
+            },
+        ) {
+            warn!(?err, "Failed to schedule frame timer.");
+            surface.pending = false;
+        }
+    }
+
+    pub fn all_outputs(&self) -> Vec<Output> {
+        self.surfaces.iter().map(|s| s.output.clone()).collect()
+    }
+
+    pub fn apply_config_for_outputs(&mut self, test_only: bool) -> Result<(), anyhow::Error> {
+        if test_only {
+            return Ok(());
+        }
+
+        // Virtual outputs can take any mode, so just make sure it is advertised.
+        for surface in &mut self.surfaces {
+            let Some(mode) = surface.output.current_mode() else {
+                continue;
+            };
+            if !surface.output.modes().contains(&mode) {
+                surface.output.add_mode(mode);
+            }
+            surface.output.set_preferred(mode);
+            // the mode might have changed, so force a new buffer
+            surface.buffer = None;
+            surface.dirty = true;
+        }
+
+        Ok(())
+    }
+
+    pub fn update_screen_filter(&mut self, screen_filter: &ScreenFilter) -> Result<()> {
+        for surface in &mut self.surfaces {
+            surface.screen_filter_state.filter = screen_filter.clone();
+        }
+        Ok(())
+    }
+}
+
+impl Surface {
+    pub fn render_output(&mut self, renderer: &mut GlowRenderer, state: &mut Common) -> Result<()> {
+        let size = self
+            .output
+            .current_mode()
+            .map(|mode| mode.size)
+            .unwrap_or_else(|| Size::from(DEFAULT_SIZE))
+            .to_logical(1)
+            .to_buffer(1, Transform::Normal);
+
+        // We always render into the same buffer, so it is either fresh or contains the last frame.
+        let age = if self.buffer.is_some() { 1 } else { 0 };
+        if self.buffer.is_none() {
+            self.buffer = Some(
+                Offscreen::<GlesRenderbuffer>::create_buffer(renderer, Fourcc::Abgr8888, size)
+                    .with_context(|| "Failed to allocate buffer")?,
+            );
+        }
+        let mut fb = renderer
+            .bind(self.buffer.as_mut().unwrap())
+            .with_context(|| "Failed to bind buffer")?;
+
+        match render::render_output(
+            None,
+            renderer,
+            &mut fb,
+            &mut self.damage_tracker,
+            age,
+            &state.shell,
+            state.clock.now(),
+            &self.output,
+            render::CursorMode::NotDefault,
+            &mut self.screen_filter_state,
+            &state.event_loop_handle,
+        ) {
+            Ok(RenderOutputResult { damage, states, .. }) => {
+                state.send_frames(&self.output, None);
+                state.update_primary_output(&self.output, &states);
+                state.send_dmabuf_feedback(&self.output, &states, |_| None);
+                if damage.is_some() {
+                    let mut output_presentation_feedback = state
+                        .shell
+                        .read()
+                        .take_presentation_feedback(&self.output, &states);
+                    output_presentation_feedback.presented(
+                        state.clock.now(),
+                        self.output
+                            .current_mode()
+                            .map(|mode| {
+                                Refresh::Fixed(Duration::from_secs_f64(
+                                    1_000.0 / mode.refresh as f64,
+                                ))
+                            })
+                            .unwrap_or(Refresh::Unknown),
+                        0,
+                        wp_presentation_feedback::Kind::empty(),
+                    )
+                }
+            }
+            Err(err) => {
+                std::mem::drop(fb);
+                self.buffer = None;
+                anyhow::bail!("Rendering failed: {}", err);
+            }
+        };
+
+        Ok(())
+    }
+}
+
+/// Parses `COSMIC_HEADLESS_MODE`, formatted as `<width>x<height>[@<refresh in Hz>]`
+fn mode_from_env() -> Mode {
+    let default = Mode {
+        size: DEFAULT_SIZE.into(),
+        refresh: DEFAULT_REFRESH,
+    };
+    let Ok(value) = std::env::var("COSMIC_HEADLESS_MODE") else {
+        return default;
+    };
+
+    parse_mode(&value).unwrap_or_else(|| {
+        warn!(
+            "Failed to parse COSMIC_HEADLESS_MODE {:?}, falling back to {}x{}@60",
+            value, default.size.w, default.size.h
+        );
+        default
+    })
+}
+
+fn parse_mode(value: &str) -> Option<Mode> {
+    let (size, refresh) = match value.trim().split_once('@') {
+        Some((size, refresh)) => (size, Some(refresh)),
+        None => (value.trim(), None),
+    };
+    let (w, h) = size.split_once('x')?;
+    let (w, h) = (w.parse::<i32>().ok()?, h.parse::<i32>().ok()?);
+    if w <= 0 || h <= 0 {
+        return None;
+    }
+    let refresh = match refresh {
+        Some(refresh) => (refresh.parse::<f64>().ok()? * 1000.0).round() as i32,
+        None => DEFAULT_REFRESH,
+    };
+    if refresh <= 0 {
+        return None;
+    }
+
+    Some(Mode {
+        size: (w, h).into(),
+        refresh,
+    })
+}
+
+/// The render path depends on the GLES shaders, which the pixman renderer can't run,
+/// so the outputs are rendered with Mesa's software EGL device (llvmpipe) instead.
+pub fn init_backend(
+    _dh: &DisplayHandle,
+    event_loop: &mut EventLoop<'static, State>,
+    state: &mut State,
+) -> Result<()> {
+    let mut renderer = super::kms::software_renderer()
+        .context("Headless backend requires a software EGL device (llvmpipe), none found")?;
+    init_shaders(renderer.borrow_mut()).context("Failed to initialize renderer")?;
+
+    let num_outputs = std::env::var("COSMIC_HEADLESS_OUTPUTS")
+        .ok()
+        .and_then(|val| val.trim().parse::<usize>().ok())
+        .unwrap_or(1)
+        .max(1);
+    let mode = mode_from_env();
+
+    let mut headless_state = HeadlessState {
+        renderer,
+        surfaces: Vec::new(),
+        loop_handle: event_loop.handle(),
+    };
+    let outputs = (0..num_outputs)
+        .map(|_| headless_state.add_output(mode))
+        .collect::<Vec<_>>();
+    state.backend = BackendData::Headless(headless_state);
+
+    info!(
+        "Running headless with {} output(s) at {}x{}",
+        num_outputs, mode.size.w, mode.size.h
+    );
+
+    state
+        .common
+        .output_configuration_state
+        .add_heads(outputs.iter());
+    {
+        for output in &outputs {
+            state.common.add_output(output);
+        }
+        if let Err(err) = state.common.config.read_outputs(
+            &mut state.common.output_configuration_state,
+            &mut state.backend,
+            &state.common.shell,
+            &state.common.event_loop_handle,
+            &mut state.common.workspace_state.update(),
+            &state.common.xdg_activation_state,
+            state.common.startup_done.clone(),
+            &state.common.clock,
+        ) {
+            error!("Unrecoverable output configuration error: {}", err);
+        }
+        state.common.refresh();
+    }
+
+    if state.common.with_xwayland {
+        state.launch_xwayland(None);
+    } else {
+        state.notify_ready();
+    }
+
+    Ok(())
+}
+
+#[cfg(test)]
+mod test {
+    use super::parse_mode;
+
+    #[test]
+    fn test_parse_mode() {
+        let mode = parse_mode("1280x720").unwrap();
+        assert_eq!(
+            (mode.size.w, mode.size.h, mode.refresh),
+            (1280, 720, 60_000)
+        );
+
+        let mode = parse_mode("3840x2160@59.94").unwrap();
+        assert_eq!(
+            (mode.size.w, mode.size.h, mode.refresh),
+            (3840, 2160, 59_940)
+        );
+
+        assert!(parse_mode("1280").is_none());
+        assert!(parse_mode("0x720").is_none());
+        assert!(parse_mode("1280x720@abc").is_none());
+    }
+}
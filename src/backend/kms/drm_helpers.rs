@@ -298,6 +298,101 @@ pub fn set_max_bpc(dev: &impl ControlDevice, conn: connector::Handle, bpc: u32)
     })
 }
 
+pub fn supports_hdr(dev: &impl ControlDevice, conn: connector::Handle) -> bool {
+    get_prop(dev, conn, "Colorspace").is_ok() && get_prop(dev, conn, "HDR_OUTPUT_METADATA").is_ok()
+}
+
+pub fn set_colorspace(
+    dev: &impl ControlDevice,
+    conn: connector::Handle,
+    bt2020: bool,
+) -> Result<()> {
+    let prop = get_prop(dev, conn, "Colorspace")?;
+    let info = dev.get_property(prop)?;
+    let property::ValueType::Enum(values) = info.value_type() else {
+        anyhow::bail!("Colorspace has wrong value type");
+    };
+    let name = if bt2020 { "BT2020_RGB" } else { "Default" };
+    let (_, entries) = values.values();
+    let entry = entries
+        .iter()
+        .find(|entry| entry.name().to_str() == Ok(name))
+        .with_context(|| format!("Colorspace {name} not supported by connector"))?;
+
+    let (_, current) = get_property_val(dev, conn, "Colorspace")?;
+    if current != entry.value() {
+        dev.set_property(conn, prop, entry.value())?;
+    }
+    Ok(())
+}
+
+// struct hdr_output_metadata from the kernel uapi
+#[repr(C)]
+struct HdrOutputMetadata {
+    metadata_type: u32,
+    eotf: u8,
+    infoframe_metadata_type: u8,
+    // chromaticity coordinates in units of 0.00002
+    display_primaries: [[u16; 2]; 3],
+    white_point: [u16; 2],
+    // in units of 1 cd/m²
+    max_display_mastering_luminance: u16,
+    // in units of 0.0001 cd/m²
+    min_display_mastering_luminance: u16,
+    max_cll: u16,
+    max_fall: u16,
+}
+
+const HDMI_STATIC_METADATA_TYPE1: u32 = 0;
+const HDMI_EOTF_SMPTE_ST2084: u8 = 2;
+
+/// Sets the HDR static metadata of the connector for PQ encoded BT.2020 content,
+/// or removes it if `pq` is false.
+pub fn set_hdr_output_metadata(
+    dev: &impl ControlDevice,
+    conn: connector::Handle,
+    pq: bool,
+) -> Result<()> {
+    let prop = get_prop(dev, conn, "HDR_OUTPUT_METADATA")?;
+    if !pq {
+        let (_, current) = get_property_val(dev, conn, "HDR_OUTPUT_METADATA")?;
+        if current != 0 {
+            dev.set_property(conn, prop, 0)?;
+        }
+        return Ok(());
+    }
+
+    let metadata = HdrOutputMetadata {
+        metadata_type: HDMI_STATIC_METADATA_TYPE1,
+        eotf: HDMI_EOTF_SMPTE_ST2084,
+        infoframe_metadata_type: 0,
+        // BT.2020 primaries and D65 white point
+        display_primaries: [[35400, 14600], [8500, 39850], [6550, 2300]],
+        white_point: [15635, 16450],
+        max_display_mastering_luminance: 1000,
+        min_display_mastering_luminance: 50,
+        // unknown
+        max_cll: 0,
+        max_fall: 0,
+    };
+    let property::Value::Blob(blob) = dev.create_property_blob(&metadata)? else {
+        anyhow::bail!("Failed to create HDR metadata blob");
+    };
+    let res = dev.set_property(conn, prop, blob);
+    // the connector keeps its own reference to the blob
+    let _ = dev.destroy_property_blob(blob);
+    res.map_err(Into::into)
+}
+
+/// Number of entries per channel of the gamma ramps of `crtc`
+pub fn gamma_size(dev: &impl ControlDevice, crtc: crtc::Handle) -> Result<usize> {
+    let size = dev.get_crtc(crtc)?.gamma_length() as usize;
+    if size < 2 {
+        anyhow::bail!("Crtc doesn't support gamma ramps");
+    }
+    Ok(size)
+}
+
 pub fn panel_orientation(dev: &impl ControlDevice, conn: connector::Handle) -> Result<Transform> {
     let (val_type, val) = get_property_val(dev, conn, "panel orientation")?;
     match val_type.convert_value(val) {
@@ -0,0 +1,60 @@
+// SPDX-License-Identifier: GPL-3.0-only
+
+use anyhow::{Context, Result};
+use smithay::reexports::drm::control::{Device as ControlDevice, crtc};
+
+use super::drm_helpers;
+use crate::utils::icc::Curve;
+
+/// Gamma ramps of a crtc, combining the calibration curves of an ICC profile
+/// with the ramps set by clients through wlr-gamma-control.
+#[derive(Debug, Default)]
+pub struct GammaState {
+    calibration: Option<[Curve; 3]>,
+    client_ramp: Option<Vec<u16>>,
+    // non-identity ramps have been applied and need to be reset
+    applied: bool,
+}
+
+impl GammaState {
+    pub fn set_calibration(&mut self, curves: Option<[Curve; 3]>) {
+        self.calibration = curves;
+    }
+
+    /// Sets the red, green and blue ramps of a client in sequence, each of the crtcs gamma size.
+    pub fn set_client_ramp(&mut self, ramp: Option<Vec<u16>>) {
+        self.client_ramp = ramp;
+    }
+
+    pub fn apply(&mut self, dev: &impl ControlDevice, crtc: crtc::Handle) -> Result<()> {
+        if self.calibration.is_none() && self.client_ramp.is_none() && !self.applied {
+            return Ok(());
+        }
+
+        let size = drm_helpers::gamma_size(dev, crtc)?;
+        let client_ramp = self
+            .client_ramp
+            .as_ref()
+            .filter(|ramp| ramp.len() == size * 3);
+        let channel = |c: usize| {
+            (0..size)
+                .map(|i| {
+                    let value = match client_ramp {
+                        Some(ramp) => ramp[c * size + i] as f64 / u16::MAX as f64,
+                        None => i as f64 / (size - 1) as f64,
+                    };
+                    let value = match &self.calibration {
+                        Some(curves) => curves[c].eval(value),
+                        None => value,
+                    };
+                    (value * u16::MAX as f64).round() as u16
+                })
+                .collect::<Vec<_>>()
+        };
+
+        dev.set_gamma(crtc, &channel(0), &channel(1), &channel(2))
+            .context("Failed to set gamma ramps")?;
+        self.applied = self.calibration.is_some() || client_ramp.is_some();
+        Ok(())
+    }
+}
@@ -51,14 +51,21 @@ use std::{
 
 mod device;
 mod drm_helpers;
+mod gamma;
 pub mod render;
 mod socket;
 mod surface;
+mod virtual_output;
 use device::*;
 pub(crate) use surface::Surface;
 pub use surface::Timings;
+pub use virtual_output::VirtualOutputs;
 
-use super::render::{CLEAR_COLOR, CursorMode, output_elements};
+use super::render::{
+    CLEAR_COLOR, CursorMode,
+    color::{Calibration, OutputColor},
+    output_elements,
+};
 
 #[derive(Debug)]
 pub struct KmsState {
@@ -73,6 +80,8 @@ pub struct KmsState {
     libinput: Libinput,
 
     pub syncobj_state: Option<DrmSyncobjState>,
+
+    pub virtual_outputs: VirtualOutputs,
 }
 
 pub struct KmsGuard<'a> {
@@ -80,6 +89,7 @@ pub struct KmsGuard<'a> {
     pub primary_node: Arc<RwLock<Option<DrmNode>>>,
     api: &'a mut GpuManager<GbmGlowBackend<DrmDeviceFd>>,
     session: &'a LibSeatSession,
+    virtual_outputs: &'a mut VirtualOutputs,
 }
 
 pub fn init_backend(
@@ -130,6 +140,8 @@ pub fn init_backend(
         libinput: libinput_context,
 
         syncobj_state: None,
+
+        virtual_outputs: VirtualOutputs::new(event_loop.handle()),
     });
 
     // manually add already present gpus
@@ -273,7 +285,7 @@ fn determine_primary_gpu(
 }
 
 /// Create `GlowRenderer` for `EGL_MESA_device_software` device, if present
-fn software_renderer() -> anyhow::Result<GlowRenderer> {
+pub(crate) fn software_renderer() -> anyhow::Result<GlowRenderer> {
     let mut devices = EGLDevice::enumerate()?;
     let device = devices
         .find(|device| {
@@ -555,6 +567,9 @@ impl KmsState {
     }
 
     pub fn schedule_render(&mut self, output: &Output) {
+        if self.virtual_outputs.schedule_render(output) {
+            return;
+        }
         for surface in self
             .drm_devices
             .values()
@@ -573,12 +588,41 @@ impl KmsState {
             .copied()
     }
 
+    /// Number of entries per channel of the gamma ramps of `output`, if supported
+    pub fn gamma_size(&self, output: &Output) -> Option<usize> {
+        self.drm_devices.values().find_map(|device| {
+            let (crtc, _) = device
+                .inner
+                .surfaces
+                .iter()
+                .find(|(_, s)| s.output == *output)?;
+            drm_helpers::gamma_size(device.drm.device(), *crtc).ok()
+        })
+    }
+
+    /// Sets the gamma ramps requested by a client for `output`, or resets them if `ramp` is `None`.
+    pub fn set_gamma_ramp(&mut self, output: &Output, ramp: Option<Vec<u16>>) -> Result<()> {
+        for device in self.drm_devices.values_mut() {
+            if let Some((crtc, surface)) = device
+                .inner
+                .surfaces
+                .iter_mut()
+                .find(|(_, s)| s.output == *output)
+            {
+                surface.gamma.set_client_ramp(ramp);
+                return surface.gamma.apply(device.drm.device(), *crtc);
+            }
+        }
+        anyhow::bail!("No crtc found for output {}", output.name())
+    }
+
     pub fn update_screen_filter(&mut self, screen_filter: &ScreenFilter) -> Result<()> {
         for device in self.drm_devices.values_mut() {
             for surface in device.inner.surfaces.values_mut() {
                 surface.set_screen_filter(screen_filter.clone());
             }
         }
+        self.virtual_outputs.update_screen_filter(screen_filter);
 
         // We don't expect this to fail in a meaningful way.
         // The shader is already compiled at this point and we don't rely on any features,
@@ -634,12 +678,45 @@ impl KmsState {
             primary_node: self.primary_node.clone(),
             api: &mut self.api,
             session: &self.session,
+            virtual_outputs: &mut self.virtual_outputs,
+        }
+    }
+
+    /// Renders a frame of a virtual output on the primary gpu
+    pub fn render_virtual_output(&mut self, output: &Output, common: &mut Common) {
+        if !self.session.is_active() {
+            // resumed by the render scheduled once the session is active again
+            self.virtual_outputs.suspend(output);
+            return;
+        }
+
+        let primary_node = *self.primary_node.read().unwrap();
+        let res = if let Some(node) = primary_node {
+            self.api
+                .single_renderer(&node)
+                .with_context(|| "Failed to create renderer")
+                .and_then(|mut renderer| {
+                    self.virtual_outputs
+                        .render(Some(&node), &mut renderer, output, common)
+                })
+        } else if let Some(renderer) = self.software_renderer.as_mut() {
+            self.virtual_outputs.render(None, renderer, output, common)
+        } else {
+            self.virtual_outputs.suspend(output);
+            Err(anyhow::anyhow!("No renderer available"))
+        };
+
+        if let Err(err) = res {
+            error!(?err, "Error rendering virtual output {}.", output.name());
         }
     }
 }
 
 impl KmsGuard<'_> {
     pub fn schedule_render(&mut self, output: &Output) {
+        if self.virtual_outputs.schedule_render(output) {
+            return;
+        }
         for surface in self
             .drm_devices
             .values()
@@ -703,6 +780,7 @@ impl KmsGuard<'_> {
                     })
                     .map(|(_, output)| output.clone())
             })
+            .chain(self.virtual_outputs.outputs().cloned())
             .collect()
     }
 
@@ -719,6 +797,8 @@ impl KmsGuard<'_> {
             return Ok(());
         }
 
+        self.virtual_outputs.apply_config(test_only)?;
+
         for device in self.drm_devices.values_mut() {
             // we only want outputs exposed to wayland - not leased ones
             // but that is also not all surface, because that doesn't contain all detected, but unmapped outputs
@@ -866,7 +946,52 @@ impl KmsGuard<'_> {
                     })
                     .ok_or(anyhow::anyhow!("Unable to find matching mode"))?;
 
+                let mut output_color = OutputColor::from_config(&output_config.0);
+                let supports_hdr = drm_helpers::supports_hdr(drm.device(), conn);
+                if output_color.is_hdr() && !supports_hdr {
+                    anyhow::bail!("Output {} does not support HDR", surface.output.name());
+                }
+
                 if !test_only {
+                    // ICC profiles describe SDR signals
+                    let icc_profile = surface
+                        .load_icc_profile(
+                            output_config
+                                .0
+                                .icc_profile
+                                .as_deref()
+                                .filter(|_| !output_color.is_hdr()),
+                        )
+                        .cloned();
+                    output_color.calibration =
+                        icc_profile.as_ref().and_then(Calibration::from_profile);
+
+                    if supports_hdr {
+                        let hdr = output_color.is_hdr();
+                        if let Err(err) = drm_helpers::set_colorspace(drm.device(), conn, hdr)
+                            .and_then(|_| {
+                                drm_helpers::set_hdr_output_metadata(drm.device(), conn, hdr)
+                            })
+                        {
+                            warn!(
+                                ?err,
+                                "Failed to set color space on connector: {}",
+                                surface.output.name()
+                            );
+                        }
+                    }
+                    surface.set_output_color(output_color);
+                    surface
+                        .gamma
+                        .set_calibration(icc_profile.and_then(|profile| profile.vcgt));
+                    if let Err(err) = surface.gamma.apply(drm.device(), *crtc) {
+                        warn!(
+                            ?err,
+                            "Failed to apply gamma ramps to output: {}",
+                            surface.output.name()
+                        );
+                    }
+
                     if !surface.is_active() {
                         let mut planes = drm
                             .device()
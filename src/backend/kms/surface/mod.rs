@@ -4,13 +4,14 @@ use crate::{
     backend::render::{
         CLEAR_COLOR, CursorMode, GlMultiError, GlMultiRenderer, PostprocessOutputConfig,
         PostprocessShader, PostprocessState,
+        color::OutputColor,
         element::{CosmicElement, DamageElement},
-        init_shaders, output_elements,
+        init_shaders, output_elements, postprocess_uniforms,
     },
     config::ScreenFilter,
     shell::Shell,
     state::SurfaceDmabufFeedback,
-    utils::prelude::*,
+    utils::{icc::IccProfile, prelude::*},
     wayland::handlers::{
         compositor::recursive_frame_time_estimation,
         image_copy_capture::{FrameHolder, PendingImageCopyData, SessionData, submit_buffer},
@@ -50,9 +51,7 @@ use smithay::{
                     constrain_render_elements,
                 },
             },
-            gles::{
-                GlesRenderbuffer, GlesRenderer, GlesTexture, Uniform, element::TextureShaderElement,
-            },
+            gles::{GlesRenderbuffer, GlesRenderer, GlesTexture, element::TextureShaderElement},
             glow::GlowRenderer,
             multigpu::{ApiDevice, Error as MultiError, GpuManager},
             sync::SyncPoint,
@@ -91,6 +90,7 @@ use std::{
     borrow::{Borrow, BorrowMut},
     collections::{HashMap, HashSet, hash_map},
     mem,
+    path::{Path, PathBuf},
     sync::{
         Arc, RwLock,
         atomic::{AtomicBool, Ordering},
@@ -103,7 +103,7 @@ use std::{
 mod timings;
 pub use self::timings::Timings;
 
-use super::{drm_helpers, render::gles::GbmGlowBackend};
+use super::{drm_helpers, gamma::GammaState, render::gles::GbmGlowBackend};
 
 #[cfg(feature = "debug")]
 use smithay_egui::EguiState;
@@ -126,6 +126,9 @@ pub struct Surface {
     thread: Option<JoinHandle<()>>,
 
     dpms: bool,
+    pub(super) gamma: GammaState,
+    /// Last loaded ICC profile with the path it was read from
+    icc_profile: Option<(PathBuf, Option<IccProfile>)>,
 }
 
 pub struct SurfaceThreadState {
@@ -146,6 +149,7 @@ pub struct SurfaceThreadState {
     output: Output,
     mirroring: Option<Output>,
     screen_filter: ScreenFilter,
+    output_color: OutputColor,
     postprocess_textures: HashMap<DrmNode, PostprocessState>,
 
     shell: Arc<parking_lot::RwLock<Shell>>,
@@ -214,6 +218,7 @@ pub enum ThreadCommand {
     },
     UpdateMirroring(Option<Output>),
     UpdateScreenFilter(ScreenFilter),
+    UpdateOutputColor(OutputColor),
     VBlank(Option<DrmEventMetadata>),
     ScheduleRender,
     AdaptiveSyncAvailable(SyncSender<Result<VrrSupport>>),
@@ -290,6 +295,8 @@ impl Surface {
                         return;
                     }
                     state.common.update_primary_output(&output_clone, &states);
+                    // surfaces may have moved between outputs of different color spaces
+                    state.refresh_color_management();
                     let kms = state.backend.kms();
                     let surface = &mut kms
                         .drm_devices
@@ -347,6 +354,8 @@ impl Surface {
             thread_token,
             thread: Some(thread),
             dpms: true,
+            gamma: GammaState::default(),
+            icc_profile: None,
         })
     }
 
@@ -404,6 +413,39 @@ impl Surface {
             .send(ThreadCommand::UpdateScreenFilter(config));
     }
 
+    /// Loads the ICC profile at `path`, only reading the file again if the path changed
+    pub fn load_icc_profile(&mut self, path: Option<&Path>) -> Option<&IccProfile> {
+        let Some(path) = path else {
+            self.icc_profile = None;
+            return None;
+        };
+        if self
+            .icc_profile
+            .as_ref()
+            .is_none_or(|(loaded, _)| loaded != path)
+        {
+            let profile = IccProfile::load(path)
+                .inspect_err(|err| {
+                    warn!(
+                        ?err,
+                        "Failed to load ICC profile for {}",
+                        self.output.name()
+                    )
+                })
+                .ok();
+            self.icc_profile = Some((path.to_path_buf(), profile));
+        }
+        self.icc_profile
+            .as_ref()
+            .and_then(|(_, profile)| profile.as_ref())
+    }
+
+    pub fn set_output_color(&mut self, color: OutputColor) {
+        let _ = self
+            .thread_command
+            .send(ThreadCommand::UpdateOutputColor(color));
+    }
+
     pub fn adaptive_sync_support(&self) -> Result<VrrSupport> {
         let (tx, rx) = std::sync::mpsc::sync_channel(1);
         let _ = self
@@ -544,6 +586,7 @@ fn surface_thread(
         output,
         mirroring: None,
         screen_filter,
+        output_color: OutputColor::default(),
         postprocess_textures: HashMap::new(),
 
         shell,
@@ -599,6 +642,9 @@ fn surface_thread(
             Event::Msg(ThreadCommand::UpdateScreenFilter(filter_config)) => {
                 state.update_screen_filter(filter_config);
             }
+            Event::Msg(ThreadCommand::UpdateOutputColor(color)) => {
+                state.update_output_color(color);
+            }
             Event::Msg(ThreadCommand::AdaptiveSyncAvailable(result)) => {
                 if let Some(compositor) = state.compositor.as_mut() {
                     let _ = result.send(
@@ -1088,11 +1134,15 @@ impl SurfaceThreadState {
         let source_output = self
             .mirroring
             .as_ref()
-            .or((!self.screen_filter.is_noop()).then_some(&self.output))
+            .or(
+                (!self.screen_filter.is_noop() || self.output_color.needs_postprocess())
+                    .then_some(&self.output),
+            )
             .filter(|output| {
                 PostprocessOutputConfig::for_output_untransformed(output)
                     != PostprocessOutputConfig::for_output(&self.output)
                     || !self.screen_filter.is_noop()
+                    || self.output_color.needs_postprocess()
             });
 
         let mut pre_postprocess_data = PrePostprocessData::default();
@@ -1100,26 +1150,42 @@ impl SurfaceThreadState {
         let res = if let Some(source_output) = source_output {
             let offscreen_output_config =
                 PostprocessOutputConfig::for_output_untransformed(source_output);
+            let output_color = self.output_color;
+            let new_postprocess_state =
+                |renderer: &mut GlMultiRenderer, output_config: PostprocessOutputConfig| {
+                    // HDR content needs headroom above SDR white while compositing
+                    if output_color.is_hdr() {
+                        match PostprocessState::new_with_renderer(
+                            renderer,
+                            Fourcc::Abgr16161616f,
+                            output_config.clone(),
+                        ) {
+                            Ok(state) => return Ok(state),
+                            Err(err) => {
+                                warn!(?err, "Failed to create floating point offscreen buffer");
+                            }
+                        }
+                    }
+                    PostprocessState::new_with_renderer(
+                        renderer,
+                        compositor.format(),
+                        output_config,
+                    )
+                };
             let postprocess_state = match self.postprocess_textures.entry(self.target_node) {
                 hash_map::Entry::Occupied(occupied) => {
                     let postprocess_state = occupied.into_mut();
                     // If output config is different, re-create offscreen state
                     if postprocess_state.output_config != offscreen_output_config {
-                        *postprocess_state = PostprocessState::new_with_renderer(
-                            &mut renderer,
-                            compositor.format(),
-                            offscreen_output_config,
-                        )?
+                        *postprocess_state =
+                            new_postprocess_state(&mut renderer, offscreen_output_config)?
                     }
                     postprocess_state
                 }
-                hash_map::Entry::Vacant(vacant) => {
-                    vacant.insert(PostprocessState::new_with_renderer(
-                        &mut renderer,
-                        compositor.format(),
-                        offscreen_output_config,
-                    )?)
-                }
+                hash_map::Entry::Vacant(vacant) => vacant.insert(new_postprocess_state(
+                    &mut renderer,
+                    offscreen_output_config,
+                )?),
             };
 
             if has_cursor_mode_none && self.mirroring.is_none() {
@@ -1269,6 +1335,7 @@ impl SurfaceThreadState {
                 &pre_postprocess_data,
                 postprocess_state,
                 &self.screen_filter,
+                &self.output_color,
             );
 
             if let Err(err) = compositor.with_compositor(|c| c.use_vrr(vrr)) {
@@ -1449,6 +1516,13 @@ impl SurfaceThreadState {
         self.postprocess_textures.clear();
     }
 
+    fn update_output_color(&mut self, color: OutputColor) {
+        if self.output_color != color {
+            self.output_color = color;
+            self.postprocess_textures.clear();
+        }
+    }
+
     fn send_frame_callbacks(&mut self) {
         if self.mirroring.is_none() {
             let _ = self
@@ -1864,6 +1938,7 @@ fn postprocess_elements<'a>(
     pre_postprocess_data: &PrePostprocessData,
     postprocess_state: &PostprocessState,
     screen_filter: &ScreenFilter,
+    output_color: &OutputColor,
 ) -> Vec<CosmicElement<GlMultiRenderer<'a>>> {
     let postprocess_texture_shader = Borrow::<GlesRenderer>::borrow(renderer.as_ref())
         .egl_context()
@@ -1895,16 +1970,7 @@ fn postprocess_elements<'a>(
         elements[0] = Some(TextureShaderElement::new(
             texture_elem,
             postprocess_texture_shader.0.clone(),
-            vec![
-                Uniform::new("invert", if screen_filter.inverted { 1. } else { 0. }),
-                Uniform::new(
-                    "color_mode",
-                    screen_filter
-                        .color_filter
-                        .map(|val| val as u8 as f32)
-                        .unwrap_or(0.),
-                ),
-            ],
+            postprocess_uniforms(screen_filter, output_color),
         ));
     }
 
@@ -1929,16 +1995,7 @@ fn postprocess_elements<'a>(
     elements[1] = Some(TextureShaderElement::new(
         texture_elem,
         postprocess_texture_shader.0.clone(),
-        vec![
-            Uniform::new("invert", if screen_filter.inverted { 1. } else { 0. }),
-            Uniform::new(
-                "color_mode",
-                screen_filter
-                    .color_filter
-                    .map(|val| val as u8 as f32)
-                    .unwrap_or(0.),
-            ),
-        ],
+        postprocess_uniforms(screen_filter, output_color),
     ));
 
     constrain_render_elements(
@@ -0,0 +1,393 @@
+// SPDX-License-Identifier: GPL-3.0-only
+
+//! Virtual outputs, which aren't backed by any connector.
+//!
+//! They are created and removed at runtime through the ipc socket and otherwise behave like
+//! any other output: They get workspaces assigned, show up in `output_configuration` and can
+//! be captured through `image_copy_capture`, e.g. to share a dedicated screen in a call.
+//!
+//! Frames are rendered offscreen on the primary gpu, paced by a timer emulating
+//! the refresh rate of the current mode.
+
+use crate::{
+    backend::render::{
+        self, CursorMode, ScreenFilterStorage,
+        element::{AsGlowRenderer, CosmicElement, FromGlesError},
+    },
+    config::ScreenFilter,
+    shell::{CosmicMappedRenderElement, WorkspaceRenderElement},
+    state::{BackendData, Common},
+    utils::prelude::*,
+};
+use anyhow::{Result, anyhow};
+use cosmic_comp_config::output::comp::{AdaptiveSync, OutputConfig, OutputState};
+use smithay::{
+    backend::{
+        allocator::Fourcc,
+        drm::DrmNode,
+        renderer::{
+            Bind, ContextId, Offscreen,
+            damage::{OutputDamageTracker, RenderOutputResult},
+            element::RenderElement,
+            gles::{GlesRenderbuffer, GlesTexture},
+        },
+    },
+    output::{Mode, Output, PhysicalProperties, Scale, Subpixel},
+    reexports::{
+        calloop::{
+            LoopHandle,
+            timer::{TimeoutAction, Timer},
+        },
+        wayland_protocols::wp::presentation_time::server::wp_presentation_feedback,
+    },
+    utils::Transform,
+    wayland::presentation::Refresh,
+};
+use std::{cell::RefCell, time::Duration};
+use tracing::warn;
+
+const NAME_PREFIX: &str = "VIRTUAL-";
+
+#[derive(Debug)]
+pub struct VirtualOutputs {
+    surfaces: Vec<VirtualSurface>,
+    loop_handle: LoopHandle<'static, State>,
+}
+
+#[derive(Debug)]
+struct VirtualSurface {
+    output: Output,
+    damage_tracker: OutputDamageTracker,
+    buffer: Option<(ContextId<GlesTexture>, GlesRenderbuffer)>,
+    dirty: bool,
+    pending: bool,
+    screen_filter_state: ScreenFilterStorage,
+}
+
+impl VirtualOutputs {
+    pub fn new(loop_handle: LoopHandle<'static, State>) -> VirtualOutputs {
+        VirtualOutputs {
+            surfaces: Vec::new(),
+            loop_handle,
+        }
+    }
+
+    /// Creates a new virtual output named `VIRTUAL-<n>`.
+    ///
+    /// The output isn't exposed yet, see [`State::create_virtual_output`].
+    pub fn create(
+        &mut self,
+        mode: Mode,
+        scale: f64,
+        position: (u32, u32),
+        screen_filter: ScreenFilter,
+    ) -> Output {
+        let idx = (1..)
+            .find(|idx| {
+                let name = format!("{NAME_PREFIX}{idx}");
+                !self.surfaces.iter().any(|s| s.output.name() == name)
+            })
+            .unwrap();
+        let name = format!("{NAME_PREFIX}{idx}");
+        let props = PhysicalProperties {
+            size: (0, 0).into(),
+            subpixel: Subpixel::Unknown,
+            make: "COSMIC".to_string(),
+            model: "Virtual".to_string(),
+            serial_number: name.clone(),
+        };
+        let output = Output::new(name, props);
+        output.add_mode(mode);
+        output.set_preferred(mode);
+        output.change_current_state(
+            Some(mode),
+            Some(Transform::Normal),
+            Some(Scale::Fractional(scale)),
+            Some((position.0 as i32, position.1 as i32).into()),
+        );
+        output.user_data().insert_if_missing(|| {
+            RefCell::new(OutputConfig {
+                mode: ((mode.size.w, mode.size.h), Some(mode.refresh as u32)),
+                vrr: AdaptiveSync::Disabled,
+                scale,
+                position,
+                ..Default::default()
+            })
+        });
+
+        self.surfaces.push(VirtualSurface {
+            damage_tracker: OutputDamageTracker::from_output(&output),
+            output: output.clone(),
+            buffer: None,
+            dirty: true,
+            pending: false,
+            screen_filter_state: ScreenFilterStorage {
+                filter: screen_filter,
+                state: None,
+            },
+        });
+
+        output
+    }
+
+    /// Removes the virtual output of the given name
+    pub fn remove(&mut self, name: &str) -> Option<Output> {
+        let idx = self.surfaces.iter().position(|s| s.output.name() == name)?;
+        Some(self.surfaces.remove(idx).output)
+    }
+
+    pub fn outputs(&self) -> impl Iterator<Item = &Output> {
+        self.surfaces.iter().map(|s| &s.output)
+    }
+
+    /// Schedules a new frame, returns `false` if `output` isn't a virtual output
+    pub fn schedule_render(&mut self, output: &Output) -> bool {
+        let Some(surface) = self.surfaces.iter_mut().find(|s| s.output == *output) else {
+            return false;
+        };
+
+        surface.dirty = true;
+        if !surface.pending {
+            surface.pending = true;
+            let output = output.clone();
+            self.loop_handle.insert_idle(move |state| {
+                if let BackendData::Kms(kms) = &mut state.backend {
+                    kms.render_virtual_output(&output, &mut state.common);
+                }
+            });
+        }
+        true
+    }
+
+    pub fn apply_config(&mut self, test_only: bool) -> Result<()> {
+        for surface in &mut self.surfaces {
+            if matches!(surface.output.config().enabled, OutputState::Mirroring(_)) {
+                return Err(anyhow!(
+                    "Virtual output {} can't mirror other outputs",
+                    surface.output.name()
+                ));
+            }
+            if test_only {
+                continue;
+            }
+
+            // Virtual outputs can take any mode, so just make sure it is advertised.
+            let Some(mode) = surface.output.current_mode() else {
+                continue;
+            };
+            if !surface.output.modes().contains(&mode) {
+                surface.output.add_mode(mode);
+            }
+            surface.output.set_preferred(mode);
+            // the mode might have changed, so force a new buffer
+            surface.buffer = None;
+            surface.dirty = true;
+        }
+
+        Ok(())
+    }
+
+    pub fn update_screen_filter(&mut self, screen_filter: &ScreenFilter) {
+        for surface in &mut self.surfaces {
+            surface.screen_filter_state.filter = screen_filter.clone();
+        }
+    }
+
+    /// Stops rendering until the next [`VirtualOutputs::schedule_render`]
+    pub fn suspend(&mut self, output: &Output) {
+        if let Some(surface) = self.surfaces.iter_mut().find(|s| s.output == *output) {
+            surface.pending = false;
+        }
+    }
+
+    pub fn render<R>(
+        &mut self,
+        gpu: Option<&DrmNode>,
+        renderer: &mut R,
+        output: &Output,
+        common: &mut Common,
+    ) -> Result<()>
+    where
+        R: AsGlowRenderer,
+        R::TextureId: Send + Clone + 'static,
+        R::Error: FromGlesError,
+        CosmicElement<R>: RenderElement<R>,
+        CosmicMappedRenderElement<R>: RenderElement<R>,
+        WorkspaceRenderElement<R>: RenderElement<R>,
+    {
+        let Some(surface) = self.surfaces.iter_mut().find(|s| s.output == *output) else {
+            return Ok(());
+        };
+
+        let result = if surface.dirty && output.is_enabled() {
+            surface.render(gpu, renderer, common)
+        } else {
+            Ok(())
+        };
+        surface.dirty = false;
+
+        // There is no vblank to wait for, so emulate one using the refresh rate of the current mode.
+        let refresh = output
+            .current_mode()
+            .map(|mode| Duration::from_secs_f64(1_000.0 / mode.refresh as f64))
+            .unwrap_or(Duration::from_millis(16));
+        let output = output.clone();
+        if let Err(err) =
+            self.loop_handle
+                .insert_source(Timer::from_duration(refresh), move |_, _, state| {
+                    if let BackendData::Kms(kms) = &mut state.backend
+                        && kms.virtual_outputs.frame_done(&output)
+                    {
+                        kms.render_virtual_output(&output, &mut state.common);
+                    }
+                    TimeoutAction::Drop
+                })
+        {
+            warn!(?err, "Failed to schedule frame timer.");
+            surface.pending = false;
+        }
+
+        result
+    }
+
+    /// Returns if another frame needs to be rendered, otherwise stops the frame timer
+    fn frame_done(&mut self, output: &Output) -> bool {
+        let Some(surface) = self.surfaces.iter_mut().find(|s| s.output == *output) else {
+            return false;
+        };
+        if !surface.dirty {
+            surface.pending = false;
+        }
+        surface.dirty
+    }
+}
+
+impl VirtualSurface {
+    fn render<R>(
+        &mut self,
+        gpu: Option<&DrmNode>,
+        renderer: &mut R,
+        state: &mut Common,
+    ) -> Result<()>
+    where
+        R: AsGlowRenderer,
+        R::TextureId: Send + Clone + 'static,
+        R::Error: FromGlesError,
+        CosmicElement<R>: RenderElement<R>,
+        CosmicMappedRenderElement<R>: RenderElement<R>,
+        WorkspaceRenderElement<R>: RenderElement<R>,
+    {
+        let size = self
+            .output
+            .current_mode()
+            .ok_or_else(|| anyhow!("Output has no mode"))?
+            .size
+            .to_logical(1)
+            .to_buffer(1, Transform::Normal);
+
+        // The primary gpu might have changed, which invalidates the buffer
+        let context_id = renderer.glow_renderer().context_id();
+        self.buffer
+            .take_if(|(id, buffer)| *id != context_id || buffer.size() != size);
+
+        // We always render into the same buffer, so it is either fresh or contains the last frame.
+        let age = if self.buffer.is_some() { 1 } else { 0 };
+        if self.buffer.is_none() {
+            let buffer =
+                Offscreen::<GlesRenderbuffer>::create_buffer(renderer, Fourcc::Abgr8888, size)
+                    .map_err(|err| anyhow!("Failed to allocate buffer: {err}"))?;
+            self.buffer = Some((context_id, buffer));
+        }
+        let (_, buffer) = self.buffer.as_mut().unwrap();
+        let mut fb = renderer
+            .bind(buffer)
+            .map_err(|err| anyhow!("Failed to bind buffer: {err}"))?;
+
+        match render::render_output(
+            gpu,
+            renderer,
+            &mut fb,
+            &mut self.damage_tracker,
+            age,
+            &state.shell,
+            state.clock.now(),
+            &self.output,
+            CursorMode::NotDefault,
+            &mut self.screen_filter_state,
+            &state.event_loop_handle,
+        ) {
+            Ok(RenderOutputResult { damage, states, .. }) => {
+                state.send_frames(&self.output, None);
+                state.update_primary_output(&self.output, &states);
+                state.send_dmabuf_feedback(&self.output, &states, |_| None);
+                if damage.is_some() {
+                    let mut output_presentation_feedback = state
+                        .shell
+                        .read()
+                        .take_presentation_feedback(&self.output, &states);
+                    output_presentation_feedback.presented(
+                        state.clock.now(),
+                        self.output
+                            .current_mode()
+                            .map(|mode| {
+                                Refresh::Fixed(Duration::from_secs_f64(
+                                    1_000.0 / mode.refresh as f64,
+                                ))
+                            })
+                            .unwrap_or(Refresh::Unknown),
+                        0,
+                        wp_presentation_feedback::Kind::empty(),
+                    )
+                }
+            }
+            Err(err) => {
+                std::mem::drop(fb);
+                self.buffer = None;
+                anyhow::bail!("Rendering failed: {}", err);
+            }
+        };
+
+        Ok(())
+    }
+}
+
+impl State {
+    /// Creates a new virtual output and places it right of all existing outputs
+    pub fn create_virtual_output(&mut self, mode: Mode, scale: f64) -> Result<Output> {
+        let position = (self.common.shell.read().global_space().size.w as u32, 0);
+        let screen_filter = self.common.config.dynamic_conf.screen_filter().clone();
+        let BackendData::Kms(kms) = &mut self.backend else {
+            anyhow::bail!("Virtual outputs are only supported on the kms backend");
+        };
+        let output = kms
+            .virtual_outputs
+            .create(mode, scale, position, screen_filter);
+
+        self.common
+            .output_configuration_state
+            .add_heads(std::iter::once(&output));
+        if let Err(err) = self.refresh_output_config() {
+            let _ = self.remove_virtual_output(&output.name());
+            return Err(err.context("Unable to load output config"));
+        }
+
+        Ok(output)
+    }
+
+    /// Removes the virtual output of the given name, moving its workspaces to the remaining outputs
+    pub fn remove_virtual_output(&mut self, name: &str) -> Result<()> {
+        let BackendData::Kms(kms) = &mut self.backend else {
+            anyhow::bail!("Virtual outputs are only supported on the kms backend");
+        };
+        let output = kms
+            .virtual_outputs
+            .remove(name)
+            .ok_or_else(|| anyhow!("No virtual output named {name}"))?;
+
+        self.common
+            .output_configuration_state
+            .remove_heads(std::iter::once(&output));
+        self.common.remove_output(&output);
+        self.refresh_output_config()
+    }
+}
@@ -11,11 +11,11 @@ use tracing::{info, warn};
 
 pub mod render;
 
+pub mod headless;
 pub mod kms;
+pub mod wayland;
 pub mod winit;
 pub mod x11;
-// TODO
-// pub mod wayland; // tbd in smithay
 
 pub fn init_backend_auto(
     dh: &DisplayHandle,
@@ -26,19 +26,21 @@ pub fn init_backend_auto(
         Ok(x) if x == "x11" => x11::init_backend(dh, event_loop, state),
         Ok(x) if x == "winit" => winit::init_backend(dh, event_loop, state),
         Ok(x) if x == "kms" => kms::init_backend(dh, event_loop, state),
+        Ok(x) if x == "headless" => headless::init_backend(dh, event_loop, state),
+        Ok(x) if x == "wayland" => wayland::init_backend(dh, event_loop, state),
         Ok(_) => unimplemented!("There is no backend with this identifier"),
         Err(_) => {
-            if std::env::var_os("DISPLAY").is_some()
-                || std::env::var_os("WAYLAND_DISPLAY").is_some()
-            {
-                match x11::init_backend(dh, event_loop, state) {
+            if std::env::var_os("WAYLAND_DISPLAY").is_some() {
+                match wayland::init_backend(dh, event_loop, state) {
                     Ok(_) => Ok(()),
                     Err(err) => {
-                        warn!(?err, "Initializing X11 Backend failed.");
-                        info!("Falling back to winit backend.");
-                        winit::init_backend(dh, event_loop, state)
+                        warn!(?err, "Initializing Wayland Backend failed.");
+                        info!("Falling back to X11 backend.");
+                        init_nested_x11(dh, event_loop, state)
                     }
                 }
+            } else if std::env::var_os("DISPLAY").is_some() {
+                init_nested_x11(dh, event_loop, state)
             } else {
                 kms::init_backend(dh, event_loop, state)
             }
@@ -162,3 +164,18 @@ pub fn init_backend_auto(
     }
     res
 }
+
+fn init_nested_x11(
+    dh: &DisplayHandle,
+    event_loop: &mut EventLoop<'static, State>,
+    state: &mut State,
+) -> Result<()> {
+    match x11::init_backend(dh, event_loop, state) {
+        Ok(_) => Ok(()),
+        Err(err) => {
+            warn!(?err, "Initializing X11 Backend failed.");
+            info!("Falling back to winit backend.");
+            winit::init_backend(dh, event_loop, state)
+        }
+    }
+}
@@ -56,61 +56,7 @@ where
     where
         R: AsGlowRenderer,
     {
-        let elem_geo = elem.geometry(scale);
-        let geo: Rectangle<i32, Physical> = geometry.to_physical_precise_round(scale);
-        let buf_size = elem.buffer_size();
-        let view = elem.view();
-
-        let transform = elem.transform();
-        let transform_matrix = Matrix3::<f32>::from_translation(Vector2::new(0.5, 0.5))
-            * transform.matrix()
-            * Matrix3::<f32>::from_translation(-Vector2::new(0.5, 0.5));
-
-        let geo_scale = {
-            let Scale { x, y } = elem_geo.size.to_f64() / geo.size.to_f64();
-            Matrix3::from_nonuniform_scale(x as f32, y as f32)
-        };
-
-        let geo_translation = {
-            let offset = (elem_geo.loc - geo.loc).to_f64();
-            Matrix3::from_translation(Vector2::new(
-                (offset.x / elem_geo.size.w as f64) as f32,
-                (offset.y / elem_geo.size.h as f64) as f32,
-            ))
-        };
-
-        let buf_scale = {
-            let Scale { x, y } = buf_size.to_f64() / view.src.size.to_f64();
-            Matrix3::from_nonuniform_scale(x as f32, y as f32)
-        };
-
-        let buf_translation = Matrix3::from_translation(Vector2::new(
-            (view.src.loc.x / buf_size.w as f64) as f32,
-            (view.src.loc.y / buf_size.h as f64) as f32,
-        ));
-
-        let input_to_geo =
-            transform_matrix * geo_scale * geo_translation * buf_scale * buf_translation;
-
-        let uniforms = vec![
-            Uniform::new("geo_size", (geometry.size.w as f32, geometry.size.h as f32)),
-            Uniform::new(
-                "corner_radius",
-                [
-                    radius[3] as f32,
-                    radius[1] as f32,
-                    radius[0] as f32,
-                    radius[2] as f32,
-                ],
-            ),
-            Uniform::new(
-                "input_to_geo",
-                UniformValue::Matrix3x3 {
-                    matrices: vec![*AsRef::<[f32; 9]>::as_ref(&input_to_geo)],
-                    transpose: false,
-                },
-            ),
-        ];
+        let uniforms = clipping_uniforms(&elem, scale, geometry, radius);
 
         Self {
             inner: elem,
@@ -130,42 +76,13 @@ where
         let elem_geo = elem.geometry(scale);
         let geo = geometry.to_physical_precise_round(scale);
 
-        let corners = Self::rounded_corners(geometry, radius);
+        let corners = rounded_corners(geometry, radius);
         let corners = corners
             .into_iter()
             .map(|rect| rect.to_physical_precise_up(scale));
         let geo = Rectangle::subtract_rects_many([geo], corners);
         !Rectangle::subtract_rects_many([elem_geo], geo).is_empty()
     }
-
-    fn rounded_corners(
-        geo: Rectangle<f64, Logical>,
-        radius: [u8; 4],
-    ) -> [Rectangle<f64, Logical>; 4] {
-        let top_left = radius[3] as f64;
-        let top_right = radius[1] as f64;
-        let bottom_right = radius[0] as f64;
-        let bottom_left = radius[2] as f64;
-
-        [
-            Rectangle::new(geo.loc, Size::from((top_left, top_left))),
-            Rectangle::new(
-                Point::from((geo.loc.x + geo.size.w - top_right, geo.loc.y)),
-                Size::from((top_right, top_right)),
-            ),
-            Rectangle::new(
-                Point::from((
-                    geo.loc.x + geo.size.w - bottom_right,
-                    geo.loc.y + geo.size.h - bottom_right,
-                )),
-                Size::from((bottom_right, bottom_right)),
-            ),
-            Rectangle::new(
-                Point::from((geo.loc.x, geo.loc.y + geo.size.h - bottom_left)),
-                Size::from((bottom_left, bottom_left)),
-            ),
-        ]
-    }
 }
 
 impl<R> Element for ClippedSurfaceRenderElement<R>
@@ -199,37 +116,18 @@ where
     ) -> DamageSet<i32, Physical> {
         // FIXME: radius changes need to cause damage.
         let damage = self.inner.damage_since(scale, commit);
-
-        // Intersect with geometry, since we're clipping by it.
-        let mut geo = self.geometry.to_physical_precise_round(scale);
-        geo.loc -= self.geometry(scale).loc;
-        damage
-            .into_iter()
-            .filter_map(|rect| rect.intersection(geo))
-            .collect()
+        clip_damage(damage, self.geometry(scale).loc, self.geometry, scale)
     }
 
     fn opaque_regions(&self, scale: Scale<f64>) -> OpaqueRegions<i32, Physical> {
         let regions = self.inner.opaque_regions(scale);
-
-        // Intersect with geometry, since we're clipping by it.
-        let mut geo = self.geometry.to_physical_precise_round(scale);
-        geo.loc -= self.geometry(scale).loc;
-        let regions = regions
-            .into_iter()
-            .filter_map(|rect| rect.intersection(geo));
-
-        // Subtract the rounded corners.
-        let corners = Self::rounded_corners(self.geometry, self.radius);
-
-        let elem_loc = self.geometry(scale).loc;
-        let corners = corners.into_iter().map(|rect| {
-            let mut rect = rect.to_physical_precise_up(scale);
-            rect.loc -= elem_loc;
-            rect
-        });
-
-        OpaqueRegions::from_slice(&Rectangle::subtract_rects_many(regions, corners))
+        clip_opaque_regions(
+            regions,
+            self.geometry(scale).loc,
+            self.geometry,
+            self.radius,
+            scale,
+        )
     }
 
     fn alpha(&self) -> f32 {
@@ -266,3 +164,139 @@ where
         None
     }
 }
+
+/// Uniforms clipping a surface to `geometry` with rounded corners of `radius`.
+///
+/// Used by the clipping shader and the color conversion shader.
+pub fn clipping_uniforms<R>(
+    elem: &WaylandSurfaceRenderElement<R>,
+    scale: Scale<f64>,
+    geometry: Rectangle<f64, Logical>,
+    radius: [u8; 4],
+) -> Vec<Uniform<'static>>
+where
+    R: Renderer + ImportAll + ImportMem,
+{
+    let elem_geo = elem.geometry(scale);
+    let geo: Rectangle<i32, Physical> = geometry.to_physical_precise_round(scale);
+    let buf_size = elem.buffer_size();
+    let view = elem.view();
+
+    let transform = elem.transform();
+    let transform_matrix = Matrix3::<f32>::from_translation(Vector2::new(0.5, 0.5))
+        * transform.matrix()
+        * Matrix3::<f32>::from_translation(-Vector2::new(0.5, 0.5));
+
+    let geo_scale = {
+        let Scale { x, y } = elem_geo.size.to_f64() / geo.size.to_f64();
+        Matrix3::from_nonuniform_scale(x as f32, y as f32)
+    };
+
+    let geo_translation = {
+        let offset = (elem_geo.loc - geo.loc).to_f64();
+        Matrix3::from_translation(Vector2::new(
+            (offset.x / elem_geo.size.w as f64) as f32,
+            (offset.y / elem_geo.size.h as f64) as f32,
+        ))
+    };
+
+    let buf_scale = {
+        let Scale { x, y } = buf_size.to_f64() / view.src.size.to_f64();
+        Matrix3::from_nonuniform_scale(x as f32, y as f32)
+    };
+
+    let buf_translation = Matrix3::from_translation(Vector2::new(
+        (view.src.loc.x / buf_size.w as f64) as f32,
+        (view.src.loc.y / buf_size.h as f64) as f32,
+    ));
+
+    let input_to_geo = transform_matrix * geo_scale * geo_translation * buf_scale * buf_translation;
+
+    vec![
+        Uniform::new("geo_size", (geometry.size.w as f32, geometry.size.h as f32)),
+        Uniform::new(
+            "corner_radius",
+            [
+                radius[3] as f32,
+                radius[1] as f32,
+                radius[0] as f32,
+                radius[2] as f32,
+            ],
+        ),
+        Uniform::new(
+            "input_to_geo",
+            UniformValue::Matrix3x3 {
+                matrices: vec![*AsRef::<[f32; 9]>::as_ref(&input_to_geo)],
+                transpose: false,
+            },
+        ),
+    ]
+}
+
+/// Intersects the damage of an element at `elem_loc` with the `geometry` it is clipped to
+pub fn clip_damage(
+    damage: DamageSet<i32, Physical>,
+    elem_loc: Point<i32, Physical>,
+    geometry: Rectangle<f64, Logical>,
+    scale: Scale<f64>,
+) -> DamageSet<i32, Physical> {
+    let mut geo = geometry.to_physical_precise_round(scale);
+    geo.loc -= elem_loc;
+    damage
+        .into_iter()
+        .filter_map(|rect| rect.intersection(geo))
+        .collect()
+}
+
+/// Removes everything outside of the clipped `geometry` and its rounded corners
+/// from the opaque regions of an element at `elem_loc`
+pub fn clip_opaque_regions(
+    regions: OpaqueRegions<i32, Physical>,
+    elem_loc: Point<i32, Physical>,
+    geometry: Rectangle<f64, Logical>,
+    radius: [u8; 4],
+    scale: Scale<f64>,
+) -> OpaqueRegions<i32, Physical> {
+    // Intersect with geometry, since we're clipping by it.
+    let mut geo = geometry.to_physical_precise_round(scale);
+    geo.loc -= elem_loc;
+    let regions = regions
+        .into_iter()
+        .filter_map(|rect| rect.intersection(geo));
+
+    // Subtract the rounded corners.
+    let corners = rounded_corners(geometry, radius);
+    let corners = corners.into_iter().map(|rect| {
+        let mut rect = rect.to_physical_precise_up(scale);
+        rect.loc -= elem_loc;
+        rect
+    });
+
+    OpaqueRegions::from_slice(&Rectangle::subtract_rects_many(regions, corners))
+}
+
+fn rounded_corners(geo: Rectangle<f64, Logical>, radius: [u8; 4]) -> [Rectangle<f64, Logical>; 4] {
+    let top_left = radius[3] as f64;
+    let top_right = radius[1] as f64;
+    let bottom_right = radius[0] as f64;
+    let bottom_left = radius[2] as f64;
+
+    [
+        Rectangle::new(geo.loc, Size::from((top_left, top_left))),
+        Rectangle::new(
+            Point::from((geo.loc.x + geo.size.w - top_right, geo.loc.y)),
+            Size::from((top_right, top_right)),
+        ),
+        Rectangle::new(
+            Point::from((
+                geo.loc.x + geo.size.w - bottom_right,
+                geo.loc.y + geo.size.h - bottom_right,
+            )),
+            Size::from((bottom_right, bottom_right)),
+        ),
+        Rectangle::new(
+            Point::from((geo.loc.x, geo.loc.y + geo.size.h - bottom_left)),
+            Size::from((bottom_left, bottom_left)),
+        ),
+    ]
+}
@@ -0,0 +1,391 @@
+// SPDX-License-Identifier: GPL-3.0-only
+
+use std::{
+    borrow::{Borrow, BorrowMut},
+    collections::HashMap,
+};
+
+use cgmath::{Matrix3, SquareMatrix, Vector3};
+use cosmic_comp_config::output::comp::{ColorSpace, DEFAULT_SDR_WHITE_LEVEL, OutputConfig};
+use smithay::{
+    backend::renderer::{
+        ImportAll, ImportMem, Renderer,
+        element::{
+            Element, Id, Kind, RenderElement, UnderlyingStorage,
+            surface::WaylandSurfaceRenderElement,
+        },
+        gles::{GlesFrame, GlesRenderer, GlesTexProgram, Uniform, UniformValue},
+        utils::{CommitCounter, DamageSet, OpaqueRegions},
+    },
+    reexports::{
+        wayland_protocols::wp::color_management::v1::server::wp_color_manager_v1::TransferFunction,
+        wayland_server::protocol::wl_surface::WlSurface,
+    },
+    utils::{Buffer, Logical, Physical, Rectangle, Scale, Transform},
+    wayland::compositor::{TraversalAction, with_surface_tree_downward},
+};
+
+use crate::{
+    backend::render::{
+        clipped_surface::{clip_damage, clip_opaque_regions, clipping_uniforms},
+        element::AsGlowRenderer,
+    },
+    utils::icc::{Curve, IccProfile},
+    wayland::protocols::color_management::{
+        Chromaticities, ImageDescription, surface_image_description,
+    },
+};
+
+pub static COLOR_CONVERSION_SHADER: &str = include_str!("./shaders/color_conversion.frag");
+pub struct ColorConversionShader(pub GlesTexProgram);
+
+impl ColorConversionShader {
+    pub fn get<R: AsGlowRenderer>(renderer: &R) -> GlesTexProgram {
+        Borrow::<GlesRenderer>::borrow(renderer.glow_renderer())
+            .egl_context()
+            .user_data()
+            .get::<ColorConversionShader>()
+            .expect("Custom Shaders not initialized")
+            .0
+            .clone()
+    }
+}
+
+/// Color encoding of an output, applied during postprocessing.
+#[derive(Debug, Clone, Copy, PartialEq)]
+pub struct OutputColor {
+    pub color_space: ColorSpace,
+    /// Luminance in nits that SDR white is mapped to
+    pub sdr_white_level: u32,
+    /// Calibration of SDR outputs from an ICC profile
+    pub calibration: Option<Calibration>,
+}
+
+impl Default for OutputColor {
+    fn default() -> Self {
+        OutputColor {
+            color_space: ColorSpace::Srgb,
+            sdr_white_level: DEFAULT_SDR_WHITE_LEVEL,
+            calibration: None,
+        }
+    }
+}
+
+impl OutputColor {
+    pub fn from_config(config: &OutputConfig) -> Self {
+        OutputColor {
+            color_space: config.color_space,
+            sdr_white_level: config.sdr_white_level.unwrap_or(DEFAULT_SDR_WHITE_LEVEL),
+            calibration: None,
+        }
+    }
+
+    pub fn is_hdr(&self) -> bool {
+        self.color_space == ColorSpace::Bt2020Pq
+    }
+
+    /// Whether the output requires a postprocessing pass for its color encoding
+    pub fn needs_postprocess(&self) -> bool {
+        self.is_hdr() || self.calibration.is_some()
+    }
+
+    /// Uniforms for the output encoding and calibration values of the postprocess shader
+    pub fn uniforms(&self) -> Vec<Uniform<'static>> {
+        let calibration = self.calibration.filter(|_| !self.is_hdr());
+        let (matrix, trc) = calibration
+            .map(|calibration| (calibration.matrix, calibration.trc))
+            .unwrap_or(([1., 0., 0., 0., 1., 0., 0., 0., 1.], IDENTITY_TRC));
+
+        let mut uniforms = vec![
+            Uniform::new("output_transfer", if self.is_hdr() { 1. } else { 0. }),
+            Uniform::new("sdr_white_level", self.sdr_white_level as f32),
+            Uniform::new("calibrate", if calibration.is_some() { 1. } else { 0. }),
+            Uniform::new(
+                "calibration_matrix",
+                UniformValue::Matrix3x3 {
+                    matrices: vec![matrix],
+                    transpose: false,
+                },
+            ),
+        ];
+        uniforms.extend(
+            TRC_UNIFORMS
+                .iter()
+                .zip(trc)
+                .map(|(name, params)| Uniform::new(*name, params)),
+        );
+        uniforms
+    }
+}
+
+// names of the tone reproduction curve parameters in offscreen.frag,
+// in the order of `Curve::parameters`
+const TRC_UNIFORMS: [&str; 7] = [
+    "trc_g", "trc_a", "trc_b", "trc_c", "trc_d", "trc_e", "trc_f",
+];
+const IDENTITY_TRC: [[f32; 3]; 7] = [
+    [1., 1., 1.],
+    [1., 1., 1.],
+    [0., 0., 0.],
+    [0., 0., 0.],
+    [0., 0., 0.],
+    [0., 0., 0.],
+    [0., 0., 0.],
+];
+
+/// Conversion of composited sRGB content into the native color space of a display,
+/// as described by the matrix and tone reproduction curves of its ICC profile.
+#[derive(Debug, Clone, Copy, PartialEq)]
+pub struct Calibration {
+    /// linear sRGB to linear device RGB
+    matrix: [f32; 9],
+    /// curve parameters of the red, green and blue channels
+    trc: [[f32; 3]; 7],
+}
+
+impl Calibration {
+    pub fn from_profile(profile: &IccProfile) -> Option<Calibration> {
+        // sRGB primaries adapted to the D50 white point of the profile connection space
+        let srgb_to_xyz = Matrix3::from_cols(
+            Vector3::new(0.4360747, 0.2225045, 0.0139322),
+            Vector3::new(0.3850649, 0.7168786, 0.0971045),
+            Vector3::new(0.1430804, 0.0606169, 0.7141733),
+        );
+        let [r, g, b] = profile.colorants?;
+        let device_to_xyz = Matrix3::from_cols(r.into(), g.into(), b.into());
+        let matrix = (device_to_xyz.invert()? * srgb_to_xyz).cast::<f32>()?;
+
+        let curves = profile.trc.as_ref()?.each_ref().map(Curve::parameters);
+        let trc = std::array::from_fn(|param| curves.map(|curve| curve[param] as f32));
+
+        Some(Calibration {
+            matrix: *AsRef::<[f32; 9]>::as_ref(&matrix),
+            trc,
+        })
+    }
+}
+
+// these values need to match with color_conversion.frag
+#[repr(u8)]
+enum TransferFunctionCode {
+    Srgb = 0,
+    Gamma22 = 1,
+    Bt1886 = 2,
+    Linear = 3,
+    Pq = 4,
+}
+
+fn rgb_to_xyz(primaries: &Chromaticities) -> Option<Matrix3<f64>> {
+    let xyz = |(x, y): (i32, i32)| {
+        let (x, y) = (x as f64 / 1_000_000., y as f64 / 1_000_000.);
+        (y > 0.).then(|| Vector3::new(x / y, 1.0, (1.0 - x - y) / y))
+    };
+
+    let rgb = Matrix3::from_cols(
+        xyz(primaries.red)?,
+        xyz(primaries.green)?,
+        xyz(primaries.blue)?,
+    );
+    let scale = rgb.invert()? * xyz(primaries.white)?;
+    Some(Matrix3::from_cols(
+        rgb.x * scale.x,
+        rgb.y * scale.y,
+        rgb.z * scale.z,
+    ))
+}
+
+fn conversion_uniforms(description: &ImageDescription) -> Vec<Uniform<'static>> {
+    let tf = match description.tf {
+        TransferFunction::Gamma22 => TransferFunctionCode::Gamma22,
+        TransferFunction::Bt1886 => TransferFunctionCode::Bt1886,
+        TransferFunction::ExtLinear => TransferFunctionCode::Linear,
+        TransferFunction::St2084Pq => TransferFunctionCode::Pq,
+        _ => TransferFunctionCode::Srgb,
+    };
+
+    let luminances = description.luminances;
+    let reference = luminances.reference.max(1) as f32;
+    let luminance_scale = match tf {
+        // PQ is absolute, 1.0 equals 10000 nits
+        TransferFunctionCode::Pq => 10_000. / reference,
+        _ => luminances.max as f32 / reference,
+    };
+
+    let to_srgb = rgb_to_xyz(&Chromaticities::SRGB)
+        .and_then(|srgb| srgb.invert())
+        .zip(rgb_to_xyz(&description.primaries))
+        .map(|(from_xyz, to_xyz)| from_xyz * to_xyz)
+        .unwrap_or_else(Matrix3::identity)
+        .cast::<f32>()
+        .unwrap_or_else(Matrix3::identity);
+
+    vec![
+        Uniform::new("transfer_function", tf as u8 as f32),
+        Uniform::new("luminance_scale", luminance_scale),
+        Uniform::new(
+            "to_srgb_primaries",
+            UniformValue::Matrix3x3 {
+                matrices: vec![*AsRef::<[f32; 9]>::as_ref(&to_srgb)],
+                transpose: false,
+            },
+        ),
+    ]
+}
+
+/// Collects the image descriptions of all surfaces in the tree requiring a color conversion,
+/// keyed by the id of their render elements.
+pub fn surface_tree_image_descriptions(surface: &WlSurface) -> HashMap<Id, ImageDescription> {
+    let mut descriptions = HashMap::new();
+    with_surface_tree_downward(
+        surface,
+        (),
+        |_, _, _| TraversalAction::DoChildren(()),
+        |surface, _, _| {
+            if let Some(description) = surface_image_description(surface)
+                && !description.is_srgb()
+            {
+                descriptions.insert(Id::from_wayland_resource(surface), description);
+            }
+        },
+        |_, _, _| true,
+    );
+    descriptions
+}
+
+/// Surface converted from a client provided image description into the compositing color space.
+#[derive(Debug)]
+pub struct ColorConvertedSurfaceRenderElement<R>
+where
+    R: Renderer + ImportAll + ImportMem,
+{
+    inner: WaylandSurfaceRenderElement<R>,
+    program: GlesTexProgram,
+    /// Geometry and corner radii the surface is clipped to, like `ClippedSurfaceRenderElement`
+    clip: Option<(Rectangle<f64, Logical>, [u8; 4])>,
+    uniforms: Vec<Uniform<'static>>,
+}
+
+impl<R> ColorConvertedSurfaceRenderElement<R>
+where
+    R: Renderer + ImportAll + ImportMem,
+{
+    pub fn new(
+        renderer: &mut R,
+        elem: WaylandSurfaceRenderElement<R>,
+        description: &ImageDescription,
+        scale: Scale<f64>,
+        clip: Option<(Rectangle<f64, Logical>, [u8; 4])>,
+    ) -> Self
+    where
+        R: AsGlowRenderer,
+    {
+        let mut uniforms = conversion_uniforms(description);
+        match clip {
+            Some((geometry, radius)) => {
+                uniforms.push(Uniform::new("clip", 1.));
+                uniforms.extend(clipping_uniforms(&elem, scale, geometry, radius));
+            }
+            None => uniforms.extend([
+                Uniform::new("clip", 0.),
+                Uniform::new("geo_size", (0., 0.)),
+                Uniform::new("corner_radius", [0., 0., 0., 0.]),
+                Uniform::new(
+                    "input_to_geo",
+                    UniformValue::Matrix3x3 {
+                        matrices: vec![[1., 0., 0., 0., 1., 0., 0., 0., 1.]],
+                        transpose: false,
+                    },
+                ),
+            ]),
+        }
+
+        Self {
+            inner: elem,
+            program: ColorConversionShader::get(renderer),
+            clip,
+            uniforms,
+        }
+    }
+}
+
+impl<R> Element for ColorConvertedSurfaceRenderElement<R>
+where
+    R: Renderer + ImportAll + ImportMem,
+{
+    fn id(&self) -> &Id {
+        self.inner.id()
+    }
+
+    fn current_commit(&self) -> CommitCounter {
+        self.inner.current_commit()
+    }
+
+    fn geometry(&self, scale: Scale<f64>) -> Rectangle<i32, Physical> {
+        self.inner.geometry(scale)
+    }
+
+    fn src(&self) -> Rectangle<f64, Buffer> {
+        self.inner.src()
+    }
+
+    fn transform(&self) -> Transform {
+        self.inner.transform()
+    }
+
+    fn damage_since(
+        &self,
+        scale: Scale<f64>,
+        commit: Option<CommitCounter>,
+    ) -> DamageSet<i32, Physical> {
+        let damage = self.inner.damage_since(scale, commit);
+        match self.clip {
+            Some((geometry, _)) => clip_damage(damage, self.geometry(scale).loc, geometry, scale),
+            None => damage,
+        }
+    }
+
+    fn opaque_regions(&self, scale: Scale<f64>) -> OpaqueRegions<i32, Physical> {
+        let regions = self.inner.opaque_regions(scale);
+        match self.clip {
+            Some((geometry, radius)) => {
+                clip_opaque_regions(regions, self.geometry(scale).loc, geometry, radius, scale)
+            }
+            None => regions,
+        }
+    }
+
+    fn alpha(&self) -> f32 {
+        self.inner.alpha()
+    }
+
+    fn kind(&self) -> Kind {
+        self.inner.kind()
+    }
+}
+
+impl<R> RenderElement<R> for ColorConvertedSurfaceRenderElement<R>
+where
+    R: AsGlowRenderer + Renderer + ImportAll + ImportMem,
+    R::TextureId: 'static,
+{
+    fn draw(
+        &self,
+        frame: &mut R::Frame<'_, '_>,
+        src: Rectangle<f64, Buffer>,
+        dst: Rectangle<i32, Physical>,
+        damage: &[Rectangle<i32, Physical>],
+        opaque_regions: &[Rectangle<i32, Physical>],
+    ) -> Result<(), R::Error> {
+        BorrowMut::<GlesFrame>::borrow_mut(<R as AsGlowRenderer>::glow_frame_mut(frame))
+            .override_default_tex_program(self.program.clone(), self.uniforms.clone());
+        self.inner.draw(frame, src, dst, damage, opaque_regions)?;
+        BorrowMut::<GlesFrame>::borrow_mut(<R as AsGlowRenderer>::glow_frame_mut(frame))
+            .clear_tex_program_override();
+        Ok(())
+    }
+
+    fn underlying_storage(&self, _renderer: &mut R) -> Option<UnderlyingStorage<'_>> {
+        // The buffer can't be scanned out directly, as it needs to be converted first
+        None
+    }
+}
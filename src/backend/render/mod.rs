@@ -16,6 +16,7 @@ use crate::{
         kms::render::gles::GbmGlowBackend,
         render::{
             clipped_surface::{CLIPPING_SHADER, ClippingShader},
+            color::{COLOR_CONVERSION_SHADER, ColorConversionShader, OutputColor},
             element::DamageElement,
             shadow::{SHADOW_SHADER, ShadowShader},
         },
@@ -30,7 +31,7 @@ use crate::{
         layout::tiling::ANIMATION_DURATION,
         zoom::ZoomState,
     },
-    utils::{prelude::*, quirks::workspace_overview_is_open},
+    utils::{night_light::temperature_to_rgb, prelude::*, quirks::workspace_overview_is_open},
     wayland::{
         handlers::{
             compositor::FRAME_TIME_FILTER,
@@ -82,6 +83,7 @@ use smithay_egui::EguiState;
 
 pub mod animations;
 pub mod clipped_surface;
+pub mod color;
 pub mod cursor;
 pub mod element;
 pub mod shadow;
@@ -136,6 +138,7 @@ pub enum Usage {
     PotentialGroupIndicator,
     SnappingIndicator,
     Border,
+    OverflowIndicator,
 }
 
 #[derive(Clone)]
@@ -414,6 +417,30 @@ pub fn init_shaders(renderer: &mut GlesRenderer) -> Result<(), GlesError> {
         &[
             UniformName::new("invert", UniformType::_1f),
             UniformName::new("color_mode", UniformType::_1f),
+            UniformName::new("color_temperature", UniformType::_3f),
+            UniformName::new("output_transfer", UniformType::_1f),
+            UniformName::new("sdr_white_level", UniformType::_1f),
+            UniformName::new("calibrate", UniformType::_1f),
+            UniformName::new("calibration_matrix", UniformType::Matrix3x3),
+            UniformName::new("trc_g", UniformType::_3f),
+            UniformName::new("trc_a", UniformType::_3f),
+            UniformName::new("trc_b", UniformType::_3f),
+            UniformName::new("trc_c", UniformType::_3f),
+            UniformName::new("trc_d", UniformType::_3f),
+            UniformName::new("trc_e", UniformType::_3f),
+            UniformName::new("trc_f", UniformType::_3f),
+        ],
+    )?;
+    let color_conversion_shader = renderer.compile_custom_texture_shader(
+        COLOR_CONVERSION_SHADER,
+        &[
+            UniformName::new("transfer_function", UniformType::_1f),
+            UniformName::new("luminance_scale", UniformType::_1f),
+            UniformName::new("to_srgb_primaries", UniformType::Matrix3x3),
+            UniformName::new("clip", UniformType::_1f),
+            UniformName::new("geo_size", UniformType::_2f),
+            UniformName::new("corner_radius", UniformType::_4f),
+            UniformName::new("input_to_geo", UniformType::Matrix3x3),
         ],
     )?;
     let clipping_shader = renderer.compile_custom_texture_shader(
@@ -454,6 +481,9 @@ pub fn init_shaders(renderer: &mut GlesRenderer) -> Result<(), GlesError> {
     egl_context
         .user_data()
         .insert_if_missing(|| ShadowShader(shadow_shader));
+    egl_context
+        .user_data()
+        .insert_if_missing(|| ColorConversionShader(color_conversion_shader));
 
     Ok(())
 }
@@ -1135,7 +1165,7 @@ impl PostprocessState {
     }
 }
 
-#[derive(Debug, PartialEq)]
+#[derive(Debug, Clone, PartialEq)]
 pub struct PostprocessOutputConfig {
     pub size: Size<i32, Physical>,
     pub fractional_scale: f64,
@@ -1167,6 +1197,31 @@ impl PostprocessOutputConfig {
     }
 }
 
+pub fn postprocess_uniforms(
+    screen_filter: &ScreenFilter,
+    output_color: &OutputColor,
+) -> Vec<Uniform<'static>> {
+    let mut uniforms = vec![
+        Uniform::new("invert", if screen_filter.inverted { 1. } else { 0. }),
+        Uniform::new(
+            "color_mode",
+            screen_filter
+                .color_filter
+                .map(|val| val as u8 as f32)
+                .unwrap_or(0.),
+        ),
+        Uniform::new(
+            "color_temperature",
+            screen_filter
+                .color_temperature
+                .map(temperature_to_rgb)
+                .unwrap_or([1.; 3]),
+        ),
+    ];
+    uniforms.extend(output_color.uniforms());
+    uniforms
+}
+
 #[derive(Debug, Default)]
 pub struct ScreenFilterStorage {
     pub filter: ScreenFilter,
@@ -1295,24 +1350,7 @@ where
                 let texture_elem = TextureShaderElement::new(
                     texture_elem,
                     postprocess_texture_shader.0.clone(),
-                    vec![
-                        Uniform::new(
-                            "invert",
-                            if screen_filter.filter.inverted {
-                                1.
-                            } else {
-                                0.
-                            },
-                        ),
-                        Uniform::new(
-                            "color_mode",
-                            screen_filter
-                                .filter
-                                .color_filter
-                                .map(|val| val as u8 as f32)
-                                .unwrap_or(0.),
-                        ),
-                    ],
+                    postprocess_uniforms(&screen_filter.filter, &OutputColor::default()),
                 );
                 constrain_render_elements(
                     std::iter::once(texture_elem),
@@ -0,0 +1,134 @@
+#version 100
+
+//_DEFINES_
+
+#if defined(EXTERNAL)
+#extension GL_OES_EGL_image_external : require
+#endif
+
+precision highp float;
+#if defined(EXTERNAL)
+uniform samplerExternalOES tex;
+#else
+uniform sampler2D tex;
+#endif
+
+uniform float alpha;
+varying vec2 v_coords;
+
+#if defined(DEBUG_FLAGS)
+uniform float tint;
+#endif
+
+// these values need to match with `TransferFunctionCode` in color.rs
+uniform float transfer_function;
+// scales decoded values, so that the reference white of the content maps to 1.0
+uniform float luminance_scale;
+// converts linear content to linear BT.709 / sRGB primaries
+uniform mat3 to_srgb_primaries;
+
+// rounded corner clipping, see clipped_surface.frag
+uniform float clip;
+uniform vec2 geo_size;
+uniform vec4 corner_radius;
+uniform mat3 input_to_geo;
+
+float rounding_alpha(vec2 coords, vec2 size) {
+    vec2 center;
+    float radius;
+
+    if (coords.x < corner_radius.x && coords.y < corner_radius.x) {
+        radius = corner_radius.x;
+        center = vec2(radius, radius);
+    } else if (size.x - corner_radius.y < coords.x && coords.y < corner_radius.y) {
+        radius = corner_radius.y;
+        center = vec2(size.x - radius, radius);
+    } else if (size.x - corner_radius.z < coords.x && size.y - corner_radius.z < coords.y) {
+        radius = corner_radius.z;
+        center = vec2(size.x - radius, size.y - radius);
+    } else if (coords.x < corner_radius.w && size.y - corner_radius.w < coords.y) {
+        radius = corner_radius.w;
+        center = vec2(radius, size.y - radius);
+    } else {
+        return 1.0;
+    }
+
+    float dist = distance(coords, center);
+    float half_px = 0.5;
+    return 1.0 - smoothstep(radius - half_px, radius + half_px, dist);
+}
+
+vec3 srgb_to_linear(vec3 color) {
+    vec3 c = abs(color);
+    vec3 linear = mix(c / 12.92, pow((c + 0.055) / 1.055, vec3(2.4)), step(0.04045, c));
+    return sign(color) * linear;
+}
+
+vec3 linear_to_srgb(vec3 color) {
+    vec3 c = abs(color);
+    vec3 encoded = mix(c * 12.92, 1.055 * pow(c, vec3(1.0 / 2.4)) - 0.055, step(0.0031308, c));
+    return sign(color) * encoded;
+}
+
+vec3 pq_to_linear(vec3 color) {
+    const float m1 = 0.1593017578125;
+    const float m2 = 78.84375;
+    const float c1 = 0.8359375;
+    const float c2 = 18.8515625;
+    const float c3 = 18.6875;
+
+    vec3 e = pow(clamp(color, 0.0, 1.0), vec3(1.0 / m2));
+    return pow(max(e - c1, 0.0) / (c2 - c3 * e), vec3(1.0 / m1));
+}
+
+void main() {
+    vec4 color = texture2D(tex, v_coords);
+#if defined(NO_ALPHA)
+    color = vec4(color.rgb, 1.0);
+#endif
+
+    // un-multiply
+    if (color.a > 0.0) {
+        color.rgb /= color.a;
+    }
+
+    vec3 linear;
+    if (transfer_function == 1.0) { // gamma 2.2
+        linear = pow(max(color.rgb, 0.0), vec3(2.2));
+    } else if (transfer_function == 2.0) { // BT.1886
+        linear = pow(max(color.rgb, 0.0), vec3(2.4));
+    } else if (transfer_function == 3.0) { // extended linear
+        linear = color.rgb;
+    } else if (transfer_function == 4.0) { // ST 2084 / PQ
+        linear = pq_to_linear(color.rgb);
+    } else { // sRGB
+        linear = srgb_to_linear(color.rgb);
+    }
+
+    linear = to_srgb_primaries * (linear * luminance_scale);
+    // values above 1.0 are kept for HDR outputs rendering into floating point buffers
+    color.rgb = linear_to_srgb(max(linear, 0.0));
+
+    // re-multiply
+    color.rgb *= color.a;
+
+    if (clip == 1.0) {
+        vec3 coords_geo = input_to_geo * vec3(v_coords, 1.0);
+        if (coords_geo.x < 0.0 || 1.0 < coords_geo.x || coords_geo.y < 0.0 || 1.0 < coords_geo.y) {
+            // Clip outside geometry.
+            color = vec4(0.0);
+        } else {
+            // Apply corner rounding inside geometry.
+            color = color * rounding_alpha(coords_geo.xy * geo_size, geo_size);
+        }
+    }
+
+    color = color * alpha;
+
+#if defined(DEBUG_FLAGS)
+    if (tint == 1.0)
+        color = vec4(0.0, 0.2, 0.0, 0.2) + color * 0.8;
+#endif
+
+    gl_FragColor = color;
+}
@@ -22,6 +22,46 @@ uniform float tint;
 
 uniform float invert;
 uniform float color_mode;
+// channel multipliers of the night light
+uniform vec3 color_temperature;
+// 0: passthrough, 1: encode for BT.2020 PQ outputs
+uniform float output_transfer;
+uniform float sdr_white_level;
+// calibration of SDR outputs from an ICC profile
+uniform float calibrate;
+uniform mat3 calibration_matrix;
+// tone reproduction curve `Y = (aX + b)^g + e` for `X >= d`, `Y = cX + f` otherwise
+uniform vec3 trc_g;
+uniform vec3 trc_a;
+uniform vec3 trc_b;
+uniform vec3 trc_c;
+uniform vec3 trc_d;
+uniform vec3 trc_e;
+uniform vec3 trc_f;
+
+vec3 srgb_to_linear(vec3 color) {
+    vec3 c = abs(color);
+    vec3 linear = mix(c / 12.92, pow((c + 0.055) / 1.055, vec3(2.4)), step(0.04045, c));
+    return sign(color) * linear;
+}
+
+vec3 linear_to_pq(vec3 color) {
+    const float m1 = 0.1593017578125;
+    const float m2 = 78.84375;
+    const float c1 = 0.8359375;
+    const float c2 = 18.8515625;
+    const float c3 = 18.6875;
+
+    vec3 p = pow(clamp(color, 0.0, 1.0), vec3(m1));
+    return pow((c1 + c2 * p) / (1.0 + c3 * p), vec3(m2));
+}
+
+// inverse of the tone reproduction curve, mapping linear light to device values
+vec3 linear_to_device(vec3 color) {
+    vec3 curve = (pow(max(color - trc_e, 0.0), 1.0 / trc_g) - trc_b) / trc_a;
+    vec3 segment = mix(trc_d, (color - trc_f) / max(trc_c, 0.0001), step(0.0001, trc_c));
+    return mix(segment, curve, step(trc_c * trc_d + trc_f, color));
+}
 
 void main() {
     vec4 color = texture2D(tex, v_coords);
@@ -83,6 +123,24 @@ void main() {
         color.rgb += correction;
     }
 
+    color.rgb *= color_temperature;
+
+    if (calibrate == 1.0) {
+        vec3 linear = calibration_matrix * srgb_to_linear(color.rgb);
+        color.rgb = clamp(linear_to_device(clamp(linear, 0.0, 1.0)), 0.0, 1.0);
+    }
+
+    if (output_transfer == 1.0) {
+        // composited content is sRGB encoded relative to SDR white, but may exceed 1.0
+        const mat3 bt709_to_bt2020 = mat3(
+            0.6274, 0.0691, 0.0164,
+            0.3293, 0.9195, 0.0880,
+            0.0433, 0.0114, 0.8956
+        );
+        vec3 linear = bt709_to_bt2020 * srgb_to_linear(color.rgb);
+        color.rgb = linear_to_pq(linear * sdr_white_level / 10000.0);
+    }
+
     // re-multiply
     color.rgb *= color.a;
 
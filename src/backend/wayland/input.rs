@@ -0,0 +1,409 @@
+// SPDX-License-Identifier: GPL-3.0-only
+
+use smithay::{
+    backend::input::{
+        AbsolutePositionEvent, Axis, AxisRelativeDirection, AxisSource, ButtonState, Device,
+        DeviceCapability, Event, InputBackend, KeyState, KeyboardKeyEvent, PointerAxisEvent,
+        PointerButtonEvent, TouchCancelEvent, TouchDownEvent, TouchEvent, TouchFrameEvent,
+        TouchMotionEvent, TouchSlot, TouchUpEvent, UnusedEvent,
+    },
+    input::keyboard::Keycode,
+    utils::{Logical, Size},
+};
+use std::path::PathBuf;
+
+/// Marker used to define the `InputBackend` types of the nested wayland backend.
+#[derive(Debug)]
+pub struct WaylandInput;
+
+/// Virtual input devices of the nested wayland backend.
+///
+/// Keyboard and pointer input of the host seat is reported through a single device,
+/// while every output gets its own touch device, so touch input can be mapped
+/// to the window it originated from.
+#[derive(Debug, Clone, PartialEq, Eq, Hash)]
+pub enum WaylandVirtualDevice {
+    Seat,
+    Touch(String),
+}
+
+impl Device for WaylandVirtualDevice {
+    fn id(&self) -> String {
+        match self {
+            WaylandVirtualDevice::Seat => "wayland-seat".to_string(),
+            WaylandVirtualDevice::Touch(output) => format!("wayland-touch-{}", output),
+        }
+    }
+
+    fn name(&self) -> String {
+        match self {
+            WaylandVirtualDevice::Seat => "Wayland virtual seat".to_string(),
+            WaylandVirtualDevice::Touch(output) => format!("Wayland virtual touch ({})", output),
+        }
+    }
+
+    fn has_capability(&self, capability: DeviceCapability) -> bool {
+        match self {
+            WaylandVirtualDevice::Seat => matches!(
+                capability,
+                DeviceCapability::Keyboard | DeviceCapability::Pointer
+            ),
+            WaylandVirtualDevice::Touch(_) => capability == DeviceCapability::Touch,
+        }
+    }
+
+    fn usb_id(&self) -> Option<(u32, u32)> {
+        None
+    }
+
+    fn syspath(&self) -> Option<PathBuf> {
+        None
+    }
+}
+
+impl InputBackend for WaylandInput {
+    type Device = WaylandVirtualDevice;
+
+    type KeyboardKeyEvent = WaylandKeyboardEvent;
+    type PointerAxisEvent = WaylandPointerAxisEvent;
+    type PointerButtonEvent = WaylandPointerButtonEvent;
+    type PointerMotionEvent = UnusedEvent;
+    type PointerMotionAbsoluteEvent = WaylandPointerMotionAbsoluteEvent;
+
+    type GestureSwipeBeginEvent = UnusedEvent;
+    type GestureSwipeUpdateEvent = UnusedEvent;
+    type GestureSwipeEndEvent = UnusedEvent;
+    type GesturePinchBeginEvent = UnusedEvent;
+    type GesturePinchUpdateEvent = UnusedEvent;
+    type GesturePinchEndEvent = UnusedEvent;
+    type GestureHoldBeginEvent = UnusedEvent;
+    type GestureHoldEndEvent = UnusedEvent;
+
+    type TouchDownEvent = WaylandTouchDownEvent;
+    type TouchUpEvent = WaylandTouchUpEvent;
+    type TouchMotionEvent = WaylandTouchMotionEvent;
+    type TouchCancelEvent = WaylandTouchCancelEvent;
+    type TouchFrameEvent = WaylandTouchFrameEvent;
+
+    type TabletToolAxisEvent = UnusedEvent;
+    type TabletToolProximityEvent = UnusedEvent;
+    type TabletToolTipEvent = UnusedEvent;
+    type TabletToolButtonEvent = UnusedEvent;
+
+    type SwitchToggleEvent = UnusedEvent;
+
+    type SpecialEvent = UnusedEvent;
+}
+
+#[derive(Debug, Clone)]
+pub struct WaylandKeyboardEvent {
+    pub(super) time: u32,
+    pub(super) key: u32,
+    pub(super) count: u32,
+    pub(super) state: KeyState,
+}
+
+impl Event<WaylandInput> for WaylandKeyboardEvent {
+    fn time(&self) -> u64 {
+        self.time as u64 * 1000
+    }
+
+    fn device(&self) -> WaylandVirtualDevice {
+        WaylandVirtualDevice::Seat
+    }
+}
+
+impl KeyboardKeyEvent<WaylandInput> for WaylandKeyboardEvent {
+    fn key_code(&self) -> Keycode {
+        // wl_keyboard reports evdev scancodes, xkb keycodes are offset by 8
+        Keycode::new(self.key + 8)
+    }
+
+    fn state(&self) -> KeyState {
+        self.state
+    }
+
+    fn count(&self) -> u32 {
+        self.count
+    }
+}
+
+#[derive(Debug, Clone)]
+pub struct WaylandPointerMotionAbsoluteEvent {
+    pub(super) time: u32,
+    pub(super) x: f64,
+    pub(super) y: f64,
+    pub(super) size: Size<i32, Logical>,
+}
+
+impl Event<WaylandInput> for WaylandPointerMotionAbsoluteEvent {
+    fn time(&self) -> u64 {
+        self.time as u64 * 1000
+    }
+
+    fn device(&self) -> WaylandVirtualDevice {
+        WaylandVirtualDevice::Seat
+    }
+}
+
+impl AbsolutePositionEvent<WaylandInput> for WaylandPointerMotionAbsoluteEvent {
+    fn x(&self) -> f64 {
+        self.x
+    }
+
+    fn y(&self) -> f64 {
+        self.y
+    }
+
+    fn x_transformed(&self, width: i32) -> f64 {
+        f64::max(self.x * width as f64 / self.size.w as f64, 0.0)
+    }
+
+    fn y_transformed(&self, height: i32) -> f64 {
+        f64::max(self.y * height as f64 / self.size.h as f64, 0.0)
+    }
+}
+
+#[derive(Debug, Clone)]
+pub struct WaylandPointerButtonEvent {
+    pub(super) time: u32,
+    pub(super) button: u32,
+    pub(super) state: ButtonState,
+}
+
+impl Event<WaylandInput> for WaylandPointerButtonEvent {
+    fn time(&self) -> u64 {
+        self.time as u64 * 1000
+    }
+
+    fn device(&self) -> WaylandVirtualDevice {
+        WaylandVirtualDevice::Seat
+    }
+}
+
+impl PointerButtonEvent<WaylandInput> for WaylandPointerButtonEvent {
+    fn button_code(&self) -> u32 {
+        self.button
+    }
+
+    fn state(&self) -> ButtonState {
+        self.state
+    }
+}
+
+/// Axis events of the host are accumulated until the next `wl_pointer.frame`.
+#[derive(Debug, Clone, Default)]
+pub struct WaylandPointerAxisEvent {
+    pub(super) time: u32,
+    pub(super) source: Option<AxisSource>,
+    pub(super) amount: [Option<f64>; 2],
+    pub(super) amount_v120: [Option<f64>; 2],
+}
+
+fn axis_index(axis: Axis) -> usize {
+    match axis {
+        Axis::Horizontal => 0,
+        Axis::Vertical => 1,
+    }
+}
+
+impl WaylandPointerAxisEvent {
+    pub(super) fn is_empty(&self) -> bool {
+        self.amount.iter().all(Option::is_none) && self.amount_v120.iter().all(Option::is_none)
+    }
+
+    pub(super) fn set_amount(&mut self, axis: Axis, amount: f64) {
+        self.amount[axis_index(axis)] = Some(amount);
+    }
+
+    pub(super) fn set_amount_v120(&mut self, axis: Axis, amount: f64) {
+        self.amount_v120[axis_index(axis)] = Some(amount);
+    }
+}
+
+impl Event<WaylandInput> for WaylandPointerAxisEvent {
+    fn time(&self) -> u64 {
+        self.time as u64 * 1000
+    }
+
+    fn device(&self) -> WaylandVirtualDevice {
+        WaylandVirtualDevice::Seat
+    }
+}
+
+impl PointerAxisEvent<WaylandInput> for WaylandPointerAxisEvent {
+    fn amount(&self, axis: Axis) -> Option<f64> {
+        self.amount[axis_index(axis)]
+    }
+
+    fn amount_v120(&self, axis: Axis) -> Option<f64> {
+        self.amount_v120[axis_index(axis)]
+    }
+
+    fn source(&self) -> AxisSource {
+        self.source.unwrap_or(AxisSource::Wheel)
+    }
+
+    fn relative_direction(&self, _axis: Axis) -> AxisRelativeDirection {
+        AxisRelativeDirection::Identical
+    }
+}
+
+#[derive(Debug, Clone)]
+pub struct WaylandTouchDownEvent {
+    pub(super) time: u32,
+    pub(super) output: String,
+    pub(super) slot: u32,
+    pub(super) x: f64,
+    pub(super) y: f64,
+    pub(super) size: Size<i32, Logical>,
+}
+
+impl Event<WaylandInput> for WaylandTouchDownEvent {
+    fn time(&self) -> u64 {
+        self.time as u64 * 1000
+    }
+
+    fn device(&self) -> WaylandVirtualDevice {
+        WaylandVirtualDevice::Touch(self.output.clone())
+    }
+}
+
+impl TouchEvent<WaylandInput> for WaylandTouchDownEvent {
+    fn slot(&self) -> TouchSlot {
+        Some(self.slot).into()
+    }
+}
+
+impl AbsolutePositionEvent<WaylandInput> for WaylandTouchDownEvent {
+    fn x(&self) -> f64 {
+        self.x
+    }
+
+    fn y(&self) -> f64 {
+        self.y
+    }
+
+    fn x_transformed(&self, width: i32) -> f64 {
+        f64::max(self.x * width as f64 / self.size.w as f64, 0.0)
+    }
+
+    fn y_transformed(&self, height: i32) -> f64 {
+        f64::max(self.y * height as f64 / self.size.h as f64, 0.0)
+    }
+}
+
+impl TouchDownEvent<WaylandInput> for WaylandTouchDownEvent {}
+
+#[derive(Debug, Clone)]
+pub struct WaylandTouchMotionEvent {
+    pub(super) time: u32,
+    pub(super) output: String,
+    pub(super) slot: u32,
+    pub(super) x: f64,
+    pub(super) y: f64,
+    pub(super) size: Size<i32, Logical>,
+}
+
+impl Event<WaylandInput> for WaylandTouchMotionEvent {
+    fn time(&self) -> u64 {
+        self.time as u64 * 1000
+    }
+
+    fn device(&self) -> WaylandVirtualDevice {
+        WaylandVirtualDevice::Touch(self.output.clone())
+    }
+}
+
+impl TouchEvent<WaylandInput> for WaylandTouchMotionEvent {
+    fn slot(&self) -> TouchSlot {
+        Some(self.slot).into()
+    }
+}
+
+impl AbsolutePositionEvent<WaylandInput> for WaylandTouchMotionEvent {
+    fn x(&self) -> f64 {
+        self.x
+    }
+
+    fn y(&self) -> f64 {
+        self.y
+    }
+
+    fn x_transformed(&self, width: i32) -> f64 {
+        f64::max(self.x * width as f64 / self.size.w as f64, 0.0)
+    }
+
+    fn y_transformed(&self, height: i32) -> f64 {
+        f64::max(self.y * height as f64 / self.size.h as f64, 0.0)
+    }
+}
+
+impl TouchMotionEvent<WaylandInput> for WaylandTouchMotionEvent {}
+
+#[derive(Debug, Clone)]
+pub struct WaylandTouchUpEvent {
+    pub(super) time: u32,
+    pub(super) output: String,
+    pub(super) slot: u32,
+}
+
+impl Event<WaylandInput> for WaylandTouchUpEvent {
+    fn time(&self) -> u64 {
+        self.time as u64 * 1000
+    }
+
+    fn device(&self) -> WaylandVirtualDevice {
+        WaylandVirtualDevice::Touch(self.output.clone())
+    }
+}
+
+impl TouchEvent<WaylandInput> for WaylandTouchUpEvent {
+    fn slot(&self) -> TouchSlot {
+        Some(self.slot).into()
+    }
+}
+
+impl TouchUpEvent<WaylandInput> for WaylandTouchUpEvent {}
+
+#[derive(Debug, Clone)]
+pub struct WaylandTouchCancelEvent {
+    pub(super) time: u32,
+    pub(super) output: String,
+}
+
+impl Event<WaylandInput> for WaylandTouchCancelEvent {
+    fn time(&self) -> u64 {
+        self.time as u64 * 1000
+    }
+
+    fn device(&self) -> WaylandVirtualDevice {
+        WaylandVirtualDevice::Touch(self.output.clone())
+    }
+}
+
+impl TouchEvent<WaylandInput> for WaylandTouchCancelEvent {
+    fn slot(&self) -> TouchSlot {
+        None.into()
+    }
+}
+
+impl TouchCancelEvent<WaylandInput> for WaylandTouchCancelEvent {}
+
+#[derive(Debug, Clone)]
+pub struct WaylandTouchFrameEvent {
+    pub(super) time: u32,
+    pub(super) output: String,
+}
+
+impl Event<WaylandInput> for WaylandTouchFrameEvent {
+    fn time(&self) -> u64 {
+        self.time as u64 * 1000
+    }
+
+    fn device(&self) -> WaylandVirtualDevice {
+        WaylandVirtualDevice::Touch(self.output.clone())
+    }
+}
+
+impl TouchFrameEvent<WaylandInput> for WaylandTouchFrameEvent {}
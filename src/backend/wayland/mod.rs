@@ -0,0 +1,1378 @@
+// SPDX-License-Identifier: GPL-3.0-only
+
+use crate::{
+    backend::render,
+    config::ScreenFilter,
+    shell::{Devices, SeatExt},
+    state::{BackendData, Common},
+    utils::prelude::*,
+};
+use anyhow::{Context, Result, anyhow};
+use calloop_wayland_source::WaylandSource;
+use cosmic_comp_config::output::comp::OutputConfig;
+use smithay::{
+    backend::{
+        allocator::{
+            Buffer, Format, Fourcc, Modifier, Slot, Swapchain,
+            dmabuf::{Dmabuf, DmabufAllocator},
+            gbm::{GbmAllocator, GbmBufferFlags},
+        },
+        drm::{DrmDeviceFd, DrmNode, NodeType},
+        egl::{EGLContext, EGLDevice, EGLDisplay},
+        input::{Axis, AxisSource, ButtonState, InputEvent, KeyState},
+        renderer::{
+            Bind,
+            damage::{OutputDamageTracker, RenderOutputResult},
+            glow::GlowRenderer,
+        },
+    },
+    desktop::layer_map_for_output,
+    output::{Mode, Output, PhysicalProperties, Scale, Subpixel},
+    reexports::{
+        calloop::{
+            EventLoop, LoopHandle,
+            timer::{TimeoutAction, Timer},
+        },
+        gbm::Device as GbmDevice,
+        wayland_protocols::wp::presentation_time::server::wp_presentation_feedback,
+        wayland_server::DisplayHandle,
+    },
+    utils::{DeviceFd, Logical, Size, Transform},
+    wayland::presentation::Refresh,
+};
+use std::{
+    borrow::BorrowMut,
+    cell::RefCell,
+    collections::HashMap,
+    fs::{File, OpenOptions},
+    os::unix::{fs::FileExt, io::OwnedFd},
+    sync::Mutex,
+    time::Duration,
+};
+use tracing::{error, info, warn};
+use wayland_client::{
+    Connection, Dispatch, Proxy, QueueHandle, WEnum, delegate_noop,
+    globals::{GlobalListContents, registry_queue_init},
+    protocol::{
+        wl_buffer::{self, WlBuffer},
+        wl_callback::{self, WlCallback},
+        wl_compositor::WlCompositor,
+        wl_keyboard::{self, WlKeyboard},
+        wl_pointer::{self, WlPointer},
+        wl_registry::{self, WlRegistry},
+        wl_seat::{self, WlSeat},
+        wl_surface::WlSurface,
+        wl_touch::{self, WlTouch},
+    },
+};
+use wayland_protocols::{
+    wp::linux_dmabuf::zv1::client::{
+        zwp_linux_buffer_params_v1::{self, ZwpLinuxBufferParamsV1},
+        zwp_linux_dmabuf_feedback_v1::{self, ZwpLinuxDmabufFeedbackV1},
+        zwp_linux_dmabuf_v1::ZwpLinuxDmabufV1,
+    },
+    xdg::{
+        decoration::zv1::client::{
+            zxdg_decoration_manager_v1::ZxdgDecorationManagerV1,
+            zxdg_toplevel_decoration_v1::{self, ZxdgToplevelDecorationV1},
+        },
+        shell::client::{
+            xdg_surface::{self, XdgSurface},
+            xdg_toplevel::{self, XdgToplevel},
+            xdg_wm_base::{self, XdgWmBase},
+        },
+    },
+};
+
+use super::render::{ScreenFilterStorage, init_shaders};
+
+mod input;
+pub use self::input::*;
+
+const DEFAULT_SIZE: (i32, i32) = (1280, 800);
+
+#[derive(Debug)]
+pub struct WaylandState {
+    queue_handle: QueueHandle<State>,
+    compositor: WlCompositor,
+    xdg_wm_base: XdgWmBase,
+    linux_dmabuf: ZwpLinuxDmabufV1,
+    decoration_manager: Option<ZxdgDecorationManagerV1>,
+    _seat: Option<WlSeat>,
+    keyboard: Option<WlKeyboard>,
+    pointer: Option<WlPointer>,
+    touch: Option<WlTouch>,
+    input: InputState,
+
+    allocator: GbmAllocator<DrmDeviceFd>,
+    format: Fourcc,
+    modifiers: Vec<Modifier>,
+    _egl: EGLDisplay,
+    pub renderer: GlowRenderer,
+    surfaces: Vec<Surface>,
+    next_output: usize,
+    loop_handle: LoopHandle<'static, State>,
+}
+
+#[derive(Debug, Default)]
+struct InputState {
+    seat_added: bool,
+    pressed_keys: Vec<u32>,
+    keyboard_focus: Option<WlSurface>,
+    pointer_focus: Option<WlSurface>,
+    pending_axis: WaylandPointerAxisEvent,
+    touch_points: HashMap<i32, WlSurface>,
+    last_touch_output: Option<String>,
+    last_time: u32,
+}
+
+#[derive(Debug)]
+pub struct Surface {
+    output: Output,
+    wl_surface: WlSurface,
+    xdg_surface: XdgSurface,
+    xdg_toplevel: XdgToplevel,
+    decoration: Option<ZxdgToplevelDecorationV1>,
+    swapchain: Swapchain<DmabufAllocator<GbmAllocator<DrmDeviceFd>>>,
+    // buffers attached to the host surface, that haven't been released yet
+    in_flight: Vec<(WlBuffer, Slot<Dmabuf>)>,
+    damage_tracker: OutputDamageTracker,
+    pending_size: Option<Size<i32, Logical>>,
+    configured: bool,
+    dirty: bool,
+    pending: bool,
+    screen_filter_state: ScreenFilterStorage,
+}
+
+/// `wl_buffer` of the host, that is cached with the swapchain slot it was created for.
+#[derive(Debug)]
+struct HostBuffer(WlBuffer);
+
+impl Drop for HostBuffer {
+    fn drop(&mut self) {
+        self.0.destroy();
+    }
+}
+
+/// Contents of the default dmabuf feedback of the host compositor.
+#[derive(Debug, Default)]
+struct HostFeedback {
+    main_device: Option<u64>,
+    formats: Vec<(u32, u64)>,
+    done: bool,
+}
+
+impl WaylandState {
+    pub fn add_output(&mut self, size: Size<i32, Logical>) -> Output {
+        let name = format!("WL-{}", self.next_output);
+        self.next_output += 1;
+
+        let qh = &self.queue_handle;
+        let wl_surface = self.compositor.create_surface(qh, ());
+        let xdg_surface = self.xdg_wm_base.get_xdg_surface(&wl_surface, qh, ());
+        let xdg_toplevel = xdg_surface.get_toplevel(qh, ());
+        xdg_toplevel.set_title(format!("COSMIC ({})", name));
+        xdg_toplevel.set_app_id("com.system76.CosmicComp".to_string());
+        let decoration = self.decoration_manager.as_ref().map(|manager| {
+            let decoration = manager.get_toplevel_decoration(&xdg_toplevel, qh, ());
+            decoration.set_mode(zxdg_toplevel_decoration_v1::Mode::ServerSide);
+            decoration
+        });
+        // initial commit without a buffer, we may only render after the first configure
+        wl_surface.commit();
+
+        let props = PhysicalProperties {
+            size: (0, 0).into(),
+            subpixel: Subpixel::Unknown,
+            make: "COSMIC".to_string(),
+            model: name.clone(),
+            serial_number: "Unknown".to_string(),
+        };
+        let mode = Mode {
+            size: (size.w, size.h).into(),
+            refresh: 60_000,
+        };
+        let output = Output::new(name, props);
+        output.add_mode(mode);
+        output.set_preferred(mode);
+        output.change_current_state(
+            Some(mode),
+            Some(Transform::Normal),
+            Some(Scale::Integer(1)),
+            Some((0, 0).into()),
+        );
+        output.user_data().insert_if_missing(|| {
+            RefCell::new(OutputConfig {
+                mode: ((size.w, size.h), None),
+                ..Default::default()
+            })
+        });
+
+        self.surfaces.push(Surface {
+            output: output.clone(),
+            wl_surface,
+            xdg_surface,
+            xdg_toplevel,
+            decoration,
+            swapchain: Swapchain::new(
+                DmabufAllocator(self.allocator.clone()),
+                size.w as u32,
+                size.h as u32,
+                self.format,
+                self.modifiers.clone(),
+            ),
+            in_flight: Vec::new(),
+            damage_tracker: OutputDamageTracker::from_output(&output),
+            pending_size: None,
+            configured: false,
+            dirty: true,
+            pending: false,
+            screen_filter_state: ScreenFilterStorage::default(),
+        });
+
+        output
+    }
+
+    pub fn schedule_render(&mut self, output: &Output) {
+        if let Some(surface) = self.surfaces.iter_mut().find(|s| s.output == *output) {
+            surface.dirty = true;
+            if !surface.pending && surface.configured {
+                surface.pending = true;
+                let output = output.clone();
+                self.loop_handle.insert_idle(move |state| {
+                    state
+                        .backend
+                        .wayland()
+                        .render_output(&output, &mut state.common);
+                });
+            }
+        }
+    }
+
+    fn render_output(&mut self, output: &Output, state: &mut Common) {
+        let Some(surface) = self.surfaces.iter_mut().find(|s| s.output == *output) else {
+            return;
+        };
+
+        let committed = match surface.render_output(
+            &mut self.renderer,
+            &self.linux_dmabuf,
+            &self.queue_handle,
+            state,
+        ) {
+            Ok(committed) => committed,
+            Err(err) => {
+                error!(?err, "Error rendering.");
+                false
+            }
+        };
+        surface.dirty = false;
+        surface.pending = true;
+
+        if !committed {
+            // Nothing was submitted, so the host won't send us a frame callback.
+            // Emulate one using the refresh rate of the current mode.
+            let refresh = output
+                .current_mode()
+                .map(|mode| Duration::from_secs_f64(1_000.0 / mode.refresh as f64))
+                .unwrap_or(Duration::from_millis(16));
+            let output = output.clone();
+            if let Err(err) =
+                self.loop_handle
+                    .insert_source(Timer::from_duration(refresh), move |_, _, state| {
+                        state
+                            .backend
+                            .wayland()
+                            .frame_done(&output, &mut state.common);
+                        TimeoutAction::Drop
+                    })
+            {
+                warn!(?err, "Failed to schedule frame timer.");
+                surface.pending = false;
+            }
+        }
+    }
+
+    fn frame_done(&mut self, output: &Output, state: &mut Common) {
+        let Some(surface) = self.surfaces.iter_mut().find(|s| s.output == *output) else {
+            return;
+        };
+
+        if surface.dirty {
+            self.render_output(output, state);
+        } else {
+            surface.pending = false;
+        }
+    }
+
+    pub fn all_outputs(&self) -> Vec<Output> {
+        self.surfaces.iter().map(|s| s.output.clone()).collect()
+    }
+
+    pub fn apply_config_for_outputs(&mut self, test_only: bool) -> Result<(), anyhow::Error> {
+        if test_only {
+            return Ok(());
+        }
+
+        // We decide the size of our windows, so any mode can be used.
+        for surface in &mut self.surfaces {
+            let Some(mode) = surface.output.current_mode() else {
+                continue;
+            };
+            if !surface.output.modes().contains(&mode) {
+                surface.output.add_mode(mode);
+            }
+            surface.output.set_preferred(mode);
+            surface
+                .swapchain
+                .resize(mode.size.w as u32, mode.size.h as u32);
+            surface.dirty = true;
+        }
+
+        Ok(())
+    }
+
+    pub fn update_screen_filter(&mut self, screen_filter: &ScreenFilter) -> Result<()> {
+        for surface in &mut self.surfaces {
+            surface.screen_filter_state.filter = screen_filter.clone();
+        }
+        Ok(())
+    }
+
+    fn surface_for(&self, wl_surface: &WlSurface) -> Option<&Surface> {
+        self.surfaces.iter().find(|s| s.wl_surface == *wl_surface)
+    }
+}
+
+impl Surface {
+    fn render_output(
+        &mut self,
+        renderer: &mut GlowRenderer,
+        linux_dmabuf: &ZwpLinuxDmabufV1,
+        qh: &QueueHandle<State>,
+        state: &mut Common,
+    ) -> Result<bool> {
+        let slot = self
+            .swapchain
+            .acquire()
+            .with_context(|| "Failed to allocate buffer")?
+            .with_context(|| "No free buffer available")?;
+        let age = slot.age() as usize;
+        let mut dmabuf = (*slot).clone();
+        let mut fb = renderer
+            .bind(&mut dmabuf)
+            .with_context(|| "Failed to bind dmabuf")?;
+
+        match render::render_output(
+            None,
+            renderer,
+            &mut fb,
+            &mut self.damage_tracker,
+            age,
+            &state.shell,
+            state.clock.now(),
+            &self.output,
+            render::CursorMode::NotDefault,
+            &mut self.screen_filter_state,
+            &state.event_loop_handle,
+        ) {
+            Ok(RenderOutputResult { damage, states, .. }) => {
+                std::mem::drop(fb);
+                let committed = if let Some(damage) = damage {
+                    let buffer = host_buffer(&slot, linux_dmabuf, qh);
+                    self.wl_surface.attach(Some(&buffer), 0, 0);
+                    for rect in damage {
+                        self.wl_surface.damage_buffer(
+                            rect.loc.x,
+                            rect.loc.y,
+                            rect.size.w,
+                            rect.size.h,
+                        );
+                    }
+                    self.wl_surface.frame(qh, self.wl_surface.clone());
+                    self.wl_surface.commit();
+                    self.swapchain.submitted(&slot);
+                    self.in_flight.push((buffer, slot));
+                    true
+                } else {
+                    false
+                };
+
+                state.send_frames(&self.output, None);
+                state.update_primary_output(&self.output, &states);
+                state.send_dmabuf_feedback(&self.output, &states, |_| None);
+                if damage.is_some() {
+                    let mut output_presentation_feedback = state
+                        .shell
+                        .read()
+                        .take_presentation_feedback(&self.output, &states);
+                    output_presentation_feedback.presented(
+                        state.clock.now(),
+                        self.output
+                            .current_mode()
+                            .map(|mode| {
+                                Refresh::Fixed(Duration::from_secs_f64(
+                                    1_000.0 / mode.refresh as f64,
+                                ))
+                            })
+                            .unwrap_or(Refresh::Unknown),
+                        0,
+                        wp_presentation_feedback::Kind::Vsync,
+                    )
+                }
+
+                Ok(committed)
+            }
+            Err(err) => {
+                self.swapchain.reset_buffers();
+                anyhow::bail!("Rendering failed: {}", err);
+            }
+        }
+    }
+}
+
+impl Drop for Surface {
+    fn drop(&mut self) {
+        if let Some(decoration) = self.decoration.take() {
+            decoration.destroy();
+        }
+        self.xdg_toplevel.destroy();
+        self.xdg_surface.destroy();
+        self.wl_surface.destroy();
+    }
+}
+
+/// Returns the host `wl_buffer` for a swapchain slot, importing it via linux-dmabuf if necessary.
+fn host_buffer(
+    slot: &Slot<Dmabuf>,
+    linux_dmabuf: &ZwpLinuxDmabufV1,
+    qh: &QueueHandle<State>,
+) -> WlBuffer {
+    if let Some(buffer) = slot.userdata().get::<HostBuffer>() {
+        return buffer.0.clone();
+    }
+
+    let dmabuf: &Dmabuf = slot;
+    let format = dmabuf.format();
+    let modifier = u64::from(format.modifier);
+    let params = linux_dmabuf.create_params(qh, ());
+    for (idx, ((fd, offset), stride)) in dmabuf
+        .handles()
+        .zip(dmabuf.offsets())
+        .zip(dmabuf.strides())
+        .enumerate()
+    {
+        params.add(
+            fd,
+            idx as u32,
+            offset,
+            stride,
+            (modifier >> 32) as u32,
+            (modifier & 0xffff_ffff) as u32,
+        );
+    }
+    let size = dmabuf.size();
+    let buffer = params.create_immed(
+        size.w,
+        size.h,
+        format.code as u32,
+        zwp_linux_buffer_params_v1::Flags::empty(),
+        qh,
+        (),
+    );
+    params.destroy();
+
+    slot.userdata()
+        .insert_if_missing_threadsafe(|| HostBuffer(buffer.clone()));
+    buffer
+}
+
+pub fn init_backend(
+    dh: &DisplayHandle,
+    event_loop: &mut EventLoop<'static, State>,
+    state: &mut State,
+) -> Result<()> {
+    let connection =
+        Connection::connect_to_env().with_context(|| "Failed to connect to wayland compositor")?;
+    let (globals, mut queue) = registry_queue_init::<State>(&connection)
+        .with_context(|| "Failed to retrieve wayland globals")?;
+    let qh = queue.handle();
+
+    let compositor = globals
+        .bind::<WlCompositor, _, _>(&qh, 4..=6, ())
+        .with_context(|| "Host compositor doesn't support wl_compositor v4")?;
+    let xdg_wm_base = globals
+        .bind::<XdgWmBase, _, _>(&qh, 1..=5, ())
+        .with_context(|| "Host compositor doesn't support xdg_wm_base")?;
+    let linux_dmabuf = globals
+        .bind::<ZwpLinuxDmabufV1, _, _>(&qh, 4..=4, ())
+        .with_context(|| "Host compositor doesn't support zwp_linux_dmabuf_v1 v4")?;
+    let decoration_manager = globals
+        .bind::<ZxdgDecorationManagerV1, _, _>(&qh, 1..=1, ())
+        .ok();
+
+    // Figure out which device the host composites on, so our buffers can be passed through.
+    let feedback = linux_dmabuf.get_default_feedback(&qh, Mutex::new(HostFeedback::default()));
+    let host_feedback = loop {
+        queue
+            .blocking_dispatch(state)
+            .with_context(|| "Failed to receive dmabuf feedback")?;
+        let mut data = feedback
+            .data::<Mutex<HostFeedback>>()
+            .unwrap()
+            .lock()
+            .unwrap();
+        if data.done {
+            break std::mem::take(&mut *data);
+        }
+    };
+    feedback.destroy();
+
+    let main_device = host_feedback
+        .main_device
+        .with_context(|| "Host compositor didn't advertise a main device")?;
+    let node = DrmNode::from_dev_id(main_device)
+        .with_context(|| "Failed to find DRM node of the host compositor")?;
+    let render_node = node
+        .node_with_type(NodeType::Render)
+        .and_then(Result::ok)
+        .unwrap_or(node);
+    let fd: OwnedFd = OpenOptions::new()
+        .read(true)
+        .write(true)
+        .open(
+            render_node
+                .dev_path()
+                .with_context(|| format!("Could not determine path for node {}", render_node))?,
+        )
+        .with_context(|| format!("Failed to open node {}", render_node))?
+        .into();
+
+    let device = EGLDevice::enumerate()
+        .with_context(|| "Failed to enumerate EGL devices")?
+        .find(|device| device.try_get_render_node().ok().flatten() == Some(render_node))
+        .with_context(|| format!("Failed to find EGLDevice for node {}", render_node))?;
+    // Initialize EGL
+    let egl = unsafe { EGLDisplay::new(device) }.with_context(|| "Failed to create EGL display")?;
+    // Create the OpenGL context
+    let context = EGLContext::new(&egl).with_context(|| "Failed to create EGL context")?;
+    // Create a renderer
+    let mut renderer =
+        unsafe { GlowRenderer::new(context) }.with_context(|| "Failed to initialize renderer")?;
+
+    init_shaders(renderer.borrow_mut()).context("Failed to initialize renderer")?;
+
+    // Pick a format both the host can sample from and we can render to.
+    let render_formats = Bind::<Dmabuf>::supported_formats(&renderer)
+        .with_context(|| "Renderer doesn't support rendering to dmabufs")?;
+    let (format, modifiers) = [Fourcc::Xrgb8888, Fourcc::Argb8888]
+        .into_iter()
+        .find_map(|code| {
+            let modifiers = host_feedback
+                .formats
+                .iter()
+                .filter(|(format, _)| *format == code as u32)
+                .map(|(_, modifier)| Modifier::from(*modifier))
+                .filter(|modifier| {
+                    render_formats.iter().any(|format| {
+                        *format
+                            == Format {
+                                code,
+                                modifier: *modifier,
+                            }
+                    })
+                })
+                .collect::<Vec<_>>();
+            (!modifiers.is_empty()).then_some((code, modifiers))
+        })
+        .with_context(|| "No common buffer format with the host compositor")?;
+
+    let gbm = GbmDevice::new(DrmDeviceFd::new(DeviceFd::from(fd)))
+        .with_context(|| "Failed to create GBM device")?;
+    super::x11::init_egl_client_side(dh, state, render_node, &mut renderer)?;
+    let seat = globals
+        .bind::<WlSeat, _, _>(&qh, 5..=8, ())
+        .map_err(|err| {
+            warn!(
+                ?err,
+                "Host compositor has no usable seat, input is disabled."
+            )
+        })
+        .ok();
+
+    let num_outputs = std::env::var("COSMIC_WAYLAND_OUTPUTS")
+        .ok()
+        .and_then(|val| val.trim().parse::<usize>().ok())
+        .unwrap_or(1)
+        .max(1);
+
+    let mut wayland_state = WaylandState {
+        queue_handle: qh,
+        compositor,
+        xdg_wm_base,
+        linux_dmabuf,
+        decoration_manager,
+        _seat: seat,
+        keyboard: None,
+        pointer: None,
+        touch: None,
+        input: InputState::default(),
+        allocator: GbmAllocator::new(gbm, GbmBufferFlags::RENDERING),
+        format,
+        modifiers,
+        _egl: egl,
+        renderer,
+        surfaces: Vec::new(),
+        next_output: 0,
+        loop_handle: event_loop.handle(),
+    };
+    let outputs = (0..num_outputs)
+        .map(|_| wayland_state.add_output(DEFAULT_SIZE.into()))
+        .collect::<Vec<_>>();
+    state.backend = BackendData::Wayland(wayland_state);
+
+    info!(
+        "Running nested with {} output(s) using {:?} buffers",
+        num_outputs, format
+    );
+
+    WaylandSource::new(connection, queue)
+        .insert(event_loop.handle())
+        .map_err(|_| anyhow!("Failed to insert wayland source into event loop"))?;
+
+    state
+        .common
+        .output_configuration_state
+        .add_heads(outputs.iter());
+    {
+        for output in &outputs {
+            state.common.add_output(output);
+        }
+        if let Err(err) = state.common.config.read_outputs(
+            &mut state.common.output_configuration_state,
+            &mut state.backend,
+            &state.common.shell,
+            &state.common.event_loop_handle,
+            &mut state.common.workspace_state.update(),
+            &state.common.xdg_activation_state,
+            state.common.startup_done.clone(),
+            &state.common.clock,
+        ) {
+            error!("Unrecoverable output configuration error: {}", err);
+        }
+        state.common.refresh();
+    }
+
+    if state.common.with_xwayland {
+        state.launch_xwayland(None);
+    } else {
+        state.notify_ready();
+    }
+
+    Ok(())
+}
+
+impl State {
+    pub fn process_wayland_event(&mut self, event: InputEvent<WaylandInput>) {
+        let output = self.wayland_event_output(&event);
+        self.process_input_event(event);
+        match output {
+            Some(output) => self.backend.wayland().schedule_render(&output),
+            None => {
+                for output in self.common.shell.read().outputs() {
+                    self.backend.wayland().schedule_render(output);
+                }
+            }
+        }
+    }
+
+    /// Output of the host surface an input event was sent to
+    fn wayland_event_output(&mut self, event: &InputEvent<WaylandInput>) -> Option<Output> {
+        let backend = self.backend.wayland();
+        let surface = match event {
+            InputEvent::Keyboard { .. } => backend
+                .input
+                .keyboard_focus
+                .as_ref()
+                .and_then(|wl_surface| backend.surface_for(wl_surface)),
+            InputEvent::PointerMotionAbsolute { .. }
+            | InputEvent::PointerButton { .. }
+            | InputEvent::PointerAxis { .. } => backend
+                .input
+                .pointer_focus
+                .as_ref()
+                .and_then(|wl_surface| backend.surface_for(wl_surface)),
+            InputEvent::TouchDown {
+                event: WaylandTouchDownEvent { output, .. },
+            }
+            | InputEvent::TouchMotion {
+                event: WaylandTouchMotionEvent { output, .. },
+            }
+            | InputEvent::TouchUp {
+                event: WaylandTouchUpEvent { output, .. },
+            }
+            | InputEvent::TouchFrame {
+                event: WaylandTouchFrameEvent { output, .. },
+            }
+            | InputEvent::TouchCancel {
+                event: WaylandTouchCancelEvent { output, .. },
+            } => backend
+                .surfaces
+                .iter()
+                .find(|surface| surface.output.name() == *output),
+            _ => None,
+        };
+        surface.map(|surface| surface.output.clone())
+    }
+
+    fn set_wayland_active_output(&mut self, wl_surface: &WlSurface) {
+        let Some(output) = self
+            .backend
+            .wayland()
+            .surface_for(wl_surface)
+            .map(|surface| surface.output.clone())
+        else {
+            return;
+        };
+
+        for seat in self.common.shell.read().seats.iter() {
+            let devices = seat.user_data().get::<Devices>().unwrap();
+            if devices.has_device(&WaylandVirtualDevice::Seat) {
+                seat.set_active_output(&output);
+                break;
+            }
+        }
+    }
+
+    /// Output name and size in surface-local coordinates of a host surface
+    fn wayland_surface_info(
+        &mut self,
+        wl_surface: &WlSurface,
+    ) -> Option<(String, Size<i32, Logical>)> {
+        self.backend
+            .wayland()
+            .surface_for(wl_surface)
+            .map(|surface| {
+                let size = surface
+                    .output
+                    .current_mode()
+                    .map(|mode| (mode.size.w, mode.size.h))
+                    .unwrap_or(DEFAULT_SIZE);
+                (surface.output.name(), size.into())
+            })
+    }
+
+    fn process_wayland_pointer_motion(&mut self, time: u32, x: f64, y: f64) {
+        let Some(wl_surface) = self.backend.wayland().input.pointer_focus.clone() else {
+            return;
+        };
+        let Some((_, size)) = self.wayland_surface_info(&wl_surface) else {
+            return;
+        };
+
+        self.process_wayland_event(InputEvent::PointerMotionAbsolute {
+            event: WaylandPointerMotionAbsoluteEvent { time, x, y, size },
+        });
+    }
+
+    fn resize_wayland_output(&mut self, wl_surface: &WlSurface, size: Size<i32, Logical>) {
+        let Some(surface) = self
+            .backend
+            .wayland()
+            .surfaces
+            .iter_mut()
+            .find(|s| s.wl_surface == *wl_surface)
+        else {
+            return;
+        };
+
+        let output = &surface.output;
+        let mode = Mode {
+            size: (size.w, size.h).into(),
+            refresh: 60_000,
+        };
+        if output.current_mode() == Some(mode) {
+            return;
+        }
+
+        {
+            let mut config = output
+                .user_data()
+                .get::<RefCell<OutputConfig>>()
+                .unwrap()
+                .borrow_mut();
+            config.mode.0 = (size.w, size.h);
+        }
+        if let Some(current_mode) = output.current_mode() {
+            output.delete_mode(current_mode);
+        }
+        output.change_current_state(Some(mode), None, None, None);
+        output.set_preferred(mode);
+        layer_map_for_output(output).arrange();
+        surface.swapchain.resize(size.w as u32, size.h as u32);
+        surface.dirty = true;
+        self.common.output_configuration_state.update();
+    }
+
+    fn close_wayland_output(&mut self, wl_surface: &WlSurface) {
+        let wayland_state = self.backend.wayland();
+        let Some(idx) = wayland_state
+            .surfaces
+            .iter()
+            .position(|s| s.wl_surface == *wl_surface)
+        else {
+            return;
+        };
+        let surface = wayland_state.surfaces.remove(idx);
+        let has_touch = wayland_state.touch.is_some();
+        let is_last = wayland_state.surfaces.is_empty();
+        let output = surface.output.clone();
+        std::mem::drop(surface);
+
+        if has_touch {
+            self.process_input_event(InputEvent::<WaylandInput>::DeviceRemoved {
+                device: WaylandVirtualDevice::Touch(output.name()),
+            });
+        }
+        self.common.remove_output(&output);
+        if is_last {
+            self.common.should_stop = true;
+        }
+    }
+}
+
+impl Dispatch<WlRegistry, GlobalListContents> for State {
+    fn event(
+        _state: &mut Self,
+        _registry: &WlRegistry,
+        _event: wl_registry::Event,
+        _data: &GlobalListContents,
+        _conn: &Connection,
+        _qh: &QueueHandle<Self>,
+    ) {
+        // we don't care about globals of the host changing after initialization
+    }
+}
+
+impl Dispatch<ZwpLinuxDmabufFeedbackV1, Mutex<HostFeedback>> for State {
+    fn event(
+        _state: &mut Self,
+        _feedback: &ZwpLinuxDmabufFeedbackV1,
+        event: zwp_linux_dmabuf_feedback_v1::Event,
+        data: &Mutex<HostFeedback>,
+        _conn: &Connection,
+        _qh: &QueueHandle<Self>,
+    ) {
+        let mut data = data.lock().unwrap();
+        match event {
+            zwp_linux_dmabuf_feedback_v1::Event::FormatTable { fd, size } => {
+                // every entry is a 32-bit format, 32-bit padding and a 64-bit modifier
+                let mut table = vec![0u8; size as usize];
+                match File::from(fd).read_exact_at(&mut table, 0) {
+                    Ok(()) => {
+                        data.formats = table
+                            .chunks_exact(16)
+                            .map(|entry| {
+                                (
+                                    u32::from_ne_bytes(entry[0..4].try_into().unwrap()),
+                                    u64::from_ne_bytes(entry[8..16].try_into().unwrap()),
+                                )
+                            })
+                            .collect();
+                    }
+                    Err(err) => warn!(?err, "Failed to read dmabuf format table of the host."),
+                }
+            }
+            zwp_linux_dmabuf_feedback_v1::Event::MainDevice { device } => {
+                data.main_device = <[u8; 8]>::try_from(device).ok().map(u64::from_ne_bytes);
+            }
+            zwp_linux_dmabuf_feedback_v1::Event::Done => {
+                data.done = true;
+            }
+            _ => {}
+        }
+    }
+}
+
+impl Dispatch<XdgWmBase, ()> for State {
+    fn event(
+        _state: &mut Self,
+        xdg_wm_base: &XdgWmBase,
+        event: xdg_wm_base::Event,
+        _data: &(),
+        _conn: &Connection,
+        _qh: &QueueHandle<Self>,
+    ) {
+        if let xdg_wm_base::Event::Ping { serial } = event {
+            xdg_wm_base.pong(serial);
+        }
+    }
+}
+
+impl Dispatch<XdgSurface, ()> for State {
+    fn event(
+        state: &mut Self,
+        xdg_surface: &XdgSurface,
+        event: xdg_surface::Event,
+        _data: &(),
+        _conn: &Connection,
+        _qh: &QueueHandle<Self>,
+    ) {
+        if let xdg_surface::Event::Configure { serial } = event {
+            xdg_surface.ack_configure(serial);
+
+            let Some(surface) = state
+                .backend
+                .wayland()
+                .surfaces
+                .iter_mut()
+                .find(|s| s.xdg_surface == *xdg_surface)
+            else {
+                return;
+            };
+            surface.configured = true;
+            let wl_surface = surface.wl_surface.clone();
+            let output = surface.output.clone();
+            if let Some(size) = surface.pending_size.take() {
+                state.resize_wayland_output(&wl_surface, size);
+            }
+            state.backend.wayland().schedule_render(&output);
+        }
+    }
+}
+
+impl Dispatch<XdgToplevel, ()> for State {
+    fn event(
+        state: &mut Self,
+        xdg_toplevel: &XdgToplevel,
+        event: xdg_toplevel::Event,
+        _data: &(),
+        _conn: &Connection,
+        _qh: &QueueHandle<Self>,
+    ) {
+        let Some(surface) = state
+            .backend
+            .wayland()
+            .surfaces
+            .iter_mut()
+            .find(|s| s.xdg_toplevel == *xdg_toplevel)
+        else {
+            return;
+        };
+
+        match event {
+            xdg_toplevel::Event::Configure { width, height, .. } => {
+                // zero means we get to decide
+                if width > 0 && height > 0 {
+                    surface.pending_size = Some((width, height).into());
+                }
+            }
+            xdg_toplevel::Event::Close => {
+                let wl_surface = surface.wl_surface.clone();
+                state.close_wayland_output(&wl_surface);
+            }
+            _ => {}
+        }
+    }
+}
+
+impl Dispatch<WlCallback, WlSurface> for State {
+    fn event(
+        state: &mut Self,
+        _callback: &WlCallback,
+        event: wl_callback::Event,
+        wl_surface: &WlSurface,
+        _conn: &Connection,
+        _qh: &QueueHandle<Self>,
+    ) {
+        if let wl_callback::Event::Done { .. } = event {
+            let wayland_state = state.backend.wayland();
+            if let Some(output) = wayland_state
+                .surface_for(wl_surface)
+                .map(|surface| surface.output.clone())
+            {
+                wayland_state.frame_done(&output, &mut state.common);
+            }
+        }
+    }
+}
+
+impl Dispatch<WlBuffer, ()> for State {
+    fn event(
+        state: &mut Self,
+        buffer: &WlBuffer,
+        event: wl_buffer::Event,
+        _data: &(),
+        _conn: &Connection,
+        _qh: &QueueHandle<Self>,
+    ) {
+        if let wl_buffer::Event::Release = event {
+            for surface in &mut state.backend.wayland().surfaces {
+                surface.in_flight.retain(|(b, _)| b != buffer);
+            }
+        }
+    }
+}
+
+impl Dispatch<WlSeat, ()> for State {
+    fn event(
+        state: &mut Self,
+        seat: &WlSeat,
+        event: wl_seat::Event,
+        _data: &(),
+        _conn: &Connection,
+        qh: &QueueHandle<Self>,
+    ) {
+        let wl_seat::Event::Capabilities {
+            capabilities: WEnum::Value(capabilities),
+        } = event
+        else {
+            return;
+        };
+
+        let wayland_state = state.backend.wayland();
+        let mut added = Vec::new();
+        let mut removed = Vec::new();
+
+        if !wayland_state.input.seat_added {
+            wayland_state.input.seat_added = true;
+            added.push(WaylandVirtualDevice::Seat);
+        }
+
+        if capabilities.contains(wl_seat::Capability::Keyboard) {
+            if wayland_state.keyboard.is_none() {
+                wayland_state.keyboard = Some(seat.get_keyboard(qh, ()));
+            }
+        } else if let Some(keyboard) = wayland_state.keyboard.take() {
+            keyboard.release();
+        }
+
+        if capabilities.contains(wl_seat::Capability::Pointer) {
+            if wayland_state.pointer.is_none() {
+                wayland_state.pointer = Some(seat.get_pointer(qh, ()));
+            }
+        } else if let Some(pointer) = wayland_state.pointer.take() {
+            pointer.release();
+        }
+
+        let touch_devices = wayland_state
+            .surfaces
+            .iter()
+            .map(|surface| WaylandVirtualDevice::Touch(surface.output.name()));
+        if capabilities.contains(wl_seat::Capability::Touch) {
+            if wayland_state.touch.is_none() {
+                wayland_state.touch = Some(seat.get_touch(qh, ()));
+                added.extend(touch_devices);
+            }
+        } else if let Some(touch) = wayland_state.touch.take() {
+            touch.release();
+            removed.extend(touch_devices);
+        }
+
+        for device in added {
+            state.process_input_event(InputEvent::<WaylandInput>::DeviceAdded { device });
+        }
+        for device in removed {
+            state.process_input_event(InputEvent::<WaylandInput>::DeviceRemoved { device });
+        }
+    }
+}
+
+impl Dispatch<WlKeyboard, ()> for State {
+    fn event(
+        state: &mut Self,
+        _keyboard: &WlKeyboard,
+        event: wl_keyboard::Event,
+        _data: &(),
+        _conn: &Connection,
+        _qh: &QueueHandle<Self>,
+    ) {
+        match event {
+            // We use our own keymap, the host only provides us with scancodes.
+            wl_keyboard::Event::Enter { surface, .. } => {
+                state.backend.wayland().input.keyboard_focus = Some(surface.clone());
+                state.set_wayland_active_output(&surface);
+            }
+            wl_keyboard::Event::Leave { .. } => {
+                // release all keys, or they will be stuck until they get pressed again
+                let input = &mut state.backend.wayland().input;
+                let time = input.last_time;
+                for key in std::mem::take(&mut input.pressed_keys) {
+                    state.process_wayland_event(InputEvent::Keyboard {
+                        event: WaylandKeyboardEvent {
+                            time,
+                            key,
+                            count: 0,
+                            state: KeyState::Released,
+                        },
+                    });
+                }
+                state.backend.wayland().input.keyboard_focus = None;
+            }
+            wl_keyboard::Event::Key {
+                time,
+                key,
+                state: WEnum::Value(key_state),
+                ..
+            } => {
+                let input = &mut state.backend.wayland().input;
+                input.last_time = time;
+                let (key_state, count) = match key_state {
+                    wl_keyboard::KeyState::Pressed => {
+                        if !input.pressed_keys.contains(&key) {
+                            input.pressed_keys.push(key);
+                        }
+                        (KeyState::Pressed, 1)
+                    }
+                    wl_keyboard::KeyState::Released => {
+                        input.pressed_keys.retain(|k| *k != key);
+                        (KeyState::Released, 0)
+                    }
+                    _ => return,
+                };
+
+                state.process_wayland_event(InputEvent::Keyboard {
+                    event: WaylandKeyboardEvent {
+                        time,
+                        key,
+                        count,
+                        state: key_state,
+                    },
+                });
+            }
+            _ => {}
+        }
+    }
+}
+
+fn axis_from_wl(axis: WEnum<wl_pointer::Axis>) -> Option<Axis> {
+    match axis {
+        WEnum::Value(wl_pointer::Axis::VerticalScroll) => Some(Axis::Vertical),
+        WEnum::Value(wl_pointer::Axis::HorizontalScroll) => Some(Axis::Horizontal),
+        _ => None,
+    }
+}
+
+impl Dispatch<WlPointer, ()> for State {
+    fn event(
+        state: &mut Self,
+        pointer: &WlPointer,
+        event: wl_pointer::Event,
+        _data: &(),
+        _conn: &Connection,
+        _qh: &QueueHandle<Self>,
+    ) {
+        match event {
+            wl_pointer::Event::Enter {
+                serial,
+                surface,
+                surface_x,
+                surface_y,
+            } => {
+                // we render our own cursor
+                pointer.set_cursor(serial, None, 0, 0);
+                state.backend.wayland().input.pointer_focus = Some(surface.clone());
+                state.set_wayland_active_output(&surface);
+                let time = state.backend.wayland().input.last_time;
+                state.process_wayland_pointer_motion(time, surface_x, surface_y);
+            }
+            wl_pointer::Event::Leave { surface, .. } => {
+                let backend = state.backend.wayland();
+                backend.input.pointer_focus = None;
+                // remove our cursor from the output left
+                if let Some(output) = backend.surface_for(&surface).map(|s| s.output.clone()) {
+                    backend.schedule_render(&output);
+                }
+            }
+            wl_pointer::Event::Motion {
+                time,
+                surface_x,
+                surface_y,
+            } => {
+                state.backend.wayland().input.last_time = time;
+                state.process_wayland_pointer_motion(time, surface_x, surface_y);
+            }
+            wl_pointer::Event::Button {
+                time,
+                button,
+                state: WEnum::Value(button_state),
+                ..
+            } => {
+                state.backend.wayland().input.last_time = time;
+                let button_state = match button_state {
+                    wl_pointer::ButtonState::Pressed => ButtonState::Pressed,
+                    _ => ButtonState::Released,
+                };
+                state.process_wayland_event(InputEvent::PointerButton {
+                    event: WaylandPointerButtonEvent {
+                        time,
+                        button,
+                        state: button_state,
+                    },
+                });
+            }
+            wl_pointer::Event::Axis { time, axis, value } => {
+                let input = &mut state.backend.wayland().input;
+                input.last_time = time;
+                input.pending_axis.time = time;
+                if let Some(axis) = axis_from_wl(axis) {
+                    input.pending_axis.set_amount(axis, value);
+                }
+            }
+            wl_pointer::Event::AxisSource {
+                axis_source: WEnum::Value(source),
+            } => {
+                state.backend.wayland().input.pending_axis.source = Some(match source {
+                    wl_pointer::AxisSource::Finger => AxisSource::Finger,
+                    wl_pointer::AxisSource::Continuous => AxisSource::Continuous,
+                    wl_pointer::AxisSource::WheelTilt => AxisSource::WheelTilt,
+                    _ => AxisSource::Wheel,
+                });
+            }
+            wl_pointer::Event::AxisStop { time, axis } => {
+                let input = &mut state.backend.wayland().input;
+                input.pending_axis.time = time;
+                if let Some(axis) = axis_from_wl(axis) {
+                    input.pending_axis.set_amount(axis, 0.0);
+                }
+            }
+            wl_pointer::Event::AxisDiscrete { axis, discrete } => {
+                if let Some(axis) = axis_from_wl(axis) {
+                    state
+                        .backend
+                        .wayland()
+                        .input
+                        .pending_axis
+                        .set_amount_v120(axis, discrete as f64 * 120.0);
+                }
+            }
+            wl_pointer::Event::AxisValue120 { axis, value120 } => {
+                if let Some(axis) = axis_from_wl(axis) {
+                    state
+                        .backend
+                        .wayland()
+                        .input
+                        .pending_axis
+                        .set_amount_v120(axis, value120 as f64);
+                }
+            }
+            wl_pointer::Event::Frame => {
+                let input = &mut state.backend.wayland().input;
+                if !input.pending_axis.is_empty() {
+                    let event = std::mem::take(&mut input.pending_axis);
+                    state.process_wayland_event(InputEvent::PointerAxis { event });
+                } else {
+                    input.pending_axis = WaylandPointerAxisEvent::default();
+                }
+            }
+            _ => {}
+        }
+    }
+}
+
+impl Dispatch<WlTouch, ()> for State {
+    fn event(
+        state: &mut Self,
+        _touch: &WlTouch,
+        event: wl_touch::Event,
+        _data: &(),
+        _conn: &Connection,
+        _qh: &QueueHandle<Self>,
+    ) {
+        match event {
+            wl_touch::Event::Down {
+                time,
+                surface,
+                id,
+                x,
+                y,
+                ..
+            } => {
+                let Some((output, size)) = state.wayland_surface_info(&surface) else {
+                    return;
+                };
+                let input = &mut state.backend.wayland().input;
+                input.last_time = time;
+                input.touch_points.insert(id, surface);
+                input.last_touch_output = Some(output.clone());
+                state.process_wayland_event(InputEvent::TouchDown {
+                    event: WaylandTouchDownEvent {
+                        time,
+                        output,
+                        slot: id as u32,
+                        x,
+                        y,
+                        size,
+                    },
+                });
+            }
+            wl_touch::Event::Motion { time, id, x, y } => {
+                let Some(surface) = state.backend.wayland().input.touch_points.get(&id).cloned()
+                else {
+                    return;
+                };
+                let Some((output, size)) = state.wayland_surface_info(&surface) else {
+                    return;
+                };
+                let input = &mut state.backend.wayland().input;
+                input.last_time = time;
+                input.last_touch_output = Some(output.clone());
+                state.process_wayland_event(InputEvent::TouchMotion {
+                    event: WaylandTouchMotionEvent {
+                        time,
+                        output,
+                        slot: id as u32,
+                        x,
+                        y,
+                        size,
+                    },
+                });
+            }
+            wl_touch::Event::Up { time, id, .. } => {
+                let Some(surface) = state.backend.wayland().input.touch_points.remove(&id) else {
+                    return;
+                };
+                let Some((output, _)) = state.wayland_surface_info(&surface) else {
+                    return;
+                };
+                let input = &mut state.backend.wayland().input;
+                input.last_time = time;
+                input.last_touch_output = Some(output.clone());
+                state.process_wayland_event(InputEvent::TouchUp {
+                    event: WaylandTouchUpEvent {
+                        time,
+                        output,
+                        slot: id as u32,
+                    },
+                });
+            }
+            wl_touch::Event::Frame => {
+                let input = &state.backend.wayland().input;
+                let time = input.last_time;
+                if let Some(output) = input.last_touch_output.clone() {
+                    state.process_wayland_event(InputEvent::TouchFrame {
+                        event: WaylandTouchFrameEvent { time, output },
+                    });
+                }
+            }
+            wl_touch::Event::Cancel => {
+                let input = &mut state.backend.wayland().input;
+                input.touch_points.clear();
+                let time = input.last_time;
+                if let Some(output) = input.last_touch_output.take() {
+                    state.process_wayland_event(InputEvent::TouchCancel {
+                        event: WaylandTouchCancelEvent { time, output },
+                    });
+                }
+            }
+            _ => {}
+        }
+    }
+}
+
+delegate_noop!(State: WlCompositor);
+delegate_noop!(State: ignore WlSurface);
+delegate_noop!(State: ignore ZwpLinuxDmabufV1);
+delegate_noop!(State: ignore ZwpLinuxBufferParamsV1);
+delegate_noop!(State: ZxdgDecorationManagerV1);
+delegate_noop!(State: ignore ZxdgToplevelDecorationV1);
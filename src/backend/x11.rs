@@ -480,7 +480,7 @@ pub fn init_backend(
     Ok(())
 }
 
-fn init_egl_client_side<R>(
+pub(super) fn init_egl_client_side<R>(
     dh: &DisplayHandle,
     state: &mut State,
     render_node: DrmNode,
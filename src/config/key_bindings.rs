@@ -1,6 +1,9 @@
+use cosmic_comp_config::bindings::{Binding, CompAction};
 use cosmic_settings_config::shortcuts::State as KeyState;
 use cosmic_settings_config::shortcuts::{self, Modifiers};
 use smithay::input::keyboard::ModifiersState;
+use tracing::warn;
+use xkbcommon::xkb::{self, Keysym};
 
 #[derive(Clone, Debug, Eq, PartialEq)]
 pub enum Action {
@@ -8,6 +11,8 @@ pub enum Action {
     Private(PrivateAction),
     /// Behaviors managed via cosmic-settings.
     Shortcut(shortcuts::Action),
+    /// Behaviors only cosmic-comp knows about, bound via its own config.
+    Compositor(CompAction),
 }
 
 #[derive(Clone, Debug, Eq, PartialEq)]
@@ -54,3 +59,32 @@ pub fn cosmic_modifiers_from_smithay(value: ModifiersState) -> Modifiers {
         logo: value.logo,
     }
 }
+
+/// Convert the compositor key bindings from the config, skipping unknown key names.
+pub fn compositor_bindings(bindings: &[Binding]) -> Vec<(shortcuts::Binding, CompAction)> {
+    bindings
+        .iter()
+        .filter_map(|binding| {
+            let key = xkb::keysym_from_name(&binding.key, xkb::KEYSYM_CASE_INSENSITIVE);
+            if key == Keysym::NoSymbol {
+                warn!(key = binding.key, "Unknown key in compositor binding");
+                return None;
+            }
+
+            Some((
+                shortcuts::Binding {
+                    modifiers: Modifiers {
+                        ctrl: binding.modifiers.ctrl,
+                        alt: binding.modifiers.alt,
+                        shift: binding.modifiers.shift,
+                        logo: binding.modifiers.logo,
+                    },
+                    keycode: None,
+                    key: Some(key),
+                    description: None,
+                },
+                binding.action.clone(),
+            ))
+        })
+        .collect()
+}
@@ -1,7 +1,10 @@
 // SPDX-License-Identifier: GPL-3.0-only
 
 use crate::{
-    shell::Shell,
+    shell::{
+        Shell,
+        layout::restore::{FloatingMemory, SavedLayout},
+    },
     state::{BackendData, State},
     utils::prelude::OutputExt,
     wayland::protocols::{
@@ -45,12 +48,18 @@ mod types;
 use cosmic::config::CosmicTk;
 pub use cosmic_comp_config::EdidProduct;
 use cosmic_comp_config::{
-    AppearanceConfig, CosmicCompConfig, KeyboardConfig, TileBehavior, XkbConfig, XwaylandDescaling,
+    AppearanceConfig, ColumnsConfig, CosmicCompConfig, FloatingPlacementConfig, KeyboardConfig,
+    MasterStackConfig, TileBehavior, TilingOverflow, XkbConfig, XwaylandDescaling,
     XwaylandEavesdropping, ZoomConfig,
+    bindings::{Binding, CompAction, Modifiers},
     input::{DeviceState as InputDeviceState, InputConfig, TouchpadOverride},
+    night_light::NightLightConfig,
     output::comp::{
         OutputConfig, OutputInfo, OutputState, OutputsConfig, TransformDef, load_outputs,
     },
+    snap_zones::SnapZonesConfig,
+    templates::TemplateNode,
+    window_rules::WindowRule,
     workspace::WorkspaceConfig,
 };
 pub use key_bindings::{Action, PrivateAction};
@@ -70,6 +79,8 @@ pub struct Config {
     pub tiling_exceptions: Vec<ApplicationException>,
     /// System actions from `com.system76.CosmicSettings.Shortcuts`
     pub system_actions: BTreeMap<shortcuts::action::System, String>,
+    /// Key bindings for compositor actions from `com.system76.CosmicComp`
+    pub compositor_bindings: Vec<(shortcuts::Binding, CompAction)>,
 }
 
 #[derive(Debug)]
@@ -77,6 +88,8 @@ pub struct DynamicConfig {
     outputs: (Option<PathBuf>, OutputsConfig),
     numlock: (Option<PathBuf>, NumlockStateConfig),
     accessibility_filter: (Option<PathBuf>, ScreenFilter),
+    layout: (Option<PathBuf>, SavedLayout),
+    floating_memory: (Option<PathBuf>, FloatingMemory),
 }
 
 #[derive(Default, Debug, Deserialize, Serialize)]
@@ -149,11 +162,14 @@ pub struct ScreenFilter {
     pub inverted: bool,
     #[serde(skip_serializing_if = "Option::is_none")]
     pub color_filter: Option<ColorFilter>,
+    /// Color temperature in Kelvin set by the night light, not persisted
+    #[serde(skip)]
+    pub color_temperature: Option<u32>,
 }
 
 impl ScreenFilter {
     pub fn is_noop(&self) -> bool {
-        !self.inverted && self.color_filter.is_none()
+        !self.inverted && self.color_filter.is_none() && self.color_temperature.is_none()
     }
 }
 
@@ -321,10 +337,12 @@ impl Config {
                 .common
                 .a11y_state
                 .set_screen_filter(filter_conf.color_filter);
+            state.update_night_light();
         });
 
         Config {
             dynamic_conf: Self::load_dynamic(&xdg),
+            compositor_bindings: key_bindings::compositor_bindings(&cosmic_comp_config.bindings),
             cosmic_conf: cosmic_comp_config,
             cosmic_helper: config,
             settings_context,
@@ -345,10 +363,18 @@ impl Config {
             .ok();
         let filter = Self::load_filter_state(&filter_path);
 
+        let layout_path = xdg.place_state_file("cosmic-comp/layout.ron").ok();
+        let layout = Self::load_layout(&layout_path);
+
+        let floating_memory_path = xdg.place_state_file("cosmic-comp/floating.ron").ok();
+        let floating_memory = Self::load_floating_memory(&floating_memory_path);
+
         DynamicConfig {
             outputs: (output_path, outputs),
             numlock: (numlock_path, numlock),
             accessibility_filter: (filter_path, filter),
+            layout: (layout_path, layout),
+            floating_memory: (floating_memory_path, floating_memory),
         }
     }
 
@@ -370,6 +396,42 @@ impl Config {
             .unwrap_or_default()
     }
 
+    fn load_layout(path: &Option<PathBuf>) -> SavedLayout {
+        path.as_deref()
+            .filter(|path| path.exists())
+            .and_then(|path| {
+                ron::de::from_reader::<_, SavedLayout>(
+                    OpenOptions::new().read(true).open(path).unwrap(),
+                )
+                .map_err(|err| {
+                    warn!(?err, "Failed to read layout.ron, resetting..");
+                    if let Err(err) = std::fs::remove_file(path) {
+                        error!(?err, "Failed to remove layout.ron.");
+                    }
+                })
+                .ok()
+            })
+            .unwrap_or_default()
+    }
+
+    fn load_floating_memory(path: &Option<PathBuf>) -> FloatingMemory {
+        path.as_deref()
+            .filter(|path| path.exists())
+            .and_then(|path| {
+                ron::de::from_reader::<_, FloatingMemory>(
+                    OpenOptions::new().read(true).open(path).unwrap(),
+                )
+                .map_err(|err| {
+                    warn!(?err, "Failed to read floating.ron, resetting..");
+                    if let Err(err) = std::fs::remove_file(path) {
+                        error!(?err, "Failed to remove floating.ron.");
+                    }
+                })
+                .ok()
+            })
+            .unwrap_or_default()
+    }
+
     fn load_filter_state(path: &Option<PathBuf>) -> ScreenFilter {
         if let Some(path) = path.as_ref()
             && path.exists()
@@ -397,6 +459,13 @@ impl Config {
         self.shortcuts.shortcut_for_action(action)
     }
 
+    pub fn shortcut_for_compositor_action(&self, action: &CompAction) -> Option<String> {
+        self.compositor_bindings
+            .iter()
+            .find(|(_, a)| a == action)
+            .map(|(binding, _)| binding.to_string())
+    }
+
     pub fn read_outputs(
         &mut self,
         output_state: &mut OutputConfigurationState<State>,
@@ -735,6 +804,27 @@ impl DynamicConfig {
             &mut self.accessibility_filter.1,
         )
     }
+
+    /// Updates the color temperature of the screen filter without persisting it
+    pub fn set_color_temperature(&mut self, temperature: Option<u32>) {
+        self.accessibility_filter.1.color_temperature = temperature;
+    }
+
+    pub fn layout(&self) -> &SavedLayout {
+        &self.layout.1
+    }
+
+    pub fn layout_mut(&mut self) -> PersistenceGuard<'_, SavedLayout> {
+        PersistenceGuard(self.layout.0.clone(), &mut self.layout.1)
+    }
+
+    pub fn floating_memory(&self) -> &FloatingMemory {
+        &self.floating_memory.1
+    }
+
+    pub fn floating_memory_mut(&mut self) -> PersistenceGuard<'_, FloatingMemory> {
+        PersistenceGuard(self.floating_memory.0.clone(), &mut self.floating_memory.1)
+    }
 }
 
 pub fn xkb_config_to_wl(config: &XkbConfig) -> WlXkbConfig<'_> {
@@ -952,6 +1042,66 @@ fn config_changed(config: cosmic_config::Config, keys: Vec<String>, state: &mut
                     }
                 }
             }
+            "window_rules" => {
+                let new = get_config::<Vec<WindowRule>>(&config, "window_rules");
+                if new != state.common.config.cosmic_conf.window_rules {
+                    state.common.config.cosmic_conf.window_rules = new;
+                    state
+                        .common
+                        .shell
+                        .write()
+                        .update_window_rules(state.common.config.cosmic_conf.window_rules.iter());
+                }
+            }
+            "bindings" => {
+                let new = get_config::<Vec<Binding>>(&config, "bindings");
+                if new != state.common.config.cosmic_conf.bindings {
+                    state.common.config.compositor_bindings =
+                        key_bindings::compositor_bindings(&new);
+                    state.common.config.cosmic_conf.bindings = new;
+                }
+            }
+            "master_stack" => {
+                state.common.config.cosmic_conf.master_stack =
+                    get_config::<MasterStackConfig>(&config, "master_stack");
+            }
+            "columns" => {
+                state.common.config.cosmic_conf.columns =
+                    get_config::<ColumnsConfig>(&config, "columns");
+            }
+            "floating_placement" => {
+                let new = get_config::<FloatingPlacementConfig>(&config, "floating_placement");
+                if new != state.common.config.cosmic_conf.floating_placement {
+                    state.common.config.cosmic_conf.floating_placement = new;
+                    state.common.update_config();
+                }
+            }
+            "tiling_overflow" => {
+                let new = get_config::<TilingOverflow>(&config, "tiling_overflow");
+                if new != state.common.config.cosmic_conf.tiling_overflow {
+                    state.common.config.cosmic_conf.tiling_overflow = new;
+                    state.common.update_config();
+                }
+            }
+            "snap_zones" => {
+                state.common.config.cosmic_conf.snap_zones =
+                    get_config::<SnapZonesConfig>(&config, "snap_zones");
+            }
+            "opacity_scroll_modifiers" => {
+                state.common.config.cosmic_conf.opacity_scroll_modifiers =
+                    get_config::<Option<Modifiers>>(&config, "opacity_scroll_modifiers");
+            }
+            "night_light" => {
+                let new = get_config::<NightLightConfig>(&config, "night_light");
+                if new != state.common.config.cosmic_conf.night_light {
+                    state.common.config.cosmic_conf.night_light = new;
+                    state.update_night_light();
+                }
+            }
+            "layout_templates" => {
+                state.common.config.cosmic_conf.layout_templates =
+                    get_config::<HashMap<String, TemplateNode>>(&config, "layout_templates");
+            }
             _ => {}
         }
     }
@@ -3,22 +3,31 @@
 use crate::{
     config::{Action, PrivateAction},
     shell::{
-        FocusResult, InvalidWorkspaceIndex, MoveResult, SeatExt, Trigger, WorkspaceDelta,
+        CosmicSurface, FocusResult, InvalidWorkspaceIndex, MoveResult, SeatExt, Trigger,
+        WorkspaceDelta,
         focus::{FocusTarget, target::KeyboardFocusTarget},
-        layout::tiling::SwapWindowGrab,
+        layout::{
+            floating::KeepLevel,
+            tiling::{Columns, MasterStack, SwapWindowGrab},
+        },
     },
     utils::prelude::*,
     wayland::{
         handlers::xdg_activation::ActivationContext, protocols::workspace::WorkspaceUpdateGuard,
     },
 };
-use cosmic_comp_config::{TileBehavior, workspace::WorkspaceLayout};
+use cosmic_comp_config::{
+    CosmicCompConfig, TileBehavior,
+    bindings::{self, CompAction},
+    workspace::WorkspaceLayout,
+};
 use cosmic_config::ConfigSet;
 use cosmic_settings_config::shortcuts;
-use cosmic_settings_config::shortcuts::action::{Direction, FocusDirection};
+use cosmic_settings_config::shortcuts::action::{Direction, FocusDirection, Orientation};
 use smithay::{
     input::{Seat, pointer::MotionEvent},
     utils::{Point, Serial},
+    wayland::seat::WaylandFocus,
 };
 #[cfg(not(feature = "debug"))]
 use tracing::info;
@@ -28,6 +37,9 @@ use std::{os::unix::process::CommandExt, thread};
 
 use super::gestures;
 
+/// Opacity change of a single shortcut press or scroll wheel notch
+pub const OPACITY_STEP: f32 = 0.05;
+
 fn propagate_by_default(action: &shortcuts::Action) -> bool {
     matches!(
         action,
@@ -64,6 +76,7 @@ impl State {
                     action, seat, serial, time, pattern, direction, propagate,
                 )
             }
+            Action::Compositor(action) => self.handle_compositor_action(action, seat),
             Action::Private(PrivateAction::Escape) => {
                 {
                     let mut shell = self.common.shell.write();
@@ -100,6 +113,217 @@ impl State {
         }
     }
 
+    pub fn handle_compositor_action(&mut self, action: CompAction, seat: &Seat<State>) {
+        match action {
+            CompAction::ToggleMasterStack => {
+                self.update_active_workspace(seat, |workspace, config| {
+                    let tiling = &mut workspace.tiling_layer;
+                    let master_stack = match tiling.master_stack() {
+                        Some(_) => None,
+                        None => Some(MasterStack {
+                            count: config.master_stack.master_count,
+                            ratio: config.master_stack.master_ratio as f64,
+                        }),
+                    };
+                    tiling.set_master_stack(master_stack);
+                })
+            }
+            CompAction::Promote | CompAction::Demote => {
+                self.update_active_workspace(seat, |workspace, _| {
+                    let Some(FocusTarget::Window(mapped)) =
+                        workspace.focus_stack.get(seat).last().cloned()
+                    else {
+                        return;
+                    };
+                    if action == CompAction::Promote {
+                        workspace.tiling_layer.promote(&mapped);
+                    } else {
+                        workspace.tiling_layer.demote(&mapped);
+                    }
+                })
+            }
+            CompAction::RotateNext => self
+                .update_active_workspace(seat, |workspace, _| workspace.tiling_layer.rotate(true)),
+            CompAction::RotatePrev => self
+                .update_active_workspace(seat, |workspace, _| workspace.tiling_layer.rotate(false)),
+            CompAction::IncreaseMasterCount => {
+                self.update_active_workspace(seat, |workspace, _| {
+                    workspace
+                        .tiling_layer
+                        .update_master_stack(|ms| ms.count += 1)
+                })
+            }
+            CompAction::DecreaseMasterCount => {
+                self.update_active_workspace(seat, |workspace, _| {
+                    workspace
+                        .tiling_layer
+                        .update_master_stack(|ms| ms.count = ms.count.saturating_sub(1))
+                })
+            }
+            CompAction::GrowMaster => self.update_active_workspace(seat, |workspace, _| {
+                workspace
+                    .tiling_layer
+                    .update_master_stack(|ms| ms.ratio += 0.05)
+            }),
+            CompAction::ShrinkMaster => self.update_active_workspace(seat, |workspace, _| {
+                workspace
+                    .tiling_layer
+                    .update_master_stack(|ms| ms.ratio -= 0.05)
+            }),
+            CompAction::ToggleColumns => self.update_active_workspace(seat, |workspace, config| {
+                let tiling = &mut workspace.tiling_layer;
+                let columns = match tiling.columns() {
+                    Some(_) => None,
+                    None => Some(Columns::new(config.columns.column_width as f64)),
+                };
+                tiling.set_columns(columns);
+            }),
+            CompAction::RotateTreeClockwise => self
+                .update_active_workspace(seat, |workspace, _| {
+                    workspace.tiling_layer.rotate_subtree(seat, true)
+                }),
+            CompAction::RotateTreeCounterClockwise => self
+                .update_active_workspace(seat, |workspace, _| {
+                    workspace.tiling_layer.rotate_subtree(seat, false)
+                }),
+            // swapping left and right flips groups laid out side by side
+            CompAction::MirrorTreeHorizontally => {
+                self.update_active_workspace(seat, |workspace, _| {
+                    workspace
+                        .tiling_layer
+                        .mirror_subtree(seat, Orientation::Vertical)
+                })
+            }
+            CompAction::MirrorTreeVertically => {
+                self.update_active_workspace(seat, |workspace, _| {
+                    workspace
+                        .tiling_layer
+                        .mirror_subtree(seat, Orientation::Horizontal)
+                })
+            }
+            CompAction::EqualizeSizes => self.update_active_workspace(seat, |workspace, _| {
+                workspace.tiling_layer.equalize_subtree(seat)
+            }),
+            CompAction::SwapWithNeighbor(direction) => {
+                let direction = match direction {
+                    bindings::Direction::Left => Direction::Left,
+                    bindings::Direction::Right => Direction::Right,
+                    bindings::Direction::Up => Direction::Up,
+                    bindings::Direction::Down => Direction::Down,
+                };
+                self.update_active_workspace(seat, |workspace, _| {
+                    workspace.tiling_layer.swap_with_neighbor(seat, direction)
+                })
+            }
+            CompAction::ApplyTemplate { name, keep_slots } => {
+                self.update_active_workspace(seat, |workspace, config| {
+                    match config.layout_templates.get(&name) {
+                        Some(template) => {
+                            workspace.tiling_layer.apply_template(template, keep_slots)
+                        }
+                        None => warn!(name, "Unknown layout template"),
+                    }
+                })
+            }
+            CompAction::SendToScratchpad(name) => {
+                let mut shell = self.common.shell.write();
+                let Some(KeyboardFocusTarget::Element(mapped)) =
+                    seat.get_keyboard().unwrap().current_focus()
+                else {
+                    return;
+                };
+                let target = shell.send_to_scratchpad(&mapped, &name, seat);
+                std::mem::drop(shell);
+                if let Some(target) = target {
+                    Shell::set_focus(self, Some(&target), seat, None, false);
+                }
+            }
+            CompAction::ToggleScratchpad(name) => {
+                let target = self.common.shell.write().toggle_scratchpad(&name, seat);
+                if let Some(target) = target {
+                    Shell::set_focus(self, Some(&target), seat, None, false);
+                }
+            }
+            CompAction::ToggleKeepAbove | CompAction::ToggleKeepBelow => {
+                let Some(KeyboardFocusTarget::Element(mapped)) =
+                    seat.get_keyboard().unwrap().current_focus()
+                else {
+                    return;
+                };
+                let level = if action == CompAction::ToggleKeepAbove {
+                    KeepLevel::Above
+                } else {
+                    KeepLevel::Below
+                };
+                self.common.shell.write().toggle_keep_level(&mapped, level);
+            }
+            CompAction::IncreaseOpacity
+            | CompAction::DecreaseOpacity
+            | CompAction::ResetOpacity => {
+                let Some(KeyboardFocusTarget::Element(mapped)) =
+                    seat.get_keyboard().unwrap().current_focus()
+                else {
+                    return;
+                };
+                let delta = match action {
+                    CompAction::IncreaseOpacity => Some(OPACITY_STEP),
+                    CompAction::DecreaseOpacity => Some(-OPACITY_STEP),
+                    _ => None,
+                };
+                self.change_window_opacity(&mapped.active_window(), delta);
+            }
+            CompAction::ToggleAutoStack => self.update_active_workspace(seat, |workspace, _| {
+                workspace.auto_stack = !workspace.auto_stack;
+            }),
+            CompAction::ToggleNightLight => {
+                let night_light = &mut self.common.config.cosmic_conf.night_light;
+                night_light.enabled = !night_light.enabled;
+                let night_light = *night_light;
+                self.update_night_light();
+
+                let config = self.common.config.cosmic_helper.clone();
+                thread::spawn(move || {
+                    if let Err(err) = config.set("night_light", night_light) {
+                        error!(?err, "Failed to update night_light key");
+                    }
+                });
+            }
+        }
+    }
+
+    /// Runs `f` on the active workspace of the output the seat is on
+    fn update_active_workspace(
+        &mut self,
+        seat: &Seat<State>,
+        f: impl FnOnce(&mut Workspace, &CosmicCompConfig),
+    ) {
+        let output = seat.active_output();
+        let config = &self.common.config.cosmic_conf;
+        let mut shell = self.common.shell.write();
+        if let Some(workspace) = shell.active_space_mut(&output) {
+            f(workspace, config);
+        }
+    }
+
+    /// Adjusts the opacity of `window` by `delta`, or resets it if `None`
+    pub(super) fn change_window_opacity(&mut self, window: &CosmicSurface, delta: Option<f32>) {
+        match delta {
+            Some(delta) => window.adjust_opacity(delta),
+            None => window.reset_opacity(),
+        }
+
+        let output = window.wl_surface().and_then(|surface| {
+            self.common
+                .shell
+                .read()
+                .visible_output_for_surface(&surface)
+                .cloned()
+        });
+        if let Some(output) = output {
+            self.backend.schedule_render(&output);
+        }
+    }
+
     pub fn handle_swipe_action(&mut self, action: gestures::SwipeAction, seat: &Seat<State>) {
         use gestures::SwipeAction;
         let wraparound: bool = self
@@ -128,6 +352,8 @@ impl State {
                     &mut self.common.workspace_state.update(),
                 );
             }
+            // scrolling follows the gesture updates
+            SwipeAction::ScrollColumns { .. } => {}
         }
     }
 
@@ -785,11 +1011,15 @@ impl State {
             }
 
             Action::Move(direction) => {
-                let res = self
-                    .common
-                    .shell
-                    .write()
-                    .move_current_element(direction, seat);
+                let res = {
+                    let mut shell = self.common.shell.write();
+                    let res = shell.move_current_element(direction, seat);
+                    // keep the moved window in view
+                    if let Some(workspace) = shell.active_space_mut(&seat.active_output()) {
+                        workspace.scroll_to_focus(seat);
+                    }
+                    res
+                };
                 match res {
                     MoveResult::MoveFurther(_move_further) => {
                         if let Some(last_mod_serial) = seat.last_modifier_change() {
@@ -16,6 +16,10 @@ pub struct SwipeEvent {
 pub enum SwipeAction {
     NextWorkspace,
     PrevWorkspace,
+    /// Scroll the columns of the active workspace, `forward` meaning to the right
+    ScrollColumns {
+        forward: bool,
+    },
 }
 
 #[derive(Debug, Clone)]
@@ -1,7 +1,7 @@
 // SPDX-License-Identifier: GPL-3.0-only
 
 use crate::{
-    backend::render::ElementFilter,
+    backend::{render::ElementFilter, wayland::WaylandVirtualDevice},
     config::{
         Action, Config, PrivateAction,
         key_bindings::{
@@ -82,6 +82,7 @@ use std::{
 
 pub mod actions;
 pub mod gestures;
+pub mod recording;
 
 /// Used for debouncing focus updates due to pointer motion, if after the focus change is
 /// triggered the event will cancel if the pointer moves to the original target
@@ -166,6 +167,10 @@ impl State {
     {
         crate::wayland::handlers::output_power::set_all_surfaces_dpms_on(self);
 
+        if let Some(recorder) = self.common.input_recorder.as_mut() {
+            recorder.record(&event);
+        }
+
         use smithay::backend::input::Event;
         match event {
             InputEvent::DeviceAdded { device } => {
@@ -899,7 +904,47 @@ impl State {
                 if let Some(seat) = maybe_seat {
                     self.common.idle_notifier_state.notify_activity(&seat);
 
-                    if seat.get_keyboard().unwrap().modifier_state().logo
+                    let modifiers = seat.get_keyboard().unwrap().modifier_state();
+                    let opacity_window = self
+                        .common
+                        .config
+                        .cosmic_conf
+                        .opacity_scroll_modifiers
+                        .filter(|m| {
+                            m.ctrl == modifiers.ctrl
+                                && m.alt == modifiers.alt
+                                && m.shift == modifiers.shift
+                                && m.logo == modifiers.logo
+                        })
+                        .and_then(|_| {
+                            let output = seat.active_output();
+                            let position =
+                                seat.get_pointer().unwrap().current_location().as_global();
+                            let shell = self.common.shell.read();
+                            match State::element_under(position, &output, &shell, &seat) {
+                                Some(KeyboardFocusTarget::Element(mapped)) => {
+                                    Some(mapped.active_window())
+                                }
+                                _ => None,
+                            }
+                        });
+
+                    if let Some(window) = opacity_window {
+                        seat.modifiers_shortcut_queue().clear();
+                        if let Some(mut steps) = event
+                            .amount_v120(Axis::Vertical)
+                            .map(|val| val / 120.)
+                            .or_else(|| event.amount(Axis::Vertical))
+                            .map(|val| val * scroll_factor)
+                        {
+                            // continuous scrolling reports pixels, not notches
+                            if event.source() != AxisSource::Wheel {
+                                steps /= 20.;
+                            }
+                            let delta = -(steps as f32) * actions::OPACITY_STEP;
+                            self.change_window_opacity(&window, Some(delta));
+                        }
+                    } else if modifiers.logo
                         && self
                             .common
                             .config
@@ -1010,7 +1055,27 @@ impl State {
                                 natural_scroll = natural;
                             }
                             activate_action = match gesture_state.fingers {
-                                3 => None, // TODO: 3 finger gestures
+                                3 => {
+                                    let columns = self
+                                        .common
+                                        .shell
+                                        .read()
+                                        .active_space(&seat.active_output())
+                                        .is_some_and(|w| w.tiling_layer.columns().is_some());
+                                    match gesture_state.direction {
+                                        Some(Direction::Left) if columns => {
+                                            Some(SwipeAction::ScrollColumns {
+                                                forward: natural_scroll,
+                                            })
+                                        }
+                                        Some(Direction::Right) if columns => {
+                                            Some(SwipeAction::ScrollColumns {
+                                                forward: !natural_scroll,
+                                            })
+                                        }
+                                        _ => None, // TODO: 3 finger gestures
+                                    }
+                                }
                                 4 => {
                                     if self.common.config.cosmic_conf.workspaces.workspace_layout
                                         == WorkspaceLayout::Horizontal
@@ -1067,6 +1132,21 @@ impl State {
                                     matches!(x, SwipeAction::NextWorkspace),
                                 )
                             }
+                            Some(SwipeAction::ScrollColumns { forward }) => {
+                                let delta = if forward {
+                                    gesture_state.delta
+                                } else {
+                                    -gesture_state.delta
+                                };
+                                if let Some(workspace) = self
+                                    .common
+                                    .shell
+                                    .write()
+                                    .active_space_mut(&seat.active_output())
+                                {
+                                    workspace.tiling_layer.update_columns_swipe(delta);
+                                }
+                            }
                             _ => {}
                         }
                     } else {
@@ -1113,6 +1193,21 @@ impl State {
                                     &mut self.common.workspace_state.update(),
                                 );
                             }
+                            Some(SwipeAction::ScrollColumns { forward }) => {
+                                let delta = if forward {
+                                    gesture_state.projected_end_pos()
+                                } else {
+                                    -gesture_state.projected_end_pos()
+                                };
+                                if let Some(workspace) = self
+                                    .common
+                                    .shell
+                                    .write()
+                                    .active_space_mut(&seat.active_output())
+                                {
+                                    workspace.tiling_layer.end_columns_swipe(delta);
+                                }
+                            }
                             _ => {}
                         }
                         self.common.gesture_state = None;
@@ -1904,6 +1999,21 @@ impl State {
                     )));
                 }
             }
+
+            // shared shortcuts bound to the same keys took precedence above
+            for (binding, action) in self.common.config.compositor_bindings.iter() {
+                if event.state() == KeyState::Pressed
+                    && binding.key.is_some_and(key_matches)
+                    && cosmic_modifiers_eq_smithay(&binding.modifiers, modifiers)
+                {
+                    modifiers_queue.clear();
+                    seat.supressed_keys().add(&handle, None);
+                    return FilterResult::Intercept(Some((
+                        Action::Compositor(action.clone()),
+                        binding.clone(),
+                    )));
+                }
+            }
         }
 
         // no binding
@@ -2360,6 +2470,10 @@ fn mapped_output_for_device<'a, D: Device + 'static>(
         config
             .map_to_output(device)
             .and_then(|name| shell.outputs().find(|output| output.name() == name))
+    } else if let Some(WaylandVirtualDevice::Touch(name)) =
+        <dyn Any>::downcast_ref::<WaylandVirtualDevice>(device)
+    {
+        shell.outputs().find(|output| output.name() == *name)
     } else {
         None
     };
@@ -0,0 +1,1427 @@
+// SPDX-License-Identifier: GPL-3.0-only
+
+//! Recording and deterministic replay of input events.
+//!
+//! Setting `COSMIC_INPUT_RECORD=<path>` writes every event handled by
+//! [`State::process_input_event`] to `<path>` as newline-delimited JSON, including the
+//! timestamps and ids of the originating devices.
+//!
+//! Setting `COSMIC_INPUT_REPLAY=<path>` feeds such a recording back through
+//! [`State::process_input_event`] once the compositor is ready, preserving the original
+//! timing. Replayed devices are prefixed with `replay-`, so they don't clash with real ones.
+//!
+//! Tablet pads don't emit events through the input backends, only their devices are recorded.
+
+use crate::utils::prelude::*;
+use anyhow::{Context, Result};
+use serde::{Deserialize, Serialize};
+use smithay::{
+    backend::input::{
+        AbsolutePositionEvent, Axis, AxisRelativeDirection, AxisSource, ButtonState, Device,
+        DeviceCapability, Event, GestureBeginEvent, GestureEndEvent, GestureHoldBeginEvent,
+        GestureHoldEndEvent, GesturePinchBeginEvent, GesturePinchEndEvent, GesturePinchUpdateEvent,
+        GestureSwipeBeginEvent, GestureSwipeEndEvent, GestureSwipeUpdateEvent, InputBackend,
+        InputEvent, KeyState, KeyboardKeyEvent, PointerAxisEvent, PointerButtonEvent,
+        PointerMotionAbsoluteEvent, PointerMotionEvent, ProximityState, Switch, SwitchState,
+        SwitchToggleEvent, TabletToolAxisEvent, TabletToolButtonEvent, TabletToolCapabilities,
+        TabletToolDescriptor, TabletToolEvent, TabletToolProximityEvent, TabletToolTipEvent,
+        TabletToolTipState, TabletToolType, TouchCancelEvent, TouchDownEvent, TouchEvent,
+        TouchFrameEvent, TouchMotionEvent, TouchSlot, TouchUpEvent, UnusedEvent,
+    },
+    input::keyboard::Keycode,
+    reexports::calloop::{
+        LoopHandle,
+        timer::{TimeoutAction, Timer},
+    },
+};
+use std::{
+    collections::{HashMap, VecDeque},
+    fs::File,
+    io::{BufRead, BufReader, LineWriter, Write},
+    path::{Path, PathBuf},
+    time::{Duration, Instant},
+};
+use tracing::{info, warn};
+
+/// Absolute positions are recorded normalized to the device size with this precision
+const POSITION_PRECISION: i32 = 1 << 20;
+
+#[derive(Debug, Serialize, Deserialize)]
+struct Record {
+    /// Microseconds since the recording was started
+    offset: u64,
+    /// Timestamp of the event in microseconds, as reported by the backend
+    time: u64,
+    device: String,
+    event: RecordedEvent,
+}
+
+#[derive(Debug, Clone, Serialize, Deserialize)]
+#[serde(rename_all = "snake_case", tag = "type")]
+enum RecordedEvent {
+    DeviceAdded(DeviceData),
+    DeviceRemoved,
+    Keyboard(KeyboardData),
+    PointerMotion(MotionData),
+    PointerMotionAbsolute(PositionData),
+    PointerButton(ButtonData),
+    PointerAxis(AxisData),
+    GestureSwipeBegin(GestureBeginData),
+    GestureSwipeUpdate(SwipeUpdateData),
+    GestureSwipeEnd(GestureEndData),
+    GesturePinchBegin(GestureBeginData),
+    GesturePinchUpdate(PinchUpdateData),
+    GesturePinchEnd(GestureEndData),
+    GestureHoldBegin(GestureBeginData),
+    GestureHoldEnd(GestureEndData),
+    TouchDown(TouchData),
+    TouchMotion(TouchData),
+    TouchUp(TouchSlotData),
+    TouchCancel(TouchSlotData),
+    TouchFrame(TouchSlotData),
+    SwitchToggle(SwitchData),
+    TabletToolAxis(TabletToolData<TabletAxisData>),
+    TabletToolProximity(TabletToolData<ProximityData>),
+    TabletToolTip(TabletToolData<TipData>),
+    TabletToolButton(TabletToolData<TabletButtonData>),
+}
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Hash, Serialize, Deserialize)]
+#[serde(rename_all = "snake_case")]
+pub enum Capability {
+    Keyboard,
+    Pointer,
+    Touch,
+    TabletTool,
+    TabletPad,
+    Gesture,
+    Switch,
+}
+
+const CAPABILITIES: [(Capability, DeviceCapability); 7] = [
+    (Capability::Keyboard, DeviceCapability::Keyboard),
+    (Capability::Pointer, DeviceCapability::Pointer),
+    (Capability::Touch, DeviceCapability::Touch),
+    (Capability::TabletTool, DeviceCapability::TabletTool),
+    (Capability::TabletPad, DeviceCapability::TabletPad),
+    (Capability::Gesture, DeviceCapability::Gesture),
+    (Capability::Switch, DeviceCapability::Switch),
+];
+
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct DeviceData {
+    name: String,
+    capabilities: Vec<Capability>,
+}
+
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct KeyboardData {
+    /// xkb keycode
+    key: u32,
+    pressed: bool,
+    count: u32,
+}
+
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct MotionData {
+    dx: f64,
+    dy: f64,
+    dx_unaccel: f64,
+    dy_unaccel: f64,
+}
+
+/// Position normalized to `0.0..=1.0`
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct PositionData {
+    x: f64,
+    y: f64,
+}
+
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct ButtonData {
+    button: u32,
+    pressed: bool,
+}
+
+#[derive(Debug, Clone, Copy, Serialize, Deserialize)]
+#[serde(rename_all = "snake_case")]
+pub enum AxisSourceData {
+    Finger,
+    Continuous,
+    Wheel,
+    WheelTilt,
+}
+
+#[derive(Debug, Clone, Default, Serialize, Deserialize)]
+pub struct AxisValue {
+    amount: Option<f64>,
+    v120: Option<f64>,
+    inverted: bool,
+}
+
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct AxisData {
+    source: AxisSourceData,
+    horizontal: AxisValue,
+    vertical: AxisValue,
+}
+
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct GestureBeginData {
+    fingers: u32,
+}
+
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct GestureEndData {
+    cancelled: bool,
+}
+
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct SwipeUpdateData {
+    dx: f64,
+    dy: f64,
+}
+
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct PinchUpdateData {
+    dx: f64,
+    dy: f64,
+    scale: f64,
+    rotation: f64,
+}
+
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct TouchData {
+    slot: Option<u32>,
+    #[serde(flatten)]
+    position: PositionData,
+}
+
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct TouchSlotData {
+    slot: Option<u32>,
+}
+
+#[derive(Debug, Clone, Copy, Serialize, Deserialize)]
+#[serde(rename_all = "snake_case")]
+pub enum SwitchKind {
+    Lid,
+    TabletMode,
+}
+
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct SwitchData {
+    switch: Option<SwitchKind>,
+    on: bool,
+}
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Serialize, Deserialize)]
+#[serde(rename_all = "snake_case")]
+pub enum ToolKind {
+    Pen,
+    Eraser,
+    Brush,
+    Pencil,
+    Airbrush,
+    Mouse,
+    Lens,
+}
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Serialize, Deserialize)]
+#[serde(rename_all = "snake_case")]
+pub enum ToolCapability {
+    Tilt,
+    Pressure,
+    Distance,
+    Rotation,
+    Slider,
+    Wheel,
+}
+
+const TOOL_CAPABILITIES: [(ToolCapability, TabletToolCapabilities); 6] = [
+    (ToolCapability::Tilt, TabletToolCapabilities::TILT),
+    (ToolCapability::Pressure, TabletToolCapabilities::PRESSURE),
+    (ToolCapability::Distance, TabletToolCapabilities::DISTANCE),
+    (ToolCapability::Rotation, TabletToolCapabilities::ROTATION),
+    (ToolCapability::Slider, TabletToolCapabilities::SLIDER),
+    (ToolCapability::Wheel, TabletToolCapabilities::WHEEL),
+];
+
+#[derive(Debug, Clone, PartialEq, Eq, Serialize, Deserialize)]
+pub struct ToolData {
+    kind: ToolKind,
+    serial: u64,
+    hardware_id: u64,
+    capabilities: Vec<ToolCapability>,
+}
+
+/// State of a tablet tool shared by all of its events, carrying the event specific `T`.
+///
+/// Axes are only set if they changed with the event.
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct TabletToolData<T> {
+    tool: ToolData,
+    #[serde(flatten)]
+    position: PositionData,
+    dx: f64,
+    dy: f64,
+    pressure: Option<f64>,
+    distance: Option<f64>,
+    tilt_x: Option<f64>,
+    tilt_y: Option<f64>,
+    slider: Option<f64>,
+    rotation: Option<f64>,
+    /// Wheel delta in degrees and in discrete steps
+    wheel: Option<(f64, i32)>,
+    #[serde(flatten)]
+    event: T,
+}
+
+/// Axis events carry nothing beyond the tool state
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct TabletAxisData {}
+
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct ProximityData {
+    in_proximity: bool,
+}
+
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct TipData {
+    down: bool,
+}
+
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct TabletButtonData {
+    button: u32,
+    seat_button_count: u32,
+    pressed: bool,
+}
+
+fn position<B: InputBackend>(event: &impl AbsolutePositionEvent<B>) -> PositionData {
+    PositionData {
+        x: event.x_transformed(POSITION_PRECISION) / POSITION_PRECISION as f64,
+        y: event.y_transformed(POSITION_PRECISION) / POSITION_PRECISION as f64,
+    }
+}
+
+fn axis_value<B: InputBackend>(event: &impl PointerAxisEvent<B>, axis: Axis) -> AxisValue {
+    AxisValue {
+        amount: event.amount(axis),
+        v120: event.amount_v120(axis),
+        inverted: event.relative_direction(axis) == AxisRelativeDirection::Inverted,
+    }
+}
+
+fn tool_data(tool: &TabletToolDescriptor) -> ToolData {
+    // tools without a counterpart are replayed as pens
+    #[allow(unreachable_patterns)]
+    let kind = match tool.tool_type {
+        TabletToolType::Pen => ToolKind::Pen,
+        TabletToolType::Eraser => ToolKind::Eraser,
+        TabletToolType::Brush => ToolKind::Brush,
+        TabletToolType::Pencil => ToolKind::Pencil,
+        TabletToolType::Airbrush => ToolKind::Airbrush,
+        TabletToolType::Mouse => ToolKind::Mouse,
+        TabletToolType::Lens => ToolKind::Lens,
+        _ => ToolKind::Pen,
+    };
+    ToolData {
+        kind,
+        serial: tool.hardware_serial,
+        hardware_id: tool.hardware_id_wacom,
+        capabilities: TOOL_CAPABILITIES
+            .iter()
+            .filter(|(_, cap)| tool.capabilities.contains(*cap))
+            .map(|(cap, _)| *cap)
+            .collect(),
+    }
+}
+
+fn tablet_tool<B, E, T>(event: &E, data: T) -> TabletToolData<T>
+where
+    B: InputBackend,
+    E: TabletToolEvent<B> + AbsolutePositionEvent<B>,
+{
+    TabletToolData {
+        tool: tool_data(&event.tool()),
+        position: position(event),
+        dx: event.delta_x(),
+        dy: event.delta_y(),
+        pressure: event.pressure_has_changed().then(|| event.pressure()),
+        distance: event.distance_has_changed().then(|| event.distance()),
+        tilt_x: event.tilt_x_has_changed().then(|| event.tilt_x()),
+        tilt_y: event.tilt_y_has_changed().then(|| event.tilt_y()),
+        slider: event.slider_has_changed().then(|| event.slider_position()),
+        rotation: event.rotation_has_changed().then(|| event.rotation()),
+        wheel: event
+            .wheel_has_changed()
+            .then(|| (event.wheel_delta(), event.wheel_delta_discrete())),
+        event: data,
+    }
+}
+
+impl RecordedEvent {
+    /// Returns the event together with the id of its device and its timestamp
+    fn from_input<B: InputBackend>(event: &InputEvent<B>) -> Option<(RecordedEvent, String, u64)> {
+        fn timed<B: InputBackend>(
+            event: &impl Event<B>,
+            recorded: RecordedEvent,
+        ) -> Option<(RecordedEvent, String, u64)> {
+            Some((recorded, event.device().id(), event.time()))
+        }
+
+        match event {
+            InputEvent::DeviceAdded { device } => Some((
+                RecordedEvent::DeviceAdded(DeviceData {
+                    name: device.name(),
+                    capabilities: CAPABILITIES
+                        .iter()
+                        .filter(|(_, cap)| device.has_capability(*cap))
+                        .map(|(cap, _)| *cap)
+                        .collect(),
+                }),
+                device.id(),
+                0,
+            )),
+            InputEvent::DeviceRemoved { device } => {
+                Some((RecordedEvent::DeviceRemoved, device.id(), 0))
+            }
+            InputEvent::Keyboard { event, .. } => timed(
+                event,
+                RecordedEvent::Keyboard(KeyboardData {
+                    key: event.key_code().raw(),
+                    pressed: event.state() == KeyState::Pressed,
+                    count: event.count(),
+                }),
+            ),
+            InputEvent::PointerMotion { event, .. } => timed(
+                event,
+                RecordedEvent::PointerMotion(MotionData {
+                    dx: event.delta_x(),
+                    dy: event.delta_y(),
+                    dx_unaccel: event.delta_x_unaccel(),
+                    dy_unaccel: event.delta_y_unaccel(),
+                }),
+            ),
+            InputEvent::PointerMotionAbsolute { event, .. } => {
+                timed(event, RecordedEvent::PointerMotionAbsolute(position(event)))
+            }
+            InputEvent::PointerButton { event, .. } => timed(
+                event,
+                RecordedEvent::PointerButton(ButtonData {
+                    button: event.button_code(),
+                    pressed: event.state() == ButtonState::Pressed,
+                }),
+            ),
+            InputEvent::PointerAxis { event, .. } => timed(
+                event,
+                RecordedEvent::PointerAxis(AxisData {
+                    source: match event.source() {
+                        AxisSource::Finger => AxisSourceData::Finger,
+                        AxisSource::Continuous => AxisSourceData::Continuous,
+                        AxisSource::Wheel => AxisSourceData::Wheel,
+                        AxisSource::WheelTilt => AxisSourceData::WheelTilt,
+                    },
+                    horizontal: axis_value(event, Axis::Horizontal),
+                    vertical: axis_value(event, Axis::Vertical),
+                }),
+            ),
+            InputEvent::GestureSwipeBegin { event, .. } => timed(
+                event,
+                RecordedEvent::GestureSwipeBegin(GestureBeginData {
+                    fingers: event.fingers(),
+                }),
+            ),
+            InputEvent::GestureSwipeUpdate { event, .. } => timed(
+                event,
+                RecordedEvent::GestureSwipeUpdate(SwipeUpdateData {
+                    dx: event.delta_x(),
+                    dy: event.delta_y(),
+                }),
+            ),
+            InputEvent::GestureSwipeEnd { event, .. } => timed(
+                event,
+                RecordedEvent::GestureSwipeEnd(GestureEndData {
+                    cancelled: event.cancelled(),
+                }),
+            ),
+            InputEvent::GesturePinchBegin { event, .. } => timed(
+                event,
+                RecordedEvent::GesturePinchBegin(GestureBeginData {
+                    fingers: event.fingers(),
+                }),
+            ),
+            InputEvent::GesturePinchUpdate { event, .. } => timed(
+                event,
+                RecordedEvent::GesturePinchUpdate(PinchUpdateData {
+                    dx: event.delta_x(),
+                    dy: event.delta_y(),
+                    scale: event.scale(),
+                    rotation: event.rotation(),
+                }),
+            ),
+            InputEvent::GesturePinchEnd { event, .. } => timed(
+                event,
+                RecordedEvent::GesturePinchEnd(GestureEndData {
+                    cancelled: event.cancelled(),
+                }),
+            ),
+            InputEvent::GestureHoldBegin { event, .. } => timed(
+                event,
+                RecordedEvent::GestureHoldBegin(GestureBeginData {
+                    fingers: event.fingers(),
+                }),
+            ),
+            InputEvent::GestureHoldEnd { event, .. } => timed(
+                event,
+                RecordedEvent::GestureHoldEnd(GestureEndData {
+                    cancelled: event.cancelled(),
+                }),
+            ),
+            InputEvent::TouchDown { event, .. } => timed(
+                event,
+                RecordedEvent::TouchDown(TouchData {
+                    slot: event.slot().into(),
+                    position: position(event),
+                }),
+            ),
+            InputEvent::TouchMotion { event, .. } => timed(
+                event,
+                RecordedEvent::TouchMotion(TouchData {
+                    slot: event.slot().into(),
+                    position: position(event),
+                }),
+            ),
+            InputEvent::TouchUp { event, .. } => timed(
+                event,
+                RecordedEvent::TouchUp(TouchSlotData {
+                    slot: event.slot().into(),
+                }),
+            ),
+            InputEvent::TouchCancel { event, .. } => timed(
+                event,
+                RecordedEvent::TouchCancel(TouchSlotData {
+                    slot: event.slot().into(),
+                }),
+            ),
+            InputEvent::TouchFrame { event, .. } => timed(
+                event,
+                RecordedEvent::TouchFrame(TouchSlotData {
+                    slot: event.slot().into(),
+                }),
+            ),
+            InputEvent::SwitchToggle { event, .. } => timed(
+                event,
+                RecordedEvent::SwitchToggle(SwitchData {
+                    switch: event.switch().map(|switch| match switch {
+                        Switch::Lid => SwitchKind::Lid,
+                        Switch::TabletMode => SwitchKind::TabletMode,
+                    }),
+                    on: event.state() == SwitchState::On,
+                }),
+            ),
+            InputEvent::TabletToolAxis { event, .. } => timed(
+                event,
+                RecordedEvent::TabletToolAxis(tablet_tool(event, TabletAxisData {})),
+            ),
+            InputEvent::TabletToolProximity { event, .. } => timed(
+                event,
+                RecordedEvent::TabletToolProximity(tablet_tool(
+                    event,
+                    ProximityData {
+                        in_proximity: matches!(event.state(), ProximityState::In),
+                    },
+                )),
+            ),
+            InputEvent::TabletToolTip { event, .. } => timed(
+                event,
+                RecordedEvent::TabletToolTip(tablet_tool(
+                    event,
+                    TipData {
+                        down: matches!(event.tip_state(), TabletToolTipState::Down),
+                    },
+                )),
+            ),
+            InputEvent::TabletToolButton { event, .. } => timed(
+                event,
+                RecordedEvent::TabletToolButton(tablet_tool(
+                    event,
+                    TabletButtonData {
+                        button: event.button(),
+                        seat_button_count: event.seat_button_count(),
+                        pressed: event.button_state() == ButtonState::Pressed,
+                    },
+                )),
+            ),
+            _ => None,
+        }
+    }
+
+    /// Capability required by a device to emit this event
+    fn capability(&self) -> Option<Capability> {
+        match self {
+            RecordedEvent::DeviceAdded(_) | RecordedEvent::DeviceRemoved => None,
+            RecordedEvent::Keyboard(_) => Some(Capability::Keyboard),
+            RecordedEvent::PointerMotion(_)
+            | RecordedEvent::PointerMotionAbsolute(_)
+            | RecordedEvent::PointerButton(_)
+            | RecordedEvent::PointerAxis(_) => Some(Capability::Pointer),
+            RecordedEvent::GestureSwipeBegin(_)
+            | RecordedEvent::GestureSwipeUpdate(_)
+            | RecordedEvent::GestureSwipeEnd(_)
+            | RecordedEvent::GesturePinchBegin(_)
+            | RecordedEvent::GesturePinchUpdate(_)
+            | RecordedEvent::GesturePinchEnd(_)
+            | RecordedEvent::GestureHoldBegin(_)
+            | RecordedEvent::GestureHoldEnd(_) => Some(Capability::Gesture),
+            RecordedEvent::TouchDown(_)
+            | RecordedEvent::TouchMotion(_)
+            | RecordedEvent::TouchUp(_)
+            | RecordedEvent::TouchCancel(_)
+            | RecordedEvent::TouchFrame(_) => Some(Capability::Touch),
+            RecordedEvent::SwitchToggle(_) => Some(Capability::Switch),
+            RecordedEvent::TabletToolAxis(_)
+            | RecordedEvent::TabletToolProximity(_)
+            | RecordedEvent::TabletToolTip(_)
+            | RecordedEvent::TabletToolButton(_) => Some(Capability::TabletTool),
+        }
+    }
+}
+
+#[derive(Debug)]
+pub struct InputRecorder {
+    path: PathBuf,
+    file: LineWriter<File>,
+    start: Instant,
+}
+
+impl InputRecorder {
+    /// Starts a new recording, if `COSMIC_INPUT_RECORD` is set
+    pub fn from_env() -> Option<InputRecorder> {
+        let path = PathBuf::from(std::env::var_os("COSMIC_INPUT_RECORD")?);
+        match File::create(&path) {
+            Ok(file) => {
+                info!("Recording input events to {}", path.display());
+                Some(InputRecorder {
+                    path,
+                    file: LineWriter::new(file),
+                    start: Instant::now(),
+                })
+            }
+            Err(err) => {
+                warn!(?err, "Failed to create input recording {}", path.display());
+                None
+            }
+        }
+    }
+
+    pub fn record<B: InputBackend>(&mut self, event: &InputEvent<B>) {
+        let Some((event, device, time)) = RecordedEvent::from_input(event) else {
+            return;
+        };
+        let record = Record {
+            offset: self.start.elapsed().as_micros() as u64,
+            time,
+            device,
+            event,
+        };
+        let res = serde_json::to_writer(&mut self.file, &record)
+            .map_err(std::io::Error::from)
+            .and_then(|_| self.file.write_all(b"\n"));
+        if let Err(err) = res {
+            warn!(
+                ?err,
+                "Failed to write to input recording {}",
+                self.path.display()
+            );
+        }
+    }
+}
+
+/// Virtual input backend of replayed recordings.
+#[derive(Debug)]
+pub struct ReplayInput;
+
+#[derive(Debug, Clone, PartialEq, Eq, Hash)]
+pub struct ReplayDevice {
+    id: String,
+    name: String,
+    capabilities: Vec<Capability>,
+}
+
+impl Device for ReplayDevice {
+    fn id(&self) -> String {
+        self.id.clone()
+    }
+
+    fn name(&self) -> String {
+        self.name.clone()
+    }
+
+    fn has_capability(&self, capability: DeviceCapability) -> bool {
+        CAPABILITIES
+            .iter()
+            .any(|(cap, device_cap)| *device_cap == capability && self.capabilities.contains(cap))
+    }
+
+    fn usb_id(&self) -> Option<(u32, u32)> {
+        None
+    }
+
+    fn syspath(&self) -> Option<PathBuf> {
+        None
+    }
+}
+
+/// A replayed event carrying the recorded data of type `T`.
+#[derive(Debug, Clone)]
+pub struct ReplayEvent<T> {
+    time: u64,
+    device: ReplayDevice,
+    data: T,
+}
+
+impl InputBackend for ReplayInput {
+    type Device = ReplayDevice;
+
+    type KeyboardKeyEvent = ReplayEvent<KeyboardData>;
+    type PointerAxisEvent = ReplayEvent<AxisData>;
+    type PointerButtonEvent = ReplayEvent<ButtonData>;
+    type PointerMotionEvent = ReplayEvent<MotionData>;
+    type PointerMotionAbsoluteEvent = ReplayEvent<PositionData>;
+
+    type GestureSwipeBeginEvent = ReplayEvent<GestureBeginData>;
+    type GestureSwipeUpdateEvent = ReplayEvent<SwipeUpdateData>;
+    type GestureSwipeEndEvent = ReplayEvent<GestureEndData>;
+    type GesturePinchBeginEvent = ReplayEvent<GestureBeginData>;
+    type GesturePinchUpdateEvent = ReplayEvent<PinchUpdateData>;
+    type GesturePinchEndEvent = ReplayEvent<GestureEndData>;
+    type GestureHoldBeginEvent = ReplayEvent<GestureBeginData>;
+    type GestureHoldEndEvent = ReplayEvent<GestureEndData>;
+
+    type TouchDownEvent = ReplayEvent<TouchData>;
+    type TouchUpEvent = ReplayEvent<TouchSlotData>;
+    type TouchMotionEvent = ReplayEvent<TouchData>;
+    type TouchCancelEvent = ReplayEvent<TouchSlotData>;
+    type TouchFrameEvent = ReplayEvent<TouchSlotData>;
+
+    type TabletToolAxisEvent = ReplayEvent<TabletToolData<TabletAxisData>>;
+    type TabletToolProximityEvent = ReplayEvent<TabletToolData<ProximityData>>;
+    type TabletToolTipEvent = ReplayEvent<TabletToolData<TipData>>;
+    type TabletToolButtonEvent = ReplayEvent<TabletToolData<TabletButtonData>>;
+
+    type SwitchToggleEvent = ReplayEvent<SwitchData>;
+
+    type SpecialEvent = UnusedEvent;
+}
+
+impl<T> Event<ReplayInput> for ReplayEvent<T> {
+    fn time(&self) -> u64 {
+        self.time
+    }
+
+    fn device(&self) -> ReplayDevice {
+        self.device.clone()
+    }
+}
+
+impl KeyboardKeyEvent<ReplayInput> for ReplayEvent<KeyboardData> {
+    fn key_code(&self) -> Keycode {
+        Keycode::new(self.data.key)
+    }
+
+    fn state(&self) -> KeyState {
+        if self.data.pressed {
+            KeyState::Pressed
+        } else {
+            KeyState::Released
+        }
+    }
+
+    fn count(&self) -> u32 {
+        self.data.count
+    }
+}
+
+impl PointerMotionEvent<ReplayInput> for ReplayEvent<MotionData> {
+    fn delta_x(&self) -> f64 {
+        self.data.dx
+    }
+
+    fn delta_y(&self) -> f64 {
+        self.data.dy
+    }
+
+    fn delta_x_unaccel(&self) -> f64 {
+        self.data.dx_unaccel
+    }
+
+    fn delta_y_unaccel(&self) -> f64 {
+        self.data.dy_unaccel
+    }
+}
+
+impl AbsolutePositionEvent<ReplayInput> for ReplayEvent<PositionData> {
+    fn x(&self) -> f64 {
+        self.data.x
+    }
+
+    fn y(&self) -> f64 {
+        self.data.y
+    }
+
+    fn x_transformed(&self, width: i32) -> f64 {
+        self.data.x * width as f64
+    }
+
+    fn y_transformed(&self, height: i32) -> f64 {
+        self.data.y * height as f64
+    }
+}
+
+impl PointerMotionAbsoluteEvent<ReplayInput> for ReplayEvent<PositionData> {}
+
+impl PointerButtonEvent<ReplayInput> for ReplayEvent<ButtonData> {
+    fn button_code(&self) -> u32 {
+        self.data.button
+    }
+
+    fn state(&self) -> ButtonState {
+        if self.data.pressed {
+            ButtonState::Pressed
+        } else {
+            ButtonState::Released
+        }
+    }
+}
+
+impl PointerAxisEvent<ReplayInput> for ReplayEvent<AxisData> {
+    fn amount(&self, axis: Axis) -> Option<f64> {
+        match axis {
+            Axis::Horizontal => self.data.horizontal.amount,
+            Axis::Vertical => self.data.vertical.amount,
+        }
+    }
+
+    fn amount_v120(&self, axis: Axis) -> Option<f64> {
+        match axis {
+            Axis::Horizontal => self.data.horizontal.v120,
+            Axis::Vertical => self.data.vertical.v120,
+        }
+    }
+
+    fn source(&self) -> AxisSource {
+        match self.data.source {
+            AxisSourceData::Finger => AxisSource::Finger,
+            AxisSourceData::Continuous => AxisSource::Continuous,
+            AxisSourceData::Wheel => AxisSource::Wheel,
+            AxisSourceData::WheelTilt => AxisSource::WheelTilt,
+        }
+    }
+
+    fn relative_direction(&self, axis: Axis) -> AxisRelativeDirection {
+        let inverted = match axis {
+            Axis::Horizontal => self.data.horizontal.inverted,
+            Axis::Vertical => self.data.vertical.inverted,
+        };
+        if inverted {
+            AxisRelativeDirection::Inverted
+        } else {
+            AxisRelativeDirection::Identical
+        }
+    }
+}
+
+impl GestureBeginEvent<ReplayInput> for ReplayEvent<GestureBeginData> {
+    fn fingers(&self) -> u32 {
+        self.data.fingers
+    }
+}
+
+impl GestureEndEvent<ReplayInput> for ReplayEvent<GestureEndData> {
+    fn cancelled(&self) -> bool {
+        self.data.cancelled
+    }
+}
+
+impl GestureSwipeBeginEvent<ReplayInput> for ReplayEvent<GestureBeginData> {}
+impl GestureSwipeEndEvent<ReplayInput> for ReplayEvent<GestureEndData> {}
+impl GesturePinchBeginEvent<ReplayInput> for ReplayEvent<GestureBeginData> {}
+impl GesturePinchEndEvent<ReplayInput> for ReplayEvent<GestureEndData> {}
+impl GestureHoldBeginEvent<ReplayInput> for ReplayEvent<GestureBeginData> {}
+impl GestureHoldEndEvent<ReplayInput> for ReplayEvent<GestureEndData> {}
+
+impl GestureSwipeUpdateEvent<ReplayInput> for ReplayEvent<SwipeUpdateData> {
+    fn delta_x(&self) -> f64 {
+        self.data.dx
+    }
+
+    fn delta_y(&self) -> f64 {
+        self.data.dy
+    }
+}
+
+impl GesturePinchUpdateEvent<ReplayInput> for ReplayEvent<PinchUpdateData> {
+    fn delta_x(&self) -> f64 {
+        self.data.dx
+    }
+
+    fn delta_y(&self) -> f64 {
+        self.data.dy
+    }
+
+    fn scale(&self) -> f64 {
+        self.data.scale
+    }
+
+    fn rotation(&self) -> f64 {
+        self.data.rotation
+    }
+}
+
+impl TouchEvent<ReplayInput> for ReplayEvent<TouchData> {
+    fn slot(&self) -> TouchSlot {
+        self.data.slot.into()
+    }
+}
+
+impl AbsolutePositionEvent<ReplayInput> for ReplayEvent<TouchData> {
+    fn x(&self) -> f64 {
+        self.data.position.x
+    }
+
+    fn y(&self) -> f64 {
+        self.data.position.y
+    }
+
+    fn x_transformed(&self, width: i32) -> f64 {
+        self.data.position.x * width as f64
+    }
+
+    fn y_transformed(&self, height: i32) -> f64 {
+        self.data.position.y * height as f64
+    }
+}
+
+impl TouchDownEvent<ReplayInput> for ReplayEvent<TouchData> {}
+impl TouchMotionEvent<ReplayInput> for ReplayEvent<TouchData> {}
+
+impl TouchEvent<ReplayInput> for ReplayEvent<TouchSlotData> {
+    fn slot(&self) -> TouchSlot {
+        self.data.slot.into()
+    }
+}
+
+impl TouchUpEvent<ReplayInput> for ReplayEvent<TouchSlotData> {}
+impl TouchCancelEvent<ReplayInput> for ReplayEvent<TouchSlotData> {}
+impl TouchFrameEvent<ReplayInput> for ReplayEvent<TouchSlotData> {}
+
+impl SwitchToggleEvent<ReplayInput> for ReplayEvent<SwitchData> {
+    fn switch(&self) -> Option<Switch> {
+        self.data.switch.map(|switch| match switch {
+            SwitchKind::Lid => Switch::Lid,
+            SwitchKind::TabletMode => Switch::TabletMode,
+        })
+    }
+
+    fn state(&self) -> SwitchState {
+        if self.data.on {
+            SwitchState::On
+        } else {
+            SwitchState::Off
+        }
+    }
+}
+
+impl<T> AbsolutePositionEvent<ReplayInput> for ReplayEvent<TabletToolData<T>> {
+    fn x(&self) -> f64 {
+        self.data.position.x
+    }
+
+    fn y(&self) -> f64 {
+        self.data.position.y
+    }
+
+    fn x_transformed(&self, width: i32) -> f64 {
+        self.data.position.x * width as f64
+    }
+
+    fn y_transformed(&self, height: i32) -> f64 {
+        self.data.position.y * height as f64
+    }
+}
+
+impl<T> TabletToolEvent<ReplayInput> for ReplayEvent<TabletToolData<T>> {
+    fn tool(&self) -> TabletToolDescriptor {
+        let tool = &self.data.tool;
+        TabletToolDescriptor {
+            tool_type: match tool.kind {
+                ToolKind::Pen => TabletToolType::Pen,
+                ToolKind::Eraser => TabletToolType::Eraser,
+                ToolKind::Brush => TabletToolType::Brush,
+                ToolKind::Pencil => TabletToolType::Pencil,
+                ToolKind::Airbrush => TabletToolType::Airbrush,
+                ToolKind::Mouse => TabletToolType::Mouse,
+                ToolKind::Lens => TabletToolType::Lens,
+            },
+            hardware_serial: tool.serial,
+            hardware_id_wacom: tool.hardware_id,
+            capabilities: TOOL_CAPABILITIES
+                .iter()
+                .filter(|(cap, _)| tool.capabilities.contains(cap))
+                .fold(TabletToolCapabilities::empty(), |caps, (_, flag)| {
+                    caps | *flag
+                }),
+        }
+    }
+
+    fn delta_x(&self) -> f64 {
+        self.data.dx
+    }
+
+    fn delta_y(&self) -> f64 {
+        self.data.dy
+    }
+
+    fn distance(&self) -> f64 {
+        self.data.distance.unwrap_or_default()
+    }
+
+    fn distance_has_changed(&self) -> bool {
+        self.data.distance.is_some()
+    }
+
+    fn pressure(&self) -> f64 {
+        self.data.pressure.unwrap_or_default()
+    }
+
+    fn pressure_has_changed(&self) -> bool {
+        self.data.pressure.is_some()
+    }
+
+    fn slider_position(&self) -> f64 {
+        self.data.slider.unwrap_or_default()
+    }
+
+    fn slider_has_changed(&self) -> bool {
+        self.data.slider.is_some()
+    }
+
+    fn tilt_x(&self) -> f64 {
+        self.data.tilt_x.unwrap_or_default()
+    }
+
+    fn tilt_x_has_changed(&self) -> bool {
+        self.data.tilt_x.is_some()
+    }
+
+    fn tilt_y(&self) -> f64 {
+        self.data.tilt_y.unwrap_or_default()
+    }
+
+    fn tilt_y_has_changed(&self) -> bool {
+        self.data.tilt_y.is_some()
+    }
+
+    fn rotation(&self) -> f64 {
+        self.data.rotation.unwrap_or_default()
+    }
+
+    fn rotation_has_changed(&self) -> bool {
+        self.data.rotation.is_some()
+    }
+
+    fn wheel_delta(&self) -> f64 {
+        self.data.wheel.map(|(delta, _)| delta).unwrap_or_default()
+    }
+
+    fn wheel_delta_discrete(&self) -> i32 {
+        self.data.wheel.map(|(_, steps)| steps).unwrap_or_default()
+    }
+
+    fn wheel_has_changed(&self) -> bool {
+        self.data.wheel.is_some()
+    }
+}
+
+impl TabletToolAxisEvent<ReplayInput> for ReplayEvent<TabletToolData<TabletAxisData>> {}
+
+impl TabletToolProximityEvent<ReplayInput> for ReplayEvent<TabletToolData<ProximityData>> {
+    fn state(&self) -> ProximityState {
+        if self.data.event.in_proximity {
+            ProximityState::In
+        } else {
+            ProximityState::Out
+        }
+    }
+}
+
+impl TabletToolTipEvent<ReplayInput> for ReplayEvent<TabletToolData<TipData>> {
+    fn tip_state(&self) -> TabletToolTipState {
+        if self.data.event.down {
+            TabletToolTipState::Down
+        } else {
+            TabletToolTipState::Up
+        }
+    }
+}
+
+impl TabletToolButtonEvent<ReplayInput> for ReplayEvent<TabletToolData<TabletButtonData>> {
+    fn button(&self) -> u32 {
+        self.data.event.button
+    }
+
+    fn seat_button_count(&self) -> u32 {
+        self.data.event.seat_button_count
+    }
+
+    fn button_state(&self) -> ButtonState {
+        if self.data.event.pressed {
+            ButtonState::Pressed
+        } else {
+            ButtonState::Released
+        }
+    }
+}
+
+struct Replay {
+    records: VecDeque<Record>,
+    devices: HashMap<String, ReplayDevice>,
+    start: Instant,
+    /// Offset between the recorded timestamps and the current clock
+    time_shift: Option<u64>,
+}
+
+impl Replay {
+    fn device(&mut self, state: &mut State, id: &str, event: &RecordedEvent) -> ReplayDevice {
+        if let Some(device) = self.devices.get(id) {
+            return device.clone();
+        }
+
+        // the device was plugged in before the recording started
+        let device = ReplayDevice {
+            id: format!("replay-{}", id),
+            name: format!("Replayed device {}", id),
+            capabilities: event.capability().into_iter().collect(),
+        };
+        self.devices.insert(id.to_string(), device.clone());
+        state.process_input_event(InputEvent::<ReplayInput>::DeviceAdded {
+            device: device.clone(),
+        });
+        device
+    }
+
+    /// Moves recorded timestamps to the current clock, keeping their distances
+    fn time(&mut self, state: &State, recorded: u64) -> u64 {
+        let now = Duration::from(state.common.clock.now()).as_micros() as u64;
+        let shift = *self
+            .time_shift
+            .get_or_insert_with(|| now.wrapping_sub(recorded));
+        recorded.wrapping_add(shift)
+    }
+
+    fn dispatch(&mut self, state: &mut State, record: Record) {
+        macro_rules! replay {
+            ($variant:ident, $data:expr) => {{
+                let time = self.time(state, record.time);
+                let device = self.device(state, &record.device, &record.event);
+                state.process_input_event(InputEvent::<ReplayInput>::$variant {
+                    event: ReplayEvent {
+                        time,
+                        device,
+                        data: $data,
+                    },
+                })
+            }};
+        }
+
+        match record.event.clone() {
+            RecordedEvent::DeviceAdded(data) => {
+                let device = ReplayDevice {
+                    id: format!("replay-{}", record.device),
+                    name: data.name,
+                    capabilities: data.capabilities,
+                };
+                self.devices.insert(record.device, device.clone());
+                state.process_input_event(InputEvent::<ReplayInput>::DeviceAdded { device });
+            }
+            RecordedEvent::DeviceRemoved => {
+                if let Some(device) = self.devices.remove(&record.device) {
+                    state.process_input_event(InputEvent::<ReplayInput>::DeviceRemoved { device });
+                }
+            }
+            RecordedEvent::Keyboard(data) => replay!(Keyboard, data),
+            RecordedEvent::PointerMotion(data) => replay!(PointerMotion, data),
+            RecordedEvent::PointerMotionAbsolute(data) => replay!(PointerMotionAbsolute, data),
+            RecordedEvent::PointerButton(data) => replay!(PointerButton, data),
+            RecordedEvent::PointerAxis(data) => replay!(PointerAxis, data),
+            RecordedEvent::GestureSwipeBegin(data) => replay!(GestureSwipeBegin, data),
+            RecordedEvent::GestureSwipeUpdate(data) => replay!(GestureSwipeUpdate, data),
+            RecordedEvent::GestureSwipeEnd(data) => replay!(GestureSwipeEnd, data),
+            RecordedEvent::GesturePinchBegin(data) => replay!(GesturePinchBegin, data),
+            RecordedEvent::GesturePinchUpdate(data) => replay!(GesturePinchUpdate, data),
+            RecordedEvent::GesturePinchEnd(data) => replay!(GesturePinchEnd, data),
+            RecordedEvent::GestureHoldBegin(data) => replay!(GestureHoldBegin, data),
+            RecordedEvent::GestureHoldEnd(data) => replay!(GestureHoldEnd, data),
+            RecordedEvent::TouchDown(data) => replay!(TouchDown, data),
+            RecordedEvent::TouchMotion(data) => replay!(TouchMotion, data),
+            RecordedEvent::TouchUp(data) => replay!(TouchUp, data),
+            RecordedEvent::TouchCancel(data) => replay!(TouchCancel, data),
+            RecordedEvent::TouchFrame(data) => replay!(TouchFrame, data),
+            RecordedEvent::SwitchToggle(data) => replay!(SwitchToggle, data),
+            RecordedEvent::TabletToolAxis(data) => replay!(TabletToolAxis, data),
+            RecordedEvent::TabletToolProximity(data) => replay!(TabletToolProximity, data),
+            RecordedEvent::TabletToolTip(data) => replay!(TabletToolTip, data),
+            RecordedEvent::TabletToolButton(data) => replay!(TabletToolButton, data),
+        }
+    }
+
+    fn finish(self, state: &mut State) {
+        for device in self.devices.into_values() {
+            state.process_input_event(InputEvent::<ReplayInput>::DeviceRemoved { device });
+        }
+        info!("Input replay finished");
+    }
+}
+
+fn read_recording(path: &Path) -> Result<VecDeque<Record>> {
+    let file = File::open(path).with_context(|| format!("Failed to open {}", path.display()))?;
+    BufReader::new(file)
+        .lines()
+        .enumerate()
+        .filter(|(_, line)| !line.as_ref().is_ok_and(|line| line.trim().is_empty()))
+        .map(|(i, line)| {
+            let line = line.with_context(|| format!("Failed to read {}", path.display()))?;
+            serde_json::from_str(&line)
+                .with_context(|| format!("Invalid input record on line {}", i + 1))
+        })
+        .collect()
+}
+
+/// Replays the recording at `COSMIC_INPUT_REPLAY`, if set
+pub fn replay_from_env(handle: &LoopHandle<'static, State>) -> Result<()> {
+    let Some(path) = std::env::var_os("COSMIC_INPUT_REPLAY").map(PathBuf::from) else {
+        return Ok(());
+    };
+    let records = read_recording(&path)?;
+    info!(
+        "Replaying {} input events from {}",
+        records.len(),
+        path.display()
+    );
+
+    let mut replay = Some(Replay {
+        records,
+        devices: HashMap::new(),
+        start: Instant::now(),
+        time_shift: None,
+    });
+    handle
+        .insert_source(Timer::immediate(), move |_, _, state| {
+            let Some(current) = replay.as_mut() else {
+                return TimeoutAction::Drop;
+            };
+
+            let elapsed = current.start.elapsed().as_micros() as u64;
+            while current
+                .records
+                .front()
+                .is_some_and(|record| record.offset <= elapsed)
+            {
+                let record = current.records.pop_front().unwrap();
+                current.dispatch(state, record);
+            }
+
+            match current.records.front() {
+                Some(next) => {
+                    TimeoutAction::ToInstant(current.start + Duration::from_micros(next.offset))
+                }
+                None => {
+                    replay.take().unwrap().finish(state);
+                    TimeoutAction::Drop
+                }
+            }
+        })
+        .map_err(|_| anyhow::anyhow!("Failed to insert input replay timer"))?;
+
+    Ok(())
+}
+
+#[cfg(test)]
+mod test {
+    use super::*;
+
+    #[test]
+    fn test_record_roundtrip() {
+        let record = Record {
+            offset: 1500,
+            time: 42_000,
+            device: String::from("event3"),
+            event: RecordedEvent::TouchDown(TouchData {
+                slot: Some(1),
+                position: PositionData { x: 0.25, y: 0.5 },
+            }),
+        };
+        let line = serde_json::to_string(&record).unwrap();
+        assert_eq!(
+            line,
+            r#"{"offset":1500,"time":42000,"device":"event3","event":{"type":"touch_down","slot":1,"x":0.25,"y":0.5}}"#
+        );
+
+        let parsed: Record = serde_json::from_str(&line).unwrap();
+        assert_eq!(parsed.offset, 1500);
+        assert!(matches!(
+            parsed.event,
+            RecordedEvent::TouchDown(TouchData { slot: Some(1), .. })
+        ));
+    }
+
+    fn test_device() -> ReplayDevice {
+        ReplayDevice {
+            id: String::from("event5"),
+            name: String::from("Test device"),
+            capabilities: vec![Capability::Keyboard, Capability::Pointer],
+        }
+    }
+
+    /// Records `event`, writes and parses the record and turns it back into a replayed event
+    fn replay_roundtrip(event: InputEvent<ReplayInput>) -> (Record, ReplayDevice) {
+        let (event, device, time) = RecordedEvent::from_input(&event).unwrap();
+        let line = serde_json::to_string(&Record {
+            offset: 0,
+            time,
+            device,
+            event,
+        })
+        .unwrap();
+        let record: Record = serde_json::from_str(&line).unwrap();
+        let device = ReplayDevice {
+            id: record.device.clone(),
+            ..test_device()
+        };
+        (record, device)
+    }
+
+    #[test]
+    fn test_replay_keyboard() {
+        let (record, device) = replay_roundtrip(InputEvent::Keyboard {
+            event: ReplayEvent {
+                time: 1234,
+                device: test_device(),
+                data: KeyboardData {
+                    key: 38,
+                    pressed: true,
+                    count: 1,
+                },
+            },
+        });
+        let RecordedEvent::Keyboard(data) = record.event else {
+            panic!("keyboard event replayed as {:?}", record.event);
+        };
+        let event = ReplayEvent {
+            time: record.time,
+            device,
+            data,
+        };
+
+        assert_eq!(event.time(), 1234);
+        assert_eq!(event.device().id(), "event5");
+        assert_eq!(event.key_code().raw(), 38);
+        assert_eq!(event.state(), KeyState::Pressed);
+        assert_eq!(event.count(), 1);
+    }
+
+    #[test]
+    fn test_replay_pointer() {
+        let (record, device) = replay_roundtrip(InputEvent::PointerButton {
+            event: ReplayEvent {
+                time: 5678,
+                device: test_device(),
+                data: ButtonData {
+                    button: 0x110,
+                    pressed: false,
+                },
+            },
+        });
+        let RecordedEvent::PointerButton(data) = record.event else {
+            panic!("pointer button event replayed as {:?}", record.event);
+        };
+        let event = ReplayEvent {
+            time: record.time,
+            device,
+            data,
+        };
+
+        assert_eq!(event.time(), 5678);
+        assert_eq!(event.button_code(), 0x110);
+        assert_eq!(event.state(), ButtonState::Released);
+
+        let (record, device) = replay_roundtrip(InputEvent::PointerMotionAbsolute {
+            event: ReplayEvent {
+                time: 5679,
+                device: test_device(),
+                data: PositionData { x: 0.25, y: 0.75 },
+            },
+        });
+        let RecordedEvent::PointerMotionAbsolute(data) = record.event else {
+            panic!("absolute motion event replayed as {:?}", record.event);
+        };
+        let event = ReplayEvent {
+            time: record.time,
+            device,
+            data,
+        };
+
+        assert_eq!(event.x_transformed(1920), 480.0);
+        assert_eq!(event.y_transformed(1080), 810.0);
+    }
+
+    #[test]
+    fn test_replay_tablet_tool() {
+        let (record, device) = replay_roundtrip(InputEvent::TabletToolTip {
+            event: ReplayEvent {
+                time: 91,
+                device: test_device(),
+                data: TabletToolData {
+                    tool: ToolData {
+                        kind: ToolKind::Eraser,
+                        serial: 7,
+                        hardware_id: 0x80a,
+                        capabilities: vec![ToolCapability::Pressure, ToolCapability::Tilt],
+                    },
+                    position: PositionData { x: 0.5, y: 0.5 },
+                    dx: 0.0,
+                    dy: 0.0,
+                    pressure: Some(0.4),
+                    distance: None,
+                    tilt_x: None,
+                    tilt_y: None,
+                    slider: None,
+                    rotation: None,
+                    wheel: None,
+                    event: TipData { down: true },
+                },
+            },
+        });
+        let RecordedEvent::TabletToolTip(data) = record.event else {
+            panic!("tablet tip event replayed as {:?}", record.event);
+        };
+        let event = ReplayEvent {
+            time: record.time,
+            device,
+            data,
+        };
+
+        // the capabilities are recorded in the order of `TOOL_CAPABILITIES`
+        assert_eq!(
+            tool_data(&event.tool()).capabilities,
+            [ToolCapability::Tilt, ToolCapability::Pressure]
+        );
+        assert_eq!(tool_data(&event.tool()).kind, ToolKind::Eraser);
+        assert!(matches!(event.tip_state(), TabletToolTipState::Down));
+        assert!(event.pressure_has_changed());
+        assert_eq!(event.pressure(), 0.4);
+        assert!(!event.tilt_x_has_changed());
+    }
+}
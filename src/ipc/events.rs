@@ -0,0 +1,297 @@
+// SPDX-License-Identifier: GPL-3.0-only
+
+//! Event stream for subscribed ipc clients.
+//!
+//! Events are derived by comparing a snapshot of the relevant compositor state on every
+//! refresh, so rapid changes in between two refreshes are coalesced into a single event.
+
+use crate::{
+    shell::{SeatExt, focus::target::KeyboardFocusTarget},
+    state::Common,
+    wayland::protocols::toplevel_info::toplevel_identifier,
+};
+use indexmap::IndexMap;
+use serde::{Deserialize, Serialize};
+use smithay::output::Output;
+
+use super::IpcState;
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Hash, Serialize, Deserialize)]
+#[serde(rename_all = "snake_case")]
+pub enum EventType {
+    Window,
+    Workspace,
+    Output,
+}
+
+#[derive(Debug, Clone, PartialEq, Serialize)]
+pub struct WindowInfo {
+    pub id: String,
+    pub title: String,
+    pub app_id: String,
+}
+
+#[derive(Debug, Clone, PartialEq, Serialize)]
+pub struct ModeInfo {
+    pub width: i32,
+    pub height: i32,
+    /// Refresh rate in mHz
+    pub refresh: i32,
+}
+
+#[derive(Debug, Clone, PartialEq, Serialize)]
+pub struct OutputInfo {
+    pub name: String,
+    pub make: String,
+    pub model: String,
+    pub mode: Option<ModeInfo>,
+    pub scale: f64,
+    pub position: (i32, i32),
+}
+
+impl OutputInfo {
+    pub fn from_output(output: &Output) -> OutputInfo {
+        let props = output.physical_properties();
+        let position = output.current_location();
+        OutputInfo {
+            name: output.name(),
+            make: props.make,
+            model: props.model,
+            mode: output.current_mode().map(|mode| ModeInfo {
+                width: mode.size.w,
+                height: mode.size.h,
+                refresh: mode.refresh,
+            }),
+            scale: output.current_scale().fractional_scale(),
+            position: (position.x, position.y),
+        }
+    }
+}
+
+#[derive(Debug, Clone, Serialize)]
+#[serde(rename_all = "snake_case", tag = "event")]
+pub enum Event {
+    WindowMapped {
+        window: WindowInfo,
+    },
+    WindowUnmapped {
+        id: String,
+    },
+    /// Keyboard focus of the last active seat moved to another window, or none.
+    WindowFocused {
+        id: Option<String>,
+    },
+    WindowTitleChanged {
+        id: String,
+        title: String,
+    },
+    WindowAppIdChanged {
+        id: String,
+        app_id: String,
+    },
+    WorkspaceActivated {
+        id: usize,
+        index: usize,
+        output: String,
+    },
+    OutputAdded {
+        output: OutputInfo,
+    },
+    OutputRemoved {
+        name: String,
+    },
+    /// Mode, scale or position of an output changed.
+    OutputChanged {
+        output: OutputInfo,
+    },
+}
+
+impl Event {
+    pub fn event_type(&self) -> EventType {
+        match self {
+            Event::WindowMapped { .. }
+            | Event::WindowUnmapped { .. }
+            | Event::WindowFocused { .. }
+            | Event::WindowTitleChanged { .. }
+            | Event::WindowAppIdChanged { .. } => EventType::Window,
+            Event::WorkspaceActivated { .. } => EventType::Workspace,
+            Event::OutputAdded { .. }
+            | Event::OutputRemoved { .. }
+            | Event::OutputChanged { .. } => EventType::Output,
+        }
+    }
+}
+
+#[derive(Debug, Default)]
+pub(super) struct Snapshot {
+    outputs: IndexMap<String, OutputInfo>,
+    /// Active workspace id and index per output
+    workspaces: IndexMap<String, (usize, usize)>,
+    windows: IndexMap<String, WindowInfo>,
+    focused: Option<String>,
+}
+
+impl Snapshot {
+    pub(super) fn new(common: &Common) -> Snapshot {
+        let shell = common.shell.read();
+
+        let outputs = shell
+            .outputs()
+            .map(|output| (output.name(), OutputInfo::from_output(output)))
+            .collect();
+        let workspaces = shell
+            .workspaces
+            .sets
+            .iter()
+            .filter_map(|(output, set)| {
+                let workspace = set.workspaces.get(set.active)?;
+                Some((output.name(), (workspace.handle.id(), set.active)))
+            })
+            .collect();
+        let windows = common
+            .toplevel_info_state
+            .registered_toplevels()
+            .filter_map(|window| {
+                let id = toplevel_identifier(window)?;
+                Some((
+                    id.clone(),
+                    WindowInfo {
+                        id,
+                        title: window.title(),
+                        app_id: window.app_id(),
+                    },
+                ))
+            })
+            .collect();
+        let focused = shell
+            .seats
+            .last_active()
+            .get_keyboard()
+            .and_then(|keyboard| keyboard.current_focus())
+            .and_then(|focus| match focus {
+                KeyboardFocusTarget::Element(mapped) => Some(mapped.active_window()),
+                KeyboardFocusTarget::Fullscreen(surface) => Some(surface),
+                _ => None,
+            })
+            .and_then(|window| toplevel_identifier(&window));
+
+        Snapshot {
+            outputs,
+            workspaces,
+            windows,
+            focused,
+        }
+    }
+
+    /// Events turning `self` into `new`, ordered so that clients never see references
+    /// to outputs or windows they were not told about.
+    fn diff(&self, new: &Snapshot) -> Vec<Event> {
+        let mut events = Vec::new();
+
+        for (name, output) in &new.outputs {
+            match self.outputs.get(name) {
+                None => events.push(Event::OutputAdded {
+                    output: output.clone(),
+                }),
+                Some(old) if old != output => events.push(Event::OutputChanged {
+                    output: output.clone(),
+                }),
+                _ => {}
+            }
+        }
+
+        for (output, (id, index)) in &new.workspaces {
+            if self.workspaces.get(output).map(|(id, _)| id) != Some(id) {
+                events.push(Event::WorkspaceActivated {
+                    id: *id,
+                    index: *index,
+                    output: output.clone(),
+                });
+            }
+        }
+
+        for (id, window) in &new.windows {
+            match self.windows.get(id) {
+                None => events.push(Event::WindowMapped {
+                    window: window.clone(),
+                }),
+                Some(old) => {
+                    if old.title != window.title {
+                        events.push(Event::WindowTitleChanged {
+                            id: id.clone(),
+                            title: window.title.clone(),
+                        });
+                    }
+                    if old.app_id != window.app_id {
+                        events.push(Event::WindowAppIdChanged {
+                            id: id.clone(),
+                            app_id: window.app_id.clone(),
+                        });
+                    }
+                }
+            }
+        }
+
+        if self.focused != new.focused {
+            events.push(Event::WindowFocused {
+                id: new.focused.clone(),
+            });
+        }
+
+        for id in self.windows.keys() {
+            if !new.windows.contains_key(id) {
+                events.push(Event::WindowUnmapped { id: id.clone() });
+            }
+        }
+
+        for name in self.outputs.keys() {
+            if !new.outputs.contains_key(name) {
+                events.push(Event::OutputRemoved { name: name.clone() });
+            }
+        }
+
+        events
+    }
+}
+
+impl IpcState {
+    fn has_subscribers(&self) -> bool {
+        self.clients
+            .values()
+            .any(|client| !client.subscriptions.is_empty())
+    }
+
+    fn broadcast(&mut self, event: &Event) {
+        let event_type = event.event_type();
+        let subscribers = self
+            .clients
+            .iter()
+            .filter(|(_, client)| client.subscriptions.contains(&event_type))
+            .map(|(id, _)| *id)
+            .collect::<Vec<_>>();
+        for id in subscribers {
+            self.send(id, event);
+        }
+    }
+}
+
+impl Common {
+    /// Sends events for everything that changed since the last call to subscribed ipc clients
+    pub fn send_ipc_events(&mut self) {
+        let Some(ipc_state) = self.ipc_state.as_ref() else {
+            return;
+        };
+        if !ipc_state.has_subscribers() {
+            self.ipc_state.as_mut().unwrap().snapshot = None;
+            return;
+        }
+
+        let snapshot = Snapshot::new(self);
+        let ipc_state = self.ipc_state.as_mut().unwrap();
+        if let Some(old) = ipc_state.snapshot.replace(snapshot) {
+            for event in old.diff(ipc_state.snapshot.as_ref().unwrap()) {
+                ipc_state.broadcast(&event);
+            }
+        }
+    }
+}
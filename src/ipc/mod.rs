@@ -0,0 +1,524 @@
+// SPDX-License-Identifier: GPL-3.0-only
+
+//! Control socket to script the compositor, similar to `swaymsg`.
+//!
+//! The socket is created in `$XDG_RUNTIME_DIR` and its path is exported to clients as
+//! `COSMIC_COMP_SOCKET`. The protocol is newline-delimited JSON: every line a client sends
+//! is parsed as a [`Request`] and answered by a single line containing a [`Response`].
+//!
+//! Actions use the same representation as the shortcuts config, and may optionally be
+//! targeted at a window (by its `ext-foreign-toplevel-list` identifier) or a workspace
+//! (by its id), which gets focused before the action is executed:
+//!
+//! ```text
+//! -> {"request":"action","action":{"Workspace":2}}
+//! <- {"response":"success"}
+//! -> {"request":"action","action":"Maximize","target":{"window":"c0ffee..."}}
+//! <- {"response":"success"}
+//! -> {"request":"action","action":"ToggleTiling","target":{"workspace":42}}
+//! <- {"response":"error","message":"No workspace with id 42"}
+//! ```
+//!
+//! Actions only the compositor provides (like the master-stack layout) are sent
+//! as `compositor_action`:
+//!
+//! ```text
+//! -> {"request":"compositor_action","action":"Promote"}
+//! <- {"response":"success"}
+//! ```
+//!
+//! Clients can also subscribe to [`Event`]s of certain [`EventType`]s, which are then sent
+//! as additional lines on the same connection:
+//!
+//! ```text
+//! -> {"request":"subscribe","events":["window","workspace"]}
+//! <- {"response":"success"}
+//! <- {"event":"workspace_activated","id":3,"index":1,"output":"DP-1"}
+//! <- {"event":"window_title_changed","id":"c0ffee...","title":"~/src"}
+//! ```
+//!
+//! The complete state of the shell (outputs, workspaces, the tiling tree, floating,
+//! stacked, sticky and minimized windows and focus stacks) can be queried with
+//! `{"request":"get_tree"}`, which is answered by a [`Tree`].
+//!
+//! Virtual outputs, which aren't backed by a connector but can be captured like any other
+//! output, are created and removed at runtime. The refresh rate is given in mHz:
+//!
+//! ```text
+//! -> {"request":"create_virtual_output","width":1920,"height":1080,"refresh":30000,"scale":1.0}
+//! <- {"response":"output","output":{"name":"VIRTUAL-1","make":"COSMIC","model":"Virtual",...}}
+//! -> {"request":"remove_virtual_output","name":"VIRTUAL-1"}
+//! <- {"response":"success"}
+//! ```
+
+use crate::{
+    config::Action,
+    shell::{SeatExt, WorkspaceDelta},
+    utils::prelude::*,
+    wayland::protocols::{
+        toplevel_info::toplevel_identifier, toplevel_management::ToplevelManagementHandler,
+    },
+};
+use anyhow::{Context, Result, anyhow};
+use cosmic_comp_config::bindings::CompAction;
+use cosmic_settings_config::shortcuts;
+use serde::{Deserialize, Serialize};
+use smithay::{
+    output::Mode as OutputMode,
+    reexports::calloop::{Interest, LoopHandle, Mode, PostAction, generic::Generic},
+    utils::SERIAL_COUNTER,
+};
+use std::{
+    collections::{HashMap, HashSet},
+    ffi::OsStr,
+    io::{self, Read, Write},
+    net::Shutdown,
+    os::unix::net::{UnixListener, UnixStream},
+    path::PathBuf,
+};
+use tracing::{debug, info, warn};
+
+mod events;
+mod tree;
+use self::events::Snapshot;
+pub use self::events::{Event, EventType, ModeInfo, OutputInfo, WindowInfo};
+pub use self::tree::*;
+
+/// Maximum size of a single request line.
+const MAX_REQUEST_SIZE: usize = 64 * 1024;
+/// Clients not reading their responses get disconnected once this much output is queued.
+const MAX_PENDING_OUTPUT: usize = 4 * 1024 * 1024;
+/// Refresh rate of virtual outputs in mHz, if none is requested
+const DEFAULT_VIRTUAL_REFRESH: i32 = 60_000;
+
+#[derive(Debug, Deserialize)]
+#[serde(rename_all = "snake_case", tag = "request")]
+pub enum Request {
+    /// Run a shortcut action, optionally after focusing the given target
+    Action {
+        action: shortcuts::Action,
+        #[serde(default)]
+        target: Option<Target>,
+    },
+    /// Run a compositor action, optionally after focusing the given target
+    CompositorAction {
+        action: CompAction,
+        #[serde(default)]
+        target: Option<Target>,
+    },
+    /// Receive events of the given types on this connection
+    Subscribe { events: HashSet<EventType> },
+    /// Dump the state of the shell
+    GetTree,
+    /// Create a virtual output, answered by its [`OutputInfo`]
+    CreateVirtualOutput {
+        width: i32,
+        height: i32,
+        /// Refresh rate in mHz
+        #[serde(default)]
+        refresh: Option<i32>,
+        #[serde(default)]
+        scale: Option<f64>,
+    },
+    /// Remove a virtual output by its name
+    RemoveVirtualOutput { name: String },
+}
+
+#[derive(Debug, Clone, Deserialize)]
+#[serde(rename_all = "snake_case")]
+pub enum Target {
+    /// `ext-foreign-toplevel-list` identifier of a window
+    Window(String),
+    /// Id of a workspace
+    Workspace(usize),
+}
+
+#[derive(Debug, Serialize)]
+#[serde(rename_all = "snake_case", tag = "response")]
+pub enum Response {
+    Success,
+    Error { message: String },
+    Tree { tree: Tree },
+    Output { output: OutputInfo },
+}
+
+impl Response {
+    fn error(err: impl std::fmt::Display) -> Response {
+        Response::Error {
+            message: err.to_string(),
+        }
+    }
+}
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Hash)]
+struct ClientId(u64);
+
+#[derive(Debug)]
+struct Client {
+    stream: UnixStream,
+    pending_output: Vec<u8>,
+    subscriptions: HashSet<EventType>,
+}
+
+#[derive(Debug)]
+pub struct IpcState {
+    path: PathBuf,
+    clients: HashMap<ClientId, Client>,
+    next_client_id: u64,
+    snapshot: Option<Snapshot>,
+}
+
+impl IpcState {
+    pub fn new(handle: &LoopHandle<'static, State>, wayland_socket: &OsStr) -> Result<IpcState> {
+        let runtime_dir = std::env::var_os("XDG_RUNTIME_DIR")
+            .map(PathBuf::from)
+            .ok_or(anyhow!("XDG_RUNTIME_DIR is not set"))?;
+        let mut file_name = OsStr::new("cosmic-comp-").to_os_string();
+        file_name.push(wayland_socket);
+        file_name.push(".sock");
+        let path = runtime_dir.join(file_name);
+
+        // We own the wayland socket of the same name, so any existing file is stale.
+        if path.exists() {
+            let _ = std::fs::remove_file(&path);
+        }
+        let listener = UnixListener::bind(&path)
+            .with_context(|| format!("Failed to bind ipc socket at {}", path.display()))?;
+        listener
+            .set_nonblocking(true)
+            .with_context(|| "Failed to set ipc socket non-blocking")?;
+
+        handle
+            .insert_source(
+                Generic::new(listener, Interest::READ, Mode::Level),
+                |_, listener, state| {
+                    // SAFETY: We don't drop the listener!
+                    let listener = unsafe { listener.get_mut() };
+                    loop {
+                        match listener.accept() {
+                            Ok((stream, _)) => state.new_ipc_client(stream),
+                            Err(err) if err.kind() == io::ErrorKind::WouldBlock => break,
+                            Err(err) => {
+                                warn!(?err, "Failed to accept ipc connection");
+                                break;
+                            }
+                        }
+                    }
+                    Ok(PostAction::Continue)
+                },
+            )
+            .with_context(|| "Failed to init the ipc socket source")?;
+
+        info!("Listening for ipc connections on {}", path.display());
+        Ok(IpcState {
+            path,
+            clients: HashMap::new(),
+            next_client_id: 0,
+            snapshot: None,
+        })
+    }
+
+    pub fn socket_path(&self) -> &PathBuf {
+        &self.path
+    }
+
+    fn send(&mut self, id: ClientId, message: &impl Serialize) {
+        let Some(client) = self.clients.get_mut(&id) else {
+            return;
+        };
+        match serde_json::to_vec(message) {
+            Ok(mut bytes) => {
+                bytes.push(b'\n');
+                client.pending_output.extend(bytes);
+            }
+            Err(err) => {
+                warn!(?err, "Failed to serialize ipc message");
+                return;
+            }
+        }
+        self.flush(id);
+    }
+
+    /// Writes as much pending output as possible, disconnecting clients on errors
+    /// or if they are too slow to keep up.
+    fn flush(&mut self, id: ClientId) {
+        let Some(client) = self.clients.get_mut(&id) else {
+            return;
+        };
+
+        let mut written = 0;
+        let result = loop {
+            if written == client.pending_output.len() {
+                break Ok(());
+            }
+            match client.stream.write(&client.pending_output[written..]) {
+                Ok(0) => break Err(io::ErrorKind::WriteZero.into()),
+                Ok(n) => written += n,
+                Err(err) if err.kind() == io::ErrorKind::WouldBlock => break Ok(()),
+                Err(err) if err.kind() == io::ErrorKind::Interrupted => continue,
+                Err(err) => break Err(err),
+            }
+        };
+        client.pending_output.drain(..written);
+
+        if let Err(err) = result {
+            debug!(?err, "Failed to write to ipc client");
+            self.disconnect(id);
+        } else if client.pending_output.len() > MAX_PENDING_OUTPUT {
+            warn!("Ipc client is not reading its messages, disconnecting");
+            self.disconnect(id);
+        }
+    }
+
+    fn disconnect(&mut self, id: ClientId) {
+        if let Some(client) = self.clients.remove(&id) {
+            // wakes up the read source, which then removes itself
+            let _ = client.stream.shutdown(Shutdown::Both);
+        }
+    }
+}
+
+impl Drop for IpcState {
+    fn drop(&mut self) {
+        let _ = std::fs::remove_file(&self.path);
+    }
+}
+
+impl State {
+    fn new_ipc_client(&mut self, stream: UnixStream) {
+        let Some(ipc_state) = self.common.ipc_state.as_mut() else {
+            return;
+        };
+
+        let writer = match stream
+            .set_nonblocking(true)
+            .and_then(|_| stream.try_clone())
+        {
+            Ok(writer) => writer,
+            Err(err) => {
+                warn!(?err, "Failed to setup ipc connection");
+                return;
+            }
+        };
+
+        let id = ClientId(ipc_state.next_client_id);
+        ipc_state.next_client_id += 1;
+
+        let mut read_buffer = Vec::new();
+        if let Err(err) = self.common.event_loop_handle.insert_source(
+            Generic::new(stream, Interest::BOTH, Mode::Edge),
+            move |readiness, stream, state| {
+                if readiness.writable
+                    && let Some(ipc_state) = state.common.ipc_state.as_mut()
+                {
+                    ipc_state.flush(id);
+                }
+                if !readiness.readable {
+                    return Ok(PostAction::Continue);
+                }
+
+                // SAFETY: We don't drop the stream!
+                let stream = unsafe { stream.get_mut() };
+                let mut closed = false;
+                let mut buf = [0u8; 4096];
+                loop {
+                    match stream.read(&mut buf) {
+                        Ok(0) => {
+                            closed = true;
+                            break;
+                        }
+                        Ok(n) => read_buffer.extend_from_slice(&buf[..n]),
+                        Err(err) if err.kind() == io::ErrorKind::WouldBlock => break,
+                        Err(err) if err.kind() == io::ErrorKind::Interrupted => continue,
+                        Err(err) => {
+                            debug!(?err, "Failed to read from ipc client");
+                            closed = true;
+                            break;
+                        }
+                    }
+                }
+
+                while let Some(pos) = read_buffer.iter().position(|b| *b == b'\n') {
+                    let line = read_buffer.drain(..=pos).collect::<Vec<_>>();
+                    let line = String::from_utf8_lossy(&line);
+                    if line.trim().is_empty() {
+                        continue;
+                    }
+                    let response = match serde_json::from_str::<Request>(&line) {
+                        Ok(request) => state.handle_ipc_request(id, request),
+                        Err(err) => Response::error(format!("Invalid request: {err}")),
+                    };
+                    match state.common.ipc_state.as_mut() {
+                        Some(ipc_state) if ipc_state.clients.contains_key(&id) => {
+                            ipc_state.send(id, &response)
+                        }
+                        _ => {
+                            closed = true;
+                            break;
+                        }
+                    }
+                }
+
+                if read_buffer.len() > MAX_REQUEST_SIZE {
+                    warn!("Ipc request exceeds maximum size, disconnecting");
+                    closed = true;
+                }
+
+                if closed
+                    || !state
+                        .common
+                        .ipc_state
+                        .as_ref()
+                        .is_some_and(|ipc_state| ipc_state.clients.contains_key(&id))
+                {
+                    if let Some(ipc_state) = state.common.ipc_state.as_mut() {
+                        ipc_state.disconnect(id);
+                    }
+                    Ok(PostAction::Remove)
+                } else {
+                    Ok(PostAction::Continue)
+                }
+            },
+        ) {
+            warn!(?err, "Failed to insert ipc connection source");
+            return;
+        }
+
+        ipc_state.clients.insert(
+            id,
+            Client {
+                stream: writer,
+                pending_output: Vec::new(),
+                subscriptions: HashSet::new(),
+            },
+        );
+    }
+
+    fn handle_ipc_action(&mut self, action: Action, target: Option<Target>) -> Response {
+        if let Some(target) = target
+            && let Err(err) = self.focus_ipc_target(&target)
+        {
+            return Response::error(err);
+        }
+
+        let seat = self.common.shell.read().seats.last_active().clone();
+        let time = self.common.clock.now().as_millis();
+        self.handle_action(
+            action,
+            &seat,
+            SERIAL_COUNTER.next_serial(),
+            time,
+            shortcuts::Binding {
+                modifiers: shortcuts::Modifiers::default(),
+                keycode: None,
+                key: None,
+                description: None,
+            },
+            None,
+        );
+        Response::Success
+    }
+
+    fn handle_ipc_request(&mut self, client: ClientId, request: Request) -> Response {
+        match request {
+            Request::Action { action, target } => {
+                self.handle_ipc_action(Action::Shortcut(action), target)
+            }
+            Request::CompositorAction { action, target } => {
+                self.handle_ipc_action(Action::Compositor(action), target)
+            }
+            Request::GetTree => Response::Tree {
+                tree: Tree::new(&self.common),
+            },
+            Request::CreateVirtualOutput {
+                width,
+                height,
+                refresh,
+                scale,
+            } => {
+                let refresh = refresh.unwrap_or(DEFAULT_VIRTUAL_REFRESH);
+                let scale = scale.unwrap_or(1.0);
+                if width <= 0 || height <= 0 || refresh <= 0 {
+                    return Response::error("Invalid mode");
+                }
+                if !(0.5..=4.0).contains(&scale) {
+                    return Response::error("Scale has to be between 0.5 and 4");
+                }
+                let mode = OutputMode {
+                    size: (width, height).into(),
+                    refresh,
+                };
+                match self.create_virtual_output(mode, scale) {
+                    Ok(output) => Response::Output {
+                        output: OutputInfo::from_output(&output),
+                    },
+                    Err(err) => Response::error(format!("{err:#}")),
+                }
+            }
+            Request::RemoveVirtualOutput { name } => match self.remove_virtual_output(&name) {
+                Ok(()) => Response::Success,
+                Err(err) => Response::error(format!("{err:#}")),
+            },
+            Request::Subscribe { events } => {
+                // start tracking changes from here, if nobody was subscribed before
+                let snapshot = self
+                    .common
+                    .ipc_state
+                    .as_ref()
+                    .is_some_and(|ipc_state| ipc_state.snapshot.is_none())
+                    .then(|| Snapshot::new(&self.common));
+                let Some(ipc_state) = self.common.ipc_state.as_mut() else {
+                    return Response::error("Ipc is not initialized");
+                };
+                if snapshot.is_some() {
+                    ipc_state.snapshot = snapshot;
+                }
+                if let Some(client) = ipc_state.clients.get_mut(&client) {
+                    client.subscriptions.extend(events);
+                }
+                Response::Success
+            }
+        }
+    }
+
+    fn focus_ipc_target(&mut self, target: &Target) -> Result<()> {
+        match target {
+            Target::Window(identifier) => {
+                let window = self
+                    .common
+                    .toplevel_info_state
+                    .registered_toplevels()
+                    .find(|window| toplevel_identifier(*window).as_deref() == Some(identifier))
+                    .cloned()
+                    .ok_or_else(|| anyhow!("No window with identifier {identifier}"))?;
+                let dh = self.common.display_handle.clone();
+                ToplevelManagementHandler::activate(self, &dh, &window, None);
+                Ok(())
+            }
+            Target::Workspace(id) => {
+                let mut shell = self.common.shell.write();
+                let (output, idx) = shell
+                    .workspaces
+                    .sets
+                    .iter()
+                    .find_map(|(output, set)| {
+                        set.workspaces
+                            .iter()
+                            .position(|workspace| workspace.handle.id() == *id)
+                            .map(|idx| (output.clone(), idx))
+                    })
+                    .ok_or_else(|| anyhow!("No workspace with id {id}"))?;
+                shell
+                    .activate(
+                        &output,
+                        idx,
+                        WorkspaceDelta::new_shortcut(),
+                        &mut self.common.workspace_state.update(),
+                    )
+                    .map_err(|_| anyhow!("Failed to activate workspace {id}"))?;
+                shell.seats.last_active().set_active_output(&output);
+                Ok(())
+            }
+        }
+    }
+}
@@ -0,0 +1,293 @@
+// SPDX-License-Identifier: GPL-3.0-only
+
+//! Serializable dump of the shell state, returned by the `get_tree` request.
+
+use crate::{
+    shell::{
+        CosmicMapped, CosmicSurface, MinimizedWindow, Workspace, WorkspaceSet,
+        focus::FocusTarget,
+        layout::tiling::{Data, TilingLayout},
+    },
+    utils::prelude::*,
+    wayland::protocols::toplevel_info::toplevel_identifier,
+};
+use cosmic_settings_config::shortcuts::action::Orientation;
+use id_tree::NodeId;
+use serde::Serialize;
+use smithay::{input::Seat, output::Output, utils::Rectangle};
+
+use super::OutputInfo;
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Serialize)]
+pub struct Geometry {
+    pub x: i32,
+    pub y: i32,
+    pub width: i32,
+    pub height: i32,
+}
+
+impl From<Rectangle<i32, Global>> for Geometry {
+    fn from(rect: Rectangle<i32, Global>) -> Self {
+        Geometry {
+            x: rect.loc.x,
+            y: rect.loc.y,
+            width: rect.size.w,
+            height: rect.size.h,
+        }
+    }
+}
+
+#[derive(Debug, Serialize)]
+pub struct Tree {
+    pub outputs: Vec<OutputNode>,
+}
+
+#[derive(Debug, Serialize)]
+pub struct OutputNode {
+    #[serde(flatten)]
+    pub info: OutputInfo,
+    /// Index of the active workspace
+    pub active_workspace: usize,
+    pub workspaces: Vec<WorkspaceNode>,
+    pub sticky: Vec<ElementNode>,
+    /// Minimized sticky windows
+    pub minimized: Vec<ElementNode>,
+}
+
+#[derive(Debug, Serialize)]
+pub struct WorkspaceNode {
+    pub id: usize,
+    pub index: usize,
+    /// Persistent id of pinned workspaces
+    pub pinned_id: Option<String>,
+    pub tiling_enabled: bool,
+    pub fullscreen: Option<WindowNode>,
+    pub tiling: Option<TilingNode>,
+    pub floating: Vec<ElementNode>,
+    pub minimized: Vec<ElementNode>,
+    pub focus_stacks: Vec<FocusStackNode>,
+}
+
+#[derive(Debug, Serialize)]
+#[serde(rename_all = "snake_case", tag = "type")]
+pub enum TilingNode {
+    Group {
+        /// Direction the children are split in, `vertical` places them side by side
+        orientation: &'static str,
+        sizes: Vec<i32>,
+        geometry: Geometry,
+        children: Vec<TilingNode>,
+    },
+    Element {
+        geometry: Geometry,
+        element: ElementNode,
+    },
+    /// Space reserved for a window being dragged or dropped
+    Placeholder { geometry: Geometry },
+}
+
+#[derive(Debug, Serialize)]
+#[serde(rename_all = "snake_case", tag = "type")]
+pub enum ElementNode {
+    Window {
+        geometry: Option<Geometry>,
+        window: WindowNode,
+    },
+    Stack {
+        geometry: Option<Geometry>,
+        /// Index of the active tab
+        active: usize,
+        tabs: Vec<WindowNode>,
+    },
+}
+
+#[derive(Debug, Serialize)]
+pub struct WindowNode {
+    /// `ext-foreign-toplevel-list` identifier, not yet set for windows that are being mapped
+    pub id: Option<String>,
+    pub title: String,
+    pub app_id: String,
+    pub activated: bool,
+    pub maximized: bool,
+    pub fullscreen: bool,
+    pub minimized: bool,
+    pub sticky: bool,
+}
+
+#[derive(Debug, Serialize)]
+pub struct FocusStackNode {
+    pub seat: String,
+    /// Window ids, most recently focused first
+    pub windows: Vec<String>,
+}
+
+impl Tree {
+    pub fn new(common: &Common) -> Tree {
+        let shell = common.shell.read();
+        let seats = shell.seats.iter().cloned().collect::<Vec<_>>();
+
+        let outputs = shell
+            .workspaces
+            .sets
+            .iter()
+            .map(|(output, set)| output_node(output, set, &seats))
+            .collect();
+
+        Tree { outputs }
+    }
+}
+
+fn output_node(output: &Output, set: &WorkspaceSet, seats: &[Seat<State>]) -> OutputNode {
+    OutputNode {
+        info: OutputInfo::from_output(output),
+        active_workspace: set.active,
+        workspaces: set
+            .workspaces
+            .iter()
+            .enumerate()
+            .map(|(index, workspace)| workspace_node(index, workspace, seats))
+            .collect(),
+        sticky: set
+            .sticky_layer
+            .mapped()
+            .map(|mapped| {
+                let geometry = set
+                    .sticky_layer
+                    .element_geometry(mapped)
+                    .map(|geo| geo.to_global(output).into());
+                element_node(mapped, geometry)
+            })
+            .collect(),
+        minimized: set.minimized_windows.iter().map(minimized_node).collect(),
+    }
+}
+
+fn workspace_node(index: usize, workspace: &Workspace, seats: &[Seat<State>]) -> WorkspaceNode {
+    let output = workspace.output();
+    let tiling = workspace
+        .tiling_layer
+        .tree()
+        .root_node_id()
+        .map(|root| tiling_node(&workspace.tiling_layer, root, output));
+
+    WorkspaceNode {
+        id: workspace.handle.id(),
+        index,
+        pinned_id: workspace.id.clone(),
+        tiling_enabled: workspace.tiling_enabled,
+        fullscreen: workspace.get_fullscreen().map(window_node),
+        tiling,
+        floating: workspace
+            .floating_layer
+            .mapped()
+            .map(|mapped| {
+                let geometry = workspace
+                    .floating_layer
+                    .element_geometry(mapped)
+                    .map(|geo| geo.to_global(output).into());
+                element_node(mapped, geometry)
+            })
+            .collect(),
+        minimized: workspace
+            .minimized_windows
+            .iter()
+            .map(minimized_node)
+            .collect(),
+        focus_stacks: seats
+            .iter()
+            .map(|seat| FocusStackNode {
+                seat: seat.name().to_string(),
+                windows: workspace
+                    .focus_stack
+                    .get(seat)
+                    .iter()
+                    .filter_map(|target| match target {
+                        FocusTarget::Window(mapped) => toplevel_identifier(&mapped.active_window()),
+                        FocusTarget::Fullscreen(surface) => toplevel_identifier(surface),
+                    })
+                    .collect(),
+            })
+            .collect(),
+    }
+}
+
+fn tiling_node(layout: &TilingLayout, id: &NodeId, output: &Output) -> TilingNode {
+    let tree = layout.tree();
+    let node = tree.get(id).unwrap();
+    match node.data() {
+        Data::Group {
+            orientation,
+            sizes,
+            last_geometry,
+            ..
+        } => TilingNode::Group {
+            orientation: match orientation {
+                Orientation::Horizontal => "horizontal",
+                Orientation::Vertical => "vertical",
+            },
+            sizes: sizes.clone(),
+            geometry: last_geometry.to_global(output).into(),
+            children: node
+                .children()
+                .iter()
+                .map(|child| tiling_node(layout, child, output))
+                .collect(),
+        },
+        Data::Mapped {
+            mapped,
+            last_geometry,
+            ..
+        } => {
+            let geometry = last_geometry.to_global(output).into();
+            TilingNode::Element {
+                geometry,
+                element: element_node(mapped, Some(geometry)),
+            }
+        }
+        Data::Placeholder { last_geometry, .. } => TilingNode::Placeholder {
+            geometry: last_geometry.to_global(output).into(),
+        },
+    }
+}
+
+fn element_node(mapped: &CosmicMapped, geometry: Option<Geometry>) -> ElementNode {
+    if let Some(stack) = mapped.stack_ref() {
+        let active = stack.active();
+        let tabs = stack.surfaces().collect::<Vec<_>>();
+        ElementNode::Stack {
+            geometry,
+            active: tabs.iter().position(|tab| tab == &active).unwrap_or(0),
+            tabs: tabs.iter().map(window_node).collect(),
+        }
+    } else {
+        ElementNode::Window {
+            geometry,
+            window: window_node(&mapped.active_window()),
+        }
+    }
+}
+
+fn minimized_node(minimized: &MinimizedWindow) -> ElementNode {
+    match minimized {
+        MinimizedWindow::Fullscreen { surface, .. } => ElementNode::Window {
+            geometry: None,
+            window: window_node(surface),
+        },
+        MinimizedWindow::Floating { window, .. } | MinimizedWindow::Tiling { window, .. } => {
+            element_node(window, None)
+        }
+    }
+}
+
+fn window_node(window: &CosmicSurface) -> WindowNode {
+    WindowNode {
+        id: toplevel_identifier(window),
+        title: window.title(),
+        app_id: window.app_id(),
+        activated: window.is_activated(false),
+        maximized: window.is_maximized(false),
+        fullscreen: window.is_fullscreen(false),
+        minimized: window.is_minimized(),
+        sticky: window.is_sticky(),
+    }
+}
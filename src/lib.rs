@@ -40,6 +40,7 @@ pub mod dbus;
 pub mod debug;
 pub mod hooks;
 pub mod input;
+pub mod ipc;
 mod logger;
 pub mod session;
 pub mod shell;
@@ -56,6 +57,8 @@ pub mod xwayland;
 static GLOBAL: profiling::tracy_client::ProfiledAllocator<std::alloc::System> =
     profiling::tracy_client::ProfiledAllocator::new(std::alloc::System, 10);
 
+const LAYOUT_SAVE_INTERVAL: Duration = Duration::from_secs(5);
+
 // called by the Xwayland source, either after starting or failing
 impl State {
     fn notify_ready(&mut self) {
@@ -78,6 +81,10 @@ impl State {
                 warn!(?err, "Failed to setup cosmic-session communication");
             }
 
+            if let Err(err) = input::recording::replay_from_env(&self.common.event_loop_handle) {
+                error!(?err, "Failed to replay input recording");
+            }
+
             let mut args = env::args().skip(1);
             self.common.kiosk_child = if let Some(exec) = args.next() {
                 // Run command in kiosk mode
@@ -176,6 +183,17 @@ pub fn run(hooks: crate::hooks::Hooks) -> Result<(), Box<dyn Error>> {
         warn!(?err, "Failed to watch theme");
     }
 
+    // periodically snapshot the window layout, to restore it after a crash or re-login
+    if let Err(err) = event_loop.handle().insert_source(
+        Timer::from_duration(LAYOUT_SAVE_INTERVAL),
+        |_, _, state| {
+            state.common.save_layout();
+            TimeoutAction::ToDuration(LAYOUT_SAVE_INTERVAL)
+        },
+    ) {
+        warn!(err = ?err.error, "Failed to schedule layout snapshots");
+    }
+
     // run the event loop
     event_loop.run(None, &mut state, |state| {
         // shall we shut down?
@@ -330,5 +348,6 @@ fn refresh(state: &mut State) {
     state::Common::refresh_focus(state);
     OverlapNotifyState::refresh(state);
     state.common.update_x11_stacking_order();
+    state.common.send_ipc_events();
     state.last_refresh = LastRefresh::At(Instant::now());
 }
@@ -69,6 +69,12 @@ pub fn get_env(common: &Common) -> Result<HashMap<String, String>> {
     if let Some(display) = common.xwayland_state.as_ref().map(|s| s.display) {
         env.insert(String::from("DISPLAY"), format!(":{}", display));
     }
+    if let Some(ipc_state) = common.ipc_state.as_ref() {
+        env.insert(
+            String::from("COSMIC_COMP_SOCKET"),
+            ipc_state.socket_path().to_string_lossy().into_owned(),
+        );
+    }
     Ok(env)
 }
 
@@ -68,7 +68,7 @@ use super::{
     ManagedLayer,
     focus::target::PointerFocusTarget,
     layout::{
-        floating::{ResizeState, TiledCorners},
+        floating::{KeepLevel, ResizeState, TiledCorners},
         tiling::NodeDesc,
     },
 };
@@ -101,6 +101,7 @@ pub struct CosmicMapped {
     pub last_geometry: Arc<Mutex<Option<Rectangle<i32, Local>>>>,
     pub moved_since_mapped: Arc<AtomicBool>,
     pub floating_tiled: Arc<Mutex<Option<TiledCorners>>>,
+    pub keep_level: Arc<Mutex<KeepLevel>>,
     //sticky
     pub previous_layer: Arc<Mutex<Option<ManagedLayer>>>,
 
@@ -118,6 +119,7 @@ impl fmt::Debug for CosmicMapped {
             .field("last_geometry", &self.last_geometry)
             .field("moved_since_mapped", &self.moved_since_mapped)
             .field("floating_tiled", &self.floating_tiled)
+            .field("keep_level", &self.keep_level)
             .finish()
     }
 }
@@ -895,8 +897,10 @@ impl CosmicMapped {
 
     pub fn ssd_height(&self, pending: bool) -> Option<i32> {
         match &self.element {
-            CosmicMappedInternal::Window(w) => (!w.surface().is_decorated(pending))
-                .then_some(crate::shell::element::window::SSD_HEIGHT),
+            CosmicMappedInternal::Window(w) => {
+                w.0.with_program(|p| p.has_ssd(pending))
+                    .then_some(crate::shell::element::window::SSD_HEIGHT)
+            }
             CosmicMappedInternal::Stack(_) => Some(crate::shell::element::stack::TAB_HEIGHT),
             _ => unreachable!(),
         }
@@ -1042,6 +1046,7 @@ impl From<CosmicWindow> for CosmicMapped {
             last_geometry: Arc::new(Mutex::new(None)),
             moved_since_mapped: Arc::new(AtomicBool::new(false)),
             floating_tiled: Arc::new(Mutex::new(None)),
+            keep_level: Arc::new(Mutex::new(KeepLevel::Normal)),
             previous_layer: Arc::new(Mutex::new(None)),
             #[cfg(feature = "debug")]
             debug: Arc::new(Mutex::new(None)),
@@ -1059,6 +1064,7 @@ impl From<CosmicStack> for CosmicMapped {
             last_geometry: Arc::new(Mutex::new(None)),
             moved_since_mapped: Arc::new(AtomicBool::new(false)),
             floating_tiled: Arc::new(Mutex::new(None)),
+            keep_level: Arc::new(Mutex::new(KeepLevel::Normal)),
             previous_layer: Arc::new(Mutex::new(None)),
             #[cfg(feature = "debug")]
             debug: Arc::new(Mutex::new(None)),
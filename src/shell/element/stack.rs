@@ -6,6 +6,7 @@ use crate::{
     backend::render::{
         IndicatorShader, Key, Usage,
         clipped_surface::ClippedSurfaceRenderElement,
+        color::{ColorConvertedSurfaceRenderElement, surface_tree_image_descriptions},
         cursor::CursorState,
         element::{AsGlowRenderer, FromGlesError},
         shadow::ShadowShader,
@@ -802,6 +803,11 @@ impl CosmicStack {
                 ))
             });
 
+            let image_descriptions = windows[active]
+                .wl_surface()
+                .map(|surface| surface_tree_image_descriptions(&surface))
+                .unwrap_or_default();
+
             border.into_iter().chain(
                 windows[active]
                     .render_elements::<R, WaylandSurfaceRenderElement<R>>(
@@ -813,16 +819,22 @@ impl CosmicStack {
                     )
                     .into_iter()
                     .map(move |elem| {
-                        let radii = radii.map(|[a, _, c, _]| [a, 0, c, 0]);
-                        if radii.is_some_and(|radii| {
-                            ClippedSurfaceRenderElement::will_clip(&elem, scale, geo, radii)
-                        }) {
+                        let radii = radii.map(|[a, _, c, _]| [a, 0, c, 0]).filter(|radii| {
+                            ClippedSurfaceRenderElement::will_clip(&elem, scale, geo, *radii)
+                        });
+                        if let Some(description) = image_descriptions.get(elem.id()) {
+                            CosmicStackRenderElement::ColorConverted(
+                                ColorConvertedSurfaceRenderElement::new(
+                                    renderer,
+                                    elem,
+                                    description,
+                                    scale,
+                                    radii.map(|radii| (geo, radii)),
+                                ),
+                            )
+                        } else if let Some(radii) = radii {
                             CosmicStackRenderElement::Clipped(ClippedSurfaceRenderElement::new(
-                                renderer,
-                                elem,
-                                scale,
-                                geo,
-                                radii.unwrap(),
+                                renderer, elem, scale, geo, radii,
                             ))
                         } else {
                             CosmicStackRenderElement::Window(elem)
@@ -1862,6 +1874,7 @@ pub enum CosmicStackRenderElement<R: Renderer + ImportAll + ImportMem> {
     Border(PixelShaderElement),
     Window(WaylandSurfaceRenderElement<R>),
     Clipped(ClippedSurfaceRenderElement<R>),
+    ColorConverted(ColorConvertedSurfaceRenderElement<R>),
 }
 
 impl<R: Renderer + ImportAll + ImportMem> From<MemoryRenderBufferRenderElement<R>>
@@ -1888,6 +1901,14 @@ impl<R: Renderer + ImportAll + ImportMem> From<ClippedSurfaceRenderElement<R>>
     }
 }
 
+impl<R: Renderer + ImportAll + ImportMem> From<ColorConvertedSurfaceRenderElement<R>>
+    for CosmicStackRenderElement<R>
+{
+    fn from(value: ColorConvertedSurfaceRenderElement<R>) -> Self {
+        Self::ColorConverted(value)
+    }
+}
+
 impl<R> Element for CosmicStackRenderElement<R>
 where
     R: Renderer + ImportAll + ImportMem,
@@ -1899,6 +1920,7 @@ where
             CosmicStackRenderElement::Border(elem) => elem.id(),
             CosmicStackRenderElement::Window(elem) => elem.id(),
             CosmicStackRenderElement::Clipped(elem) => elem.id(),
+            CosmicStackRenderElement::ColorConverted(elem) => elem.id(),
         }
     }
 
@@ -1909,6 +1931,7 @@ where
             CosmicStackRenderElement::Border(elem) => elem.current_commit(),
             CosmicStackRenderElement::Window(elem) => elem.current_commit(),
             CosmicStackRenderElement::Clipped(elem) => elem.current_commit(),
+            CosmicStackRenderElement::ColorConverted(elem) => elem.current_commit(),
         }
     }
 
@@ -1919,6 +1942,7 @@ where
             CosmicStackRenderElement::Border(elem) => elem.src(),
             CosmicStackRenderElement::Window(elem) => elem.src(),
             CosmicStackRenderElement::Clipped(elem) => elem.src(),
+            CosmicStackRenderElement::ColorConverted(elem) => elem.src(),
         }
     }
 
@@ -1929,6 +1953,7 @@ where
             CosmicStackRenderElement::Border(elem) => elem.geometry(scale),
             CosmicStackRenderElement::Window(elem) => elem.geometry(scale),
             CosmicStackRenderElement::Clipped(elem) => elem.geometry(scale),
+            CosmicStackRenderElement::ColorConverted(elem) => elem.geometry(scale),
         }
     }
 
@@ -1939,6 +1964,7 @@ where
             CosmicStackRenderElement::Border(elem) => elem.location(scale),
             CosmicStackRenderElement::Window(elem) => elem.location(scale),
             CosmicStackRenderElement::Clipped(elem) => elem.location(scale),
+            CosmicStackRenderElement::ColorConverted(elem) => elem.location(scale),
         }
     }
 
@@ -1949,6 +1975,7 @@ where
             CosmicStackRenderElement::Border(elem) => elem.transform(),
             CosmicStackRenderElement::Window(elem) => elem.transform(),
             CosmicStackRenderElement::Clipped(elem) => elem.transform(),
+            CosmicStackRenderElement::ColorConverted(elem) => elem.transform(),
         }
     }
 
@@ -1963,6 +1990,7 @@ where
             CosmicStackRenderElement::Border(elem) => elem.damage_since(scale, commit),
             CosmicStackRenderElement::Window(elem) => elem.damage_since(scale, commit),
             CosmicStackRenderElement::Clipped(elem) => elem.damage_since(scale, commit),
+            CosmicStackRenderElement::ColorConverted(elem) => elem.damage_since(scale, commit),
         }
     }
 
@@ -1973,6 +2001,7 @@ where
             CosmicStackRenderElement::Border(elem) => elem.opaque_regions(scale),
             CosmicStackRenderElement::Window(elem) => elem.opaque_regions(scale),
             CosmicStackRenderElement::Clipped(elem) => elem.opaque_regions(scale),
+            CosmicStackRenderElement::ColorConverted(elem) => elem.opaque_regions(scale),
         }
     }
 
@@ -1983,6 +2012,7 @@ where
             CosmicStackRenderElement::Border(elem) => elem.alpha(),
             CosmicStackRenderElement::Window(elem) => elem.alpha(),
             CosmicStackRenderElement::Clipped(elem) => elem.alpha(),
+            CosmicStackRenderElement::ColorConverted(elem) => elem.alpha(),
         }
     }
 
@@ -1993,6 +2023,7 @@ where
             CosmicStackRenderElement::Border(elem) => elem.kind(),
             CosmicStackRenderElement::Window(elem) => elem.kind(),
             CosmicStackRenderElement::Clipped(elem) => elem.kind(),
+            CosmicStackRenderElement::ColorConverted(elem) => elem.kind(),
         }
     }
 }
@@ -2032,6 +2063,9 @@ where
             CosmicStackRenderElement::Clipped(elem) => {
                 elem.draw(frame, src, dst, damage, opaque_regions)
             }
+            CosmicStackRenderElement::ColorConverted(elem) => {
+                elem.draw(frame, src, dst, damage, opaque_regions)
+            }
         }
     }
 
@@ -2043,6 +2077,7 @@ where
             }
             CosmicStackRenderElement::Window(elem) => elem.underlying_storage(renderer),
             CosmicStackRenderElement::Clipped(elem) => elem.underlying_storage(renderer),
+            CosmicStackRenderElement::ColorConverted(elem) => elem.underlying_storage(renderer),
         }
     }
 }
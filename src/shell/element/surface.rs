@@ -39,7 +39,7 @@ use smithay::{
             },
         },
         wayland_protocols_misc::server_decoration::server::org_kde_kwin_server_decoration::Mode as KdeMode,
-        wayland_server::protocol::wl_surface::WlSurface,
+        wayland_server::{DisplayHandle, Resource, protocol::wl_surface::WlSurface},
     },
     utils::{
         IsAlive, Logical, Physical, Point, Rectangle, Scale, Serial, Size, user_data::UserDataMap,
@@ -121,6 +121,25 @@ struct Sticky(AtomicBool);
 #[derive(Default)]
 struct GlobalGeometry(Mutex<Option<Rectangle<i32, Global>>>);
 
+#[derive(Default)]
+struct Opacity(Mutex<OpacityState>);
+
+#[derive(Debug, Default, Clone, Copy)]
+struct OpacityState {
+    active: Option<f32>,
+    /// Opacity while the window isn't activated, `active` is used if unset
+    inactive: Option<f32>,
+}
+
+/// Lowest opacity reachable by adjusting it, so windows can't become invisible by accident
+const MIN_ADJUSTED_OPACITY: f32 = 0.1;
+
+#[derive(Default)]
+struct DecorationsDisabled(AtomicBool);
+
+#[derive(Default)]
+struct InhibitsIdle(AtomicBool);
+
 impl CosmicSurface {
     pub fn title(&self) -> String {
         match self.0.underlying_surface() {
@@ -317,7 +336,7 @@ impl CosmicSurface {
                             }
                         }
                     })
-                } else {
+                } else if !self.decorations_disabled() {
                     let previous_mode = PreferredDecorationMode::mode(&self.0);
                     toplevel.with_pending_state(|pending| {
                         pending.decoration_mode = previous_mode;
@@ -476,6 +495,108 @@ impl CosmicSurface {
             .store(sticky, Ordering::SeqCst);
     }
 
+    /// Opacity the window contents are rendered with, depending on whether the window is activated
+    pub fn opacity(&self) -> f32 {
+        let activated = self.is_activated(false);
+        let state = *self
+            .0
+            .user_data()
+            .get_or_insert_threadsafe(Opacity::default)
+            .0
+            .lock()
+            .unwrap();
+        let opacity = if activated {
+            state.active
+        } else {
+            state.inactive.or(state.active)
+        };
+        opacity.unwrap_or(1.0)
+    }
+
+    pub fn set_opacity(&self, opacity: Option<f32>) {
+        self.0
+            .user_data()
+            .get_or_insert_threadsafe(Opacity::default)
+            .0
+            .lock()
+            .unwrap()
+            .active = opacity.map(|opacity| opacity.clamp(0.0, 1.0));
+    }
+
+    pub fn set_inactive_opacity(&self, opacity: Option<f32>) {
+        self.0
+            .user_data()
+            .get_or_insert_threadsafe(Opacity::default)
+            .0
+            .lock()
+            .unwrap()
+            .inactive = opacity.map(|opacity| opacity.clamp(0.0, 1.0));
+    }
+
+    /// Changes the opacity currently in effect by `delta`
+    pub fn adjust_opacity(&self, delta: f32) {
+        let activated = self.is_activated(false);
+        let mut state = self
+            .0
+            .user_data()
+            .get_or_insert_threadsafe(Opacity::default)
+            .0
+            .lock()
+            .unwrap();
+        let opacity = if !activated && state.inactive.is_some() {
+            &mut state.inactive
+        } else {
+            &mut state.active
+        };
+        *opacity = Some((opacity.unwrap_or(1.0) + delta).clamp(MIN_ADJUSTED_OPACITY, 1.0));
+    }
+
+    /// Makes the window fully opaque again, active or not
+    pub fn reset_opacity(&self) {
+        *self
+            .0
+            .user_data()
+            .get_or_insert_threadsafe(Opacity::default)
+            .0
+            .lock()
+            .unwrap() = OpacityState::default();
+    }
+
+    /// Whether neither client- nor server-side decorations should be drawn
+    pub fn decorations_disabled(&self) -> bool {
+        self.0
+            .user_data()
+            .get_or_insert_threadsafe(DecorationsDisabled::default)
+            .0
+            .load(Ordering::SeqCst)
+    }
+
+    pub fn set_decorations_disabled(&self, disabled: bool) {
+        self.0
+            .user_data()
+            .get_or_insert_threadsafe(DecorationsDisabled::default)
+            .0
+            .store(disabled, Ordering::SeqCst);
+        self.try_force_undecorated(disabled);
+    }
+
+    /// Whether the window inhibits idle while visible, regardless of the idle-inhibit protocol
+    pub fn inhibits_idle(&self) -> bool {
+        self.0
+            .user_data()
+            .get_or_insert_threadsafe(InhibitsIdle::default)
+            .0
+            .load(Ordering::SeqCst)
+    }
+
+    pub fn set_inhibits_idle(&self, inhibit: bool) {
+        self.0
+            .user_data()
+            .get_or_insert_threadsafe(InhibitsIdle::default)
+            .0
+            .store(inhibit, Ordering::SeqCst);
+    }
+
     pub fn set_suspended(&self, suspended: bool) {
         if let WindowSurface::Wayland(window) = self.0.underlying_surface() {
             window.with_pending_state(|state| {
@@ -807,6 +928,7 @@ impl CosmicSurface {
         R::TextureId: Clone + 'static,
         C: From<WaylandSurfaceRenderElement<R>>,
     {
+        let alpha = alpha * self.opacity();
         match self.0.underlying_surface() {
             WindowSurface::Wayland(toplevel) => {
                 let surface = toplevel.wl_surface();
@@ -859,6 +981,22 @@ impl CosmicSurface {
         self.0.x11_surface()
     }
 
+    /// Process id of the client owning the window, if known
+    pub fn pid(&self) -> Option<u32> {
+        match self.0.underlying_surface() {
+            WindowSurface::Wayland(toplevel) => {
+                let surface = toplevel.wl_surface();
+                let client = surface.client()?;
+                let dh = DisplayHandle::from(surface.handle().upgrade()?);
+                client
+                    .get_credentials(&dh)
+                    .ok()
+                    .map(|credentials| credentials.pid as u32)
+            }
+            WindowSurface::X11(surface) => surface.pid(),
+        }
+    }
+
     pub fn downgrade(&self) -> WeakCosmicSurface {
         WeakCosmicSurface(self.0.downgrade())
     }
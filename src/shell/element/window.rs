@@ -2,6 +2,7 @@ use crate::{
     backend::render::{
         IndicatorShader, Key, Usage,
         clipped_surface::ClippedSurfaceRenderElement,
+        color::{ColorConvertedSurfaceRenderElement, surface_tree_image_descriptions},
         cursor::CursorState,
         element::{AsGlowRenderer, FromGlesError},
         shadow::ShadowShader,
@@ -176,7 +177,7 @@ impl CosmicWindowInternal {
 
     /// returns if the window has any current or pending server-side decorations
     pub fn has_ssd(&self, pending: bool) -> bool {
-        !self.window.is_decorated(pending)
+        !self.window.is_decorated(pending) && !self.window.decorations_disabled()
     }
 
     /// returns if the window is currently tiled
@@ -529,15 +530,21 @@ impl CosmicWindow {
             elements.push(elem);
         }
 
-        let window_elements = self.0.with_program(|p| {
-            p.window
-                .render_elements::<R, WaylandSurfaceRenderElement<R>>(
-                    renderer,
-                    window_loc,
-                    scale,
-                    alpha,
-                    scanout_override,
-                )
+        let (window_elements, image_descriptions) = self.0.with_program(|p| {
+            (
+                p.window
+                    .render_elements::<R, WaylandSurfaceRenderElement<R>>(
+                        renderer,
+                        window_loc,
+                        scale,
+                        alpha,
+                        scanout_override,
+                    ),
+                p.window
+                    .wl_surface()
+                    .map(|surface| surface_tree_image_descriptions(&surface))
+                    .unwrap_or_default(),
+            )
         });
         if window_elements.is_empty() {
             return Vec::new();
@@ -548,10 +555,18 @@ impl CosmicWindow {
                 radii[1] = 0;
                 radii[3] = 0;
             }
-            if radii.iter().any(|x| *x != 0)
+            let clipped = radii.iter().any(|x| *x != 0)
                 && clip
-                && ClippedSurfaceRenderElement::will_clip(&elem, scale, geo, radii)
-            {
+                && ClippedSurfaceRenderElement::will_clip(&elem, scale, geo, radii);
+            if let Some(description) = image_descriptions.get(elem.id()) {
+                CosmicWindowRenderElement::ColorConverted(ColorConvertedSurfaceRenderElement::new(
+                    renderer,
+                    elem,
+                    description,
+                    scale,
+                    clipped.then_some((geo, radii)),
+                ))
+            } else if clipped {
                 CosmicWindowRenderElement::Clipped(ClippedSurfaceRenderElement::new(
                     renderer, elem, scale, geo, radii,
                 ))
@@ -604,7 +619,7 @@ impl CosmicWindow {
         self.0
             .with_program(|p| p.window.min_size_without_ssd())
             .map(|size| {
-                if self.0.with_program(|p| !p.window.is_decorated(false)) {
+                if self.0.with_program(|p| p.has_ssd(false)) {
                     size + (0, SSD_HEIGHT).into()
                 } else {
                     size
@@ -615,7 +630,7 @@ impl CosmicWindow {
         self.0
             .with_program(|p| p.window.max_size_without_ssd())
             .map(|size| {
-                if self.0.with_program(|p| !p.window.is_decorated(false)) {
+                if self.0.with_program(|p| p.has_ssd(false)) {
                     size + (0, SSD_HEIGHT).into()
                 } else {
                     size
@@ -1233,6 +1248,7 @@ pub enum CosmicWindowRenderElement<R: Renderer + ImportAll + ImportMem> {
     Border(PixelShaderElement),
     Window(WaylandSurfaceRenderElement<R>),
     Clipped(ClippedSurfaceRenderElement<R>),
+    ColorConverted(ColorConvertedSurfaceRenderElement<R>),
 }
 
 impl<R: Renderer + ImportAll + ImportMem> From<MemoryRenderBufferRenderElement<R>>
@@ -1259,6 +1275,14 @@ impl<R: Renderer + ImportAll + ImportMem> From<ClippedSurfaceRenderElement<R>>
     }
 }
 
+impl<R: Renderer + ImportAll + ImportMem> From<ColorConvertedSurfaceRenderElement<R>>
+    for CosmicWindowRenderElement<R>
+{
+    fn from(value: ColorConvertedSurfaceRenderElement<R>) -> Self {
+        Self::ColorConverted(value)
+    }
+}
+
 impl<R> Element for CosmicWindowRenderElement<R>
 where
     R: Renderer + ImportAll + ImportMem,
@@ -1270,6 +1294,7 @@ where
             CosmicWindowRenderElement::Border(elem) => elem.id(),
             CosmicWindowRenderElement::Window(elem) => elem.id(),
             CosmicWindowRenderElement::Clipped(elem) => elem.id(),
+            CosmicWindowRenderElement::ColorConverted(elem) => elem.id(),
         }
     }
 
@@ -1280,6 +1305,7 @@ where
             CosmicWindowRenderElement::Border(elem) => elem.current_commit(),
             CosmicWindowRenderElement::Window(elem) => elem.current_commit(),
             CosmicWindowRenderElement::Clipped(elem) => elem.current_commit(),
+            CosmicWindowRenderElement::ColorConverted(elem) => elem.current_commit(),
         }
     }
 
@@ -1290,6 +1316,7 @@ where
             CosmicWindowRenderElement::Border(elem) => elem.src(),
             CosmicWindowRenderElement::Window(elem) => elem.src(),
             CosmicWindowRenderElement::Clipped(elem) => elem.src(),
+            CosmicWindowRenderElement::ColorConverted(elem) => elem.src(),
         }
     }
 
@@ -1300,6 +1327,7 @@ where
             CosmicWindowRenderElement::Border(elem) => elem.geometry(scale),
             CosmicWindowRenderElement::Window(elem) => elem.geometry(scale),
             CosmicWindowRenderElement::Clipped(elem) => elem.geometry(scale),
+            CosmicWindowRenderElement::ColorConverted(elem) => elem.geometry(scale),
         }
     }
 
@@ -1310,6 +1338,7 @@ where
             CosmicWindowRenderElement::Border(elem) => elem.location(scale),
             CosmicWindowRenderElement::Window(elem) => elem.location(scale),
             CosmicWindowRenderElement::Clipped(elem) => elem.location(scale),
+            CosmicWindowRenderElement::ColorConverted(elem) => elem.location(scale),
         }
     }
 
@@ -1320,6 +1349,7 @@ where
             CosmicWindowRenderElement::Border(elem) => elem.transform(),
             CosmicWindowRenderElement::Window(elem) => elem.transform(),
             CosmicWindowRenderElement::Clipped(elem) => elem.transform(),
+            CosmicWindowRenderElement::ColorConverted(elem) => elem.transform(),
         }
     }
 
@@ -1334,6 +1364,7 @@ where
             CosmicWindowRenderElement::Border(elem) => elem.damage_since(scale, commit),
             CosmicWindowRenderElement::Window(elem) => elem.damage_since(scale, commit),
             CosmicWindowRenderElement::Clipped(elem) => elem.damage_since(scale, commit),
+            CosmicWindowRenderElement::ColorConverted(elem) => elem.damage_since(scale, commit),
         }
     }
 
@@ -1344,6 +1375,7 @@ where
             CosmicWindowRenderElement::Border(elem) => elem.opaque_regions(scale),
             CosmicWindowRenderElement::Window(elem) => elem.opaque_regions(scale),
             CosmicWindowRenderElement::Clipped(elem) => elem.opaque_regions(scale),
+            CosmicWindowRenderElement::ColorConverted(elem) => elem.opaque_regions(scale),
         }
     }
 
@@ -1354,6 +1386,7 @@ where
             CosmicWindowRenderElement::Border(elem) => elem.alpha(),
             CosmicWindowRenderElement::Window(elem) => elem.alpha(),
             CosmicWindowRenderElement::Clipped(elem) => elem.alpha(),
+            CosmicWindowRenderElement::ColorConverted(elem) => elem.alpha(),
         }
     }
 
@@ -1364,6 +1397,7 @@ where
             CosmicWindowRenderElement::Border(elem) => elem.kind(),
             CosmicWindowRenderElement::Window(elem) => elem.kind(),
             CosmicWindowRenderElement::Clipped(elem) => elem.kind(),
+            CosmicWindowRenderElement::ColorConverted(elem) => elem.kind(),
         }
     }
 }
@@ -1403,6 +1437,9 @@ where
             CosmicWindowRenderElement::Clipped(elem) => {
                 elem.draw(frame, src, dst, damage, opaque_regions)
             }
+            CosmicWindowRenderElement::ColorConverted(elem) => {
+                elem.draw(frame, src, dst, damage, opaque_regions)
+            }
         }
     }
 
@@ -1414,6 +1451,7 @@ where
             }
             CosmicWindowRenderElement::Window(elem) => elem.underlying_storage(renderer),
             CosmicWindowRenderElement::Clipped(elem) => elem.underlying_storage(renderer),
+            CosmicWindowRenderElement::ColorConverted(elem) => elem.underlying_storage(renderer),
         }
     }
 }
@@ -233,6 +233,8 @@ impl Shell {
         if Some(&target) != focus_stack.last() {
             trace!(?target, "Focusing window.");
             focus_stack.append(target);
+            std::mem::drop(focus_stack);
+            workspace.scroll_to_focus(seat);
             // also remove popup grabs, if we are switching focus
             if let Some(mut popup_grab) = seat
                 .user_data()
@@ -446,6 +448,7 @@ fn raise_with_children(floating_layer: &mut FloatingLayout, focused: &CosmicMapp
         {
             raise_with_children(floating_layer, &element);
         }
+        floating_layer.restack();
     }
 }
 
@@ -1,3 +1,4 @@
+use cosmic_comp_config::bindings::CompAction;
 use cosmic_settings_config::shortcuts::Action;
 use smithay::{
     input::pointer::MotionEvent, reexports::wayland_server::protocol::wl_surface::WlSurface,
@@ -11,6 +12,7 @@ use crate::{
         CosmicSurface, PointGlobalExt, Shell,
         element::{CosmicMapped, CosmicWindow},
         grabs::ReleaseMode,
+        layout::floating::KeepLevel,
     },
     state::State,
     utils::{prelude::SeatExt, screenshot::screenshot_window},
@@ -246,8 +248,12 @@ pub fn window_items(
     let screenshot_clone = window.clone();
     let stack_clone = window.clone();
     let sticky_clone = window.clone();
+    let keep_above_clone = window.clone();
+    let keep_below_clone = window.clone();
     let close_clone = window.clone();
 
+    let keep_level = *window.keep_level.lock().unwrap();
+
     vec![
         (!is_stacked).then_some(
             Item::new(fl!("window-menu-stack"), move |handle| {
@@ -545,6 +551,30 @@ pub fn window_items(
             })
             .toggled(is_sticky),
         ),
+        Some(
+            Item::new(fl!("window-menu-keep-above"), move |handle| {
+                let mapped = keep_above_clone.clone();
+                let _ = handle.insert_idle(move |state| {
+                    let mut shell = state.common.shell.write();
+                    shell.toggle_keep_level(&mapped, KeepLevel::Above);
+                });
+            })
+            .shortcut(config.shortcut_for_compositor_action(&CompAction::ToggleKeepAbove))
+            .toggled(keep_level == KeepLevel::Above)
+            .disabled(is_tiled),
+        ),
+        Some(
+            Item::new(fl!("window-menu-keep-below"), move |handle| {
+                let mapped = keep_below_clone.clone();
+                let _ = handle.insert_idle(move |state| {
+                    let mut shell = state.common.shell.write();
+                    shell.toggle_keep_level(&mapped, KeepLevel::Below);
+                });
+            })
+            .shortcut(config.shortcut_for_compositor_action(&CompAction::ToggleKeepBelow))
+            .toggled(keep_level == KeepLevel::Below)
+            .disabled(is_tiled),
+        ),
         Some(Item::Separator),
         if is_stacked {
             Some(Item::new(fl!("window-menu-close-all"), move |_handle| {
@@ -19,12 +19,13 @@ use crate::{
 
 use calloop::LoopHandle;
 use cosmic::theme::CosmicTheme;
+use cosmic_comp_config::snap_zones::SnapZone;
 use smithay::{
     backend::{
         input::ButtonState,
         renderer::{
             ImportAll, ImportMem, Renderer,
-            element::{AsRenderElements, RenderElement, utils::RescaleRenderElement},
+            element::{AsRenderElements, Id, RenderElement, utils::RescaleRenderElement},
         },
     },
     desktop::{WindowSurfaceType, layer_map_for_output, space::SpaceElement},
@@ -61,6 +62,9 @@ pub struct MoveGrabState {
     start: Instant,
     previous: ManagedLayer,
     snapping_zone: Option<SnappingZone>,
+    /// User-defined snap zones of the cursor output, while their modifiers are held
+    snap_zones: Vec<(Id, Rectangle<i32, Local>)>,
+    snap_zone: Option<Rectangle<i32, Local>>,
     stacking_indicator: Option<(StackHover, Point<i32, Logical>)>,
     location: Point<f64, Logical>,
     cursor_output: Output,
@@ -188,6 +192,46 @@ impl MoveGrabState {
             _ => vec![],
         };
 
+        let snap_zone_elements = self
+            .snap_zones
+            .iter()
+            .filter(|_| &self.cursor_output == output)
+            .flat_map(|(id, geometry)| {
+                let hovered = self.snap_zone == Some(*geometry);
+                let base_color = theme.palette.neutral_9;
+                let outline = hovered.then(|| {
+                    CosmicMappedRenderElement::from(IndicatorShader::element(
+                        renderer,
+                        Key::Window(Usage::SnappingIndicator, self.window.key()),
+                        *geometry,
+                        thickness,
+                        [
+                            theme.radius_s()[0] as u8,
+                            theme.radius_s()[1] as u8,
+                            theme.radius_s()[2] as u8,
+                            theme.radius_s()[3] as u8,
+                        ],
+                        1.0,
+                        output_scale.x,
+                        [
+                            active_window_hint.red,
+                            active_window_hint.green,
+                            active_window_hint.blue,
+                        ],
+                    ))
+                });
+                let backdrop = CosmicMappedRenderElement::from(BackdropShader::element(
+                    renderer,
+                    id.clone(),
+                    *geometry,
+                    theme.radius_s()[0],
+                    if hovered { 0.4 } else { 0.15 },
+                    [base_color.red, base_color.green, base_color.blue],
+                ));
+                outline.into_iter().chain(std::iter::once(backdrop))
+            })
+            .collect::<Vec<_>>();
+
         let w_elements = self
             .window
             .render_elements::<R, CosmicMappedRenderElement<R>>(
@@ -260,6 +304,7 @@ impl MoveGrabState {
                     }),
             )
             .chain(snapping_indicator)
+            .chain(snap_zone_elements)
             .map(I::from)
             .collect()
     }
@@ -470,8 +515,47 @@ impl MoveGrab {
                 });
             }
 
-            // Check for overlapping with zones
+            // Check for overlapping with user-defined zones first
             if grab_state.previous == ManagedLayer::Floating {
+                let config = &state.common.config.cosmic_conf.snap_zones;
+                let held = self.seat.get_keyboard().unwrap().modifier_state();
+                let zones = if (!config.modifiers.ctrl || held.ctrl)
+                    && (!config.modifiers.alt || held.alt)
+                    && (!config.modifiers.shift || held.shift)
+                    && (!config.modifiers.logo || held.logo)
+                {
+                    let gaps = state.common.theme.cosmic().gaps;
+                    snap_zone_geometries(
+                        config.zones(&current_output.name()),
+                        &current_output,
+                        (gaps.0 as i32, gaps.1 as i32),
+                    )
+                } else {
+                    Vec::new()
+                };
+                // keep the ids, so the overlay isn't recreated on every motion
+                if !zones
+                    .iter()
+                    .eq(grab_state.snap_zones.iter().map(|(_, geometry)| geometry))
+                {
+                    grab_state.snap_zones = zones
+                        .into_iter()
+                        .map(|geometry| (Id::new(), geometry))
+                        .collect();
+                }
+                let cursor = location
+                    .as_global()
+                    .to_local(&current_output)
+                    .to_i32_floor();
+                grab_state.snap_zone = grab_state
+                    .snap_zones
+                    .iter()
+                    .map(|(_, geometry)| *geometry)
+                    .find(|geometry| geometry.contains(cursor));
+            }
+
+            // Check for overlapping with zones
+            if grab_state.previous == ManagedLayer::Floating && grab_state.snap_zones.is_empty() {
                 let output_geometry = current_output.geometry().to_local(&current_output);
                 grab_state.snapping_zone = [
                     SnappingZone::Maximize,
@@ -495,12 +579,40 @@ impl MoveGrab {
                     )
                 })
                 .cloned();
+            } else {
+                grab_state.snapping_zone = None;
             }
         }
         drop(borrow);
     }
 }
 
+/// Geometries of user-defined snap zones on `output`, separated by gaps
+fn snap_zone_geometries(
+    zones: &[SnapZone],
+    output: &Output,
+    gaps: (i32, i32),
+) -> Vec<Rectangle<i32, Local>> {
+    let (_, inner) = gaps;
+    let area = layer_map_for_output(output).non_exclusive_zone();
+    let (x, y) = (area.loc.x + inner / 2, area.loc.y + inner / 2);
+    let (w, h) = (area.size.w - inner, area.size.h - inner);
+
+    zones
+        .iter()
+        .map(|zone| {
+            let left = x + (zone.x * w as f32).round() as i32;
+            let top = y + (zone.y * h as f32).round() as i32;
+            let right = x + ((zone.x + zone.width) * w as f32).round() as i32;
+            let bottom = y + ((zone.y + zone.height) * h as f32).round() as i32;
+            Rectangle::new(
+                (left + inner / 2, top + inner / 2).into(),
+                ((right - left - inner).max(1), (bottom - top - inner).max(1)).into(),
+            )
+        })
+        .collect()
+}
+
 impl PointerGrab<State> for MoveGrab {
     fn motion(
         &mut self,
@@ -753,6 +865,8 @@ impl MoveGrab {
             start: Instant::now(),
             stacking_indicator: None,
             snapping_zone: None,
+            snap_zones: Vec::new(),
+            snap_zone: None,
             previous: previous_layer,
             location: start_data.location(),
             cursor_output: cursor_output.clone(),
@@ -867,6 +981,15 @@ impl Drop for MoveGrab {
                             );
 
                             if matches!(previous, ManagedLayer::Floating)
+                                && let Some(zone) = grab_state.snap_zone
+                            {
+                                workspace.floating_layer.map_internal(
+                                    window.clone(),
+                                    Some(zone.loc),
+                                    Some(zone.size.as_logical()),
+                                    None,
+                                );
+                            } else if matches!(previous, ManagedLayer::Floating)
                                 && let Some(sz) = grab_state.snapping_zone
                             {
                                 if sz == SnappingZone::Maximize {
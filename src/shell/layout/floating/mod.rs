@@ -43,7 +43,9 @@ use crate::{
 };
 
 mod grabs;
+mod placement;
 pub use self::grabs::*;
+pub use self::placement::Placement;
 
 pub const ANIMATION_DURATION: Duration = Duration::from_millis(200);
 pub const MINIMIZE_ANIMATION_DURATION: Duration = Duration::from_millis(320);
@@ -164,6 +166,17 @@ impl Animation {
     }
 }
 
+/// Band of the stacking order a floating window is kept in
+#[derive(Debug, Clone, Copy, Default, PartialEq, Eq, PartialOrd, Ord, Hash)]
+pub enum KeepLevel {
+    /// Always below the other windows of the layer
+    Below,
+    #[default]
+    Normal,
+    /// Always above the other windows of the layer
+    Above,
+}
+
 #[derive(Debug, Clone, Copy, PartialEq, Eq, Hash)]
 pub enum TiledCorners {
     Top,
@@ -608,6 +621,7 @@ impl FloatingLayout {
             );
         }
         self.space.map_element(mapped, position.as_logical(), false);
+        self.restack();
         self.space.refresh();
     }
 
@@ -635,6 +649,7 @@ impl FloatingLayout {
 
         self.space
             .map_element(mapped.clone(), position.as_logical(), true);
+        self.restack();
         self.space.refresh();
         let target_geometry = self.space.element_geometry(&mapped).unwrap().as_local();
 
@@ -1300,6 +1315,21 @@ impl FloatingLayout {
         self.space.elements().rev()
     }
 
+    /// Moves windows kept above or below back into their band of the stacking order,
+    /// keeping the order of windows within each band.
+    pub fn restack(&mut self) {
+        let level = |mapped: &CosmicMapped| *mapped.keep_level.lock().unwrap();
+        if self.space.elements().map(level).is_sorted() {
+            return;
+        }
+
+        let mut elements = self.space.elements().cloned().collect::<Vec<_>>();
+        elements.sort_by_key(level);
+        for mapped in elements {
+            self.space.raise_element(&mapped, false);
+        }
+    }
+
     pub fn windows(&self) -> impl Iterator<Item = CosmicSurface> + '_ {
         self.mapped().flat_map(|e| e.windows().map(|(w, _)| w))
     }
@@ -1362,6 +1392,7 @@ impl FloatingLayout {
 
     #[profiling::function]
     pub fn refresh(&mut self) {
+        self.restack();
         self.space.refresh();
 
         if let Some(pos) = self.spawn_order.iter().position(|w| !w.alive()) {
@@ -0,0 +1,153 @@
+// SPDX-License-Identifier: GPL-3.0-only
+
+//! Placement strategies for newly mapped floating windows, besides the default cascade.
+
+use std::iter;
+
+use smithay::{
+    desktop::layer_map_for_output,
+    utils::{Point, Rectangle, Size},
+};
+
+use crate::{shell::element::CosmicMapped, utils::prelude::*};
+
+use super::FloatingLayout;
+
+#[derive(Debug, Clone, Copy, PartialEq)]
+pub enum Placement {
+    /// Cascade from the last placed window
+    Cascade,
+    /// Center of the output
+    Centered,
+    /// Centered on a point, usually the cursor
+    Under(Point<f64, Local>),
+    /// Position overlapping the least with the other windows
+    Smart,
+    /// Centered on the parent window
+    Parent(Rectangle<i32, Local>),
+}
+
+/// Moves `loc` so a window of `size` stays inside of `area`, as far as possible
+fn clamp(
+    loc: Point<i32, Local>,
+    size: Size<i32, Local>,
+    area: Rectangle<i32, Local>,
+) -> Point<i32, Local> {
+    let max_x = area.loc.x + (area.size.w - size.w).max(0);
+    let max_y = area.loc.y + (area.size.h - size.h).max(0);
+    Point::from((
+        loc.x.clamp(area.loc.x, max_x),
+        loc.y.clamp(area.loc.y, max_y),
+    ))
+}
+
+fn centered_on(
+    center: Point<i32, Local>,
+    size: Size<i32, Local>,
+    area: Rectangle<i32, Local>,
+) -> Point<i32, Local> {
+    clamp(center - Point::from((size.w / 2, size.h / 2)), size, area)
+}
+
+fn center_of(rect: Rectangle<i32, Local>) -> Point<i32, Local> {
+    rect.loc + Point::from((rect.size.w / 2, rect.size.h / 2))
+}
+
+fn overlap(a: Rectangle<i32, Local>, b: Rectangle<i32, Local>) -> i64 {
+    a.intersection(b)
+        .map(|rect| rect.size.w as i64 * rect.size.h as i64)
+        .unwrap_or(0)
+}
+
+impl FloatingLayout {
+    /// Maps a new window, placed according to `placement`.
+    ///
+    /// Windows with a previous floating geometry keep their last position.
+    pub fn map_placed(&mut self, mapped: CosmicMapped, placement: Placement) {
+        let restored = mapped.last_geometry.lock().unwrap().is_some();
+        self.map_internal(mapped.clone(), None, None, None);
+        if restored || placement == Placement::Cascade {
+            return;
+        }
+
+        let Some(geometry) = self.space.element_geometry(&mapped).map(RectExt::as_local) else {
+            return;
+        };
+        let output = self.space.outputs().next().unwrap().clone();
+        // the non-exclusive zone keeps windows clear of panels and docks
+        let area = layer_map_for_output(&output)
+            .non_exclusive_zone()
+            .as_local();
+        let size = geometry.size;
+        let loc = match placement {
+            Placement::Cascade => unreachable!(),
+            Placement::Centered => centered_on(center_of(area), size, area),
+            Placement::Under(point) => centered_on(point.to_i32_round(), size, area),
+            Placement::Parent(parent) => centered_on(center_of(parent), size, area),
+            Placement::Smart => self.smart_position(&mapped, size, area),
+        };
+        if loc == geometry.loc {
+            return;
+        }
+
+        // later windows shouldn't cascade from a window placed otherwise
+        self.spawn_order.retain(|w| w != &mapped);
+        mapped.set_geometry(Rectangle::new(loc, size).to_global(&output));
+        mapped.configure();
+        self.space.map_element(mapped, loc.as_logical(), false);
+        self.space.refresh();
+    }
+
+    /// Position overlapping the least with all other windows.
+    ///
+    /// Candidates are the center of `area` and positions touching the edges of `area`
+    /// or of other windows, preferring the center and then the top-left.
+    fn smart_position(
+        &self,
+        mapped: &CosmicMapped,
+        size: Size<i32, Local>,
+        area: Rectangle<i32, Local>,
+    ) -> Point<i32, Local> {
+        let others = self
+            .space
+            .elements()
+            .filter(|other| *other != mapped)
+            .filter_map(|other| self.space.element_geometry(other))
+            .map(RectExt::as_local)
+            .collect::<Vec<_>>();
+
+        let mut xs = vec![area.loc.x, area.loc.x + area.size.w - size.w];
+        let mut ys = vec![area.loc.y, area.loc.y + area.size.h - size.h];
+        for other in &others {
+            xs.extend([other.loc.x + other.size.w, other.loc.x - size.w]);
+            ys.extend([other.loc.y + other.size.h, other.loc.y - size.h]);
+        }
+        xs.sort_unstable();
+        xs.dedup();
+        ys.sort_unstable();
+        ys.dedup();
+
+        let center = centered_on(center_of(area), size, area);
+        let candidates = iter::once(center).chain(
+            ys.iter()
+                .flat_map(|y| xs.iter().map(move |x| Point::from((*x, *y))))
+                .map(|loc| clamp(loc, size, area)),
+        );
+
+        let mut best = (i64::MAX, center);
+        for loc in candidates {
+            let rect = Rectangle::new(loc, size);
+            let covered = others
+                .iter()
+                .map(|other| overlap(rect, *other))
+                .sum::<i64>();
+            if covered < best.0 {
+                best = (covered, loc);
+                if covered == 0 {
+                    break;
+                }
+            }
+        }
+        best.1
+    }
+}
@@ -0,0 +1,544 @@
+// SPDX-License-Identifier: GPL-3.0-only
+
+//! Snapshots of the window layout, to put windows back into their previous slots
+//! when they reappear after a crash or re-login.
+//!
+//! Windows are identified by app_id and title, falling back to just the app_id,
+//! as titles often change between sessions.
+//!
+//! Independently of the session, the floating geometry of closed windows is remembered
+//! per application, to re-open floating windows where they were last. Titles matching
+//! the title regex of a window rule are keyed by that regex instead.
+
+use std::time::{Duration, Instant};
+
+use id_tree::{NodeId, Tree};
+use serde::{Deserialize, Serialize};
+use smithay::utils::{Logical, Rectangle, Size};
+
+use crate::{
+    shell::{
+        CosmicMapped, CosmicSurface, FloatingRestoreData, element::surface::WeakCosmicSurface,
+    },
+    utils::prelude::*,
+};
+
+use super::{
+    Orientation,
+    tiling::{Data, TilingLayout, TreeShape},
+};
+
+/// Time after startup in which mapped windows are matched against the saved layout
+const RESTORE_TIMEOUT: Duration = Duration::from_secs(120);
+
+#[derive(Debug, Clone, PartialEq, Eq, Serialize, Deserialize)]
+pub struct WindowKey {
+    pub app_id: String,
+    pub title: String,
+}
+
+impl WindowKey {
+    fn new(window: &CosmicSurface) -> WindowKey {
+        WindowKey {
+            app_id: window.app_id(),
+            title: window.title(),
+        }
+    }
+
+    fn matches(&self, other: &WindowKey, exact: bool) -> bool {
+        self.app_id == other.app_id && (!exact || self.title == other.title)
+    }
+}
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Serialize, Deserialize)]
+pub enum SavedOrientation {
+    Horizontal,
+    Vertical,
+}
+
+impl From<Orientation> for SavedOrientation {
+    fn from(orientation: Orientation) -> Self {
+        match orientation {
+            Orientation::Horizontal => SavedOrientation::Horizontal,
+            Orientation::Vertical => SavedOrientation::Vertical,
+        }
+    }
+}
+
+impl From<SavedOrientation> for Orientation {
+    fn from(orientation: SavedOrientation) -> Self {
+        match orientation {
+            SavedOrientation::Horizontal => Orientation::Horizontal,
+            SavedOrientation::Vertical => Orientation::Vertical,
+        }
+    }
+}
+
+#[derive(Debug, Clone, PartialEq, Serialize, Deserialize)]
+pub enum SavedNode {
+    Group {
+        orientation: SavedOrientation,
+        sizes: Vec<i32>,
+        children: Vec<SavedNode>,
+    },
+    /// A window, or a stack if there are multiple windows
+    Element { windows: Vec<WindowKey> },
+}
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Serialize, Deserialize)]
+pub struct SavedGeometry {
+    pub x: i32,
+    pub y: i32,
+    pub width: i32,
+    pub height: i32,
+}
+
+#[derive(Debug, Clone, PartialEq, Serialize, Deserialize)]
+pub struct SavedFloating {
+    pub windows: Vec<WindowKey>,
+    /// Geometry relative to the output
+    pub geometry: SavedGeometry,
+}
+
+#[derive(Debug, Clone, PartialEq, Serialize, Deserialize)]
+pub struct SavedWorkspace {
+    pub output: String,
+    pub index: usize,
+    pub tiling: Option<SavedNode>,
+    pub floating: Vec<SavedFloating>,
+}
+
+/// Tiled and floating windows of all workspaces.
+///
+/// Sticky, minimized and fullscreen windows are not part of the snapshot.
+#[derive(Debug, Clone, Default, PartialEq, Serialize, Deserialize)]
+pub struct SavedLayout {
+    pub workspaces: Vec<SavedWorkspace>,
+}
+
+fn element_keys(mapped: &CosmicMapped) -> Vec<WindowKey> {
+    mapped.windows().map(|(w, _)| WindowKey::new(&w)).collect()
+}
+
+fn saved_node(tree: &Tree<Data>, id: &NodeId) -> Option<SavedNode> {
+    let node = tree.get(id).ok()?;
+    match node.data() {
+        Data::Group {
+            orientation, sizes, ..
+        } => {
+            let (sizes, children): (Vec<_>, Vec<_>) = node
+                .children()
+                .iter()
+                .zip(sizes.iter())
+                .filter_map(|(child, size)| Some((*size, saved_node(tree, child)?)))
+                .unzip();
+            (!children.is_empty()).then(|| SavedNode::Group {
+                orientation: (*orientation).into(),
+                sizes,
+                children,
+            })
+        }
+        Data::Mapped { mapped, .. } => Some(SavedNode::Element {
+            windows: element_keys(mapped),
+        }),
+        Data::Placeholder { .. } => None,
+    }
+}
+
+impl SavedLayout {
+    pub fn new(shell: &Shell) -> SavedLayout {
+        let mut workspaces = Vec::new();
+        for (output, set) in shell.workspaces.sets.iter() {
+            for (index, workspace) in set.workspaces.iter().enumerate() {
+                let tree = workspace.tiling_layer.tree();
+                let tiling = tree.root_node_id().and_then(|root| saved_node(tree, root));
+                let floating = workspace
+                    .floating_layer
+                    .mapped()
+                    .filter_map(|mapped| {
+                        let geo = workspace.floating_layer.element_geometry(mapped)?;
+                        Some(SavedFloating {
+                            windows: element_keys(mapped),
+                            geometry: SavedGeometry {
+                                x: geo.loc.x,
+                                y: geo.loc.y,
+                                width: geo.size.w,
+                                height: geo.size.h,
+                            },
+                        })
+                    })
+                    .collect::<Vec<_>>();
+
+                if tiling.is_some() || !floating.is_empty() {
+                    workspaces.push(SavedWorkspace {
+                        output: output.name(),
+                        index,
+                        tiling,
+                        floating,
+                    });
+                }
+            }
+        }
+
+        SavedLayout { workspaces }
+    }
+}
+
+/// Saved windows of an element, and the windows restored into it so far
+#[derive(Debug)]
+struct PendingElement {
+    windows: Vec<(WindowKey, Option<WeakCosmicSurface>)>,
+}
+
+impl PendingElement {
+    fn new(windows: &[WindowKey]) -> PendingElement {
+        PendingElement {
+            windows: windows.iter().map(|key| (key.clone(), None)).collect(),
+        }
+    }
+
+    fn restored(&self) -> impl Iterator<Item = CosmicSurface> + '_ {
+        self.windows
+            .iter()
+            .filter_map(|(_, surface)| surface.as_ref()?.upgrade())
+    }
+
+    fn is_pending(&self) -> bool {
+        self.windows.iter().any(|(_, surface)| surface.is_none())
+    }
+
+    fn contains(&self, window: &CosmicSurface) -> bool {
+        self.windows
+            .iter()
+            .any(|(_, surface)| surface.as_ref().is_some_and(|surface| window == surface))
+    }
+
+    /// Claims a free slot matching `key`, returns another already restored window of this element
+    fn claim(
+        &mut self,
+        key: &WindowKey,
+        exact: bool,
+        window: &CosmicSurface,
+    ) -> Option<Option<CosmicSurface>> {
+        let (_, slot) = self
+            .windows
+            .iter_mut()
+            .find(|(saved, surface)| surface.is_none() && saved.matches(key, exact))?;
+        *slot = Some(window.downgrade());
+        Some(self.restored().find(|surface| &surface != window))
+    }
+}
+
+#[derive(Debug)]
+enum PendingNode {
+    Group {
+        orientation: Orientation,
+        sizes: Vec<i32>,
+        children: Vec<PendingNode>,
+    },
+    Element(PendingElement),
+}
+
+impl PendingNode {
+    fn new(node: &SavedNode) -> PendingNode {
+        match node {
+            SavedNode::Group {
+                orientation,
+                sizes,
+                children,
+            } => PendingNode::Group {
+                orientation: (*orientation).into(),
+                sizes: sizes.clone(),
+                children: children.iter().map(PendingNode::new).collect(),
+            },
+            SavedNode::Element { windows } => PendingNode::Element(PendingElement::new(windows)),
+        }
+    }
+
+    fn elements(&self) -> Box<dyn Iterator<Item = &PendingElement> + '_> {
+        match self {
+            PendingNode::Group { children, .. } => {
+                Box::new(children.iter().flat_map(PendingNode::elements))
+            }
+            PendingNode::Element(element) => Box::new(std::iter::once(element)),
+        }
+    }
+
+    fn elements_mut(&mut self) -> Box<dyn Iterator<Item = &mut PendingElement> + '_> {
+        match self {
+            PendingNode::Group { children, .. } => {
+                Box::new(children.iter_mut().flat_map(PendingNode::elements_mut))
+            }
+            PendingNode::Element(element) => Box::new(std::iter::once(element)),
+        }
+    }
+
+    /// Shape of this node, limited to the elements already restored into `layout`
+    fn shape(&self, layout: &TilingLayout, used: &mut Vec<CosmicMapped>) -> Option<TreeShape> {
+        match self {
+            PendingNode::Group {
+                orientation,
+                sizes,
+                children,
+            } => {
+                let (sizes, children): (Vec<_>, Vec<_>) = children
+                    .iter()
+                    .zip(sizes.iter())
+                    .filter_map(|(child, size)| Some(((*size).max(1), child.shape(layout, used)?)))
+                    .unzip();
+                (!children.is_empty()).then(|| TreeShape::Group {
+                    orientation: *orientation,
+                    sizes,
+                    children,
+                })
+            }
+            PendingNode::Element(element) => {
+                let mapped = element.restored().find_map(|surface| {
+                    layout
+                        .mapped()
+                        .map(|(mapped, _)| mapped)
+                        .find(|mapped| mapped.windows().any(|(w, _)| w == surface))
+                        .cloned()
+                })?;
+                if used.contains(&mapped) {
+                    return None;
+                }
+                used.push(mapped.clone());
+                Some(TreeShape::Mapped(mapped))
+            }
+        }
+    }
+}
+
+#[derive(Debug)]
+struct PendingWorkspace {
+    output: String,
+    index: usize,
+    tiling: Option<PendingNode>,
+    floating: Vec<(PendingElement, Rectangle<i32, Local>)>,
+}
+
+/// Where a window should be restored to
+#[derive(Debug)]
+pub struct RestoreTarget {
+    pub output: String,
+    pub workspace: usize,
+    /// Geometry relative to the output, if the window was floating
+    pub floating: Option<Rectangle<i32, Local>>,
+    /// Already restored window, that the new one was stacked with
+    pub stack_with: Option<CosmicSurface>,
+}
+
+/// The saved layout of the last session, while its windows reappear
+#[derive(Debug, Default)]
+pub struct LayoutRestore {
+    workspaces: Vec<PendingWorkspace>,
+    deadline: Option<Instant>,
+}
+
+impl LayoutRestore {
+    pub fn new(saved: &SavedLayout) -> LayoutRestore {
+        LayoutRestore {
+            workspaces: saved
+                .workspaces
+                .iter()
+                .map(|workspace| PendingWorkspace {
+                    output: workspace.output.clone(),
+                    index: workspace.index,
+                    tiling: workspace.tiling.as_ref().map(PendingNode::new),
+                    floating: workspace
+                        .floating
+                        .iter()
+                        .map(|floating| {
+                            (
+                                PendingElement::new(&floating.windows),
+                                Rectangle::new(
+                                    (floating.geometry.x, floating.geometry.y).into(),
+                                    (floating.geometry.width, floating.geometry.height).into(),
+                                ),
+                            )
+                        })
+                        .collect(),
+                })
+                .collect(),
+            deadline: Some(Instant::now() + RESTORE_TIMEOUT),
+        }
+    }
+
+    fn is_expired(&self) -> bool {
+        self.deadline
+            .is_none_or(|deadline| Instant::now() >= deadline)
+    }
+
+    /// If windows of the saved layout may still reappear.
+    ///
+    /// Snapshots shouldn't overwrite the saved layout in the meantime.
+    pub fn is_pending(&mut self) -> bool {
+        if self.is_expired() {
+            self.workspaces.clear();
+            return false;
+        }
+
+        self.workspaces.iter().any(|workspace| {
+            workspace
+                .tiling
+                .iter()
+                .flat_map(PendingNode::elements)
+                .chain(workspace.floating.iter().map(|(element, _)| element))
+                .any(PendingElement::is_pending)
+        })
+    }
+
+    /// Claims the saved slot of a newly mapped window
+    pub fn take(&mut self, window: &CosmicSurface) -> Option<RestoreTarget> {
+        if !self.is_pending() {
+            return None;
+        }
+
+        let key = WindowKey::new(window);
+        for exact in [true, false] {
+            for workspace in &mut self.workspaces {
+                for element in workspace
+                    .tiling
+                    .iter_mut()
+                    .flat_map(PendingNode::elements_mut)
+                {
+                    if let Some(stack_with) = element.claim(&key, exact, window) {
+                        return Some(RestoreTarget {
+                            output: workspace.output.clone(),
+                            workspace: workspace.index,
+                            floating: None,
+                            stack_with,
+                        });
+                    }
+                }
+                for (element, geometry) in &mut workspace.floating {
+                    if let Some(stack_with) = element.claim(&key, exact, window) {
+                        return Some(RestoreTarget {
+                            output: workspace.output.clone(),
+                            workspace: workspace.index,
+                            floating: Some(*geometry),
+                            stack_with,
+                        });
+                    }
+                }
+            }
+        }
+
+        None
+    }
+
+    /// Saved shape of the tiling tree `window` was restored into,
+    /// limited to the windows restored into `layout` so far
+    pub fn tiling_shape(&self, window: &CosmicSurface, layout: &TilingLayout) -> Option<TreeShape> {
+        let node = self
+            .workspaces
+            .iter()
+            .filter_map(|workspace| workspace.tiling.as_ref())
+            .find(|node| node.elements().any(|element| element.contains(window)))?;
+        node.shape(layout, &mut Vec::new())
+    }
+}
+
+/// Number of applications whose floating geometry is remembered
+const MAX_REMEMBERED_FLOATING: usize = 64;
+
+#[derive(Debug, Clone, PartialEq, Serialize, Deserialize)]
+pub struct RememberedFloating {
+    pub window: WindowKey,
+    /// Geometry relative to the output
+    pub geometry: SavedGeometry,
+    pub output_width: i32,
+    pub output_height: i32,
+}
+
+/// Last floating geometry of recently closed windows, to re-open them at the same place.
+///
+/// Most recently closed windows come first.
+#[derive(Debug, Clone, Default, PartialEq, Serialize, Deserialize)]
+pub struct FloatingMemory {
+    pub windows: Vec<RememberedFloating>,
+}
+
+/// Key of `window`, with the title replaced by `title_pattern` if given
+fn floating_key(window: &CosmicSurface, title_pattern: Option<&str>) -> WindowKey {
+    let mut key = WindowKey::new(window);
+    if let Some(pattern) = title_pattern {
+        key.title = pattern.to_string();
+    }
+    key
+}
+
+impl FloatingMemory {
+    pub fn remember(
+        &mut self,
+        window: &CosmicSurface,
+        title_pattern: Option<&str>,
+        geometry: Rectangle<i32, Local>,
+        output_size: Size<i32, Logical>,
+    ) {
+        let key = floating_key(window, title_pattern);
+        if key.app_id.is_empty() {
+            return;
+        }
+
+        self.windows
+            .retain(|entry| !entry.window.matches(&key, true));
+        self.windows.insert(
+            0,
+            RememberedFloating {
+                window: key,
+                geometry: SavedGeometry {
+                    x: geometry.loc.x,
+                    y: geometry.loc.y,
+                    width: geometry.size.w,
+                    height: geometry.size.h,
+                },
+                output_width: output_size.w,
+                output_height: output_size.h,
+            },
+        );
+        self.windows.truncate(MAX_REMEMBERED_FLOATING);
+    }
+
+    /// Remembered geometry of `window` on an output of `output_size`.
+    ///
+    /// Prefers an entry with the same title, or the same `title_pattern` if given,
+    /// over the last closed window of the application.
+    pub fn geometry(
+        &self,
+        window: &CosmicSurface,
+        title_pattern: Option<&str>,
+        output_size: Size<i32, Logical>,
+    ) -> Option<Rectangle<i32, Local>> {
+        let key = floating_key(window, title_pattern);
+        if key.app_id.is_empty() {
+            return None;
+        }
+        let entry = self
+            .windows
+            .iter()
+            .find(|entry| entry.window.matches(&key, true))
+            .or_else(|| {
+                self.windows
+                    .iter()
+                    .find(|entry| entry.window.matches(&key, false))
+            })?;
+
+        let geometry = Rectangle::new(
+            (entry.geometry.x, entry.geometry.y).into(),
+            (entry.geometry.width, entry.geometry.height).into(),
+        );
+        let restore = FloatingRestoreData {
+            geometry,
+            output_size: (entry.output_width, entry.output_height).into(),
+            was_maximized: false,
+            was_snapped: None,
+        };
+        let size = Size::from((
+            geometry.size.w.min(output_size.w),
+            geometry.size.h.min(output_size.h),
+        ));
+        Some(Rectangle::new(restore.position_relative(output_size), size))
+    }
+}
@@ -0,0 +1,460 @@
+// SPDX-License-Identifier: GPL-3.0-only
+
+//! Scrollable columns layout, in the spirit of PaperWM or niri.
+//!
+//! Windows are arranged in columns on a horizontal strip, which may be wider than the output.
+//! The tree is kept in the shape of a vertical root group with one child per column, each
+//! being a single window or a horizontal group of windows. The sizes of the root group are
+//! the absolute column widths, which `update_positions` lays out starting at the current
+//! scroll offset instead of squeezing them onto the output.
+
+use id_tree::{NodeId, Tree};
+use smithay::{output::Output, utils::Rectangle};
+
+use crate::{
+    shell::{element::CosmicMapped, layout::Orientation},
+    utils::prelude::*,
+};
+
+use super::{ANIMATION_DURATION, Data, TilingLayout, TreeShape};
+
+/// Arbitrary length window heights are computed for, `update_positions` scales them to the output
+const LENGTH: i32 = 1000;
+
+#[derive(Debug, Clone)]
+pub struct Columns {
+    /// Width of new columns, as share of the usable output width
+    pub column_width: f64,
+    /// Horizontal position of the viewport on the strip
+    pub offset: i32,
+    /// Offset at the start of the current swipe gesture
+    swipe_start: Option<i32>,
+}
+
+impl Columns {
+    pub fn new(column_width: f64) -> Self {
+        Columns {
+            column_width: column_width.clamp(0.1, 1.0),
+            offset: 0,
+            swipe_start: None,
+        }
+    }
+
+    fn default_width(&self, output: &Output, gaps: (i32, i32)) -> i32 {
+        (area_width(output, gaps) as f64 * self.column_width).round() as i32
+    }
+}
+
+#[derive(Debug)]
+struct Column {
+    windows: Vec<CosmicMapped>,
+    sizes: Vec<i32>,
+    width: i32,
+}
+
+impl Column {
+    fn single(mapped: CosmicMapped, width: i32) -> Self {
+        Column {
+            windows: vec![mapped],
+            sizes: vec![LENGTH],
+            width,
+        }
+    }
+
+    fn from_node(tree: &Tree<Data>, node_id: &NodeId, width: i32) -> Option<Self> {
+        let node = tree.get(node_id).unwrap();
+        match node.data() {
+            Data::Mapped { mapped, .. } => Some(Column::single(mapped.clone(), width)),
+            Data::Group {
+                orientation: Orientation::Horizontal,
+                sizes,
+                ..
+            } if node
+                .children()
+                .iter()
+                .all(|child| tree.get(child).unwrap().data().is_mapped(None)) =>
+            {
+                Some(Column {
+                    windows: node
+                        .children()
+                        .iter()
+                        .map(|child| match tree.get(child).unwrap().data() {
+                            Data::Mapped { mapped, .. } => mapped.clone(),
+                            _ => unreachable!(),
+                        })
+                        .collect(),
+                    sizes: sizes.clone(),
+                    width,
+                })
+            }
+            _ => {
+                // anything nested deeper gets stacked up in one column
+                let windows = tree
+                    .traverse_pre_order(node_id)
+                    .unwrap()
+                    .filter_map(|node| match node.data() {
+                        Data::Mapped { mapped, .. } => Some(mapped.clone()),
+                        _ => None,
+                    })
+                    .collect::<Vec<_>>();
+                (!windows.is_empty()).then(|| Column {
+                    sizes: vec![LENGTH; windows.len()],
+                    windows,
+                    width,
+                })
+            }
+        }
+    }
+
+    fn shape(self) -> TreeShape {
+        if self.windows.len() == 1 {
+            return TreeShape::Mapped(self.windows.into_iter().next().unwrap());
+        }
+
+        TreeShape::Group {
+            orientation: Orientation::Horizontal,
+            sizes: self.sizes,
+            children: self.windows.into_iter().map(TreeShape::Mapped).collect(),
+        }
+    }
+}
+
+/// Usable width of the output, which column widths are relative to
+fn area_width(output: &Output, gaps: (i32, i32)) -> i32 {
+    TilingLayout::tiling_area(output, gaps).size.w
+}
+
+fn columns_of(tree: &Tree<Data>, default_width: i32) -> Vec<Column> {
+    let Some(root_id) = tree.root_node_id() else {
+        return Vec::new();
+    };
+    let root = tree.get(root_id).unwrap();
+    match root.data() {
+        Data::Group {
+            orientation: Orientation::Vertical,
+            sizes,
+            ..
+        } => root
+            .children()
+            .iter()
+            .zip(sizes)
+            .filter_map(|(child, width)| Column::from_node(tree, child, *width))
+            .collect(),
+        Data::Group { .. }
+            if !root
+                .children()
+                .iter()
+                .all(|child| tree.get(child).unwrap().data().is_mapped(None)) =>
+        {
+            root.children()
+                .iter()
+                .filter_map(|child| Column::from_node(tree, child, default_width))
+                .collect()
+        }
+        _ => Column::from_node(tree, root_id, default_width)
+            .into_iter()
+            .collect(),
+    }
+}
+
+fn strip_shape(mut columns: Vec<Column>) -> Option<TreeShape> {
+    match columns.len() {
+        0 => None,
+        1 => Some(columns.pop().unwrap().shape()),
+        _ => Some(TreeShape::Group {
+            orientation: Orientation::Vertical,
+            sizes: columns.iter().map(|column| column.width).collect(),
+            children: columns.into_iter().map(Column::shape).collect(),
+        }),
+    }
+}
+
+/// If the tree already has the shape of a strip of columns
+fn is_strip(tree: &Tree<Data>) -> bool {
+    let Some(root_id) = tree.root_node_id() else {
+        return true;
+    };
+    let is_column = |node_id: &NodeId| {
+        let node = tree.get(node_id).unwrap();
+        match node.data() {
+            Data::Mapped { .. } => true,
+            Data::Group {
+                orientation: Orientation::Horizontal,
+                ..
+            } => node
+                .children()
+                .iter()
+                .all(|child| tree.get(child).unwrap().data().is_mapped(None)),
+            _ => false,
+        }
+    };
+
+    let root = tree.get(root_id).unwrap();
+    match root.data() {
+        Data::Group {
+            orientation: Orientation::Vertical,
+            ..
+        } => root.children().iter().all(is_column),
+        _ => is_column(root_id),
+    }
+}
+
+/// Index of the column containing `node_id`
+fn column_index(tree: &Tree<Data>, node_id: &NodeId) -> Option<usize> {
+    let root_id = tree.root_node_id()?;
+    if !matches!(
+        tree.get(root_id).unwrap().data(),
+        Data::Group {
+            orientation: Orientation::Vertical,
+            ..
+        }
+    ) {
+        return Some(0);
+    }
+
+    let column_id = std::iter::once(node_id)
+        .chain(tree.ancestor_ids(node_id).ok()?)
+        .find(|id| tree.get(id).unwrap().parent() == Some(root_id))?;
+    tree.children_ids(root_id)
+        .unwrap()
+        .position(|id| id == column_id)
+}
+
+fn has_placeholders(tree: &Tree<Data>) -> bool {
+    tree.root_node_id().is_some_and(|root_id| {
+        tree.traverse_pre_order(root_id)
+            .unwrap()
+            .any(|node| node.data().is_placeholder())
+    })
+}
+
+impl TilingLayout {
+    pub fn columns(&self) -> Option<&Columns> {
+        self.columns.as_ref()
+    }
+
+    /// Enables or disables the scrollable columns mode
+    pub fn set_columns(&mut self, columns: Option<Columns>) {
+        let gaps = self.gaps();
+        let mut tree = self.queue.trees.back().unwrap().0.copy_clone();
+        if columns.is_some() {
+            self.master_stack = None;
+            let default_width = columns.as_ref().unwrap().default_width(&self.output, gaps);
+            if let Some(shape) = strip_shape(columns_of(&tree, default_width)) {
+                TilingLayout::replace_with_shape(&mut tree, shape);
+            }
+        }
+        self.columns = columns;
+        if let Some(columns) = self.columns.as_mut() {
+            columns.offset = 0;
+        }
+
+        let blocker =
+            TilingLayout::update_positions(&self.output, &mut tree, gaps, self.columns.as_ref());
+        self.queue.push_tree(tree, ANIMATION_DURATION, blocker);
+    }
+
+    /// Inserts `window` as a new column right of the column of `focused`
+    pub(super) fn insert_column(
+        tree: &mut Tree<Data>,
+        columns: &Columns,
+        window: CosmicMapped,
+        focused: Option<NodeId>,
+        minimize_rect: Option<Rectangle<i32, Local>>,
+        output: &Output,
+        gaps: (i32, i32),
+    ) {
+        let default_width = columns.default_width(output, gaps);
+        let mut strip = columns_of(tree, default_width);
+        let idx = focused
+            .and_then(|node_id| column_index(tree, &node_id))
+            .map(|idx| (idx + 1).min(strip.len()))
+            .unwrap_or(strip.len());
+        strip.insert(idx, Column::single(window.clone(), default_width));
+
+        TilingLayout::replace_with_shape(tree, strip_shape(strip).unwrap());
+        let node_id = window.tiling_node_id.lock().unwrap().clone().unwrap();
+        if let Data::Mapped {
+            minimize_rect: rect,
+            ..
+        } = tree.get_mut(&node_id).unwrap().data_mut()
+        {
+            *rect = minimize_rect;
+        }
+    }
+
+    /// Removes `mapped` from its column, without changing the width of other columns
+    pub(super) fn remove_from_columns(
+        tree: &mut Tree<Data>,
+        mapped: &CosmicMapped,
+        output: &Output,
+        gaps: (i32, i32),
+    ) {
+        let mut strip = columns_of(tree, area_width(output, gaps));
+        for column in strip.iter_mut() {
+            if let Some(idx) = column.windows.iter().position(|m| m == mapped) {
+                column.windows.remove(idx);
+                column.sizes.remove(idx);
+            }
+        }
+        strip.retain(|column| !column.windows.is_empty());
+
+        match strip_shape(strip) {
+            Some(shape) => TilingLayout::replace_with_shape(tree, shape),
+            None => *tree = Tree::new(),
+        }
+    }
+
+    /// Brings the tree back into the shape of a strip and keeps the scroll offset in bounds
+    pub(super) fn refresh_columns(&mut self) {
+        let Some(columns) = self.columns.as_ref() else {
+            return;
+        };
+        let tree = &self.queue.trees.back().unwrap().0;
+        // leave drag-and-drop placeholders alone, the window gets arranged once it is dropped
+        if has_placeholders(tree) {
+            return;
+        }
+
+        let max_offset = self.max_offset(tree);
+        if is_strip(tree) && columns.offset <= max_offset {
+            return;
+        }
+
+        let gaps = self.gaps();
+        let default_width = columns.default_width(&self.output, gaps);
+        let mut tree = tree.copy_clone();
+        if !is_strip(&tree)
+            && let Some(shape) = strip_shape(columns_of(&tree, default_width))
+        {
+            TilingLayout::replace_with_shape(&mut tree, shape);
+        }
+        let max_offset = self.max_offset(&tree);
+        let columns = self.columns.as_mut().unwrap();
+        columns.offset = columns.offset.clamp(0, max_offset);
+
+        let blocker =
+            TilingLayout::update_positions(&self.output, &mut tree, gaps, self.columns.as_ref());
+        self.queue.push_tree(tree, ANIMATION_DURATION, blocker);
+    }
+
+    fn strip_width(tree: &Tree<Data>) -> Option<i32> {
+        match tree.get(tree.root_node_id()?).unwrap().data() {
+            Data::Group {
+                orientation: Orientation::Vertical,
+                sizes,
+                ..
+            } => Some(sizes.iter().sum()),
+            _ => None,
+        }
+    }
+
+    fn max_offset(&self, tree: &Tree<Data>) -> i32 {
+        TilingLayout::strip_width(tree)
+            .map(|width| (width - area_width(&self.output, self.gaps())).max(0))
+            .unwrap_or(0)
+    }
+
+    /// Scrolls the strip to show the column of `focused`
+    pub fn scroll_to_focus(&mut self, focused: &CosmicMapped) {
+        if self
+            .columns
+            .as_ref()
+            .is_none_or(|columns| columns.swipe_start.is_some())
+        {
+            return;
+        }
+        let Some(node_id) = focused.tiling_node_id.lock().unwrap().clone() else {
+            return;
+        };
+
+        let tree = &self.queue.trees.back().unwrap().0;
+        // anything else is a single column, which isn't wider than the output
+        let Some(Data::Group {
+            orientation: Orientation::Vertical,
+            sizes,
+            ..
+        }) = tree.root_node_id().map(|id| tree.get(id).unwrap().data())
+        else {
+            return;
+        };
+        let Some(idx) = tree
+            .get(&node_id)
+            .ok()
+            .and_then(|_| column_index(tree, &node_id))
+        else {
+            return;
+        };
+
+        let start: i32 = sizes[..idx].iter().sum();
+        let end = start + sizes[idx];
+        let width = area_width(&self.output, self.gaps());
+        let columns = self.columns.as_ref().unwrap();
+        let offset = if start < columns.offset {
+            start
+        } else if end > columns.offset + width {
+            (end - width).min(start)
+        } else {
+            return;
+        };
+        self.scroll_to(offset, ANIMATION_DURATION);
+    }
+
+    fn scroll_to(&mut self, offset: i32, duration: impl Into<Option<std::time::Duration>>) {
+        let gaps = self.gaps();
+        let max_offset = self.max_offset(&self.queue.trees.back().unwrap().0);
+        let Some(columns) = self.columns.as_mut() else {
+            return;
+        };
+        let offset = offset.clamp(0, max_offset);
+        if columns.offset == offset {
+            return;
+        }
+        columns.offset = offset;
+
+        let mut tree = self.queue.trees.back().unwrap().0.copy_clone();
+        let blocker =
+            TilingLayout::update_positions(&self.output, &mut tree, gaps, self.columns.as_ref());
+        self.queue.push_tree(tree, duration, blocker);
+    }
+
+    /// Follows a swipe gesture, `delta` being the distance moved since the gesture started
+    pub fn update_columns_swipe(&mut self, delta: f64) {
+        let Some(columns) = self.columns.as_mut() else {
+            return;
+        };
+        let start = *columns.swipe_start.get_or_insert(columns.offset);
+        self.scroll_to(start + delta.round() as i32, None);
+    }
+
+    /// Ends a swipe gesture, snapping the viewport to the column closest to `projected_delta`
+    pub fn end_columns_swipe(&mut self, projected_delta: f64) {
+        let Some(start) = self
+            .columns
+            .as_mut()
+            .and_then(|columns| columns.swipe_start.take())
+        else {
+            return;
+        };
+
+        let target = start + projected_delta.round() as i32;
+        let tree = &self.queue.trees.back().unwrap().0;
+        let offset = match tree.root_node_id().map(|id| tree.get(id).unwrap().data()) {
+            Some(Data::Group {
+                orientation: Orientation::Vertical,
+                sizes,
+                ..
+            }) => sizes
+                .iter()
+                .scan(0, |start, width| {
+                    let column_start = *start;
+                    *start += width;
+                    Some(column_start)
+                })
+                .min_by_key(|column_start| (column_start - target).abs())
+                .unwrap_or(0),
+            _ => 0,
+        };
+        self.scroll_to(offset, ANIMATION_DURATION);
+    }
+}
@@ -0,0 +1,268 @@
+// SPDX-License-Identifier: GPL-3.0-only
+
+//! Master-stack layout mode, as known from dwm or xmonad.
+//!
+//! Instead of a separate layout, the tree is automatically arranged into a master area
+//! on the left and a stack area on the right, each splitting its windows vertically.
+//! The order of windows is the pre-order of the tree, so anything moving windows
+//! around in the tree (keyboard moves, drag and drop) also moves them through the areas.
+
+use id_tree::{NodeId, Tree};
+
+use crate::shell::{element::CosmicMapped, layout::Orientation};
+
+use super::{ANIMATION_DURATION, Data, TilingLayout, TreeShape};
+
+/// Arbitrary length sizes are computed for, `update_positions` scales them to the output
+const LENGTH: f64 = 1000.;
+
+#[derive(Debug, Clone, Copy, PartialEq)]
+pub struct MasterStack {
+    /// Number of windows in the master area
+    pub count: usize,
+    /// Share of the output width taken by the master area
+    pub ratio: f64,
+}
+
+impl MasterStack {
+    fn shape(&self, mut order: Vec<CosmicMapped>) -> Option<TreeShape> {
+        if order.is_empty() {
+            return None;
+        }
+
+        let stack = order.split_off(self.count.clamp(1, order.len()));
+        let master = column(order);
+        if stack.is_empty() {
+            return Some(master);
+        }
+
+        let ratio = self.ratio.clamp(0.1, 0.9);
+        Some(TreeShape::Group {
+            orientation: Orientation::Vertical,
+            sizes: vec![
+                (ratio * LENGTH).round() as i32,
+                ((1. - ratio) * LENGTH).round() as i32,
+            ],
+            children: vec![master, column(stack)],
+        })
+    }
+}
+
+fn column(mut windows: Vec<CosmicMapped>) -> TreeShape {
+    if windows.len() == 1 {
+        return TreeShape::Mapped(windows.pop().unwrap());
+    }
+
+    TreeShape::Group {
+        orientation: Orientation::Horizontal,
+        sizes: vec![LENGTH as i32; windows.len()],
+        children: windows.into_iter().map(TreeShape::Mapped).collect(),
+    }
+}
+
+fn shape_matches(tree: &Tree<Data>, node_id: &NodeId, shape: &TreeShape) -> bool {
+    let Ok(node) = tree.get(node_id) else {
+        return false;
+    };
+    match (node.data(), shape) {
+        (
+            Data::Group { orientation, .. },
+            TreeShape::Group {
+                orientation: expected,
+                children,
+                ..
+            },
+        ) => {
+            orientation == expected
+                && node.children().len() == children.len()
+                && node
+                    .children()
+                    .iter()
+                    .zip(children)
+                    .all(|(child, shape)| shape_matches(tree, child, shape))
+        }
+        (Data::Mapped { mapped, .. }, TreeShape::Mapped(expected)) => mapped == expected,
+        _ => false,
+    }
+}
+
+pub(super) fn tiled_order(tree: &Tree<Data>) -> Vec<CosmicMapped> {
+    tree.root_node_id()
+        .into_iter()
+        .flat_map(|root| tree.traverse_pre_order(root).unwrap())
+        .filter_map(|node| match node.data() {
+            Data::Mapped { mapped, .. } => Some(mapped.clone()),
+            _ => None,
+        })
+        .collect()
+}
+
+impl TilingLayout {
+    pub fn master_stack(&self) -> Option<MasterStack> {
+        self.master_stack
+    }
+
+    /// Enables or disables the master-stack mode
+    pub fn set_master_stack(&mut self, master_stack: Option<MasterStack>) {
+        if master_stack.is_some() && self.columns.is_some() {
+            self.set_columns(None);
+        }
+        self.master_stack = master_stack;
+        self.rearrange(None, true);
+    }
+
+    /// Changes the master count or ratio, if the master-stack mode is enabled
+    pub fn update_master_stack(&mut self, update: impl FnOnce(&mut MasterStack)) {
+        if let Some(master_stack) = self.master_stack.as_mut() {
+            update(master_stack);
+            master_stack.count = master_stack.count.max(1);
+            master_stack.ratio = master_stack.ratio.clamp(0.1, 0.9);
+            self.rearrange(None, true);
+        }
+    }
+
+    /// Moves `mapped` into the first master slot, or swaps it with the next window if it already is
+    pub fn promote(&mut self, mapped: &CosmicMapped) {
+        let mut order = tiled_order(&self.queue.trees.back().unwrap().0);
+        match order.iter().position(|m| m == mapped) {
+            Some(0) if order.len() > 1 => order.swap(0, 1),
+            Some(idx) => {
+                let mapped = order.remove(idx);
+                order.insert(0, mapped);
+            }
+            None => return,
+        }
+        self.rearrange(Some(order), false);
+    }
+
+    /// Moves `mapped` from the master area to the top of the stack area
+    pub fn demote(&mut self, mapped: &CosmicMapped) {
+        let Some(master_stack) = self.master_stack else {
+            return;
+        };
+        let mut order = tiled_order(&self.queue.trees.back().unwrap().0);
+        let Some(idx) = order.iter().position(|m| m == mapped) else {
+            return;
+        };
+        if idx >= master_stack.count || order.len() <= master_stack.count {
+            return;
+        }
+        let mapped = order.remove(idx);
+        order.insert(master_stack.count, mapped);
+        self.rearrange(Some(order), false);
+    }
+
+    /// Rotates all windows by one slot through the master and stack areas
+    pub fn rotate(&mut self, forward: bool) {
+        let mut order = tiled_order(&self.queue.trees.back().unwrap().0);
+        if order.len() < 2 {
+            return;
+        }
+        if forward {
+            order.rotate_right(1);
+        } else {
+            order.rotate_left(1);
+        }
+        self.rearrange(Some(order), false);
+    }
+
+    /// Shape the tree needs to be rebuilt in for the master-stack mode, if it doesn't have it already.
+    ///
+    /// `order` defaults to the current order of windows in the tree.
+    /// If the tree already has the right shape, the ratio is updated from the current sizes.
+    fn required_shape(
+        tree: &Tree<Data>,
+        master_stack: &mut MasterStack,
+        order: Option<Vec<CosmicMapped>>,
+        force: bool,
+    ) -> Option<TreeShape> {
+        let root_id = tree.root_node_id()?;
+        // leave drag-and-drop placeholders alone, the window gets arranged once it is dropped
+        if tree
+            .traverse_pre_order(root_id)
+            .unwrap()
+            .any(|node| node.data().is_placeholder() && !node.data().is_reserved())
+        {
+            return None;
+        }
+
+        let order = order.unwrap_or_else(|| tiled_order(tree));
+        let shape = master_stack.shape(order)?;
+        if !force && shape_matches(tree, root_id, &shape) {
+            // keep the ratio, if the master area was resized
+            if let TreeShape::Group { .. } = shape
+                && let Data::Group { sizes, .. } = tree.get(root_id).unwrap().data()
+            {
+                let total: i32 = sizes.iter().sum();
+                if total > 0 {
+                    master_stack.ratio = sizes[0] as f64 / total as f64;
+                }
+            }
+            return None;
+        }
+
+        Some(shape)
+    }
+
+    /// Arranges the tree in master-stack mode. Returns if the tree changed.
+    pub(super) fn arrange_master_stack(
+        tree: &mut Tree<Data>,
+        master_stack: &mut MasterStack,
+        order: Option<Vec<CosmicMapped>>,
+        force: bool,
+    ) -> bool {
+        match TilingLayout::required_shape(tree, master_stack, order, force) {
+            Some(shape) => {
+                TilingLayout::replace_with_shape(tree, shape);
+                true
+            }
+            None => false,
+        }
+    }
+
+    pub(super) fn replace_with_shape(tree: &mut Tree<Data>, shape: TreeShape) {
+        // keep minimize animations going
+        let minimize_rects = tree
+            .root_node_id()
+            .into_iter()
+            .flat_map(|root_id| tree.traverse_pre_order(root_id).unwrap())
+            .filter_map(|node| match node.data() {
+                Data::Mapped {
+                    mapped,
+                    minimize_rect: Some(rect),
+                    ..
+                } => Some((mapped.clone(), *rect)),
+                _ => None,
+            })
+            .collect::<Vec<_>>();
+        let mut new_tree = TilingLayout::tree_from_shape(shape);
+        for (mapped, rect) in minimize_rects {
+            let node_id = mapped.tiling_node_id.lock().unwrap().clone();
+            if let Some(Data::Mapped { minimize_rect, .. }) = node_id
+                .and_then(|id| new_tree.get_mut(&id).ok())
+                .map(|node| node.data_mut())
+            {
+                *minimize_rect = Some(rect);
+            }
+        }
+        *tree = new_tree;
+    }
+
+    /// Re-arranges the current tree in master-stack mode
+    pub(super) fn rearrange(&mut self, order: Option<Vec<CosmicMapped>>, force: bool) {
+        let Some(master_stack) = self.master_stack.as_mut() else {
+            return;
+        };
+        let current = &self.queue.trees.back().unwrap().0;
+        let Some(shape) = TilingLayout::required_shape(current, master_stack, order, force) else {
+            return;
+        };
+
+        let gaps = self.gaps();
+        let mut tree = current.copy_clone();
+        TilingLayout::replace_with_shape(&mut tree, shape);
+        let blocker =
+            TilingLayout::update_positions(&self.output, &mut tree, gaps, self.columns.as_ref());
+        self.queue.push_tree(tree, ANIMATION_DURATION, blocker);
+    }
+}
@@ -72,9 +72,15 @@ use tracing::trace;
 use wayland_backend::server::ClientId;
 
 mod blocker;
+mod columns;
 mod grabs;
+mod master_stack;
+mod size_hints;
+mod template;
 pub use self::blocker::*;
+pub use self::columns::Columns;
 pub use self::grabs::*;
+pub use self::master_stack::MasterStack;
 
 pub const ANIMATION_DURATION: Duration = Duration::from_millis(200);
 pub const MINIMIZE_ANIMATION_DURATION: Duration = Duration::from_millis(320);
@@ -112,6 +118,8 @@ impl TargetZone {
 struct TreeQueue {
     trees: VecDeque<(Tree<Data>, Duration, Option<TilingBlocker>)>,
     animation_start: Option<Instant>,
+    /// Set when a tree is pushed, until the shape of the layout mode was checked
+    changed: bool,
 }
 
 impl TreeQueue {
@@ -122,7 +130,8 @@ impl TreeQueue {
         blocker: Option<TilingBlocker>,
     ) {
         self.trees
-            .push_back((tree, duration.into().unwrap_or(Duration::ZERO), blocker))
+            .push_back((tree, duration.into().unwrap_or(Duration::ZERO), blocker));
+        self.changed = true;
     }
 }
 
@@ -133,6 +142,8 @@ pub struct TilingLayout {
     backdrop_id: Id,
     swapping_stack_surface_id: Id,
     last_overview_hover: Option<(Option<Instant>, TargetZone)>,
+    master_stack: Option<MasterStack>,
+    columns: Option<Columns>,
     pub theme: cosmic::Theme,
     pub appearance: AppearanceConfig,
 }
@@ -168,6 +179,8 @@ pub enum Data {
 pub enum PlaceholderType {
     GrabbedWindow,
     DropZone,
+    /// Slot of a layout template, filled by the next mapped window
+    Reserved,
 }
 
 impl Data {
@@ -205,6 +218,15 @@ impl Data {
     fn is_placeholder(&self) -> bool {
         matches!(self, Data::Placeholder { .. })
     }
+    fn is_reserved(&self) -> bool {
+        matches!(
+            self,
+            Data::Placeholder {
+                type_: PlaceholderType::Reserved,
+                ..
+            }
+        )
+    }
 
     fn orientation(&self) -> Orientation {
         match self {
@@ -213,6 +235,37 @@ impl Data {
         }
     }
 
+    /// Changes the orientation of a group, rescaling its sizes to the other axis
+    fn set_orientation(&mut self, new_orientation: Orientation) {
+        if let Data::Group {
+            orientation,
+            sizes,
+            last_geometry,
+            ..
+        } = self
+        {
+            let previous_length = match orientation {
+                Orientation::Horizontal => last_geometry.size.h,
+                Orientation::Vertical => last_geometry.size.w,
+            };
+            let new_length = match new_orientation {
+                Orientation::Horizontal => last_geometry.size.h,
+                Orientation::Vertical => last_geometry.size.w,
+            };
+
+            sizes.iter_mut().for_each(|len| {
+                *len = (((*len as f64) / (previous_length as f64)) * (new_length as f64)).round()
+                    as i32;
+            });
+            let sum: i32 = sizes.iter().sum();
+            if sum < new_length {
+                *sizes.last_mut().unwrap() += new_length - sum;
+            }
+
+            *orientation = new_orientation;
+        }
+    }
+
     fn add_window(&mut self, idx: usize) {
         match self {
             Data::Group {
@@ -345,6 +398,31 @@ pub struct RestoreTilingState {
     pub sizes: Vec<i32>,
 }
 
+/// Shape of a tiling tree, used to rebuild a layout from a saved one
+#[derive(Debug, Clone)]
+pub enum TreeShape {
+    Group {
+        orientation: Orientation,
+        sizes: Vec<i32>,
+        children: Vec<TreeShape>,
+    },
+    Mapped(CosmicMapped),
+    /// Reserved slot for the next mapped window
+    Slot,
+}
+
+impl TreeShape {
+    fn mapped(&self) -> Vec<&CosmicMapped> {
+        match self {
+            TreeShape::Group { children, .. } => {
+                children.iter().flat_map(TreeShape::mapped).collect()
+            }
+            TreeShape::Mapped(mapped) => vec![mapped],
+            TreeShape::Slot => Vec::new(),
+        }
+    }
+}
+
 impl TilingLayout {
     pub fn new(
         theme: cosmic::Theme,
@@ -359,11 +437,14 @@ impl TilingLayout {
                     queue
                 },
                 animation_start: None,
+                changed: false,
             },
             output: output.clone(),
             backdrop_id: Id::new(),
             swapping_stack_surface_id: Id::new(),
             last_overview_hover: None,
+            master_stack: None,
+            columns: None,
             theme,
             appearance,
         }
@@ -385,7 +466,8 @@ impl TilingLayout {
             }
         }
 
-        let blocker = TilingLayout::update_positions(output, &mut tree, gaps);
+        let blocker =
+            TilingLayout::update_positions(output, &mut tree, gaps, self.columns.as_ref());
         self.queue.push_tree(tree, None, blocker);
         self.output = output.clone();
     }
@@ -420,15 +502,44 @@ impl TilingLayout {
             ANIMATION_DURATION
         };
 
-        TilingLayout::map_to_tree(
-            &mut tree,
-            window,
-            &self.output,
-            last_active,
-            direction,
-            minimize_rect,
-        );
-        let blocker = TilingLayout::update_positions(&self.output, &mut tree, gaps);
+        let window: CosmicMapped = window.into();
+        if let Some(slot_id) = TilingLayout::first_reserved_slot(&tree) {
+            let data = tree.get_mut(&slot_id).unwrap().data_mut();
+            *data = Data::Mapped {
+                mapped: window.clone(),
+                last_geometry: *data.geometry(),
+                minimize_rect,
+            };
+            *window.tiling_node_id.lock().unwrap() = Some(slot_id);
+        } else if let Some(columns) = self.columns.as_ref() {
+            TilingLayout::insert_column(
+                &mut tree,
+                columns,
+                window.clone(),
+                last_active,
+                minimize_rect,
+                &self.output,
+                gaps,
+            );
+        } else {
+            TilingLayout::map_to_tree(
+                &mut tree,
+                window.clone(),
+                &self.output,
+                last_active,
+                direction,
+                minimize_rect,
+            );
+        }
+        if let Some(master_stack) = self.master_stack.as_mut() {
+            // new windows become the master
+            let mut order = master_stack::tiled_order(&tree);
+            order.retain(|mapped| mapped != &window);
+            order.insert(0, window);
+            TilingLayout::arrange_master_stack(&mut tree, master_stack, Some(order), true);
+        }
+        let blocker =
+            TilingLayout::update_positions(&self.output, &mut tree, gaps, self.columns.as_ref());
         self.queue.push_tree(tree, duration, blocker);
     }
 
@@ -493,7 +604,12 @@ impl TilingLayout {
                 tree.make_nth_sibling(&new_id, idx).unwrap();
                 *window.tiling_node_id.lock().unwrap() = Some(new_id);
 
-                let blocker = TilingLayout::update_positions(&self.output, &mut tree, gaps);
+                let blocker = TilingLayout::update_positions(
+                    &self.output,
+                    &mut tree,
+                    gaps,
+                    self.columns.as_ref(),
+                );
                 self.queue
                     .push_tree(tree, MINIMIZE_ANIMATION_DURATION, blocker);
                 return;
@@ -531,7 +647,12 @@ impl TilingLayout {
 
                 *window.tiling_node_id.lock().unwrap() = Some(new_id);
 
-                let blocker = TilingLayout::update_positions(&self.output, &mut tree, gaps);
+                let blocker = TilingLayout::update_positions(
+                    &self.output,
+                    &mut tree,
+                    gaps,
+                    self.columns.as_ref(),
+                );
                 self.queue
                     .push_tree(tree, MINIMIZE_ANIMATION_DURATION, blocker);
                 return;
@@ -612,6 +733,92 @@ impl TilingLayout {
         *window.tiling_node_id.lock().unwrap() = Some(window_id);
     }
 
+    /// Rebuilds the tree in the given shape.
+    ///
+    /// Does nothing and returns `false`, if the shape doesn't contain exactly the currently tiled elements.
+    pub fn apply_shape(&mut self, shape: TreeShape) -> bool {
+        let shape_mapped = shape.mapped();
+        let current = self.mapped().map(|(mapped, _)| mapped).collect::<Vec<_>>();
+        if shape_mapped.len() != current.len()
+            || !current.iter().all(|mapped| shape_mapped.contains(mapped))
+        {
+            return false;
+        }
+
+        let gaps = self.gaps();
+        let mut tree = TilingLayout::tree_from_shape(shape);
+        let blocker =
+            TilingLayout::update_positions(&self.output, &mut tree, gaps, self.columns.as_ref());
+        self.queue.push_tree(tree, ANIMATION_DURATION, blocker);
+        true
+    }
+
+    fn tree_from_shape(shape: TreeShape) -> Tree<Data> {
+        let mut tree = Tree::new();
+        TilingLayout::insert_shape(&mut tree, None, shape);
+        tree
+    }
+
+    fn insert_shape(tree: &mut Tree<Data>, parent: Option<&NodeId>, shape: TreeShape) {
+        let behavior = match parent {
+            Some(parent) => InsertBehavior::UnderNode(parent),
+            None => InsertBehavior::AsRoot,
+        };
+        match shape {
+            TreeShape::Group {
+                orientation,
+                sizes,
+                children,
+            } => {
+                // sizes are rescaled relative to this geometry by `update_positions`
+                let length = sizes.iter().sum();
+                let size = match orientation {
+                    Orientation::Horizontal => (100, length),
+                    Orientation::Vertical => (length, 100),
+                };
+                let group_id = tree
+                    .insert(
+                        Node::new(Data::Group {
+                            orientation,
+                            sizes,
+                            last_geometry: Rectangle::from_size(size.into()),
+                            alive: Arc::new(()),
+                            pill_indicator: None,
+                        }),
+                        behavior,
+                    )
+                    .unwrap();
+                for child in children {
+                    TilingLayout::insert_shape(tree, Some(&group_id), child);
+                }
+            }
+            TreeShape::Mapped(mapped) => {
+                let node_id = tree
+                    .insert(
+                        Node::new(Data::Mapped {
+                            mapped: mapped.clone(),
+                            last_geometry: Rectangle::from_size((100, 100).into()),
+                            minimize_rect: None,
+                        }),
+                        behavior,
+                    )
+                    .unwrap();
+                *mapped.tiling_node_id.lock().unwrap() = Some(node_id);
+            }
+            TreeShape::Slot => {
+                tree.insert(
+                    Node::new(Data::Placeholder {
+                        id: Id::new(),
+                        last_geometry: Rectangle::from_size((100, 100).into()),
+                        type_: PlaceholderType::Reserved,
+                    }),
+                    behavior,
+                )
+                .unwrap();
+            }
+        }
+    }
+
     pub fn replace_window(&mut self, old: &CosmicMapped, new: &CosmicMapped) {
         let gaps = self.gaps();
         let Some(old_id) = old.tiling_node_id.lock().unwrap().clone() else {
@@ -634,7 +841,12 @@ impl TilingLayout {
             old.output_leave(&self.output);
             new.output_enter(&self.output, new.bbox());
 
-            let blocker = TilingLayout::update_positions(&self.output, &mut tree, gaps);
+            let blocker = TilingLayout::update_positions(
+                &self.output,
+                &mut tree,
+                gaps,
+                self.columns.as_ref(),
+            );
             self.queue.push_tree(tree, ANIMATION_DURATION, blocker);
         }
     }
@@ -811,12 +1023,20 @@ impl TilingLayout {
                 let other_gaps = other.gaps();
 
                 TilingLayout::unmap_internal(&mut this_tree, &desc.node);
-                let blocker =
-                    TilingLayout::update_positions(&this.output, &mut this_tree, this_gaps);
+                let blocker = TilingLayout::update_positions(
+                    &this.output,
+                    &mut this_tree,
+                    this_gaps,
+                    this.columns.as_ref(),
+                );
                 this.queue.push_tree(this_tree, ANIMATION_DURATION, blocker);
 
-                let blocker =
-                    TilingLayout::update_positions(&other.output, &mut other_tree, other_gaps);
+                let blocker = TilingLayout::update_positions(
+                    &other.output,
+                    &mut other_tree,
+                    other_gaps,
+                    other.columns.as_ref(),
+                );
                 other
                     .queue
                     .push_tree(other_tree, ANIMATION_DURATION, blocker);
@@ -1252,18 +1472,24 @@ impl TilingLayout {
         }
 
         let this_gaps = this.gaps();
-        let blocker = TilingLayout::update_positions(&this.output, &mut this_tree, this_gaps);
+        let blocker = TilingLayout::update_positions(
+            &this.output,
+            &mut this_tree,
+            this_gaps,
+            this.columns.as_ref(),
+        );
         this.queue.push_tree(this_tree, ANIMATION_DURATION, blocker);
 
         let has_other_tree = other_tree.is_some();
         if let Some(mut other_tree) = other_tree {
-            let (other_queue, gaps) = if let Some(other) = other.as_mut() {
+            let (other_queue, gaps, columns) = if let Some(other) = other.as_mut() {
                 let other_gaps = other.gaps();
-                (&mut other.queue, other_gaps)
+                (&mut other.queue, other_gaps, other.columns.as_ref())
             } else {
-                (&mut this.queue, this_gaps)
+                (&mut this.queue, this_gaps, this.columns.as_ref())
             };
-            let blocker = TilingLayout::update_positions(&other_output, &mut other_tree, gaps);
+            let blocker =
+                TilingLayout::update_positions(&other_output, &mut other_tree, gaps, columns);
             other_queue.push_tree(other_tree, ANIMATION_DURATION, blocker);
         }
 
@@ -1425,14 +1651,26 @@ impl TilingLayout {
         {
             let mut tree = self.queue.trees.back().unwrap().0.copy_clone();
 
-            TilingLayout::unmap_internal(&mut tree, &node_id);
+            if self.columns.is_some() {
+                TilingLayout::remove_from_columns(&mut tree, mapped, &self.output, gaps);
+            } else {
+                TilingLayout::unmap_internal(&mut tree, &node_id);
+            }
+            if let Some(master_stack) = self.master_stack.as_mut() {
+                TilingLayout::arrange_master_stack(&mut tree, master_stack, None, false);
+            }
 
             let duration = if minimizing {
                 MINIMIZE_ANIMATION_DURATION
             } else {
                 ANIMATION_DURATION
             };
-            let blocker = TilingLayout::update_positions(&self.output, &mut tree, gaps);
+            let blocker = TilingLayout::update_positions(
+                &self.output,
+                &mut tree,
+                gaps,
+                self.columns.as_ref(),
+            );
             self.queue.push_tree(tree, duration, blocker);
 
             return true;
@@ -1548,7 +1786,12 @@ impl TilingLayout {
                     .unwrap();
                     *mapped.tiling_node_id.lock().unwrap() = Some(new_id);
 
-                    let blocker = TilingLayout::update_positions(&self.output, &mut tree, gaps);
+                    let blocker = TilingLayout::update_positions(
+                        &self.output,
+                        &mut tree,
+                        gaps,
+                        self.columns.as_ref(),
+                    );
                     self.queue.push_tree(tree, ANIMATION_DURATION, blocker);
                     return MoveResult::ShiftFocus(mapped.into());
                 }
@@ -1624,7 +1867,12 @@ impl TilingLayout {
                     .data_mut()
                     .remove_window(og_idx);
 
-                let blocker = TilingLayout::update_positions(&self.output, &mut tree, gaps);
+                let blocker = TilingLayout::update_positions(
+                    &self.output,
+                    &mut tree,
+                    gaps,
+                    self.columns.as_ref(),
+                );
                 self.queue.push_tree(tree, ANIMATION_DURATION, blocker);
                 return MoveResult::Done;
             }
@@ -1650,7 +1898,12 @@ impl TilingLayout {
                     .data_mut()
                     .remove_window(og_idx);
 
-                let blocker = TilingLayout::update_positions(&self.output, &mut tree, gaps);
+                let blocker = TilingLayout::update_positions(
+                    &self.output,
+                    &mut tree,
+                    gaps,
+                    self.columns.as_ref(),
+                );
                 self.queue.push_tree(tree, ANIMATION_DURATION, blocker);
                 return MoveResult::Done;
             }
@@ -1806,7 +2059,12 @@ impl TilingLayout {
                     MoveResult::Done
                 };
 
-                let blocker = TilingLayout::update_positions(&self.output, &mut tree, gaps);
+                let blocker = TilingLayout::update_positions(
+                    &self.output,
+                    &mut tree,
+                    gaps,
+                    self.columns.as_ref(),
+                );
                 self.queue.push_tree(tree, ANIMATION_DURATION, blocker);
                 return result;
             }
@@ -2093,37 +2351,183 @@ impl TilingLayout {
         let mut tree = self.queue.trees.back().unwrap().0.copy_clone();
         if let Some((last_active, _)) = TilingLayout::currently_focused_node(&tree, target)
             && let Some(group) = tree.get(&last_active).unwrap().parent().cloned()
-            && let &mut Data::Group {
-                ref mut orientation,
-                ref mut sizes,
-                ref last_geometry,
-                ..
-            } = tree.get_mut(&group).unwrap().data_mut()
         {
-            let previous_length = match orientation {
-                Orientation::Horizontal => last_geometry.size.h,
-                Orientation::Vertical => last_geometry.size.w,
-            };
-            let new_orientation = new_orientation.unwrap_or(!*orientation);
-            let new_length = match new_orientation {
-                Orientation::Horizontal => last_geometry.size.h,
-                Orientation::Vertical => last_geometry.size.w,
-            };
+            let data = tree.get_mut(&group).unwrap().data_mut();
+            let new_orientation = new_orientation.unwrap_or(!data.orientation());
+            data.set_orientation(new_orientation);
+
+            let blocker = TilingLayout::update_positions(
+                &self.output,
+                &mut tree,
+                gaps,
+                self.columns.as_ref(),
+            );
+            self.queue.push_tree(tree, ANIMATION_DURATION, blocker);
+        }
+    }
 
-            sizes.iter_mut().for_each(|len| {
-                *len = (((*len as f64) / (previous_length as f64)) * (new_length as f64)).round()
-                    as i32;
-            });
-            let sum: i32 = sizes.iter().sum();
-            if sum < new_length {
-                *sizes.last_mut().unwrap() += new_length - sum;
+    /// Group the tree transformations apply to: the focused group or the parent of the focused window
+    fn focused_subtree(tree: &Tree<Data>, seat: &Seat<State>) -> Option<NodeId> {
+        let target = seat.get_keyboard().unwrap().current_focus()?;
+        let (node_id, data) = TilingLayout::currently_focused_node(tree, target)?;
+        match data {
+            FocusedNodeData::Group(..) => Some(node_id),
+            FocusedNodeData::Window(_) => tree.get(&node_id).unwrap().parent().cloned(),
+        }
+    }
+
+    /// Reverses the order of the children of a group
+    fn reverse_children(tree: &mut Tree<Data>, group: &NodeId) {
+        let children = tree
+            .children_ids(group)
+            .unwrap()
+            .cloned()
+            .collect::<Vec<_>>();
+        for (idx, child) in children.iter().rev().enumerate() {
+            tree.make_nth_sibling(child, idx).unwrap();
+        }
+        if let Data::Group { sizes, .. } = tree.get_mut(group).unwrap().data_mut() {
+            sizes.reverse();
+        }
+    }
+
+    fn transform_focused_subtree(
+        &mut self,
+        seat: &Seat<State>,
+        transform: impl Fn(&mut Tree<Data>, &NodeId),
+    ) {
+        let gaps = self.gaps();
+        let mut tree = self.queue.trees.back().unwrap().0.copy_clone();
+        let Some(subtree) = TilingLayout::focused_subtree(&tree, seat) else {
+            return;
+        };
+
+        transform(&mut tree, &subtree);
+
+        let blocker =
+            TilingLayout::update_positions(&self.output, &mut tree, gaps, self.columns.as_ref());
+        self.queue.push_tree(tree, ANIMATION_DURATION, blocker);
+    }
+
+    /// Rotates the focused subtree by 90 degrees
+    pub fn rotate_subtree(&mut self, seat: &Seat<State>, clockwise: bool) {
+        self.transform_focused_subtree(seat, |tree, subtree| {
+            let groups = tree
+                .traverse_pre_order_ids(subtree)
+                .unwrap()
+                .filter(|id| tree.get(id).unwrap().data().is_group())
+                .collect::<Vec<_>>();
+            for group in groups {
+                let data = tree.get_mut(&group).unwrap().data_mut();
+                let orientation = data.orientation();
+                data.set_orientation(!orientation);
+                // left to right becomes top to bottom when rotating clockwise,
+                // but top to bottom becomes right to left.
+                if clockwise == (orientation == Orientation::Horizontal) {
+                    TilingLayout::reverse_children(tree, &group);
+                }
             }
+        });
+    }
 
-            *orientation = new_orientation;
+    /// Mirrors the focused subtree, `orientation` being the axis to flip along
+    pub fn mirror_subtree(&mut self, seat: &Seat<State>, orientation: Orientation) {
+        self.transform_focused_subtree(seat, |tree, subtree| {
+            let groups = tree
+                .traverse_pre_order_ids(subtree)
+                .unwrap()
+                .filter(|id| {
+                    let data = tree.get(id).unwrap().data();
+                    data.is_group() && data.orientation() == orientation
+                })
+                .collect::<Vec<_>>();
+            for group in groups {
+                TilingLayout::reverse_children(tree, &group);
+            }
+        });
+    }
 
-            let blocker = TilingLayout::update_positions(&self.output, &mut tree, gaps);
-            self.queue.push_tree(tree, ANIMATION_DURATION, blocker);
+    /// Gives all children of the focused group the same size
+    pub fn equalize_subtree(&mut self, seat: &Seat<State>) {
+        self.transform_focused_subtree(seat, |tree, subtree| {
+            if let Data::Group { sizes, .. } = tree.get_mut(subtree).unwrap().data_mut() {
+                let length: i32 = sizes.iter().sum();
+                let len = sizes.len() as i32;
+                sizes.iter_mut().for_each(|size| *size = length / len);
+                *sizes.last_mut().unwrap() += length % len;
+            }
+        });
+    }
+
+    /// Swaps the focused window with the closest window in `direction`, keeping the tree as is
+    pub fn swap_with_neighbor(&mut self, seat: &Seat<State>, direction: Direction) {
+        let gaps = self.gaps();
+        let mut tree = self.queue.trees.back().unwrap().0.copy_clone();
+        let Some(target) = seat.get_keyboard().unwrap().current_focus() else {
+            return;
+        };
+        let Some((node_id, FocusedNodeData::Window(focused))) =
+            TilingLayout::currently_focused_node(&tree, target)
+        else {
+            return;
+        };
+
+        let geo = *tree.get(&node_id).unwrap().data().geometry();
+        // distance along `direction` and overlap on the other axis
+        let distance = |other: &Rectangle<i32, Local>| match direction {
+            Direction::Left => (
+                geo.loc.x - (other.loc.x + other.size.w),
+                geo.loc.y.max(other.loc.y)
+                    - (geo.loc.y + geo.size.h).min(other.loc.y + other.size.h),
+            ),
+            Direction::Right => (
+                other.loc.x - (geo.loc.x + geo.size.w),
+                geo.loc.y.max(other.loc.y)
+                    - (geo.loc.y + geo.size.h).min(other.loc.y + other.size.h),
+            ),
+            Direction::Up => (
+                geo.loc.y - (other.loc.y + other.size.h),
+                geo.loc.x.max(other.loc.x)
+                    - (geo.loc.x + geo.size.w).min(other.loc.x + other.size.w),
+            ),
+            Direction::Down => (
+                other.loc.y - (geo.loc.y + geo.size.h),
+                geo.loc.x.max(other.loc.x)
+                    - (geo.loc.x + geo.size.w).min(other.loc.x + other.size.w),
+            ),
+        };
+        let Some((neighbor_id, neighbor)) = tree
+            .traverse_pre_order_ids(tree.root_node_id().unwrap())
+            .unwrap()
+            .filter_map(|id| match tree.get(&id).unwrap().data() {
+                Data::Mapped {
+                    mapped,
+                    last_geometry,
+                    ..
+                } if id != node_id => {
+                    let (distance, overlap) = distance(last_geometry);
+                    // negative overlap means the windows are side by side
+                    (distance >= 0 && overlap < 0)
+                        .then(|| ((distance, overlap), id.clone(), mapped.clone()))
+                }
+                _ => None,
+            })
+            .min_by_key(|(key, _, _)| *key)
+            .map(|(_, id, mapped)| (id, mapped))
+        else {
+            return;
+        };
+
+        for (id, mapped) in [(&node_id, &neighbor), (&neighbor_id, &focused)] {
+            if let Data::Mapped { mapped: slot, .. } = tree.get_mut(id).unwrap().data_mut() {
+                *slot = mapped.clone();
+            }
+            *mapped.tiling_node_id.lock().unwrap() = Some(id.clone());
         }
+
+        let blocker =
+            TilingLayout::update_positions(&self.output, &mut tree, gaps, self.columns.as_ref());
+        self.queue.push_tree(tree, ANIMATION_DURATION, blocker);
     }
 
     pub fn toggle_stacking(
@@ -2241,7 +2645,8 @@ impl TilingLayout {
             }
         };
 
-        let blocker = TilingLayout::update_positions(&self.output, &mut tree, gaps);
+        let blocker =
+            TilingLayout::update_positions(&self.output, &mut tree, gaps, self.columns.as_ref());
         self.queue.push_tree(tree, ANIMATION_DURATION, blocker);
 
         Some(result)
@@ -2318,7 +2723,12 @@ impl TilingLayout {
                         minimize_rect: None,
                     };
 
-                    let blocker = TilingLayout::update_positions(&self.output, &mut tree, gaps);
+                    let blocker = TilingLayout::update_positions(
+                        &self.output,
+                        &mut tree,
+                        gaps,
+                        self.columns.as_ref(),
+                    );
                     self.queue.push_tree(tree, ANIMATION_DURATION, blocker);
 
                     return Some(KeyboardFocusTarget::Element(mapped));
@@ -2333,7 +2743,8 @@ impl TilingLayout {
         let gaps = self.gaps();
 
         let mut tree = self.queue.trees.back().unwrap().0.copy_clone();
-        let blocker = TilingLayout::update_positions(&self.output, &mut tree, gaps);
+        let blocker =
+            TilingLayout::update_positions(&self.output, &mut tree, gaps, self.columns.as_ref());
         self.queue.push_tree(tree, ANIMATION_DURATION, blocker);
     }
 
@@ -2351,6 +2762,13 @@ impl TilingLayout {
         for (mapped, _) in self.mapped() {
             mapped.refresh();
         }
+
+        // pick up changes to the tree, that didn't keep the master-stack or columns shape
+        if self.queue.changed {
+            self.rearrange(None, false);
+            self.refresh_columns();
+            self.queue.changed = false;
+        }
     }
 
     pub fn animations_going(&self) -> bool {
@@ -2606,7 +3024,12 @@ impl TilingLayout {
                         _ => true,
                     });
             if should_configure {
-                let blocker = TilingLayout::update_positions(&self.output, &mut tree, gaps);
+                let blocker = TilingLayout::update_positions(
+                    &self.output,
+                    &mut tree,
+                    gaps,
+                    self.columns.as_ref(),
+                );
                 self.queue.push_tree(tree, None, blocker);
             }
 
@@ -2633,7 +3056,7 @@ impl TilingLayout {
         if let Some(root) = old_tree.root_node_id() {
             for id in old_tree.traverse_pre_order_ids(root).unwrap() {
                 match old_tree.get(&id).map(|node| node.data()) {
-                    Ok(Data::Placeholder { .. }) => {
+                    Ok(data @ Data::Placeholder { .. }) if !data.is_reserved() => {
                         // Copy a tree on write
                         let new_tree = new_tree.get_or_insert_with(|| old_tree.copy_clone());
                         TilingLayout::unmap_internal(new_tree, &id)
@@ -2653,8 +3076,12 @@ impl TilingLayout {
 
             // If anything was changed, push updated tree
             if let Some(mut new_tree) = new_tree {
-                let blocker =
-                    TilingLayout::update_positions(&self.output, &mut new_tree, self.gaps());
+                let blocker = TilingLayout::update_positions(
+                    &self.output,
+                    &mut new_tree,
+                    self.gaps(),
+                    self.columns.as_ref(),
+                );
                 self.queue.push_tree(new_tree, ANIMATION_DURATION, blocker);
             }
         }
@@ -2804,7 +3231,9 @@ impl TilingLayout {
                 .into_iter()
             {
                 match tree.get_mut(&id).map(|node| node.data_mut()) {
-                    Ok(Data::Placeholder { .. }) => TilingLayout::unmap_internal(&mut tree, &id),
+                    Ok(data @ Data::Placeholder { .. }) if !data.is_reserved() => {
+                        TilingLayout::unmap_internal(&mut tree, &id)
+                    }
                     Ok(Data::Group { pill_indicator, .. }) if pill_indicator.is_some() => {
                         pill_indicator.take();
                     }
@@ -2813,7 +3242,8 @@ impl TilingLayout {
             }
         }
 
-        let blocker = TilingLayout::update_positions(&self.output, &mut tree, gaps);
+        let blocker =
+            TilingLayout::update_positions(&self.output, &mut tree, gaps, self.columns.as_ref());
         self.queue.push_tree(tree, ANIMATION_DURATION, blocker);
 
         let location = self.element_geometry(&mapped).unwrap().loc;
@@ -2991,21 +3421,40 @@ impl TilingLayout {
         }
     }
 
+    /// Area of the output available to tiled windows
+    fn tiling_area(output: &Output, gaps: (i32, i32)) -> Rectangle<i32, Local> {
+        let (outer, _) = gaps;
+        let mut geo = layer_map_for_output(output).non_exclusive_zone().as_local();
+        geo.loc.x += outer;
+        geo.loc.y += outer;
+        geo.size.w -= outer * 2;
+        geo.size.h -= outer * 2;
+        geo
+    }
+
     #[profiling::function]
     fn update_positions(
         output: &Output,
         tree: &mut Tree<Data>,
         gaps: (i32, i32),
+        columns: Option<&Columns>,
     ) -> Option<TilingBlocker> {
         if let Some(root_id) = tree.root_node_id() {
             let mut configures = Vec::new();
 
-            let (outer, inner) = gaps;
-            let mut geo = layer_map_for_output(output).non_exclusive_zone().as_local();
-            geo.loc.x += outer;
-            geo.loc.y += outer;
-            geo.size.w -= outer * 2;
-            geo.size.h -= outer * 2;
+            let (_, inner) = gaps;
+            let mut geo = TilingLayout::tiling_area(output, gaps);
+            // a strip of columns extends beyond the output
+            if let Some(columns) = columns
+                && let Data::Group {
+                    orientation: Orientation::Vertical,
+                    sizes,
+                    ..
+                } = tree.get(root_id).unwrap().data()
+            {
+                geo.loc.x -= columns.offset;
+                geo.size.w = sizes.iter().sum();
+            }
             let mut stack = vec![geo];
 
             for node_id in tree
@@ -3084,6 +3533,16 @@ impl TilingLayout {
                         geo.size -= gap.1.into();
                     }
 
+                    let limits = match data {
+                        Data::Group { orientation, .. } => Some(
+                            tree.children_ids(&node_id)
+                                .unwrap()
+                                .map(|child| size_hints::limits(tree, child, *orientation, inner))
+                                .collect::<Vec<_>>(),
+                        ),
+                        _ => None,
+                    };
+
                     let node = tree.get_mut(&node_id).unwrap();
                     let data = node.data_mut();
                     data.update_geometry(geo);
@@ -3091,28 +3550,33 @@ impl TilingLayout {
                     match data {
                         Data::Group {
                             orientation, sizes, ..
-                        } => match orientation {
-                            Orientation::Horizontal => {
-                                let mut previous: i32 = sizes.iter().sum();
-                                for size in sizes.iter().rev() {
-                                    previous -= *size;
-                                    stack.push(Rectangle::new(
-                                        (geo.loc.x, geo.loc.y + previous).into(),
-                                        (geo.size.w, *size).into(),
-                                    ));
-                                }
+                        } => {
+                            if let Some(limits) = limits.as_deref() {
+                                size_hints::constrain(sizes, limits);
                             }
-                            Orientation::Vertical => {
-                                let mut previous: i32 = sizes.iter().sum();
-                                for size in sizes.iter().rev() {
-                                    previous -= *size;
-                                    stack.push(Rectangle::new(
-                                        (geo.loc.x + previous, geo.loc.y).into(),
-                                        (*size, geo.size.h).into(),
-                                    ));
+                            match orientation {
+                                Orientation::Horizontal => {
+                                    let mut previous: i32 = sizes.iter().sum();
+                                    for size in sizes.iter().rev() {
+                                        previous -= *size;
+                                        stack.push(Rectangle::new(
+                                            (geo.loc.x, geo.loc.y + previous).into(),
+                                            (geo.size.w, *size).into(),
+                                        ));
+                                    }
+                                }
+                                Orientation::Vertical => {
+                                    let mut previous: i32 = sizes.iter().sum();
+                                    for size in sizes.iter().rev() {
+                                        previous -= *size;
+                                        stack.push(Rectangle::new(
+                                            (geo.loc.x + previous, geo.loc.y).into(),
+                                            (*size, geo.size.h).into(),
+                                        ));
+                                    }
                                 }
                             }
-                        },
+                        }
                         Data::Mapped { mapped, .. } => {
                             if !(mapped.is_fullscreen(true) || mapped.is_maximized(true)) {
                                 mapped.set_tiled(true);
@@ -3374,7 +3838,12 @@ impl TilingLayout {
                     InsertBehavior::AsRoot,
                 )
                 .unwrap();
-                let blocker = TilingLayout::update_positions(&self.output, &mut tree, gaps);
+                let blocker = TilingLayout::update_positions(
+                    &self.output,
+                    &mut tree,
+                    gaps,
+                    self.columns.as_ref(),
+                );
                 self.queue.push_tree(tree, ANIMATION_DURATION, blocker);
             }
             return;
@@ -3725,7 +4194,10 @@ impl TilingLayout {
                                 let removed = if let TargetZone::InitialPlaceholder(node_id) =
                                     old_target_zone
                                 {
-                                    if tree.get(node_id).is_ok() {
+                                    if tree
+                                        .get(node_id)
+                                        .is_ok_and(|node| !node.data().is_reserved())
+                                    {
                                         TilingLayout::unmap_internal(&mut tree, node_id);
                                     }
                                     true
@@ -3846,6 +4318,7 @@ impl TilingLayout {
                                         &self.output,
                                         &mut tree,
                                         gaps,
+                                        self.columns.as_ref(),
                                     );
                                     self.queue.push_tree(tree, duration, blocker);
                                 }
@@ -3945,7 +4418,8 @@ impl TilingLayout {
         };
         TilingLayout::merge_trees(src, &mut dst, orientation);
 
-        let blocker = TilingLayout::update_positions(&self.output, &mut dst, gaps);
+        let blocker =
+            TilingLayout::update_positions(&self.output, &mut dst, gaps, self.columns.as_ref());
         self.queue.push_tree(dst, ANIMATION_DURATION, blocker);
     }
 
@@ -5562,6 +6036,23 @@ where
             if let Data::Mapped { mapped, .. } = data {
                 let elem_geometry = mapped.geometry().to_physical_precise_round(output_scale);
 
+                // mark windows cut off, as they don't fit their tile
+                if !is_overview && !animating && size_hints::is_overflowing(mapped, geo.size) {
+                    let warning = theme.warning_color();
+                    indicators.push(CosmicMappedRenderElement::FocusIndicator(
+                        IndicatorShader::element(
+                            renderer,
+                            Key::Window(Usage::OverflowIndicator, mapped.key()),
+                            geo,
+                            2,
+                            mapped.corner_radius(geo.size.as_logical(), 0),
+                            alpha,
+                            output_scale,
+                            [warning.red, warning.green, warning.blue],
+                        ),
+                    ));
+                }
+
                 let scale = geo.size.to_f64() / original_geo.size.to_f64();
                 // In overview mode, don't pass max_size to avoid pre-clipping.
                 // Let constrain_render_elements handle scaling instead.
@@ -0,0 +1,314 @@
+// SPDX-License-Identifier: GPL-3.0-only
+
+//! Min and max size hints of tiled windows.
+//!
+//! The space of a group is redistributed among its children, so every child gets at least
+//! its minimum and at most its maximum length, as far as the group is large enough.
+//! Windows still not fitting are overflowing, see [`TilingOverflow`](cosmic_comp_config::TilingOverflow).
+
+use id_tree::{NodeId, Tree};
+use smithay::utils::{Logical, Size};
+
+use crate::{
+    shell::{element::CosmicMapped, layout::Orientation},
+    utils::prelude::*,
+};
+
+use super::{ANIMATION_DURATION, Data, TilingLayout};
+
+/// Minimum and maximum length of a node along the axis of its parent group
+#[derive(Debug, Clone, Copy, PartialEq)]
+pub(super) struct Limits {
+    min: i32,
+    /// `None` if unbounded
+    max: Option<i32>,
+}
+
+fn length(size: Size<i32, Logical>, orientation: Orientation) -> i32 {
+    match orientation {
+        Orientation::Horizontal => size.h,
+        Orientation::Vertical => size.w,
+    }
+}
+
+/// Limits of the node `id`, as a child of a group of `orientation`.
+///
+/// `gap` is added to the size hints of every window, as tiles are shrunk by the gaps.
+pub(super) fn limits(tree: &Tree<Data>, id: &NodeId, orientation: Orientation, gap: i32) -> Limits {
+    let node = tree.get(id).unwrap();
+    match node.data() {
+        Data::Mapped { mapped, .. } => Limits {
+            min: mapped
+                .min_size()
+                .map(|size| length(size, orientation))
+                .filter(|min| *min > 0)
+                .map(|min| min + gap)
+                .unwrap_or(0),
+            max: mapped
+                .max_size()
+                .map(|size| length(size, orientation))
+                .filter(|max| *max > 0)
+                .map(|max| max + gap),
+        },
+        Data::Group {
+            orientation: group_orientation,
+            ..
+        } => {
+            let children = node
+                .children()
+                .iter()
+                .map(|child| limits(tree, child, orientation, gap))
+                .collect::<Vec<_>>();
+            let maxima = children
+                .iter()
+                .map(|limits| limits.max)
+                .collect::<Option<Vec<_>>>();
+            if *group_orientation == orientation {
+                // children are laid out along the axis
+                Limits {
+                    min: children.iter().map(|limits| limits.min).sum(),
+                    max: maxima.map(|maxima| maxima.iter().sum()),
+                }
+            } else {
+                // children span the whole axis
+                Limits {
+                    min: children.iter().map(|limits| limits.min).max().unwrap_or(0),
+                    max: maxima.and_then(|maxima| maxima.into_iter().max()),
+                }
+            }
+        }
+        Data::Placeholder { .. } => Limits { min: 0, max: None },
+    }
+}
+
+/// Adjusts `sizes` to `limits`, keeping their sum.
+///
+/// Space is taken from and given to children within their limits, proportionally to their size.
+/// If the minimums don't fit, the space is shared proportionally to the minimums instead.
+pub(super) fn constrain(sizes: &mut [i32], limits: &[Limits]) {
+    let total: i32 = sizes.iter().sum();
+    let min_total: i32 = limits.iter().map(|limits| limits.min).sum();
+    if min_total == 0 && limits.iter().all(|limits| limits.max.is_none()) {
+        return;
+    }
+
+    if min_total > total {
+        for (size, limits) in sizes.iter_mut().zip(limits) {
+            *size = ((limits.min as f64 / min_total as f64) * total as f64).round() as i32;
+        }
+    } else {
+        // every round pins at least one child to a limit or distributes all space
+        for _ in 0..=sizes.len() {
+            for (size, limits) in sizes.iter_mut().zip(limits) {
+                *size = (*size).max(limits.min);
+                if let Some(max) = limits.max {
+                    *size = (*size).min(max.max(limits.min));
+                }
+            }
+
+            let diff = total - sizes.iter().sum::<i32>();
+            if diff == 0 {
+                break;
+            }
+            let flexible = sizes
+                .iter()
+                .zip(limits)
+                .enumerate()
+                .filter(|(_, (size, limits))| {
+                    if diff > 0 {
+                        limits.max.is_none_or(|max| **size < max)
+                    } else {
+                        **size > limits.min
+                    }
+                })
+                .map(|(idx, _)| idx)
+                .collect::<Vec<_>>();
+            if flexible.is_empty() {
+                break;
+            }
+
+            let flexible_total: i32 = flexible.iter().map(|idx| sizes[*idx].max(1)).sum();
+            for idx in &flexible {
+                let share = sizes[*idx].max(1) as f64 / flexible_total as f64;
+                sizes[*idx] += (diff as f64 * share).round() as i32;
+            }
+        }
+    }
+
+    // fix rounding issues, or space nobody can take
+    let diff = total - sizes.iter().sum::<i32>();
+    if diff != 0 {
+        let idx = (0..sizes.len())
+            .rev()
+            .find(|idx| {
+                let size = sizes[*idx] + diff;
+                size >= limits[*idx].min && limits[*idx].max.is_none_or(|max| size <= max)
+            })
+            .unwrap_or(sizes.len() - 1);
+        sizes[idx] += diff;
+    }
+}
+
+/// Whether the tile of `mapped` is smaller than its minimum size
+pub(super) fn is_overflowing(mapped: &CosmicMapped, tile: Size<i32, Local>) -> bool {
+    if mapped.is_fullscreen(true) || mapped.is_maximized(true) {
+        return false;
+    }
+    mapped
+        .min_size()
+        .is_some_and(|min| tile.w < min.w || tile.h < min.h)
+}
+
+impl TilingLayout {
+    /// Tiled windows, that don't fit their minimum size
+    pub fn overflowing(&self) -> impl Iterator<Item = &CosmicMapped> {
+        self.mapped()
+            .filter(|(mapped, geo)| is_overflowing(mapped, geo.size))
+            .map(|(mapped, _)| mapped)
+    }
+
+    /// Moves the windows of `mapped` into a neighboring tiled window,
+    /// which is turned into a stack if necessary.
+    pub fn stack_with_neighbor(&mut self, mapped: &CosmicMapped) -> bool {
+        let Some(node_id) = mapped.tiling_node_id.lock().unwrap().clone() else {
+            return false;
+        };
+        let gaps = self.gaps();
+        let mut tree = self.queue.trees.back().unwrap().0.copy_clone();
+        let Some(parent_id) = tree
+            .get(&node_id)
+            .ok()
+            .and_then(|node| node.parent())
+            .cloned()
+        else {
+            return false;
+        };
+
+        let siblings = tree
+            .children_ids(&parent_id)
+            .unwrap()
+            .cloned()
+            .collect::<Vec<_>>();
+        let idx = siblings.iter().position(|id| id == &node_id).unwrap();
+        let Some(neighbor_id) = siblings[..idx]
+            .iter()
+            .rev()
+            .chain(&siblings[idx + 1..])
+            .find(|id| tree.get(id).unwrap().data().is_mapped(None))
+            .cloned()
+        else {
+            return false;
+        };
+
+        let Data::Mapped {
+            mapped: neighbor, ..
+        } = tree.get_mut(&neighbor_id).unwrap().data_mut()
+        else {
+            unreachable!()
+        };
+        if neighbor.is_window() {
+            neighbor.convert_to_stack(
+                (&self.output, neighbor.bbox()),
+                self.theme.clone(),
+                self.appearance,
+            );
+        }
+        let stack = neighbor.stack_ref().unwrap();
+        for (surface, _) in mapped.windows() {
+            stack.add_window(surface, None, None);
+        }
+
+        if self.columns.is_some() {
+            TilingLayout::remove_from_columns(&mut tree, mapped, &self.output, gaps);
+        } else {
+            TilingLayout::unmap_internal(&mut tree, &node_id);
+        }
+        if let Some(master_stack) = self.master_stack.as_mut() {
+            TilingLayout::arrange_master_stack(&mut tree, master_stack, None, false);
+        }
+
+        let blocker =
+            TilingLayout::update_positions(&self.output, &mut tree, gaps, self.columns.as_ref());
+        self.queue.push_tree(tree, ANIMATION_DURATION, blocker);
+        true
+    }
+}
+
+#[cfg(test)]
+mod test {
+    use super::{Limits, constrain};
+
+    fn unbounded(min: i32) -> Limits {
+        Limits { min, max: None }
+    }
+
+    fn bounded(min: i32, max: i32) -> Limits {
+        Limits {
+            min,
+            max: Some(max),
+        }
+    }
+
+    #[test]
+    fn test_minimums_not_fitting() {
+        let mut sizes = [300, 300];
+        constrain(&mut sizes, &[unbounded(600), unbounded(300)]);
+        assert_eq!(sizes, [400, 200]);
+
+        // rounding leftovers go to the last child
+        let mut sizes = [333, 333, 334];
+        constrain(&mut sizes, &[unbounded(400); 3]);
+        assert_eq!(sizes, [333, 333, 334]);
+    }
+
+    #[test]
+    fn test_minimums_taken_from_siblings() {
+        let mut sizes = [100, 200, 300];
+        constrain(&mut sizes, &[unbounded(250), unbounded(0), unbounded(0)]);
+        assert_eq!(sizes, [250, 140, 210]);
+    }
+
+    #[test]
+    fn test_maximums_pinned() {
+        let mut sizes = [300, 300, 300];
+        constrain(&mut sizes, &[bounded(0, 100), unbounded(0), unbounded(0)]);
+        assert_eq!(sizes, [100, 400, 400]);
+
+        // leftover space of a second pinned child goes to the unbounded one
+        let mut sizes = [300, 300, 300];
+        constrain(
+            &mut sizes,
+            &[bounded(0, 100), bounded(0, 250), unbounded(0)],
+        );
+        assert_eq!(sizes, [100, 250, 550]);
+    }
+
+    #[test]
+    fn test_sum_preserved() {
+        let mut sizes = [100, 100, 100, 100];
+        constrain(
+            &mut sizes,
+            &[bounded(0, 50), unbounded(0), unbounded(0), unbounded(0)],
+        );
+        assert_eq!(sizes[0], 50);
+        assert_eq!(sizes.iter().sum::<i32>(), 400);
+
+        for total in [97, 480, 1001, 1920] {
+            let mut sizes = [total / 3, total / 3, total - 2 * (total / 3)];
+            constrain(
+                &mut sizes,
+                &[bounded(40, 200), unbounded(120), bounded(10, 90)],
+            );
+            assert_eq!(sizes.iter().sum::<i32>(), total);
+        }
+    }
+
+    #[test]
+    fn test_single_child() {
+        for limits in [unbounded(600), bounded(0, 200), bounded(100, 800)] {
+            let mut sizes = [500];
+            constrain(&mut sizes, &[limits]);
+            assert_eq!(sizes, [500]);
+        }
+    }
+}
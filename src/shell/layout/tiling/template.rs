@@ -0,0 +1,111 @@
+// SPDX-License-Identifier: GPL-3.0-only
+
+//! Layout templates, applied to a workspace to re-arrange its tiled windows.
+//!
+//! The windows of the workspace fill the slots of the template in their current order.
+//! Slots left over can be kept as reserved placeholders, which are then filled by
+//! the next windows mapped on the workspace.
+
+use cosmic_comp_config::templates::{TemplateNode, TemplateOrientation};
+use id_tree::{NodeId, Tree};
+use tracing::info;
+
+use crate::shell::{element::CosmicMapped, layout::Orientation};
+
+use super::{ANIMATION_DURATION, Data, TilingLayout, TreeShape, master_stack};
+
+/// Arbitrary length sizes are computed for, `update_positions` scales them to the output
+const LENGTH: f64 = 1000.;
+
+/// Converts `node` into a tree shape, taking windows for its slots from the front of `windows`
+fn shape_of(
+    node: &TemplateNode,
+    windows: &mut impl Iterator<Item = CosmicMapped>,
+    keep_slots: bool,
+) -> Option<TreeShape> {
+    match node {
+        TemplateNode::Slot => match windows.next() {
+            Some(mapped) => Some(TreeShape::Mapped(mapped)),
+            None if keep_slots => Some(TreeShape::Slot),
+            None => None,
+        },
+        TemplateNode::Split {
+            orientation,
+            children,
+        } => {
+            let children = children
+                .iter()
+                .filter_map(|(weight, child)| {
+                    shape_of(child, windows, keep_slots).map(|shape| (weight.max(0.01), shape))
+                })
+                .collect::<Vec<_>>();
+            if children.len() <= 1 {
+                return children.into_iter().next().map(|(_, shape)| shape);
+            }
+
+            let total: f32 = children.iter().map(|(weight, _)| weight).sum();
+            let (sizes, children) = children
+                .into_iter()
+                .map(|(weight, shape)| {
+                    (
+                        ((weight / total) as f64 * LENGTH).round().max(1.) as i32,
+                        shape,
+                    )
+                })
+                .unzip();
+            Some(TreeShape::Group {
+                orientation: match orientation {
+                    TemplateOrientation::Horizontal => Orientation::Horizontal,
+                    TemplateOrientation::Vertical => Orientation::Vertical,
+                },
+                sizes,
+                children,
+            })
+        }
+    }
+}
+
+impl TilingLayout {
+    /// Re-arranges the tiled windows according to `template`.
+    ///
+    /// Windows not fitting into the template are tiled as usual afterwards.
+    /// With `keep_slots`, unfilled slots are reserved for the next mapped windows.
+    ///
+    /// Templates describe the whole tree, which the master-stack and columns modes
+    /// would immediately re-arrange again, so applying one turns these modes off.
+    pub fn apply_template(&mut self, template: &TemplateNode, keep_slots: bool) {
+        if self.master_stack.take().is_some() {
+            info!(output = %self.output.name(), "Layout template turned off master-stack.");
+        }
+        if self.columns.is_some() {
+            self.set_columns(None);
+            info!(output = %self.output.name(), "Layout template turned off columns.");
+        }
+
+        let gaps = self.gaps();
+        let current = &self.queue.trees.back().unwrap().0;
+        let mut windows = master_stack::tiled_order(current).into_iter();
+        let shape = shape_of(template, &mut windows, keep_slots);
+
+        let mut tree = current.copy_clone();
+        match shape {
+            Some(shape) => TilingLayout::replace_with_shape(&mut tree, shape),
+            None => tree = Tree::new(),
+        }
+        for mapped in windows {
+            TilingLayout::map_to_tree(&mut tree, mapped, &self.output, None, None, None);
+        }
+
+        let blocker =
+            TilingLayout::update_positions(&self.output, &mut tree, gaps, self.columns.as_ref());
+        self.queue.push_tree(tree, ANIMATION_DURATION, blocker);
+    }
+
+    /// First slot reserved by a template, that is still waiting for a window
+    pub(super) fn first_reserved_slot(tree: &Tree<Data>) -> Option<NodeId> {
+        let root_id = tree.root_node_id()?;
+        tree.traverse_pre_order_ids(root_id)
+            .unwrap()
+            .find(|id| tree.get(id).unwrap().data().is_reserved())
+    }
+}
@@ -2,7 +2,11 @@ use calloop::LoopHandle;
 use focus::target::WindowGroup;
 use grabs::{MenuAlignment, SeatMoveGrabState};
 use indexmap::IndexMap;
-use layout::TilingExceptions;
+use layout::{
+    TilingExceptions,
+    restore::{FloatingMemory, LayoutRestore, SavedLayout},
+};
+use scratchpad::Scratchpad;
 use std::{
     collections::HashMap,
     sync::{Mutex, atomic::Ordering},
@@ -10,6 +14,7 @@ use std::{
     time::{Duration, Instant},
 };
 use wayland_backend::server::ClientId;
+use window_rules::WindowRules;
 
 use crate::{
     shell::{focus::FocusTarget, grabs::fullscreen_items, layout::tiling::PlaceholderType},
@@ -19,7 +24,9 @@ use crate::{
     },
 };
 use cosmic_comp_config::{
-    AppearanceConfig, TileBehavior, ZoomConfig, ZoomMovement,
+    AppearanceConfig, FloatingPlacement, FloatingPlacementConfig, TileBehavior, TilingOverflow,
+    ZoomConfig, ZoomMovement,
+    window_rules::WindowRule,
     workspace::{PinnedWorkspace, WorkspaceLayout, WorkspaceMode},
 };
 use cosmic_config::ConfigSet;
@@ -86,7 +93,9 @@ pub mod element;
 pub mod focus;
 pub mod grabs;
 pub mod layout;
+mod scratchpad;
 mod seats;
+mod window_rules;
 mod workspace;
 pub mod zoom;
 pub use self::element::{CosmicMapped, CosmicMappedRenderElement, CosmicSurface};
@@ -106,7 +115,7 @@ use self::{
         window_items,
     },
     layout::{
-        floating::{FloatingLayout, ResizeState},
+        floating::{FloatingLayout, KeepLevel, Placement, ResizeState},
         tiling::{NodeDesc, ResizeForkGrab, TilingLayout},
     },
 };
@@ -289,6 +298,12 @@ pub struct Shell {
     zoom_state: Option<ZoomState>,
     appearance_conf: AppearanceConfig,
     tiling_exceptions: TilingExceptions,
+    window_rules: WindowRules,
+    layout_restore: LayoutRestore,
+    floating_memory: FloatingMemory,
+    scratchpads: HashMap<String, Scratchpad>,
+    floating_placement: FloatingPlacementConfig,
+    tiling_overflow: TilingOverflow,
 
     #[cfg(feature = "debug")]
     pub debug_active: bool,
@@ -1422,6 +1437,25 @@ impl Workspaces {
 pub struct InvalidWorkspaceIndex;
 
 impl Common {
+    /// Persists a snapshot of the window layout, unless windows of the previous session are still being restored.
+    ///
+    /// Also persists the remembered floating geometries of closed windows.
+    pub fn save_layout(&mut self) {
+        let (layout, floating_memory) = {
+            let mut shell = self.shell.write();
+            let layout = (!shell.layout_restore.is_pending()).then(|| SavedLayout::new(&shell));
+            (layout, shell.floating_memory.clone())
+        };
+        if let Some(layout) = layout
+            && &layout != self.config.dynamic_conf.layout()
+        {
+            *self.config.dynamic_conf.layout_mut() = layout;
+        }
+        if &floating_memory != self.config.dynamic_conf.floating_memory() {
+            *self.config.dynamic_conf.floating_memory_mut() = floating_memory;
+        }
+    }
+
     pub fn add_output(&mut self, output: &Output) {
         let mut shell = self.shell.write();
         shell
@@ -1465,6 +1499,8 @@ impl Common {
         let shell_ref = &mut *shell;
         shell_ref.active_hint = self.config.cosmic_conf.active_hint;
         shell_ref.appearance_conf = self.config.cosmic_conf.appearance_settings;
+        shell_ref.floating_placement = self.config.cosmic_conf.floating_placement;
+        shell_ref.tiling_overflow = self.config.cosmic_conf.tiling_overflow;
         if let Some(zoom_state) = shell_ref.zoom_state.as_mut() {
             zoom_state.increment = self.config.cosmic_conf.accessibility_zoom.increment;
             zoom_state.movement = self.config.cosmic_conf.accessibility_zoom.view_moves;
@@ -1508,11 +1544,18 @@ impl Common {
     pub fn refresh_idle_inhibit(&mut self) {
         self.idle_inhibiting_surfaces.retain(|s| s.alive());
 
-        let is_inhibited = self.idle_inhibiting_surfaces.iter().any(|surface| {
+        let is_visible = |surface: &WlSurface| {
             with_states(surface, |states| {
                 surface_primary_scanout_output(surface, states).is_some()
             })
-        });
+        };
+        let is_inhibited = self.idle_inhibiting_surfaces.iter().any(is_visible)
+            || self
+                .toplevel_info_state
+                .registered_toplevels()
+                .filter(|window| window.inhibits_idle())
+                .filter_map(|window| window.wl_surface())
+                .any(|surface| is_visible(&surface));
         self.idle_notifier_state.set_is_inhibited(is_inhibited);
     }
 
@@ -1578,6 +1621,8 @@ impl Shell {
         let theme = cosmic::theme::system_preference();
 
         let tiling_exceptions = layout::TilingExceptions::new(config.tiling_exceptions.iter());
+        let window_rules = WindowRules::new(config.cosmic_conf.window_rules.iter());
+        let layout_restore = LayoutRestore::new(config.dynamic_conf.layout());
 
         Shell {
             workspaces: Workspaces::new(config, theme.clone()),
@@ -1601,6 +1646,12 @@ impl Shell {
             appearance_conf: config.cosmic_conf.appearance_settings,
             zoom_state: None,
             tiling_exceptions,
+            window_rules,
+            layout_restore,
+            floating_memory: config.dynamic_conf.floating_memory().clone(),
+            scratchpads: HashMap::new(),
+            floating_placement: config.cosmic_conf.floating_placement,
+            tiling_overflow: config.cosmic_conf.tiling_overflow,
 
             #[cfg(feature = "debug")]
             debug_active: false,
@@ -2055,27 +2106,28 @@ impl Shell {
     where
         CosmicSurface: PartialEq<S>,
     {
-        self.workspaces.sets.values().any(|set| {
-            set.minimized_windows
-                .iter()
-                .any(|w| w.windows().any(|s| &s == surface))
-                || set
-                    .sticky_layer
-                    .mapped()
-                    .any(|m| m.windows().any(|(s, _)| &s == surface))
-                || set.workspaces.iter().any(|w| {
-                    w.get_fullscreen().is_some_and(|s| s == surface)
-                        || w.minimized_windows
-                            .iter()
-                            .any(|m| m.windows().any(|s| &s == surface))
-                        || w.floating_layer
-                            .mapped()
-                            .any(|m| m.windows().any(|(s, _)| &s == surface))
-                        || w.tiling_layer
-                            .mapped()
-                            .any(|(m, _)| m.windows().any(|(s, _)| &s == surface))
-                })
-        })
+        self.is_scratchpad_surface(surface)
+            || self.workspaces.sets.values().any(|set| {
+                set.minimized_windows
+                    .iter()
+                    .any(|w| w.windows().any(|s| &s == surface))
+                    || set
+                        .sticky_layer
+                        .mapped()
+                        .any(|m| m.windows().any(|(s, _)| &s == surface))
+                    || set.workspaces.iter().any(|w| {
+                        w.get_fullscreen().is_some_and(|s| s == surface)
+                            || w.minimized_windows
+                                .iter()
+                                .any(|m| m.windows().any(|s| &s == surface))
+                            || w.floating_layer
+                                .mapped()
+                                .any(|m| m.windows().any(|(s, _)| &s == surface))
+                            || w.tiling_layer
+                                .mapped()
+                                .any(|(m, _)| m.windows().any(|(s, _)| &s == surface))
+                    })
+            })
     }
 
     pub fn space_for(&self, mapped: &CosmicMapped) -> Option<&Workspace> {
@@ -2459,6 +2511,7 @@ impl Shell {
 
         self.workspaces
             .refresh(workspace_state, xdg_activation_state);
+        self.resolve_tiling_overflow();
 
         for output in self.outputs() {
             let mut map = layer_map_for_output(output);
@@ -2476,6 +2529,37 @@ impl Shell {
             .retain(|pending| pending.surface.alive());
     }
 
+    /// Floats or stacks tiled windows, which don't fit their minimum size
+    fn resolve_tiling_overflow(&mut self) {
+        let stack = match self.tiling_overflow {
+            TilingOverflow::Clip => return,
+            TilingOverflow::Float => false,
+            TilingOverflow::Stack => true,
+        };
+
+        for workspace in self.workspaces.spaces_mut() {
+            if workspace.tiling_layer.animations_going() {
+                continue;
+            }
+            // one at a time, as the tree changes with every window.
+            // Stacks don't get any smaller by stacking them further.
+            let Some(mapped) = workspace
+                .tiling_layer
+                .overflowing()
+                .find(|mapped| !stack || mapped.is_window())
+                .cloned()
+            else {
+                continue;
+            };
+            // windows without a neighbor to stack with are floated,
+            // instead of being found again on every refresh
+            if !stack || !workspace.tiling_layer.stack_with_neighbor(&mapped) {
+                let _ = workspace.tiling_layer.unmap(&mapped, None);
+                workspace.floating_layer.map(mapped, None);
+            }
+        }
+    }
+
     pub fn update_pointer_position(&mut self, location: Point<f64, Local>, output: &Output) {
         for (o, set) in self.workspaces.sets.iter_mut() {
             if o == output {
@@ -2700,13 +2784,78 @@ impl Shell {
             false
         };
 
+        let parent = window
+            .0
+            .toplevel()
+            .and_then(|toplevel| toplevel.parent())
+            .and_then(|parent| {
+                self.element_for_surface(&parent).and_then(|mapped| {
+                    mapped
+                        .windows()
+                        .find(|(w, _)| w.wl_surface().as_deref() == Some(&parent))
+                        .map(|(w, _)| w)
+                })
+            });
+        let parent_mapped = parent
+            .as_ref()
+            .and_then(|parent| self.element_for_surface(parent))
+            .cloned();
+        let rules = self.window_rules.effects(&window, parent.as_ref());
+        if let Some(opacity) = rules.opacity {
+            window.set_opacity(Some(opacity));
+        }
+        if let Some(opacity) = rules.inactive_opacity {
+            window.set_inactive_opacity(Some(opacity));
+        }
+        if rules.no_decorations == Some(true) {
+            window.set_decorations_disabled(true);
+        }
+        if rules.inhibit_idle == Some(true) {
+            window.set_inhibits_idle(true);
+        }
+        let should_be_sticky = rules.sticky.unwrap_or(parent_is_sticky);
+        let should_be_maximized = rules.maximized.unwrap_or(should_be_maximized);
+
+        let rule_output = rules
+            .output
+            .as_ref()
+            .and_then(|name| self.outputs().find(|o| &o.name() == name).cloned());
+        let rule_workspace = rules
+            .workspace
+            .and_then(|idx| {
+                let output = rule_output.clone().unwrap_or_else(|| seat.active_output());
+                self.workspaces.get(idx, &output)
+            })
+            .or_else(|| rule_output.as_ref().and_then(|o| self.active_space(o)))
+            .map(|workspace| workspace.handle);
+
+        let should_be_fullscreen = rules.fullscreen.unwrap_or(output.is_some());
+        let restore = if should_be_fullscreen {
+            None
+        } else {
+            self.layout_restore.take(&window)
+        };
+        let restore_workspace = restore
+            .as_ref()
+            .and_then(|restore| {
+                let output = self
+                    .outputs()
+                    .find(|o| o.name() == restore.output)
+                    .cloned()
+                    .unwrap_or_else(|| seat.active_output());
+                let last = self.workspaces.len(&output).saturating_sub(1);
+                self.workspaces.get(restore.workspace.min(last), &output)
+            })
+            .map(|workspace| workspace.handle);
+        let placed_workspace = rule_workspace.or(restore_workspace);
+
         let pending_activation = self.pending_activations.remove(&(&window).into());
-        let workspace_handle = match pending_activation {
+        let activation_handle = match pending_activation {
             Some(ActivationContext::Workspace(handle)) => Some(handle),
             _ => None,
         };
+        let workspace_handle = placed_workspace.or(activation_handle);
 
-        let should_be_fullscreen = output.is_some();
         let mut output = output.unwrap_or_else(|| seat.active_output());
 
         // this is beyond stupid, just to make the borrow checker happy
@@ -2747,11 +2896,69 @@ impl Shell {
         let mut workspace_state = workspace_state.update();
 
         let workspace_output = workspace.output.clone();
-        let was_activated = workspace_handle.is_some()
+        let was_activated = placed_workspace.is_none()
+            && activation_handle.is_some()
             && (workspace_output != seat.active_output() || active_handle != workspace.handle);
         let workspace_handle = workspace.handle;
         let is_dialog = layout::is_dialog(&window);
-        let floating_exception = layout::has_floating_exception(&self.tiling_exceptions, &window);
+        let should_float = rules.floating.unwrap_or_else(|| match restore.as_ref() {
+            Some(restore) => restore.floating.is_some(),
+            None => is_dialog || layout::has_floating_exception(&self.tiling_exceptions, &window),
+        });
+        let floating_geometry = rules
+            .geometry
+            .map(|geo| Rectangle::new((geo.x, geo.y).into(), (geo.width, geo.height).into()))
+            .or(restore.as_ref().and_then(|restore| restore.floating))
+            .or_else(|| {
+                (should_float && !is_dialog)
+                    .then(|| {
+                        self.floating_memory.geometry(
+                            &window,
+                            self.window_rules.title_pattern(&window),
+                            workspace_output.geometry().size.as_logical(),
+                        )
+                    })
+                    .flatten()
+            });
+        let stack_target = rules
+            .stack_with
+            .as_deref()
+            .and_then(|regex| window_rules::anchored_regex(regex).ok())
+            .and_then(|regex| {
+                workspace
+                    .mapped()
+                    .find(|m| m.windows().any(|(w, _)| regex.is_match(&w.app_id())))
+                    .cloned()
+            })
+            .or_else(|| {
+                let peer = restore.as_ref()?.stack_with.as_ref()?;
+                workspace
+                    .mapped()
+                    .find(|m| m.windows().any(|(w, _)| &w == peer))
+                    .cloned()
+            })
+            .or_else(|| {
+                if !workspace.auto_stack
+                    || restore.is_some()
+                    || is_dialog
+                    || should_be_maximized
+                    || should_be_fullscreen
+                {
+                    return None;
+                }
+                let app_id = window.app_id();
+                if app_id.is_empty() {
+                    return None;
+                }
+                // join a window of the layer the new window would be mapped to
+                let tiled = workspace.tiling_enabled && !should_float;
+                workspace
+                    .mapped()
+                    .filter(|m| !m.is_maximized(false) && !m.is_minimized())
+                    .filter(|m| workspace.is_tiled(&m.active_window()) == tiled)
+                    .find(|m| m.windows().any(|(w, _)| w.app_id() == app_id))
+                    .cloned()
+            });
 
         if should_be_fullscreen {
             if let Some((surface, state, _)) = workspace.map_fullscreen(&window, &seat, None, None)
@@ -2768,10 +2975,36 @@ impl Shell {
                 .then_some(KeyboardFocusTarget::Fullscreen(window));
         }
 
+        if let Some(target) = stack_target {
+            let stack = if target.is_stack() {
+                Some(target)
+            } else {
+                match self.toggle_stacking(&seat, &target) {
+                    Some(KeyboardFocusTarget::Element(mapped)) if mapped.is_stack() => Some(mapped),
+                    _ => None,
+                }
+            };
+            if let Some(stack) = stack {
+                stack.stack_ref().unwrap().add_window(window, None, None);
+                if was_activated {
+                    workspace_state.add_workspace_state(&workspace_handle, WState::Urgent);
+                }
+                return (workspace_output == seat.active_output()
+                    && active_handle == workspace_handle)
+                    .then_some(KeyboardFocusTarget::Element(stack));
+            }
+        }
+        let workspace = self
+            .workspaces
+            .space_for_handle_mut(&workspace_handle)
+            .unwrap();
+
         let maybe_focused = workspace.focus_stack.get(&seat).iter().next().cloned();
         if let Some(FocusTarget::Window(focused)) = maybe_focused
+            && rules.stack_with.is_none()
+            && restore.is_none()
             && (focused.is_stack() && !is_dialog && !should_be_maximized)
-            && !(workspace.is_tiled(&focused.active_window()) && floating_exception)
+            && !(workspace.is_tiled(&focused.active_window()) && should_float)
         {
             focused.stack_ref().unwrap().add_window(window, None, None);
             if was_activated {
@@ -2793,8 +3026,37 @@ impl Shell {
         }
 
         let workspace_empty = workspace.mapped().next().is_none();
-        if is_dialog || floating_exception || !workspace.tiling_enabled {
-            workspace.floating_layer.map(mapped.clone(), None);
+        if should_float || !workspace.tiling_enabled {
+            if let Some(geometry) = floating_geometry {
+                workspace.floating_layer.map_internal(
+                    mapped.clone(),
+                    Some(geometry.loc),
+                    Some(Size::from((geometry.size.w, geometry.size.h))),
+                    None,
+                );
+            } else {
+                let placement = parent_mapped
+                    .as_ref()
+                    .filter(|_| is_dialog && self.floating_placement.center_dialogs_on_parent)
+                    .and_then(|parent| workspace.element_geometry(parent))
+                    .map(Placement::Parent)
+                    .unwrap_or_else(|| match self.floating_placement.strategy {
+                        FloatingPlacement::Cascade => Placement::Cascade,
+                        FloatingPlacement::Centered => Placement::Centered,
+                        FloatingPlacement::Smart => Placement::Smart,
+                        FloatingPlacement::UnderCursor => {
+                            let cursor = seat.get_pointer().unwrap().current_location().as_global();
+                            if workspace_output.geometry().to_f64().contains(cursor) {
+                                Placement::Under(cursor.to_local(&workspace_output))
+                            } else {
+                                Placement::Centered
+                            }
+                        }
+                    });
+                workspace
+                    .floating_layer
+                    .map_placed(mapped.clone(), placement);
+            }
         } else {
             for mapped in workspace
                 .mapped()
@@ -2809,9 +3071,16 @@ impl Shell {
             workspace
                 .tiling_layer
                 .map(mapped.clone(), Some(focus_stack.iter()), None);
+            if restore.is_some()
+                && let Some(shape) = self
+                    .layout_restore
+                    .tiling_shape(&window, &workspace.tiling_layer)
+            {
+                workspace.tiling_layer.apply_shape(shape);
+            }
         }
 
-        if parent_is_sticky {
+        if should_be_sticky {
             self.toggle_sticky(&seat, &mapped);
         }
 
@@ -2821,7 +3090,7 @@ impl Shell {
 
         let new_target = if (workspace_output == seat.active_output()
             && active_handle == workspace_handle)
-            || parent_is_sticky
+            || should_be_sticky
         {
             // TODO: enforce focus stealing prevention by also checking the same rules as for the else case.
             Some(KeyboardFocusTarget::from(mapped.clone()))
@@ -2883,6 +3152,50 @@ impl Shell {
         wants_focus.then(|| pending.surface.into())
     }
 
+    /// Remembers the geometry of `surface`, if it is a floating window about to be closed
+    fn remember_floating_geometry<S>(&mut self, surface: &S)
+    where
+        CosmicSurface: PartialEq<S>,
+    {
+        for set in self.workspaces.sets.values() {
+            let output_size = set.output.geometry().size.as_logical();
+            let floating = set
+                .sticky_layer
+                .mapped()
+                .find(|m| m.windows().any(|(s, _)| &s == surface))
+                .and_then(|m| Some((m, set.sticky_layer.element_geometry(m)?)))
+                .or_else(|| {
+                    set.workspaces.iter().find_map(|w| {
+                        let m = w
+                            .floating_layer
+                            .mapped()
+                            .find(|m| m.windows().any(|(s, _)| &s == surface))?;
+                        Some((m, w.floating_layer.element_geometry(m)?))
+                    })
+                });
+
+            if let Some((mapped, geometry)) = floating {
+                let window = mapped
+                    .windows()
+                    .map(|(s, _)| s)
+                    .find(|s| s == surface)
+                    .unwrap();
+                if !mapped.is_maximized(false)
+                    && !mapped.is_fullscreen(false)
+                    && !layout::is_dialog(&window)
+                {
+                    self.floating_memory.remember(
+                        &window,
+                        self.window_rules.title_pattern(&window),
+                        geometry,
+                        output_size,
+                    );
+                }
+                return;
+            }
+        }
+    }
+
     pub fn unmap_surface<S>(
         &mut self,
         surface: &S,
@@ -2892,6 +3205,17 @@ impl Shell {
     where
         CosmicSurface: PartialEq<S>,
     {
+        self.remember_floating_geometry(surface);
+        if let Some(surface) = self.unmap_scratchpad_surface(surface) {
+            toplevel_info.remove_toplevel(&surface);
+            return Some(PendingWindow {
+                surface,
+                seat: seat.clone(),
+                fullscreen: None,
+                maximized: false,
+            });
+        }
+
         for set in self.workspaces.sets.values_mut() {
             let sticky_res = set.sticky_layer.mapped().find_map(|m| {
                 m.windows()
@@ -4538,6 +4862,35 @@ impl Shell {
         }
     }
 
+    /// Keeps a floating window above or below the other floating windows, or back in the normal order if it already is.
+    ///
+    /// Tiled windows are not affected.
+    pub fn toggle_keep_level(&mut self, mapped: &CosmicMapped, level: KeepLevel) {
+        let floating_layer = self.workspaces.sets.values_mut().find_map(|set| {
+            if set.sticky_layer.mapped().any(|m| m == mapped) {
+                Some(&mut set.sticky_layer)
+            } else {
+                set.workspaces
+                    .iter_mut()
+                    .map(|w| &mut w.floating_layer)
+                    .find(|layer| layer.mapped().any(|m| m == mapped))
+            }
+        });
+        let Some(floating_layer) = floating_layer else {
+            return;
+        };
+
+        {
+            let mut keep_level = mapped.keep_level.lock().unwrap();
+            *keep_level = if *keep_level == level {
+                KeepLevel::Normal
+            } else {
+                level
+            };
+        }
+        floating_layer.restack();
+    }
+
     pub fn toggle_sticky(&mut self, seat: &Seat<State>, mapped: &CosmicMapped) {
         // clean from focus-stacks
         for workspace in self.workspaces.spaces_mut() {
@@ -4844,6 +5197,13 @@ impl Shell {
         self.tiling_exceptions = layout::TilingExceptions::new(exceptions);
     }
 
+    pub fn update_window_rules<'a, I>(&mut self, rules: I)
+    where
+        I: Iterator<Item = &'a WindowRule>,
+    {
+        self.window_rules = WindowRules::new(rules);
+    }
+
     pub fn take_presentation_feedback(
         &self,
         output: &Output,
@@ -0,0 +1,267 @@
+// SPDX-License-Identifier: GPL-3.0-only
+
+//! Named scratchpads, as known from i3.
+//!
+//! A window sent to a scratchpad is minimized out of every workspace and not rendered
+//! anywhere. Toggling the scratchpad shows it floating and centered in the sticky layer
+//! of the focused output, toggling again hides it.
+
+use smithay::{desktop::layer_map_for_output, input::Seat, output::Output, utils::Point};
+
+use crate::{
+    state::State,
+    utils::prelude::*,
+    wayland::{
+        handlers::toplevel_management::minimize_rectangle,
+        protocols::toplevel_info::{
+            toplevel_enter_output, toplevel_leave_output, toplevel_leave_workspace,
+        },
+    },
+};
+
+use super::{
+    CosmicMapped, CosmicSurface, FloatingRestoreData, ManagedLayer, MinimizedWindow, Shell,
+    focus::target::KeyboardFocusTarget,
+};
+
+#[derive(Debug)]
+pub enum Scratchpad {
+    /// Window taken out of all workspaces
+    Hidden(MinimizedWindow),
+    /// Window currently shown in the sticky layer of an output
+    Shown(CosmicMapped),
+}
+
+impl Scratchpad {
+    fn mapped(&self) -> &CosmicMapped {
+        match self {
+            Scratchpad::Hidden(minimized) => minimized.mapped().unwrap(),
+            Scratchpad::Shown(mapped) => mapped,
+        }
+    }
+}
+
+impl Shell {
+    /// Hides `mapped` in the scratchpad called `name`.
+    ///
+    /// A window previously held under the same name is moved to a generated name
+    /// (`name-2`, `name-3`, ...) and shown if it was hidden, so it doesn't get lost.
+    /// Without an output to show it on, it stays hidden under the generated name.
+    pub fn send_to_scratchpad(
+        &mut self,
+        mapped: &CosmicMapped,
+        name: &str,
+        seat: &Seat<State>,
+    ) -> Option<KeyboardFocusTarget> {
+        if mapped.is_fullscreen(true) {
+            return None;
+        }
+        self.scratchpads
+            .retain(|_, scratchpad| scratchpad.mapped() != mapped);
+        let minimized = self.take_for_scratchpad(mapped)?;
+
+        let previous = self
+            .scratchpads
+            .insert(name.to_string(), Scratchpad::Hidden(minimized));
+        let (previous, focus) = match previous? {
+            Scratchpad::Hidden(minimized) => {
+                let output = seat.focused_or_active_output();
+                match self.show_scratchpad(minimized, &output) {
+                    Ok(mapped) => (
+                        Scratchpad::Shown(mapped.clone()),
+                        Some(KeyboardFocusTarget::Element(mapped)),
+                    ),
+                    Err(minimized) => (Scratchpad::Hidden(minimized), None),
+                }
+            }
+            shown => (shown, None),
+        };
+        let generated = (2..)
+            .map(|i| format!("{name}-{i}"))
+            .find(|name| !self.scratchpads.contains_key(name))
+            .unwrap();
+        self.scratchpads.insert(generated, previous);
+        focus
+    }
+
+    /// Shows the window of the scratchpad called `name` on the focused output, or hides it again
+    pub fn toggle_scratchpad(
+        &mut self,
+        name: &str,
+        seat: &Seat<State>,
+    ) -> Option<KeyboardFocusTarget> {
+        let output = seat.focused_or_active_output();
+        let minimized = match self.scratchpads.remove(name)? {
+            Scratchpad::Hidden(minimized) => minimized,
+            Scratchpad::Shown(mapped) => {
+                if mapped.is_fullscreen(true) {
+                    self.scratchpads
+                        .insert(name.to_string(), Scratchpad::Shown(mapped));
+                    return None;
+                }
+                let visible_here = self.workspaces.sets.get(&output).is_some_and(|set| {
+                    set.sticky_layer.mapped().any(|m| m == &mapped)
+                        || set.workspaces[set.active].mapped().any(|m| m == &mapped)
+                });
+                // windows closed in the meantime are simply forgotten
+                let minimized = self.take_for_scratchpad(&mapped)?;
+                if visible_here {
+                    self.scratchpads
+                        .insert(name.to_string(), Scratchpad::Hidden(minimized));
+                    return None;
+                }
+                // shown or minimized elsewhere, bring it over
+                minimized
+            }
+        };
+
+        match self.show_scratchpad(minimized, &output) {
+            Ok(mapped) => {
+                self.scratchpads
+                    .insert(name.to_string(), Scratchpad::Shown(mapped.clone()));
+                Some(KeyboardFocusTarget::Element(mapped))
+            }
+            Err(minimized) => {
+                self.scratchpads
+                    .insert(name.to_string(), Scratchpad::Hidden(minimized));
+                None
+            }
+        }
+    }
+
+    /// Removes `surface` from a hidden scratchpad window, if it belongs to one
+    pub(super) fn unmap_scratchpad_surface<S>(&mut self, surface: &S) -> Option<CosmicSurface>
+    where
+        CosmicSurface: PartialEq<S>,
+    {
+        let name = self.scratchpads.iter().find_map(|(name, scratchpad)| {
+            matches!(scratchpad, Scratchpad::Hidden(_))
+                .then(|| scratchpad.mapped())
+                .filter(|mapped| mapped.windows().any(|(s, _)| &s == surface))
+                .map(|_| name.clone())
+        })?;
+
+        let mapped = self.scratchpads[&name].mapped().clone();
+        if let Some(stack) = mapped.stack_ref() {
+            let idx = stack.surfaces().position(|s| &s == surface)?;
+            stack.remove_idx(idx)
+        } else {
+            let scratchpad = self.scratchpads.remove(&name).unwrap();
+            Some(scratchpad.mapped().active_window())
+        }
+    }
+
+    pub(super) fn is_scratchpad_surface<S>(&self, surface: &S) -> bool
+    where
+        CosmicSurface: PartialEq<S>,
+    {
+        self.scratchpads.values().any(|scratchpad| {
+            matches!(scratchpad, Scratchpad::Hidden(_))
+                && scratchpad.mapped().windows().any(|(s, _)| &s == surface)
+        })
+    }
+
+    /// Minimizes `mapped` out of the sticky layer or workspace it is on
+    fn take_for_scratchpad(&mut self, mapped: &CosmicMapped) -> Option<MinimizedWindow> {
+        for workspace in self.workspaces.spaces_mut() {
+            for seat in self.seats.iter() {
+                workspace.focus_stack.get_mut(seat).remove(mapped);
+            }
+        }
+
+        if mapped.is_maximized(false)
+            && self
+                .workspaces
+                .sets
+                .values()
+                .any(|set| set.sticky_layer.mapped().any(|m| m == mapped))
+        {
+            self.unmaximize_request(mapped);
+        }
+
+        for set in self.workspaces.sets.values_mut() {
+            let output = set.output.clone();
+            let minimized = if let Some(idx) =
+                set.minimized_windows.iter().position(|m| m == mapped)
+            {
+                Some(set.minimized_windows.remove(idx))
+            } else if set.sticky_layer.mapped().any(|m| m == mapped) {
+                let to = minimize_rectangle(&output, &mapped.active_window());
+                let geometry = set.sticky_layer.unmap(mapped, Some(to)).unwrap();
+                mapped.set_minimized(true);
+                mapped.configure();
+                Some(MinimizedWindow::Floating {
+                    window: mapped.clone(),
+                    previous: FloatingRestoreData {
+                        geometry,
+                        output_size: output.geometry().size.as_logical(),
+                        was_maximized: false,
+                        was_snapped: None,
+                    },
+                })
+            } else if let Some(workspace) = set.workspaces.iter_mut().find(|w| {
+                w.mapped().any(|m| m == mapped) || w.minimized_windows.iter().any(|m| m == mapped)
+            }) {
+                let minimized = match workspace.minimized_windows.iter().position(|m| m == mapped) {
+                    Some(idx) => workspace.minimized_windows.remove(idx),
+                    None => {
+                        let to = minimize_rectangle(&output, &mapped.active_window());
+                        workspace.minimize(&mapped.active_window(), to)?
+                    }
+                };
+                for (window, _) in mapped.windows() {
+                    toplevel_leave_workspace(&window, &workspace.handle);
+                }
+                Some(minimized)
+            } else {
+                None
+            };
+
+            if let Some(minimized) = minimized {
+                for (window, _) in mapped.windows() {
+                    window.set_sticky(false);
+                    toplevel_leave_output(&window, &output);
+                }
+                return Some(minimized);
+            }
+        }
+
+        None
+    }
+
+    /// Maps a hidden scratchpad window centered into the sticky layer of `output`.
+    ///
+    /// Hands the window back, if `output` has no workspaces to show it on.
+    fn show_scratchpad(
+        &mut self,
+        minimized: MinimizedWindow,
+        output: &Output,
+    ) -> Result<CosmicMapped, MinimizedWindow> {
+        let (Some(mapped), Some(set)) = (
+            minimized.mapped().cloned(),
+            self.workspaces.sets.get_mut(output),
+        ) else {
+            return Err(minimized);
+        };
+
+        let area = layer_map_for_output(output).non_exclusive_zone().as_local();
+        let size = mapped.geometry().size.as_local();
+        let position = area.loc
+            + Point::from((
+                (area.size.w - size.w).max(0) / 2,
+                (area.size.h - size.h).max(0) / 2,
+            ));
+        let from = minimize_rectangle(output, &mapped.active_window());
+
+        *mapped.previous_layer.lock().unwrap() = Some(ManagedLayer::Floating);
+        for (window, _) in mapped.windows() {
+            window.set_sticky(true);
+            toplevel_enter_output(&window, output);
+        }
+        mapped.set_minimized(false);
+        set.sticky_layer
+            .remap_minimized(mapped.clone(), from, position);
+
+        Ok(mapped)
+    }
+}
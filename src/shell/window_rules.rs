@@ -0,0 +1,109 @@
+// SPDX-License-Identifier: GPL-3.0-only
+
+use cosmic_comp_config::window_rules::{WindowEffects, WindowRule};
+use regex::Regex;
+use tracing::warn;
+
+use super::CosmicSurface;
+
+#[derive(Debug, Clone)]
+struct CompiledRule {
+    app_id: Option<Regex>,
+    title: Option<Regex>,
+    xwayland: Option<bool>,
+    parent_app_id: Option<Regex>,
+    pid: Option<u32>,
+    effects: WindowEffects,
+}
+
+/// Window rules from the compositor config, with their regexes compiled
+#[derive(Debug, Clone, Default)]
+pub struct WindowRules {
+    rules: Vec<CompiledRule>,
+}
+
+/// Compiles a rule regex, which has to match the whole string
+pub fn anchored_regex(regex: &str) -> Result<Regex, regex::Error> {
+    Regex::new(&format!("^(?:{regex})$"))
+}
+
+fn compile(regex: &Option<String>) -> Result<Option<Regex>, regex::Error> {
+    regex.as_deref().map(anchored_regex).transpose()
+}
+
+impl WindowRules {
+    pub fn new<'a, I>(rules_config: I) -> Self
+    where
+        I: Iterator<Item = &'a WindowRule>,
+    {
+        let mut rules = Vec::new();
+
+        for rule in rules_config {
+            let compiled = (|| {
+                if let Some(stack_with) = rule.apply.stack_with.as_ref() {
+                    anchored_regex(stack_with)?;
+                }
+                Ok::<_, regex::Error>(CompiledRule {
+                    app_id: compile(&rule.matches.app_id)?,
+                    title: compile(&rule.matches.title)?,
+                    xwayland: rule.matches.xwayland,
+                    parent_app_id: compile(&rule.matches.parent_app_id)?,
+                    pid: rule.matches.pid,
+                    effects: rule.apply.clone(),
+                })
+            })();
+
+            match compiled {
+                Ok(compiled) => rules.push(compiled),
+                Err(err) => warn!(?rule, "Invalid regex in window rule: {}", err),
+            }
+        }
+
+        Self { rules }
+    }
+
+    /// Merged effects of all rules matching `window`
+    pub fn effects(&self, window: &CosmicSurface, parent: Option<&CosmicSurface>) -> WindowEffects {
+        let mut effects = WindowEffects::default();
+        if self.rules.is_empty() {
+            return effects;
+        }
+
+        let app_id = window.app_id();
+        let title = window.title();
+        let is_x11 = window.x11_surface().is_some();
+        let parent_app_id = parent.map(CosmicSurface::app_id).unwrap_or_default();
+        let pid = window.pid();
+
+        for rule in &self.rules {
+            if rule.app_id.as_ref().is_some_and(|re| !re.is_match(&app_id))
+                || rule.title.as_ref().is_some_and(|re| !re.is_match(&title))
+                || rule.xwayland.is_some_and(|xwayland| xwayland != is_x11)
+                || rule
+                    .parent_app_id
+                    .as_ref()
+                    .is_some_and(|re| !re.is_match(&parent_app_id))
+                || rule.pid.is_some_and(|rule_pid| Some(rule_pid) != pid)
+            {
+                continue;
+            }
+            effects.merge(&rule.effects);
+        }
+
+        effects
+    }
+
+    /// Title regex of the first rule matching the app_id and title of `window`.
+    ///
+    /// Windows with titles matching the same regex share their remembered floating geometry.
+    pub fn title_pattern(&self, window: &CosmicSurface) -> Option<&str> {
+        let app_id = window.app_id();
+        let title = window.title();
+        self.rules.iter().find_map(|rule| {
+            let title_re = rule.title.as_ref()?;
+            (rule.app_id.as_ref().is_none_or(|re| re.is_match(&app_id))
+                && title_re.is_match(&title))
+            .then(|| title_re.as_str())
+        })
+    }
+}
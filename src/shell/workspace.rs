@@ -4,6 +4,7 @@ use crate::wayland::handlers::xdg_activation::ActivationContext;
 use crate::{
     backend::render::{
         BackdropShader,
+        color::{ColorConvertedSurfaceRenderElement, surface_tree_image_descriptions},
         element::{AsGlowRenderer, FromGlesError},
     },
     shell::{
@@ -47,7 +48,7 @@ use smithay::{
     output::Output,
     reexports::wayland_server::Client,
     utils::{Buffer as BufferCoords, IsAlive, Logical, Physical, Point, Rectangle, Scale, Size},
-    wayland::xdg_activation::XdgActivationState,
+    wayland::{seat::WaylandFocus, xdg_activation::XdgActivationState},
 };
 use std::{
     collections::{HashMap, VecDeque},
@@ -106,6 +107,8 @@ pub struct Workspace {
     pub floating_layer: FloatingLayout,
     pub minimized_windows: Vec<MinimizedWindow>,
     pub tiling_enabled: bool,
+    /// New windows join the stack of an existing window of the same application
+    pub auto_stack: bool,
     pub fullscreen: Option<FullscreenSurface>,
     pub pinned: bool,
     pub id: Option<String>,
@@ -375,6 +378,7 @@ impl Workspace {
             tiling_layer,
             floating_layer,
             tiling_enabled,
+            auto_stack: false,
             minimized_windows: Vec::new(),
             fullscreen: None,
             pinned: false,
@@ -408,6 +412,7 @@ impl Workspace {
             tiling_layer,
             floating_layer,
             tiling_enabled: pinned.tiling_enabled,
+            auto_stack: false,
             minimized_windows: Vec::new(),
             fullscreen: None,
             pinned: true,
@@ -452,6 +457,13 @@ impl Workspace {
         self.tiling_layer.refresh();
     }
 
+    /// Scrolls the columns layout to the window focused by `seat`
+    pub fn scroll_to_focus(&mut self, seat: &Seat<State>) {
+        if let Some(FocusTarget::Window(mapped)) = self.focus_stack.get(seat).last() {
+            self.tiling_layer.scroll_to_focus(mapped);
+        }
+    }
+
     fn has_activation_token(&self, xdg_activation_state: &XdgActivationState) -> bool {
         xdg_activation_state.tokens().any(|(_, data)| {
             if let ActivationContext::Workspace(handle) =
@@ -1612,9 +1624,15 @@ impl Workspace {
                 }
             };
 
+            let image_descriptions = fullscreen
+                .surface
+                .wl_surface()
+                .map(|surface| surface_tree_image_descriptions(&surface))
+                .unwrap_or_default();
+
             fullscreen
                 .surface
-                .render_elements::<R, CosmicWindowRenderElement<R>>(
+                .render_elements::<R, WaylandSurfaceRenderElement<R>>(
                     renderer,
                     render_loc,
                     output_scale.into(),
@@ -1622,6 +1640,18 @@ impl Workspace {
                     Some(true),
                 )
                 .into_iter()
+                .map(|elem| match image_descriptions.get(elem.id()) {
+                    Some(description) => CosmicWindowRenderElement::ColorConverted(
+                        ColorConvertedSurfaceRenderElement::new(
+                            renderer,
+                            elem,
+                            description,
+                            output_scale.into(),
+                            None,
+                        ),
+                    ),
+                    None => CosmicWindowRenderElement::Window(elem),
+                })
                 .map(animation_rescale)
                 .collect::<Vec<_>>()
         } else {
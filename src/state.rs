@@ -2,22 +2,27 @@
 
 use crate::{
     backend::{
+        headless::HeadlessState,
         kms::{KmsGuard, KmsState},
         render::{GlMultiError, RendererRef},
+        wayland::WaylandState,
         winit::WinitState,
         x11::X11State,
     },
     config::{CompOutputConfig, Config, ScreenFilter},
     dbus::a11y_keyboard_monitor::A11yKeyboardMonitorState,
-    input::{PointerFocusState, gestures::GestureState},
+    input::{PointerFocusState, gestures::GestureState, recording::InputRecorder},
+    ipc::IpcState,
     shell::{CosmicSurface, SeatExt, Shell, grabs::SeatMoveGrabState},
     utils::prelude::OutputExt,
     wayland::{
         handlers::{data_device::get_dnd_icon, image_copy_capture::SessionHolder},
         protocols::{
             a11y::A11yState,
+            color_management::ColorManagementState,
             corner_radius::CornerRadiusState,
             drm::WlDrmState,
+            gamma_control::GammaControlState,
             image_capture_source::CosmicImageCaptureSourceState,
             output_configuration::OutputConfigurationState,
             output_power::OutputPowerState,
@@ -243,16 +248,21 @@ pub struct Common {
     pub should_stop: bool,
 
     pub gesture_state: Option<GestureState>,
+    pub input_recorder: Option<InputRecorder>,
 
     pub kiosk_child: Option<Child>,
+    pub ipc_state: Option<IpcState>,
     pub theme: cosmic::Theme,
+    pub night_light_timer: Option<RegistrationToken>,
 
     // wayland state
+    pub color_management_state: ColorManagementState,
     pub compositor_state: CompositorState,
     pub corner_radius_state: CornerRadiusState,
     pub data_device_state: DataDeviceState,
     pub dmabuf_state: DmabufState,
     pub fractional_scale_state: FractionalScaleManagerState,
+    pub gamma_control_state: GammaControlState,
     pub keyboard_shortcuts_inhibit_state: KeyboardShortcutsInhibitState,
     pub output_state: OutputManagerState,
     pub output_configuration_state: OutputConfigurationState<State>,
@@ -305,8 +315,8 @@ pub enum BackendData {
     X11(X11State),
     Winit(WinitState),
     Kms(KmsState),
-    // TODO
-    // Wayland(WaylandState),
+    Headless(HeadlessState),
+    Wayland(WaylandState),
     Unset,
 }
 
@@ -314,6 +324,8 @@ pub enum LockedBackend<'a> {
     X11(&'a mut X11State),
     Winit(&'a mut WinitState),
     Kms(KmsGuard<'a>),
+    Headless(&'a mut HeadlessState),
+    Wayland(&'a mut WaylandState),
 }
 
 #[derive(Debug, Clone)]
@@ -357,6 +369,20 @@ impl BackendData {
         }
     }
 
+    pub fn headless(&mut self) -> &mut HeadlessState {
+        match self {
+            BackendData::Headless(headless_state) => headless_state,
+            _ => unreachable!("Called headless in non headless backend"),
+        }
+    }
+
+    pub fn wayland(&mut self) -> &mut WaylandState {
+        match self {
+            BackendData::Wayland(wayland_state) => wayland_state,
+            _ => unreachable!("Called wayland in non wayland backend"),
+        }
+    }
+
     pub fn schedule_render(&mut self, output: &Output) {
         match self {
             BackendData::Winit(_) => {} // We cannot do this on the winit backend.
@@ -364,6 +390,8 @@ impl BackendData {
             // Swapping with damage (which should be empty on these frames) is likely good enough anyway.
             BackendData::X11(state) => state.schedule_render(output),
             BackendData::Kms(state) => state.schedule_render(output),
+            BackendData::Headless(state) => state.schedule_render(output),
+            BackendData::Wayland(state) => state.schedule_render(output),
             _ => unreachable!("No backend was initialized"),
         }
     }
@@ -384,6 +412,12 @@ impl BackendData {
             BackendData::X11(state) => {
                 state.renderer.import_dmabuf(&dmabuf, None)?;
             }
+            BackendData::Headless(state) => {
+                state.renderer.import_dmabuf(&dmabuf, None)?;
+            }
+            BackendData::Wayland(state) => {
+                state.renderer.import_dmabuf(&dmabuf, None)?;
+            }
             _ => unreachable!("No backend set when importing dmabuf"),
         };
         Ok(None)
@@ -417,6 +451,8 @@ impl BackendData {
             }
             BackendData::Winit(winit) => Ok(RendererRef::Glow(winit.backend.renderer())),
             BackendData::X11(x11) => Ok(RendererRef::Glow(&mut x11.renderer)),
+            BackendData::Headless(headless) => Ok(RendererRef::Glow(&mut headless.renderer)),
+            BackendData::Wayland(wayland) => Ok(RendererRef::Glow(&mut wayland.renderer)),
             _ => unreachable!("No backend set when getting offscreen renderer"),
         }
     }
@@ -426,6 +462,8 @@ impl BackendData {
             BackendData::Kms(state) => state.update_screen_filter(screen_filter),
             BackendData::Winit(state) => state.update_screen_filter(screen_filter),
             BackendData::X11(state) => state.update_screen_filter(screen_filter),
+            BackendData::Headless(state) => state.update_screen_filter(screen_filter),
+            BackendData::Wayland(state) => state.update_screen_filter(screen_filter),
             _ => unreachable!("No backend set when setting screen filters"),
         }
     }
@@ -435,6 +473,8 @@ impl BackendData {
             BackendData::Kms(state) => LockedBackend::Kms(state.lock_devices()),
             BackendData::X11(state) => LockedBackend::X11(state),
             BackendData::Winit(state) => LockedBackend::Winit(state),
+            BackendData::Headless(state) => LockedBackend::Headless(state),
+            BackendData::Wayland(state) => LockedBackend::Wayland(state),
             _ => unreachable!("Tried to lock unset backend"),
         }
     }
@@ -446,6 +486,8 @@ impl LockedBackend<'_> {
             LockedBackend::Kms(state) => state.all_outputs(),
             LockedBackend::X11(state) => state.all_outputs(),
             LockedBackend::Winit(state) => state.all_outputs(),
+            LockedBackend::Headless(state) => state.all_outputs(),
+            LockedBackend::Wayland(state) => state.all_outputs(),
         }
     }
 
@@ -533,6 +575,8 @@ impl LockedBackend<'_> {
             ),
             LockedBackend::Winit(state) => state.apply_config_for_outputs(test_only),
             LockedBackend::X11(state) => state.apply_config_for_outputs(test_only),
+            LockedBackend::Headless(state) => state.apply_config_for_outputs(test_only),
+            LockedBackend::Wayland(state) => state.apply_config_for_outputs(test_only),
         }?;
 
         let mut shell_ref = shell.write();
@@ -580,6 +624,8 @@ impl LockedBackend<'_> {
                 // Swapping with damage (which should be empty on these frames) is likely good enough anyway.
                 LockedBackend::X11(state) => state.schedule_render(&output),
                 LockedBackend::Kms(state) => state.schedule_render(&output),
+                LockedBackend::Headless(state) => state.schedule_render(&output),
+                LockedBackend::Wayland(state) => state.schedule_render(&output),
             }
         }
 
@@ -587,6 +633,8 @@ impl LockedBackend<'_> {
             state.update_inhibitor_locks();
             state.common.update_xwayland_settings();
             state.common.update_xwayland_primary_output();
+            state.refresh_color_management();
+            GammaControlState::refresh(state);
         });
 
         Ok(())
@@ -637,11 +685,13 @@ impl State {
 
         let clock = Clock::new();
         let config = Config::load(&handle);
+        let color_management_state = ColorManagementState::new::<Self>(dh);
         let compositor_state = CompositorState::new::<Self>(dh);
         let corner_radius_state = CornerRadiusState::new::<Self>(dh);
         let data_device_state = DataDeviceState::new::<Self>(dh);
         let dmabuf_state = DmabufState::new();
         let fractional_scale_state = FractionalScaleManagerState::new::<State>(dh);
+        let gamma_control_state = GammaControlState::new::<Self, _>(dh, client_not_sandboxed);
         let keyboard_shortcuts_inhibit_state = KeyboardShortcutsInhibitState::new::<Self>(dh);
         let output_state = OutputManagerState::new_with_xdg_output::<Self>(dh);
         let output_configuration_state =
@@ -732,6 +782,10 @@ impl State {
             tracing::warn!(?err, "Failed to initialize dbus handlers");
         }
 
+        let ipc_state = IpcState::new(&handle, &socket)
+            .map_err(|err| tracing::warn!(?err, "Failed to initialize ipc socket"))
+            .ok();
+
         let a11y_state = A11yState::new::<State, _>(dh, client_not_sandboxed);
 
         let a11y_keyboard_monitor_state = A11yKeyboardMonitorState::new(&async_executor);
@@ -752,15 +806,20 @@ impl State {
                 startup_done: Arc::new(AtomicBool::new(false)),
                 should_stop: false,
                 gesture_state: None,
+                input_recorder: InputRecorder::from_env(),
 
                 kiosk_child: None,
+                ipc_state,
                 theme: cosmic::theme::system_preference(),
+                night_light_timer: None,
 
+                color_management_state,
                 compositor_state,
                 corner_radius_state,
                 data_device_state,
                 dmabuf_state,
                 fractional_scale_state,
+                gamma_control_state,
                 idle_notifier_state,
                 idle_inhibit_manager_state,
                 idle_inhibiting_surfaces,
@@ -0,0 +1,434 @@
+// SPDX-License-Identifier: GPL-3.0-only
+
+//! Minimal parser for ICC display profiles.
+//!
+//! Only matrix/TRC based profiles and `vcgt` calibration curves are supported,
+//! which covers the profiles created by common calibration software.
+
+use std::path::Path;
+
+use anyhow::{Context, Result, bail, ensure};
+
+const HEADER_SIZE: usize = 128;
+
+/// A tone reproduction curve mapping normalized input values to output values.
+#[derive(Debug, Clone, PartialEq)]
+pub enum Curve {
+    Gamma(f64),
+    /// Parameters `[g, a, b, c, d, e, f]` of the curve
+    /// `Y = (aX + b)^g + e` for `X >= d`, `Y = cX + f` otherwise.
+    Parametric([f64; 7]),
+    Table(Vec<u16>),
+}
+
+impl Curve {
+    pub const IDENTITY: Curve = Curve::Gamma(1.0);
+
+    pub fn eval(&self, x: f64) -> f64 {
+        let x = x.clamp(0., 1.);
+        match self {
+            Curve::Gamma(g) => x.powf(*g),
+            Curve::Parametric([g, a, b, c, d, e, f]) => {
+                if x >= *d {
+                    (a * x + b).max(0.).powf(*g) + e
+                } else {
+                    c * x + f
+                }
+            }
+            Curve::Table(table) => {
+                let pos = x * (table.len() - 1) as f64;
+                let idx = pos.floor() as usize;
+                let next = (idx + 1).min(table.len() - 1);
+                let fract = pos - idx as f64;
+                (table[idx] as f64 * (1. - fract) + table[next] as f64 * fract) / u16::MAX as f64
+            }
+        }
+        .clamp(0., 1.)
+    }
+
+    /// Parameters of this curve in the form of [`Curve::Parametric`].
+    ///
+    /// Sampled curves are approximated by a pure power function.
+    pub fn parameters(&self) -> [f64; 7] {
+        match self {
+            Curve::Gamma(g) => [*g, 1., 0., 0., 0., 0., 0.],
+            Curve::Parametric(params) => *params,
+            Curve::Table(_) => {
+                // least squares fit of `ln y = g * ln x`
+                let (num, denom) = (1..20)
+                    .map(|i| i as f64 / 20.)
+                    .filter_map(|x| {
+                        let y = self.eval(x);
+                        (y > 0.).then(|| (x.ln(), y.ln()))
+                    })
+                    .fold((0., 0.), |(num, denom), (ln_x, ln_y)| {
+                        (num + ln_x * ln_y, denom + ln_x * ln_x)
+                    });
+                let g = if denom > 0. { num / denom } else { 1. };
+                [g, 1., 0., 0., 0., 0., 0.]
+            }
+        }
+    }
+}
+
+/// Display profile read from an ICC file
+#[derive(Debug, Clone, PartialEq)]
+pub struct IccProfile {
+    /// CIE XYZ (D50) values of the red, green and blue colorants
+    pub colorants: Option<[[f64; 3]; 3]>,
+    /// Curves of the red, green and blue channels mapping device values to linear light
+    pub trc: Option<[Curve; 3]>,
+    /// Calibration curves meant to be loaded into the gamma ramps of the display
+    pub vcgt: Option<[Curve; 3]>,
+}
+
+impl IccProfile {
+    pub fn load(path: impl AsRef<Path>) -> Result<IccProfile> {
+        let path = path.as_ref();
+        let data = std::fs::read(path)
+            .with_context(|| format!("Failed to read ICC profile {}", path.display()))?;
+        IccProfile::parse(&data)
+            .with_context(|| format!("Failed to parse ICC profile {}", path.display()))
+    }
+
+    pub fn parse(data: &[u8]) -> Result<IccProfile> {
+        ensure!(data.len() >= HEADER_SIZE + 4, "File too small");
+        ensure!(&data[36..40] == b"acsp", "Not an ICC profile");
+        ensure!(&data[16..20] == b"RGB ", "Only RGB profiles are supported");
+
+        let mut tags = Vec::new();
+        let count = read_u32(data, HEADER_SIZE)? as usize;
+        for i in 0..count {
+            let entry = HEADER_SIZE + 4 + i * 12;
+            let signature = read_bytes(data, entry, 4)?;
+            let offset = read_u32(data, entry + 4)? as usize;
+            let size = read_u32(data, entry + 8)? as usize;
+            tags.push((signature, read_bytes(data, offset, size)?));
+        }
+        let tag = |signature: &[u8]| {
+            tags.iter()
+                .find(|(sig, _)| *sig == signature)
+                .map(|(_, data)| *data)
+        };
+
+        let colorants = match (tag(b"rXYZ"), tag(b"gXYZ"), tag(b"bXYZ")) {
+            (Some(r), Some(g), Some(b)) => Some([parse_xyz(r)?, parse_xyz(g)?, parse_xyz(b)?]),
+            _ => None,
+        };
+        let trc = match (tag(b"rTRC"), tag(b"gTRC"), tag(b"bTRC")) {
+            (Some(r), Some(g), Some(b)) => {
+                Some([parse_curve(r)?, parse_curve(g)?, parse_curve(b)?])
+            }
+            _ => None,
+        };
+        let vcgt = tag(b"vcgt").map(parse_vcgt).transpose()?;
+
+        ensure!(
+            (colorants.is_some() && trc.is_some()) || vcgt.is_some(),
+            "Profile contains neither a matrix/TRC description nor calibration curves"
+        );
+
+        Ok(IccProfile {
+            colorants: colorants.filter(|_| trc.is_some()),
+            trc: trc.filter(|_| colorants.is_some()),
+            vcgt,
+        })
+    }
+}
+
+fn read_bytes(data: &[u8], offset: usize, len: usize) -> Result<&[u8]> {
+    data.get(offset..offset + len)
+        .context("Unexpected end of profile")
+}
+
+fn read_u16(data: &[u8], offset: usize) -> Result<u16> {
+    Ok(u16::from_be_bytes(
+        read_bytes(data, offset, 2)?.try_into().unwrap(),
+    ))
+}
+
+fn read_u32(data: &[u8], offset: usize) -> Result<u32> {
+    Ok(u32::from_be_bytes(
+        read_bytes(data, offset, 4)?.try_into().unwrap(),
+    ))
+}
+
+fn read_s15_16(data: &[u8], offset: usize) -> Result<f64> {
+    Ok(read_u32(data, offset)? as i32 as f64 / 65536.)
+}
+
+fn parse_xyz(data: &[u8]) -> Result<[f64; 3]> {
+    ensure!(read_bytes(data, 0, 4)? == b"XYZ ", "Invalid XYZ tag");
+    Ok([
+        read_s15_16(data, 8)?,
+        read_s15_16(data, 12)?,
+        read_s15_16(data, 16)?,
+    ])
+}
+
+fn parse_curve(data: &[u8]) -> Result<Curve> {
+    match read_bytes(data, 0, 4)? {
+        b"curv" => match read_u32(data, 8)? as usize {
+            0 => Ok(Curve::IDENTITY),
+            // u8Fixed8Number
+            1 => Ok(Curve::Gamma(read_u16(data, 12)? as f64 / 256.)),
+            count => Ok(Curve::Table(
+                (0..count)
+                    .map(|i| read_u16(data, 12 + i * 2))
+                    .collect::<Result<_>>()?,
+            )),
+        },
+        b"para" => {
+            let function = read_u16(data, 8)?;
+            let param = |i: usize| read_s15_16(data, 12 + i * 4);
+            let g = param(0)?;
+            // normalize all function types to the form of type 4
+            let params = match function {
+                0 => [g, 1., 0., 0., 0., 0., 0.],
+                1 => {
+                    let (a, b) = (param(1)?, param(2)?);
+                    [g, a, b, 0., -b / a, 0., 0.]
+                }
+                2 => {
+                    let (a, b, c) = (param(1)?, param(2)?, param(3)?);
+                    [g, a, b, 0., -b / a, c, c]
+                }
+                3 => [g, param(1)?, param(2)?, param(3)?, param(4)?, 0., 0.],
+                4 => [
+                    g,
+                    param(1)?,
+                    param(2)?,
+                    param(3)?,
+                    param(4)?,
+                    param(5)?,
+                    param(6)?,
+                ],
+                x => bail!("Unknown parametric curve type {x}"),
+            };
+            Ok(Curve::Parametric(params))
+        }
+        _ => bail!("Unsupported curve type"),
+    }
+}
+
+fn parse_vcgt(data: &[u8]) -> Result<[Curve; 3]> {
+    ensure!(read_bytes(data, 0, 4)? == b"vcgt", "Invalid vcgt tag");
+    match read_u32(data, 8)? {
+        // table
+        0 => {
+            let channels = read_u16(data, 12)? as usize;
+            let count = read_u16(data, 14)? as usize;
+            let entry_size = read_u16(data, 16)? as usize;
+            ensure!(count > 1, "Invalid vcgt table size");
+            let channel = |c: usize| -> Result<Curve> {
+                // single channel tables apply to all channels
+                let start = 18 + (c % channels.max(1)) * count * entry_size;
+                (0..count)
+                    .map(|i| match entry_size {
+                        1 => Ok(data
+                            .get(start + i)
+                            .map(|val| *val as u16 * 257)
+                            .context("Unexpected end of vcgt table")?),
+                        2 => read_u16(data, start + i * 2),
+                        x => bail!("Unsupported vcgt entry size {x}"),
+                    })
+                    .collect::<Result<_>>()
+                    .map(Curve::Table)
+            };
+            Ok([channel(0)?, channel(1)?, channel(2)?])
+        }
+        // formula: `min + (max - min) * x^gamma`
+        1 => {
+            let channel = |c: usize| -> Result<Curve> {
+                let offset = 12 + c * 12;
+                let gamma = read_s15_16(data, offset)?;
+                let min = read_s15_16(data, offset + 4)?;
+                let max = read_s15_16(data, offset + 8)?;
+                let scale = (max - min).powf(1. / gamma);
+                Ok(Curve::Parametric([gamma, scale, 0., 0., 0., min, min]))
+            };
+            Ok([channel(0)?, channel(1)?, channel(2)?])
+        }
+        x => bail!("Unknown vcgt type {x}"),
+    }
+}
+
+#[cfg(test)]
+mod test {
+    use super::*;
+
+    fn s15_16(value: f64) -> [u8; 4] {
+        ((value * 65536.) as i32).to_be_bytes()
+    }
+
+    fn tag(signature: &[u8; 4], body: &[u8]) -> Vec<u8> {
+        let mut data = signature.to_vec();
+        data.extend([0; 4]);
+        data.extend(body);
+        data
+    }
+
+    fn curv(entries: &[u16]) -> Vec<u8> {
+        let mut body = (entries.len() as u32).to_be_bytes().to_vec();
+        body.extend(entries.iter().flat_map(|entry| entry.to_be_bytes()));
+        tag(b"curv", &body)
+    }
+
+    fn para(function: u16, params: &[f64]) -> Vec<u8> {
+        let mut body = function.to_be_bytes().to_vec();
+        body.extend([0; 2]);
+        body.extend(params.iter().flat_map(|param| s15_16(*param)));
+        tag(b"para", &body)
+    }
+
+    fn xyz(values: [f64; 3]) -> Vec<u8> {
+        tag(b"XYZ ", &values.map(s15_16).concat())
+    }
+
+    fn profile(tags: &[(&[u8; 4], Vec<u8>)]) -> Vec<u8> {
+        let mut header = vec![0; HEADER_SIZE];
+        header[16..20].copy_from_slice(b"RGB ");
+        header[36..40].copy_from_slice(b"acsp");
+
+        let mut table = (tags.len() as u32).to_be_bytes().to_vec();
+        let mut offset = HEADER_SIZE + 4 + tags.len() * 12;
+        let mut data = Vec::new();
+        for (signature, body) in tags {
+            table.extend(*signature);
+            table.extend((offset as u32).to_be_bytes());
+            table.extend((body.len() as u32).to_be_bytes());
+            offset += body.len();
+            data.extend(body);
+        }
+        [header, table, data].concat()
+    }
+
+    fn matrix_profile(trc: Vec<u8>) -> Vec<u8> {
+        profile(&[
+            (b"rXYZ", xyz([0.4360, 0.2225, 0.0139])),
+            (b"gXYZ", xyz([0.3851, 0.7169, 0.0971])),
+            (b"bXYZ", xyz([0.1431, 0.0606, 0.7141])),
+            (b"rTRC", trc.clone()),
+            (b"gTRC", trc.clone()),
+            (b"bTRC", trc),
+        ])
+    }
+
+    #[test]
+    fn test_curv() {
+        assert_eq!(parse_curve(&curv(&[])).unwrap(), Curve::IDENTITY);
+        assert_eq!(
+            parse_curve(&curv(&[0x0233])).unwrap(),
+            Curve::Gamma(563. / 256.)
+        );
+        assert_eq!(
+            parse_curve(&curv(&[0, 0x8000, 0xffff])).unwrap(),
+            Curve::Table(vec![0, 0x8000, 0xffff])
+        );
+        // fewer entries than announced
+        let mut truncated = curv(&[0, 0x8000, 0xffff]);
+        truncated.truncate(truncated.len() - 2);
+        assert!(parse_curve(&truncated).is_err());
+    }
+
+    #[test]
+    fn test_para() {
+        let parse = |function, params: &[f64]| parse_curve(&para(function, params)).unwrap();
+        assert_eq!(
+            parse(0, &[2.]),
+            Curve::Parametric([2., 1., 0., 0., 0., 0., 0.])
+        );
+        assert_eq!(
+            parse(1, &[2., 2., -0.5]),
+            Curve::Parametric([2., 2., -0.5, 0., 0.25, 0., 0.])
+        );
+        assert_eq!(
+            parse(2, &[2., 2., -0.5, 0.25]),
+            Curve::Parametric([2., 2., -0.5, 0., 0.25, 0.25, 0.25])
+        );
+        assert_eq!(
+            parse(3, &[2., 1., 0., 0.5, 0.25]),
+            Curve::Parametric([2., 1., 0., 0.5, 0.25, 0., 0.])
+        );
+        assert_eq!(
+            parse(4, &[2., 1., 0., 0.5, 0.25, 0.125, 0.0625]),
+            Curve::Parametric([2., 1., 0., 0.5, 0.25, 0.125, 0.0625])
+        );
+        assert!(parse_curve(&para(5, &[2.])).is_err());
+        // missing parameters
+        assert!(parse_curve(&para(4, &[2., 1., 0.])).is_err());
+    }
+
+    #[test]
+    fn test_vcgt_table() {
+        let mut body = 0u32.to_be_bytes().to_vec();
+        body.extend([3u16, 2, 2].iter().flat_map(|val| val.to_be_bytes()));
+        body.extend(
+            [0u16, 0xffff, 0, 0x8000, 0x1000, 0xf000]
+                .iter()
+                .flat_map(|val| val.to_be_bytes()),
+        );
+        assert_eq!(
+            parse_vcgt(&tag(b"vcgt", &body)).unwrap(),
+            [
+                Curve::Table(vec![0, 0xffff]),
+                Curve::Table(vec![0, 0x8000]),
+                Curve::Table(vec![0x1000, 0xf000]),
+            ]
+        );
+
+        // single channel with byte sized entries
+        let mut body = 0u32.to_be_bytes().to_vec();
+        body.extend([1u16, 3, 1].iter().flat_map(|val| val.to_be_bytes()));
+        body.extend([0u8, 0x80, 0xff]);
+        let curve = Curve::Table(vec![0, 0x8080, 0xffff]);
+        assert_eq!(
+            parse_vcgt(&tag(b"vcgt", &body)).unwrap(),
+            [curve.clone(), curve.clone(), curve]
+        );
+    }
+
+    #[test]
+    fn test_vcgt_formula() {
+        let mut body = 1u32.to_be_bytes().to_vec();
+        for (gamma, min, max) in [(1., 0., 0.5), (1., 0.25, 1.), (2., 0., 1.)] {
+            body.extend([s15_16(gamma), s15_16(min), s15_16(max)].concat());
+        }
+        let [r, g, b] = parse_vcgt(&tag(b"vcgt", &body)).unwrap();
+        assert_eq!(r, Curve::Parametric([1., 0.5, 0., 0., 0., 0., 0.]));
+        assert_eq!(r.eval(1.), 0.5);
+        assert_eq!(g.eval(0.), 0.25);
+        assert_eq!(g.eval(1.), 1.);
+        assert_eq!(b.eval(0.5), 0.25);
+    }
+
+    #[test]
+    fn test_profile() {
+        let parsed = IccProfile::parse(&matrix_profile(para(0, &[2.]))).unwrap();
+        let [r, _, b] = parsed.colorants.unwrap();
+        assert!((r[0] - 0.4360).abs() < 0.001);
+        assert!((b[2] - 0.7141).abs() < 0.001);
+        assert_eq!(
+            parsed.trc,
+            Some([(); 3].map(|_| Curve::Parametric([2., 1., 0., 0., 0., 0., 0.])))
+        );
+        assert_eq!(parsed.vcgt, None);
+
+        // neither a complete matrix/TRC description nor calibration curves
+        let incomplete = profile(&[(b"rXYZ", xyz([0.4360, 0.2225, 0.0139]))]);
+        assert!(IccProfile::parse(&incomplete).is_err());
+    }
+
+    #[test]
+    fn test_truncated_tag_table() {
+        let data = matrix_profile(curv(&[]));
+        // cut off within the tag table
+        assert!(IccProfile::parse(&data[..HEADER_SIZE + 4 + 12 * 3]).is_err());
+        // cut off within the tag data
+        assert!(IccProfile::parse(&data[..data.len() - 1]).is_err());
+        // tag count exceeding the tag table
+        let mut data = data;
+        data[HEADER_SIZE..HEADER_SIZE + 4].copy_from_slice(&1000u32.to_be_bytes());
+        assert!(IccProfile::parse(&data).is_err());
+    }
+}
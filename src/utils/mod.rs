@@ -4,7 +4,9 @@ pub mod env;
 mod ids;
 pub(crate) use self::ids::id_gen;
 pub mod geometry;
+pub mod icc;
 pub mod iced;
+pub mod night_light;
 pub mod prelude;
 pub mod quirks;
 pub mod rlimit;
@@ -0,0 +1,245 @@
+// SPDX-License-Identifier: GPL-3.0-only
+
+use std::{f64::consts::PI, time::Duration};
+
+use calloop::timer::{TimeoutAction, Timer};
+use cosmic_comp_config::night_light::{NightLightConfig, NightLightSchedule};
+use tracing::warn;
+
+use crate::state::State;
+
+/// Color temperature of unfiltered output
+pub const NEUTRAL_TEMPERATURE: u32 = 6500;
+
+const MINUTES_PER_DAY: f64 = 1440.;
+// re-evaluation interval while transitioning and otherwise
+const TRANSITION_INTERVAL: Duration = Duration::from_secs(2);
+const IDLE_INTERVAL: Duration = Duration::from_secs(60);
+
+/// Channel multipliers approximating the white point of the given color temperature,
+/// normalized so that [`NEUTRAL_TEMPERATURE`] maps to white.
+pub fn temperature_to_rgb(temperature: u32) -> [f32; 3] {
+    // approximation of the planckian locus by Tanner Helland
+    let rgb = |temperature: u32| {
+        let t = temperature.clamp(1000, 40000) as f64 / 100.;
+        let r = if t <= 66. {
+            1.
+        } else {
+            329.698727446 * (t - 60.).powf(-0.1332047592) / 255.
+        };
+        let g = if t <= 66. {
+            (99.4708025861 * t.ln() - 161.1195681661) / 255.
+        } else {
+            288.1221695283 * (t - 60.).powf(-0.0755148492) / 255.
+        };
+        let b = if t >= 66. {
+            1.
+        } else if t <= 19. {
+            0.
+        } else {
+            (138.5177312231 * (t - 10.).ln() - 305.0447927307) / 255.
+        };
+        [r.clamp(0., 1.), g.clamp(0., 1.), b.clamp(0., 1.)]
+    };
+
+    let neutral = rgb(NEUTRAL_TEMPERATURE);
+    let color = rgb(temperature);
+    std::array::from_fn(|i| (color[i] / neutral[i]).min(1.) as f32)
+}
+
+enum Daylight {
+    /// Sunrise and sunset in minutes after midnight UTC
+    Times(f64, f64),
+    PolarDay,
+    PolarNight,
+}
+
+// NOAA's approximation of the solar position
+fn daylight(day_of_year: u32, latitude: f64, longitude: f64) -> Daylight {
+    let gamma = 2. * PI / 365. * (day_of_year as f64 - 0.5);
+    let eqtime = 229.18
+        * (0.000075 + 0.001868 * gamma.cos()
+            - 0.032077 * gamma.sin()
+            - 0.014615 * (2. * gamma).cos()
+            - 0.040849 * (2. * gamma).sin());
+    let decl = 0.006918 - 0.399912 * gamma.cos() + 0.070257 * gamma.sin()
+        - 0.006758 * (2. * gamma).cos()
+        + 0.000907 * (2. * gamma).sin()
+        - 0.002697 * (3. * gamma).cos()
+        + 0.00148 * (3. * gamma).sin();
+
+    let lat = latitude.to_radians();
+    // includes atmospheric refraction and the size of the solar disk
+    let cos_ha = 90.833f64.to_radians().cos() / (lat.cos() * decl.cos()) - lat.tan() * decl.tan();
+    if cos_ha > 1. {
+        return Daylight::PolarNight;
+    }
+    if cos_ha < -1. {
+        return Daylight::PolarDay;
+    }
+
+    let ha = cos_ha.acos().to_degrees();
+    Daylight::Times(
+        720. - 4. * (longitude + ha) - eqtime,
+        720. - 4. * (longitude - ha) - eqtime,
+    )
+}
+
+/// Strength of the night light between 0 (day) and 1 (night) at `now` minutes of the day,
+/// for a night from `start` to `end` with transitions of `duration` minutes centered on both.
+fn night_factor(now: f64, start: f64, end: f64, duration: f64) -> f64 {
+    let duration = duration.max(1.);
+    let ramp = |distance: f64| ((distance + duration / 2.) / duration).clamp(0., 1.);
+
+    let since_start = (now - start).rem_euclid(MINUTES_PER_DAY);
+    let night_length = (end - start).rem_euclid(MINUTES_PER_DAY);
+    if since_start < night_length {
+        ramp(since_start).min(ramp(night_length - since_start))
+    } else {
+        ramp(since_start - MINUTES_PER_DAY).max(ramp(night_length - since_start))
+    }
+}
+
+/// Current strength of the night light between 0 (inactive) and 1 (fully active)
+pub fn night_light_factor(config: &NightLightConfig) -> f64 {
+    if !config.enabled {
+        return 0.;
+    }
+
+    let now = jiff::Zoned::now();
+    let time = now.time();
+    let minutes = time.hour() as f64 * 60. + time.minute() as f64 + time.second() as f64 / 60.;
+    let duration = config.transition_duration as f64;
+    match config.schedule {
+        NightLightSchedule::Always => 1.,
+        NightLightSchedule::Fixed { start, end } => {
+            let minutes_of = |(hour, minute): (u8, u8)| hour as f64 * 60. + minute as f64;
+            night_factor(minutes, minutes_of(start), minutes_of(end), duration)
+        }
+        NightLightSchedule::SunsetToSunrise {
+            latitude,
+            longitude,
+        } => match daylight(now.day_of_year() as u32, latitude, longitude) {
+            Daylight::Times(sunrise, sunset) => {
+                let offset = now.offset().seconds() as f64 / 60.;
+                night_factor(minutes, sunset + offset, sunrise + offset, duration)
+            }
+            Daylight::PolarDay => 0.,
+            Daylight::PolarNight => 1.,
+        },
+    }
+}
+
+impl State {
+    /// Applies the current color temperature of the night light
+    /// and schedules the next update if necessary.
+    pub fn update_night_light(&mut self) {
+        if let Some(token) = self.common.night_light_timer.take() {
+            self.common.event_loop_handle.remove(token);
+        }
+
+        let config = self.common.config.cosmic_conf.night_light;
+        let factor = night_light_factor(&config);
+        let temperature = (factor > 0.).then(|| {
+            let neutral = NEUTRAL_TEMPERATURE as f64;
+            (neutral - (neutral - config.temperature as f64) * factor).round() as u32
+        });
+
+        let screen_filter = self.common.config.dynamic_conf.screen_filter();
+        if screen_filter.color_temperature != temperature {
+            let mut updated = screen_filter.clone();
+            updated.color_temperature = temperature;
+            if let Err(err) = self.backend.update_screen_filter(&updated) {
+                warn!("Failed to apply night light: {}", err);
+            } else {
+                self.common
+                    .config
+                    .dynamic_conf
+                    .set_color_temperature(temperature);
+            }
+        }
+
+        if !config.enabled || config.schedule == NightLightSchedule::Always {
+            return;
+        }
+        let interval = if factor > 0. && factor < 1. {
+            TRANSITION_INTERVAL
+        } else {
+            IDLE_INTERVAL
+        };
+        match self.common.event_loop_handle.insert_source(
+            Timer::from_duration(interval),
+            |_, _, state| {
+                state.common.night_light_timer = None;
+                state.update_night_light();
+                TimeoutAction::Drop
+            },
+        ) {
+            Ok(token) => self.common.night_light_timer = Some(token),
+            Err(err) => warn!(?err, "Failed to schedule night light update"),
+        }
+    }
+}
+
+#[cfg(test)]
+mod test {
+    use super::*;
+
+    const HOUR: f64 = 60.;
+
+    #[test]
+    fn test_temperature_to_rgb() {
+        assert_eq!(temperature_to_rgb(NEUTRAL_TEMPERATURE), [1.0; 3]);
+
+        let [r, g, b] = temperature_to_rgb(3000);
+        assert_eq!(r, 1.0);
+        assert!(b < g && g < 1.0);
+    }
+
+    #[test]
+    fn test_night_across_midnight() {
+        let (start, end) = (22. * HOUR, 6. * HOUR);
+        assert_eq!(night_factor(0., start, end, HOUR), 1.0);
+        assert_eq!(night_factor(3. * HOUR, start, end, HOUR), 1.0);
+        assert_eq!(night_factor(12. * HOUR, start, end, HOUR), 0.0);
+        assert_eq!(night_factor(21. * HOUR, start, end, HOUR), 0.0);
+    }
+
+    #[test]
+    fn test_transitions() {
+        let (start, end) = (22. * HOUR, 6. * HOUR);
+        // centered on the start of the night
+        assert_eq!(night_factor(start - 45., start, end, HOUR), 0.0);
+        assert_eq!(night_factor(start - 15., start, end, HOUR), 0.25);
+        assert_eq!(night_factor(start, start, end, HOUR), 0.5);
+        assert_eq!(night_factor(start + 15., start, end, HOUR), 0.75);
+        assert_eq!(night_factor(start + 30., start, end, HOUR), 1.0);
+        // and on its end
+        assert_eq!(night_factor(end - 30., start, end, HOUR), 1.0);
+        assert_eq!(night_factor(end - 15., start, end, HOUR), 0.75);
+        assert_eq!(night_factor(end, start, end, HOUR), 0.5);
+        assert_eq!(night_factor(end + 15., start, end, HOUR), 0.25);
+        assert_eq!(night_factor(end + 45., start, end, HOUR), 0.0);
+    }
+
+    #[test]
+    fn test_daylight() {
+        // around the june solstice
+        assert!(matches!(daylight(172, 80., 0.), Daylight::PolarDay));
+        assert!(matches!(daylight(172, -80., 0.), Daylight::PolarNight));
+        // around the december solstice
+        assert!(matches!(daylight(355, 80., 0.), Daylight::PolarNight));
+        assert!(matches!(daylight(355, -80., 0.), Daylight::PolarDay));
+
+        // roughly twelve hours of daylight at the equator, shifted by the longitude
+        let Daylight::Times(sunrise, sunset) = daylight(80, 0., 0.) else {
+            panic!("no sunrise at the equator");
+        };
+        assert!((sunrise - 6. * HOUR).abs() < 20.);
+        assert!((sunset - 18. * HOUR).abs() < 20.);
+        let Daylight::Times(east_sunrise, _) = daylight(80, 0., 90.) else {
+            panic!("no sunrise at the equator");
+        };
+        assert!((sunrise - east_sunrise - 6. * HOUR).abs() < 1.);
+    }
+}
@@ -0,0 +1,63 @@
+// SPDX-License-Identifier: GPL-3.0-only
+
+use cosmic_comp_config::output::comp::{ColorSpace, DEFAULT_SDR_WHITE_LEVEL};
+use smithay::{
+    desktop::utils::surface_primary_scanout_output,
+    output::Output,
+    reexports::wayland_server::protocol::wl_surface::WlSurface,
+    wayland::compositor::{get_parent, with_states},
+};
+
+use crate::{
+    state::State,
+    utils::prelude::{OutputExt, SeatExt},
+    wayland::protocols::color_management::{
+        ColorManagementHandler, ColorManagementState, ImageDescription, delegate_color_management,
+    },
+};
+
+impl ColorManagementHandler for State {
+    fn color_management_state(&mut self) -> &mut ColorManagementState {
+        &mut self.common.color_management_state
+    }
+
+    fn output_image_description(&self, output: &Output) -> ImageDescription {
+        let config = output.config();
+        match config.color_space {
+            ColorSpace::Srgb => ImageDescription::srgb(),
+            ColorSpace::Bt2020Pq => ImageDescription::bt2020_pq(
+                config.sdr_white_level.unwrap_or(DEFAULT_SDR_WHITE_LEVEL),
+            ),
+        }
+    }
+
+    fn preferred_image_description(&self, surface: &WlSurface) -> ImageDescription {
+        let mut root = surface.clone();
+        while let Some(parent) = get_parent(&root) {
+            root = parent;
+        }
+
+        let output = with_states(&root, |states| {
+            surface_primary_scanout_output(&root, states)
+        })
+        .or_else(|| {
+            self.common
+                .shell
+                .read()
+                .visible_output_for_surface(&root)
+                .cloned()
+        })
+        .unwrap_or_else(|| self.common.shell.read().seats.last_active().active_output());
+
+        self.output_image_description(&output)
+    }
+}
+
+impl State {
+    /// Notifies color managed clients about changed output or preferred image descriptions
+    pub fn refresh_color_management(&mut self) {
+        ColorManagementState::refresh(self);
+    }
+}
+
+delegate_color_management!(State);
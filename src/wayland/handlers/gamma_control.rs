@@ -0,0 +1,37 @@
+// SPDX-License-Identifier: GPL-3.0-only
+
+use smithay::output::Output;
+use tracing::warn;
+
+use crate::{
+    state::{BackendData, State},
+    wayland::protocols::gamma_control::{
+        GammaControlHandler, GammaControlState, delegate_gamma_control,
+    },
+};
+
+impl GammaControlHandler for State {
+    fn gamma_control_state(&mut self) -> &mut GammaControlState {
+        &mut self.common.gamma_control_state
+    }
+
+    fn gamma_size(&mut self, output: &Output) -> Option<u32> {
+        match &self.backend {
+            BackendData::Kms(kms_state) => kms_state.gamma_size(output).map(|size| size as u32),
+            _ => None,
+        }
+    }
+
+    fn set_gamma(&mut self, output: &Output, ramp: Option<Vec<u16>>) -> bool {
+        let BackendData::Kms(kms_state) = &mut self.backend else {
+            return false;
+        };
+        if let Err(err) = kms_state.set_gamma_ramp(output, ramp) {
+            warn!(?err, "Failed to set gamma ramps of {}", output.name());
+            return false;
+        }
+        true
+    }
+}
+
+delegate_gamma_control!(State);
@@ -0,0 +1,1027 @@
+// SPDX-License-Identifier: GPL-3.0-only
+
+use smithay::{
+    output::{Output, WeakOutput},
+    reexports::{
+        wayland_protocols::wp::color_management::v1::server::{
+            wp_color_management_output_v1::{self, WpColorManagementOutputV1},
+            wp_color_management_surface_feedback_v1::{self, WpColorManagementSurfaceFeedbackV1},
+            wp_color_management_surface_v1::{self, WpColorManagementSurfaceV1},
+            wp_color_manager_v1::{
+                self, Feature, Primaries, RenderIntent, TransferFunction, WpColorManagerV1,
+            },
+            wp_image_description_creator_icc_v1::WpImageDescriptionCreatorIccV1,
+            wp_image_description_creator_params_v1::{self, WpImageDescriptionCreatorParamsV1},
+            wp_image_description_info_v1::WpImageDescriptionInfoV1,
+            wp_image_description_v1::{self, WpImageDescriptionV1},
+        },
+        wayland_server::{
+            Client, DataInit, Dispatch, DisplayHandle, GlobalDispatch, New, Resource, WEnum, Weak,
+            protocol::wl_surface::WlSurface,
+        },
+    },
+    wayland::compositor::{Cacheable, with_states},
+};
+use std::sync::{
+    Mutex,
+    atomic::{AtomicBool, Ordering},
+};
+use wayland_backend::server::GlobalId;
+
+/// CIE 1931 xy chromaticity coordinates, multiplied by 1_000_000
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Hash)]
+pub struct Chromaticities {
+    pub red: (i32, i32),
+    pub green: (i32, i32),
+    pub blue: (i32, i32),
+    pub white: (i32, i32),
+}
+
+const D65: (i32, i32) = (312_700, 329_000);
+
+impl Chromaticities {
+    pub const SRGB: Chromaticities = Chromaticities {
+        red: (640_000, 330_000),
+        green: (300_000, 600_000),
+        blue: (150_000, 60_000),
+        white: D65,
+    };
+    pub const BT2020: Chromaticities = Chromaticities {
+        red: (708_000, 292_000),
+        green: (170_000, 797_000),
+        blue: (131_000, 46_000),
+        white: D65,
+    };
+
+    pub fn named(primaries: Primaries) -> Option<Chromaticities> {
+        Some(match primaries {
+            Primaries::Srgb => Self::SRGB,
+            Primaries::Bt2020 => Self::BT2020,
+            Primaries::DisplayP3 => Chromaticities {
+                red: (680_000, 320_000),
+                green: (265_000, 690_000),
+                blue: (150_000, 60_000),
+                white: D65,
+            },
+            Primaries::DciP3 => Chromaticities {
+                red: (680_000, 320_000),
+                green: (265_000, 690_000),
+                blue: (150_000, 60_000),
+                white: (314_000, 351_000),
+            },
+            Primaries::AdobeRgb => Chromaticities {
+                red: (640_000, 330_000),
+                green: (210_000, 710_000),
+                blue: (150_000, 60_000),
+                white: D65,
+            },
+            _ => return None,
+        })
+    }
+}
+
+/// Luminances as described by the protocol, `min` in 0.0001 cd/m², the rest in cd/m²
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Hash)]
+pub struct Luminances {
+    pub min: u32,
+    pub max: u32,
+    pub reference: u32,
+}
+
+impl Luminances {
+    pub fn default_for(tf: TransferFunction) -> Luminances {
+        match tf {
+            TransferFunction::St2084Pq => Luminances {
+                min: 50,
+                max: 10_000,
+                reference: 203,
+            },
+            _ => Luminances {
+                min: 2_000,
+                max: 80,
+                reference: 80,
+            },
+        }
+    }
+}
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Hash)]
+pub struct ImageDescription {
+    pub tf: TransferFunction,
+    pub primaries: Chromaticities,
+    pub named_primaries: Option<Primaries>,
+    pub luminances: Luminances,
+    pub mastering_primaries: Option<Chromaticities>,
+    pub mastering_luminance: Option<(u32, u32)>,
+    pub max_cll: Option<u32>,
+    pub max_fall: Option<u32>,
+}
+
+impl ImageDescription {
+    pub fn srgb() -> ImageDescription {
+        ImageDescription {
+            tf: TransferFunction::Srgb,
+            primaries: Chromaticities::SRGB,
+            named_primaries: Some(Primaries::Srgb),
+            luminances: Luminances::default_for(TransferFunction::Srgb),
+            mastering_primaries: None,
+            mastering_luminance: None,
+            max_cll: None,
+            max_fall: None,
+        }
+    }
+
+    pub fn bt2020_pq(sdr_white_level: u32) -> ImageDescription {
+        ImageDescription {
+            tf: TransferFunction::St2084Pq,
+            primaries: Chromaticities::BT2020,
+            named_primaries: Some(Primaries::Bt2020),
+            luminances: Luminances {
+                reference: sdr_white_level,
+                ..Luminances::default_for(TransferFunction::St2084Pq)
+            },
+            mastering_primaries: None,
+            mastering_luminance: None,
+            max_cll: None,
+            max_fall: None,
+        }
+    }
+
+    /// Whether content with this description can be composited without any conversion
+    pub fn is_srgb(&self) -> bool {
+        matches!(self.tf, TransferFunction::Srgb | TransferFunction::Gamma22)
+            && self.primaries == Chromaticities::SRGB
+            && self.luminances.max == self.luminances.reference
+    }
+}
+
+pub const SUPPORTED_TRANSFER_FUNCTIONS: [TransferFunction; 5] = [
+    TransferFunction::Srgb,
+    TransferFunction::Gamma22,
+    TransferFunction::Bt1886,
+    TransferFunction::ExtLinear,
+    TransferFunction::St2084Pq,
+];
+pub const SUPPORTED_PRIMARIES: [Primaries; 5] = [
+    Primaries::Srgb,
+    Primaries::Bt2020,
+    Primaries::DisplayP3,
+    Primaries::DciP3,
+    Primaries::AdobeRgb,
+];
+
+/// Image description of a surface, applied on commit
+#[derive(Default, Debug, Copy, Clone)]
+pub struct CacheableImageDescription(pub Option<ImageDescription>);
+
+impl Cacheable for CacheableImageDescription {
+    fn commit(&mut self, _dh: &DisplayHandle) -> Self {
+        *self
+    }
+    fn merge_into(self, into: &mut Self, _dh: &DisplayHandle) {
+        *into = self;
+    }
+}
+
+/// Returns the image description currently attached to a surface, if any
+pub fn surface_image_description(surface: &WlSurface) -> Option<ImageDescription> {
+    with_states(surface, |states| {
+        states
+            .cached_state
+            .get::<CacheableImageDescription>()
+            .current()
+            .0
+    })
+}
+
+#[derive(Debug)]
+pub struct ColorManagementState {
+    global: GlobalId,
+    outputs: Vec<WpColorManagementOutputV1>,
+    feedbacks: Vec<WpColorManagementSurfaceFeedbackV1>,
+    next_identity: u32,
+}
+
+impl ColorManagementState {
+    pub fn new<D>(dh: &DisplayHandle) -> ColorManagementState
+    where
+        D: GlobalDispatch<WpColorManagerV1, ()>
+            + Dispatch<WpColorManagerV1, ()>
+            + ColorManagementHandler
+            + 'static,
+    {
+        let global = dh.create_global::<D, WpColorManagerV1, _>(1, ());
+        ColorManagementState {
+            global,
+            outputs: Vec::new(),
+            feedbacks: Vec::new(),
+            next_identity: 1,
+        }
+    }
+
+    pub fn global_id(&self) -> GlobalId {
+        self.global.clone()
+    }
+
+    /// Hands out a new identity, without keeping track of the description it belongs to
+    fn new_identity(&mut self) -> u32 {
+        let identity = self.next_identity;
+        self.next_identity = self.next_identity.checked_add(1).unwrap_or(1);
+        identity
+    }
+
+    /// Identity of `description`, reusing the one of the last announced description if it matches
+    fn identity(&mut self, announced: &Announced, description: &ImageDescription) -> u32 {
+        match *announced.0.lock().unwrap() {
+            Some((last, identity)) if last == *description => identity,
+            _ => self.new_identity(),
+        }
+    }
+
+    /// Records `description` as announced, returning its identity and if it changed
+    fn announce(&mut self, announced: &Announced, description: ImageDescription) -> (u32, bool) {
+        let mut announced = announced.0.lock().unwrap();
+        match *announced {
+            Some((last, identity)) if last == description => (identity, false),
+            _ => {
+                let identity = self.new_identity();
+                *announced = Some((description, identity));
+                (identity, true)
+            }
+        }
+    }
+
+    /// Re-evaluates output and preferred surface descriptions and notifies clients about changes.
+    pub fn refresh<D: ColorManagementHandler>(state: &mut D) {
+        let outputs = state.color_management_state().outputs.clone();
+        for obj in outputs {
+            let data = obj.data::<ColorOutputData>().unwrap();
+            let Some(output) = data.output.as_ref().and_then(WeakOutput::upgrade) else {
+                continue;
+            };
+            let description = state.output_image_description(&output);
+            let (_, changed) = state
+                .color_management_state()
+                .announce(&data.announced, description);
+            if changed {
+                obj.image_description_changed();
+            }
+        }
+
+        let feedbacks = state.color_management_state().feedbacks.clone();
+        for obj in feedbacks {
+            let data = obj.data::<ColorFeedbackData>().unwrap();
+            let Ok(surface) = data.surface.upgrade() else {
+                continue;
+            };
+            let description = state.preferred_image_description(&surface);
+            let (identity, changed) = state
+                .color_management_state()
+                .announce(&data.announced, description);
+            if changed {
+                obj.preferred_changed(identity);
+            }
+        }
+    }
+}
+
+pub trait ColorManagementHandler {
+    fn color_management_state(&mut self) -> &mut ColorManagementState;
+    fn output_image_description(&self, output: &Output) -> ImageDescription;
+    fn preferred_image_description(&self, surface: &WlSurface) -> ImageDescription;
+}
+
+#[derive(Debug)]
+pub struct ColorOutputData {
+    output: Option<WeakOutput>,
+    announced: Announced,
+}
+
+#[derive(Debug)]
+pub struct ColorSurfaceData {
+    surface: Weak<WlSurface>,
+}
+
+#[derive(Debug)]
+pub struct ColorFeedbackData {
+    surface: Weak<WlSurface>,
+    announced: Announced,
+}
+
+/// Image description last announced to a client, with its identity
+#[derive(Debug, Default)]
+struct Announced(Mutex<Option<(ImageDescription, u32)>>);
+
+#[derive(Debug)]
+pub struct ImageDescriptionData {
+    description: Option<ImageDescription>,
+    /// Only descriptions handed out by the compositor may be inspected by clients
+    allow_information: bool,
+}
+
+impl ImageDescriptionData {
+    pub fn description(&self) -> Option<&ImageDescription> {
+        self.description.as_ref()
+    }
+}
+
+#[derive(Debug, Default)]
+pub struct ParamsBuilder {
+    tf: Option<TransferFunction>,
+    primaries: Option<(Chromaticities, Option<Primaries>)>,
+    luminances: Option<Luminances>,
+    mastering_primaries: Option<Chromaticities>,
+    mastering_luminance: Option<(u32, u32)>,
+    max_cll: Option<u32>,
+    max_fall: Option<u32>,
+}
+
+pub type ParamsCreatorData = Mutex<ParamsBuilder>;
+
+#[derive(Debug, Default)]
+struct SurfaceHasColorManagement(AtomicBool);
+
+impl<D> GlobalDispatch<WpColorManagerV1, (), D> for ColorManagementState
+where
+    D: GlobalDispatch<WpColorManagerV1, ()>
+        + Dispatch<WpColorManagerV1, ()>
+        + ColorManagementHandler
+        + 'static,
+{
+    fn bind(
+        _state: &mut D,
+        _dh: &DisplayHandle,
+        _client: &Client,
+        resource: New<WpColorManagerV1>,
+        _global_data: &(),
+        data_init: &mut DataInit<'_, D>,
+    ) {
+        let manager = data_init.init(resource, ());
+        manager.supported_intent(RenderIntent::Perceptual);
+        for feature in [
+            Feature::Parametric,
+            Feature::SetPrimaries,
+            Feature::SetLuminances,
+            Feature::SetMasteringDisplayPrimaries,
+        ] {
+            manager.supported_feature(feature);
+        }
+        for tf in SUPPORTED_TRANSFER_FUNCTIONS {
+            manager.supported_tf_named(tf);
+        }
+        for primaries in SUPPORTED_PRIMARIES {
+            manager.supported_primaries_named(primaries);
+        }
+        manager.done();
+    }
+}
+
+impl<D> Dispatch<WpColorManagerV1, (), D> for ColorManagementState
+where
+    D: Dispatch<WpColorManagerV1, ()>
+        + Dispatch<WpColorManagementOutputV1, ColorOutputData>
+        + Dispatch<WpColorManagementSurfaceV1, ColorSurfaceData>
+        + Dispatch<WpColorManagementSurfaceFeedbackV1, ColorFeedbackData>
+        + Dispatch<WpImageDescriptionCreatorIccV1, ()>
+        + Dispatch<WpImageDescriptionCreatorParamsV1, ParamsCreatorData>
+        + Dispatch<WpImageDescriptionV1, ImageDescriptionData>
+        + ColorManagementHandler
+        + 'static,
+{
+    fn request(
+        state: &mut D,
+        _client: &Client,
+        resource: &WpColorManagerV1,
+        request: <WpColorManagerV1 as Resource>::Request,
+        _data: &(),
+        _dh: &DisplayHandle,
+        data_init: &mut DataInit<'_, D>,
+    ) {
+        match request {
+            wp_color_manager_v1::Request::GetOutput { id, output } => {
+                let output = Output::from_resource(&output);
+                let announced = Announced::default();
+                if let Some(output) = output.as_ref() {
+                    let description = state.output_image_description(output);
+                    state
+                        .color_management_state()
+                        .announce(&announced, description);
+                }
+                let obj = data_init.init(
+                    id,
+                    ColorOutputData {
+                        output: output.map(|o| o.downgrade()),
+                        announced,
+                    },
+                );
+                state.color_management_state().outputs.push(obj);
+            }
+            wp_color_manager_v1::Request::GetSurface { id, surface } => {
+                let exists = with_states(&surface, |states| {
+                    states
+                        .data_map
+                        .get_or_insert_threadsafe(SurfaceHasColorManagement::default)
+                        .0
+                        .swap(true, Ordering::AcqRel)
+                });
+                data_init.init(
+                    id,
+                    ColorSurfaceData {
+                        surface: surface.downgrade(),
+                    },
+                );
+                if exists {
+                    resource.post_error(
+                        wp_color_manager_v1::Error::SurfaceExists as u32,
+                        format!("{surface:?} already has a color management surface"),
+                    );
+                }
+            }
+            wp_color_manager_v1::Request::GetSurfaceFeedback { id, surface } => {
+                let description = state.preferred_image_description(&surface);
+                let announced = Announced::default();
+                state
+                    .color_management_state()
+                    .announce(&announced, description);
+                let obj = data_init.init(
+                    id,
+                    ColorFeedbackData {
+                        surface: surface.downgrade(),
+                        announced,
+                    },
+                );
+                state.color_management_state().feedbacks.push(obj);
+            }
+            wp_color_manager_v1::Request::CreateIccCreator { obj } => {
+                data_init.init(obj, ());
+                resource.post_error(
+                    wp_color_manager_v1::Error::UnsupportedFeature as u32,
+                    "ICC profiles are not supported",
+                );
+            }
+            wp_color_manager_v1::Request::CreateParametricCreator { obj } => {
+                data_init.init(obj, Mutex::new(ParamsBuilder::default()));
+            }
+            wp_color_manager_v1::Request::CreateWindowsScrgb { image_description } => {
+                data_init.init(
+                    image_description,
+                    ImageDescriptionData {
+                        description: None,
+                        allow_information: false,
+                    },
+                );
+                resource.post_error(
+                    wp_color_manager_v1::Error::UnsupportedFeature as u32,
+                    "scRGB image descriptions are not supported",
+                );
+            }
+            wp_color_manager_v1::Request::Destroy => {}
+            _ => unimplemented!(),
+        }
+    }
+}
+
+impl<D> Dispatch<WpColorManagementOutputV1, ColorOutputData, D> for ColorManagementState
+where
+    D: Dispatch<WpColorManagementOutputV1, ColorOutputData>
+        + Dispatch<WpImageDescriptionV1, ImageDescriptionData>
+        + ColorManagementHandler
+        + 'static,
+{
+    fn request(
+        state: &mut D,
+        _client: &Client,
+        _resource: &WpColorManagementOutputV1,
+        request: <WpColorManagementOutputV1 as Resource>::Request,
+        data: &ColorOutputData,
+        _dh: &DisplayHandle,
+        data_init: &mut DataInit<'_, D>,
+    ) {
+        match request {
+            wp_color_management_output_v1::Request::GetImageDescription { image_description } => {
+                let Some(output) = data.output.as_ref().and_then(WeakOutput::upgrade) else {
+                    let obj = data_init.init(
+                        image_description,
+                        ImageDescriptionData {
+                            description: None,
+                            allow_information: false,
+                        },
+                    );
+                    obj.failed(
+                        wp_image_description_v1::Cause::NoOutput,
+                        "Output was removed".into(),
+                    );
+                    return;
+                };
+                let description = state.output_image_description(&output);
+                let identity = state
+                    .color_management_state()
+                    .identity(&data.announced, &description);
+                init_description(image_description, description, identity, true, data_init);
+            }
+            wp_color_management_output_v1::Request::Destroy => {}
+            _ => unimplemented!(),
+        }
+    }
+
+    fn destroyed(
+        state: &mut D,
+        _client: wayland_backend::server::ClientId,
+        resource: &WpColorManagementOutputV1,
+        _data: &ColorOutputData,
+    ) {
+        state
+            .color_management_state()
+            .outputs
+            .retain(|o| o != resource);
+    }
+}
+
+impl<D> Dispatch<WpColorManagementSurfaceV1, ColorSurfaceData, D> for ColorManagementState
+where
+    D: Dispatch<WpColorManagementSurfaceV1, ColorSurfaceData> + ColorManagementHandler + 'static,
+{
+    fn request(
+        _state: &mut D,
+        _client: &Client,
+        resource: &WpColorManagementSurfaceV1,
+        request: <WpColorManagementSurfaceV1 as Resource>::Request,
+        data: &ColorSurfaceData,
+        _dh: &DisplayHandle,
+        _data_init: &mut DataInit<'_, D>,
+    ) {
+        let surface = data.surface.upgrade().ok();
+        let pending = match request {
+            wp_color_management_surface_v1::Request::SetImageDescription {
+                image_description,
+                render_intent,
+            } => {
+                if render_intent != WEnum::Value(RenderIntent::Perceptual) {
+                    resource.post_error(
+                        wp_color_management_surface_v1::Error::RenderIntent as u32,
+                        format!("Unsupported render intent {render_intent:?}"),
+                    );
+                    return;
+                }
+                let Some(description) = image_description
+                    .data::<ImageDescriptionData>()
+                    .and_then(|data| data.description)
+                else {
+                    resource.post_error(
+                        wp_color_management_surface_v1::Error::ImageDescription as u32,
+                        format!("{image_description:?} is not ready"),
+                    );
+                    return;
+                };
+                Some(description)
+            }
+            wp_color_management_surface_v1::Request::UnsetImageDescription => None,
+            wp_color_management_surface_v1::Request::Destroy => {
+                if let Some(surface) = surface {
+                    with_states(&surface, |states| {
+                        *states
+                            .cached_state
+                            .get::<CacheableImageDescription>()
+                            .pending() = CacheableImageDescription(None);
+                        if let Some(marker) = states.data_map.get::<SurfaceHasColorManagement>() {
+                            marker.0.store(false, Ordering::Release);
+                        }
+                    });
+                }
+                return;
+            }
+            _ => unimplemented!(),
+        };
+
+        let Some(surface) = surface else {
+            resource.post_error(
+                wp_color_management_surface_v1::Error::Inert as u32,
+                "Surface was destroyed",
+            );
+            return;
+        };
+        with_states(&surface, |states| {
+            *states
+                .cached_state
+                .get::<CacheableImageDescription>()
+                .pending() = CacheableImageDescription(pending);
+        });
+    }
+}
+
+impl<D> Dispatch<WpColorManagementSurfaceFeedbackV1, ColorFeedbackData, D> for ColorManagementState
+where
+    D: Dispatch<WpColorManagementSurfaceFeedbackV1, ColorFeedbackData>
+        + Dispatch<WpImageDescriptionV1, ImageDescriptionData>
+        + ColorManagementHandler
+        + 'static,
+{
+    fn request(
+        state: &mut D,
+        _client: &Client,
+        resource: &WpColorManagementSurfaceFeedbackV1,
+        request: <WpColorManagementSurfaceFeedbackV1 as Resource>::Request,
+        data: &ColorFeedbackData,
+        _dh: &DisplayHandle,
+        data_init: &mut DataInit<'_, D>,
+    ) {
+        match request {
+            wp_color_management_surface_feedback_v1::Request::GetPreferred {
+                image_description,
+            }
+            | wp_color_management_surface_feedback_v1::Request::GetPreferredParametric {
+                image_description,
+            } => {
+                let Ok(surface) = data.surface.upgrade() else {
+                    data_init.init(
+                        image_description,
+                        ImageDescriptionData {
+                            description: None,
+                            allow_information: false,
+                        },
+                    );
+                    resource.post_error(
+                        wp_color_management_surface_feedback_v1::Error::Inert as u32,
+                        "Surface was destroyed",
+                    );
+                    return;
+                };
+                let description = state.preferred_image_description(&surface);
+                let identity = state
+                    .color_management_state()
+                    .identity(&data.announced, &description);
+                init_description(image_description, description, identity, true, data_init);
+            }
+            wp_color_management_surface_feedback_v1::Request::Destroy => {}
+            _ => unimplemented!(),
+        }
+    }
+
+    fn destroyed(
+        state: &mut D,
+        _client: wayland_backend::server::ClientId,
+        resource: &WpColorManagementSurfaceFeedbackV1,
+        _data: &ColorFeedbackData,
+    ) {
+        state
+            .color_management_state()
+            .feedbacks
+            .retain(|f| f != resource);
+    }
+}
+
+impl<D> Dispatch<WpImageDescriptionCreatorIccV1, (), D> for ColorManagementState
+where
+    D: Dispatch<WpImageDescriptionCreatorIccV1, ()> + 'static,
+{
+    fn request(
+        _state: &mut D,
+        _client: &Client,
+        _resource: &WpImageDescriptionCreatorIccV1,
+        _request: <WpImageDescriptionCreatorIccV1 as Resource>::Request,
+        _data: &(),
+        _dh: &DisplayHandle,
+        _data_init: &mut DataInit<'_, D>,
+    ) {
+        // The client already received a protocol error when creating this object.
+    }
+}
+
+impl<D> Dispatch<WpImageDescriptionCreatorParamsV1, ParamsCreatorData, D> for ColorManagementState
+where
+    D: Dispatch<WpImageDescriptionCreatorParamsV1, ParamsCreatorData>
+        + Dispatch<WpImageDescriptionV1, ImageDescriptionData>
+        + ColorManagementHandler
+        + 'static,
+{
+    fn request(
+        state: &mut D,
+        _client: &Client,
+        resource: &WpImageDescriptionCreatorParamsV1,
+        request: <WpImageDescriptionCreatorParamsV1 as Resource>::Request,
+        data: &ParamsCreatorData,
+        _dh: &DisplayHandle,
+        data_init: &mut DataInit<'_, D>,
+    ) {
+        use wp_image_description_creator_params_v1::{Error, Request};
+
+        let mut builder = data.lock().unwrap();
+        let already_set = |resource: &WpImageDescriptionCreatorParamsV1, what: &str| {
+            resource.post_error(Error::AlreadySet as u32, format!("{what} was already set"));
+        };
+
+        match request {
+            Request::SetTfNamed { tf } => {
+                if builder.tf.is_some() {
+                    return already_set(resource, "transfer function");
+                }
+                match tf {
+                    WEnum::Value(tf) if SUPPORTED_TRANSFER_FUNCTIONS.contains(&tf) => {
+                        builder.tf = Some(tf);
+                    }
+                    tf => resource.post_error(
+                        Error::InvalidTf as u32,
+                        format!("Unsupported transfer function {tf:?}"),
+                    ),
+                }
+            }
+            Request::SetTfPower { .. } => {
+                resource.post_error(
+                    Error::UnsupportedFeature as u32,
+                    "Power transfer functions are not supported",
+                );
+            }
+            Request::SetPrimariesNamed { primaries } => {
+                if builder.primaries.is_some() {
+                    return already_set(resource, "primaries");
+                }
+                match primaries {
+                    WEnum::Value(named) if SUPPORTED_PRIMARIES.contains(&named) => {
+                        builder.primaries = Chromaticities::named(named).map(|c| (c, Some(named)));
+                    }
+                    primaries => resource.post_error(
+                        Error::InvalidPrimariesNamed as u32,
+                        format!("Unsupported primaries {primaries:?}"),
+                    ),
+                }
+            }
+            Request::SetPrimaries {
+                r_x,
+                r_y,
+                g_x,
+                g_y,
+                b_x,
+                b_y,
+                w_x,
+                w_y,
+            } => {
+                if builder.primaries.is_some() {
+                    return already_set(resource, "primaries");
+                }
+                builder.primaries = Some((
+                    Chromaticities {
+                        red: (r_x, r_y),
+                        green: (g_x, g_y),
+                        blue: (b_x, b_y),
+                        white: (w_x, w_y),
+                    },
+                    None,
+                ));
+            }
+            Request::SetLuminances {
+                min_lum,
+                max_lum,
+                reference_lum,
+            } => {
+                if builder.luminances.is_some() {
+                    return already_set(resource, "luminances");
+                }
+                // `min_lum` is in 0.0001 cd/m², the others in cd/m²
+                if max_lum as u64 * 10_000 <= min_lum as u64
+                    || reference_lum as u64 * 10_000 <= min_lum as u64
+                {
+                    resource.post_error(
+                        Error::InvalidLuminance as u32,
+                        "Maximum and reference luminance must exceed the minimum",
+                    );
+                    return;
+                }
+                builder.luminances = Some(Luminances {
+                    min: min_lum,
+                    max: max_lum,
+                    reference: reference_lum,
+                });
+            }
+            Request::SetMasteringDisplayPrimaries {
+                r_x,
+                r_y,
+                g_x,
+                g_y,
+                b_x,
+                b_y,
+                w_x,
+                w_y,
+            } => {
+                if builder.mastering_primaries.is_some() {
+                    return already_set(resource, "mastering display primaries");
+                }
+                builder.mastering_primaries = Some(Chromaticities {
+                    red: (r_x, r_y),
+                    green: (g_x, g_y),
+                    blue: (b_x, b_y),
+                    white: (w_x, w_y),
+                });
+            }
+            Request::SetMasteringLuminance { min_lum, max_lum } => {
+                if builder.mastering_luminance.is_some() {
+                    return already_set(resource, "mastering luminance");
+                }
+                if max_lum as u64 * 10_000 <= min_lum as u64 {
+                    resource.post_error(
+                        Error::InvalidLuminance as u32,
+                        "Maximum mastering luminance must exceed the minimum",
+                    );
+                    return;
+                }
+                builder.mastering_luminance = Some((min_lum, max_lum));
+            }
+            Request::SetMaxCll { max_cll } => {
+                if builder.max_cll.is_some() {
+                    return already_set(resource, "max_cll");
+                }
+                builder.max_cll = Some(max_cll);
+            }
+            Request::SetMaxFall { max_fall } => {
+                if builder.max_fall.is_some() {
+                    return already_set(resource, "max_fall");
+                }
+                builder.max_fall = Some(max_fall);
+            }
+            Request::Create { image_description } => {
+                let (Some(tf), Some((primaries, named_primaries))) =
+                    (builder.tf, builder.primaries)
+                else {
+                    data_init.init(
+                        image_description,
+                        ImageDescriptionData {
+                            description: None,
+                            allow_information: false,
+                        },
+                    );
+                    resource.post_error(
+                        Error::IncompleteSet as u32,
+                        "Transfer function and primaries are required",
+                    );
+                    return;
+                };
+                let description = ImageDescription {
+                    tf,
+                    primaries,
+                    named_primaries,
+                    luminances: builder
+                        .luminances
+                        .unwrap_or_else(|| Luminances::default_for(tf)),
+                    mastering_primaries: builder.mastering_primaries,
+                    mastering_luminance: builder.mastering_luminance,
+                    max_cll: builder.max_cll,
+                    max_fall: builder.max_fall,
+                };
+                std::mem::drop(builder);
+                let identity = state.color_management_state().new_identity();
+                init_description(image_description, description, identity, false, data_init);
+            }
+            _ => unimplemented!(),
+        }
+    }
+}
+
+impl<D> Dispatch<WpImageDescriptionV1, ImageDescriptionData, D> for ColorManagementState
+where
+    D: Dispatch<WpImageDescriptionV1, ImageDescriptionData>
+        + Dispatch<WpImageDescriptionInfoV1, ()>
+        + 'static,
+{
+    fn request(
+        _state: &mut D,
+        _client: &Client,
+        resource: &WpImageDescriptionV1,
+        request: <WpImageDescriptionV1 as Resource>::Request,
+        data: &ImageDescriptionData,
+        _dh: &DisplayHandle,
+        data_init: &mut DataInit<'_, D>,
+    ) {
+        match request {
+            wp_image_description_v1::Request::GetInformation { information } => {
+                let info = data_init.init(information, ());
+                let Some(description) = data.description.as_ref() else {
+                    resource.post_error(
+                        wp_image_description_v1::Error::NotReady as u32,
+                        "Image description is not ready",
+                    );
+                    return;
+                };
+                if !data.allow_information {
+                    resource.post_error(
+                        wp_image_description_v1::Error::NoInformation as u32,
+                        "Information is only available for compositor provided descriptions",
+                    );
+                    return;
+                }
+                send_information(&info, description);
+            }
+            wp_image_description_v1::Request::Destroy => {}
+            _ => unimplemented!(),
+        }
+    }
+}
+
+impl<D> Dispatch<WpImageDescriptionInfoV1, (), D> for ColorManagementState
+where
+    D: Dispatch<WpImageDescriptionInfoV1, ()> + 'static,
+{
+    fn request(
+        _state: &mut D,
+        _client: &Client,
+        _resource: &WpImageDescriptionInfoV1,
+        _request: <WpImageDescriptionInfoV1 as Resource>::Request,
+        _data: &(),
+        _dh: &DisplayHandle,
+        _data_init: &mut DataInit<'_, D>,
+    ) {
+    }
+}
+
+fn init_description<D>(
+    id: New<WpImageDescriptionV1>,
+    description: ImageDescription,
+    identity: u32,
+    allow_information: bool,
+    data_init: &mut DataInit<'_, D>,
+) where
+    D: Dispatch<WpImageDescriptionV1, ImageDescriptionData> + 'static,
+{
+    let obj = data_init.init(
+        id,
+        ImageDescriptionData {
+            description: Some(description),
+            allow_information,
+        },
+    );
+    obj.ready(identity);
+}
+
+fn coordinates(primaries: &Chromaticities) -> [i32; 8] {
+    [
+        primaries.red.0,
+        primaries.red.1,
+        primaries.green.0,
+        primaries.green.1,
+        primaries.blue.0,
+        primaries.blue.1,
+        primaries.white.0,
+        primaries.white.1,
+    ]
+}
+
+fn send_information(info: &WpImageDescriptionInfoV1, description: &ImageDescription) {
+    let [r_x, r_y, g_x, g_y, b_x, b_y, w_x, w_y] = coordinates(&description.primaries);
+    info.primaries(r_x, r_y, g_x, g_y, b_x, b_y, w_x, w_y);
+    if let Some(named) = description.named_primaries {
+        info.primaries_named(named);
+    }
+    info.tf_named(description.tf);
+    let luminances = description.luminances;
+    info.luminances(luminances.min, luminances.max, luminances.reference);
+
+    let [r_x, r_y, g_x, g_y, b_x, b_y, w_x, w_y] = coordinates(
+        description
+            .mastering_primaries
+            .as_ref()
+            .unwrap_or(&description.primaries),
+    );
+    info.target_primaries(r_x, r_y, g_x, g_y, b_x, b_y, w_x, w_y);
+    let (min, max) = description
+        .mastering_luminance
+        .unwrap_or((luminances.min, luminances.max));
+    info.target_luminance(min, max);
+    if let Some(max_cll) = description.max_cll {
+        info.target_max_cll(max_cll);
+    }
+    if let Some(max_fall) = description.max_fall {
+        info.target_max_fall(max_fall);
+    }
+    info.done();
+}
+
+macro_rules! delegate_color_management {
+    ($(@<$( $lt:tt $( : $clt:tt $(+ $dlt:tt )* )? ),+>)? $ty: ty) => {
+        smithay::reexports::wayland_server::delegate_global_dispatch!($(@< $( $lt $( : $clt $(+ $dlt )* )? ),+ >)? $ty: [
+            smithay::reexports::wayland_protocols::wp::color_management::v1::server::wp_color_manager_v1::WpColorManagerV1: ()
+        ] => $crate::wayland::protocols::color_management::ColorManagementState);
+        smithay::reexports::wayland_server::delegate_dispatch!($(@< $( $lt $( : $clt $(+ $dlt )* )? ),+ >)? $ty: [
+            smithay::reexports::wayland_protocols::wp::color_management::v1::server::wp_color_manager_v1::WpColorManagerV1: ()
+        ] => $crate::wayland::protocols::color_management::ColorManagementState);
+        smithay::reexports::wayland_server::delegate_dispatch!($(@< $( $lt $( : $clt $(+ $dlt )* )? ),+ >)? $ty: [
+            smithay::reexports::wayland_protocols::wp::color_management::v1::server::wp_color_management_output_v1::WpColorManagementOutputV1: $crate::wayland::protocols::color_management::ColorOutputData
+        ] => $crate::wayland::protocols::color_management::ColorManagementState);
+        smithay::reexports::wayland_server::delegate_dispatch!($(@< $( $lt $( : $clt $(+ $dlt )* )? ),+ >)? $ty: [
+            smithay::reexports::wayland_protocols::wp::color_management::v1::server::wp_color_management_surface_v1::WpColorManagementSurfaceV1: $crate::wayland::protocols::color_management::ColorSurfaceData
+        ] => $crate::wayland::protocols::color_management::ColorManagementState);
+        smithay::reexports::wayland_server::delegate_dispatch!($(@< $( $lt $( : $clt $(+ $dlt )* )? ),+ >)? $ty: [
+            smithay::reexports::wayland_protocols::wp::color_management::v1::server::wp_color_management_surface_feedback_v1::WpColorManagementSurfaceFeedbackV1: $crate::wayland::protocols::color_management::ColorFeedbackData
+        ] => $crate::wayland::protocols::color_management::ColorManagementState);
+        smithay::reexports::wayland_server::delegate_dispatch!($(@< $( $lt $( : $clt $(+ $dlt )* )? ),+ >)? $ty: [
+            smithay::reexports::wayland_protocols::wp::color_management::v1::server::wp_image_description_creator_icc_v1::WpImageDescriptionCreatorIccV1: ()
+        ] => $crate::wayland::protocols::color_management::ColorManagementState);
+        smithay::reexports::wayland_server::delegate_dispatch!($(@< $( $lt $( : $clt $(+ $dlt )* )? ),+ >)? $ty: [
+            smithay::reexports::wayland_protocols::wp::color_management::v1::server::wp_image_description_creator_params_v1::WpImageDescriptionCreatorParamsV1: $crate::wayland::protocols::color_management::ParamsCreatorData
+        ] => $crate::wayland::protocols::color_management::ColorManagementState);
+        smithay::reexports::wayland_server::delegate_dispatch!($(@< $( $lt $( : $clt $(+ $dlt )* )? ),+ >)? $ty: [
+            smithay::reexports::wayland_protocols::wp::color_management::v1::server::wp_image_description_v1::WpImageDescriptionV1: $crate::wayland::protocols::color_management::ImageDescriptionData
+        ] => $crate::wayland::protocols::color_management::ColorManagementState);
+        smithay::reexports::wayland_server::delegate_dispatch!($(@< $( $lt $( : $clt $(+ $dlt )* )? ),+ >)? $ty: [
+            smithay::reexports::wayland_protocols::wp::color_management::v1::server::wp_image_description_info_v1::WpImageDescriptionInfoV1: ()
+        ] => $crate::wayland::protocols::color_management::ColorManagementState);
+    };
+}
+pub(crate) use delegate_color_management;
@@ -0,0 +1,247 @@
+// SPDX-License-Identifier: GPL-3.0-only
+
+use smithay::{
+    output::{Output, WeakOutput},
+    reexports::{
+        wayland_protocols_wlr::gamma_control::v1::server::{
+            zwlr_gamma_control_manager_v1::{self, ZwlrGammaControlManagerV1},
+            zwlr_gamma_control_v1::{self, ZwlrGammaControlV1},
+        },
+        wayland_server::{
+            Client, DataInit, Dispatch, DisplayHandle, GlobalDispatch, New, Resource,
+            backend::GlobalId,
+        },
+    },
+};
+use std::{fs::File, os::unix::fs::FileExt};
+use wayland_backend::server::ClientId;
+
+pub trait GammaControlHandler {
+    fn gamma_control_state(&mut self) -> &mut GammaControlState;
+    /// Number of entries per channel of the gamma ramps of `output`, if supported
+    fn gamma_size(&mut self, output: &Output) -> Option<u32>;
+    /// Applies the red, green and blue ramps (in sequence) to `output`,
+    /// or restores the default ramps if `ramp` is `None`.
+    ///
+    /// Returns `false` if the ramps couldn't be applied.
+    fn set_gamma(&mut self, output: &Output, ramp: Option<Vec<u16>>) -> bool;
+}
+
+#[derive(Debug)]
+pub struct GammaControlState {
+    global: GlobalId,
+    // controls that haven't failed, at most one per output
+    controls: Vec<ZwlrGammaControlV1>,
+}
+
+impl GammaControlState {
+    pub fn new<D, F>(dh: &DisplayHandle, client_filter: F) -> GammaControlState
+    where
+        D: GlobalDispatch<ZwlrGammaControlManagerV1, GammaControlManagerGlobalData> + 'static,
+        F: for<'a> Fn(&'a Client) -> bool + Clone + Send + Sync + 'static,
+    {
+        let global = dh.create_global::<D, ZwlrGammaControlManagerV1, _>(
+            1,
+            GammaControlManagerGlobalData {
+                filter: Box::new(client_filter.clone()),
+            },
+        );
+
+        GammaControlState {
+            global,
+            controls: Vec::new(),
+        }
+    }
+
+    pub fn global_id(&self) -> GlobalId {
+        self.global.clone()
+    }
+
+    /// Fails all gamma controls of outputs that were removed or don't support gamma ramps anymore.
+    pub fn refresh<D: GammaControlHandler>(state: &mut D) {
+        let controls = std::mem::take(&mut state.gamma_control_state().controls);
+        let controls = controls
+            .into_iter()
+            .filter(|control| {
+                let data = control.data::<GammaControlData>().unwrap();
+                let valid = data
+                    .output
+                    .upgrade()
+                    .and_then(|output| state.gamma_size(&output))
+                    .is_some_and(|size| size == data.size);
+                if !valid {
+                    control.failed();
+                }
+                valid
+            })
+            .collect();
+        state.gamma_control_state().controls = controls;
+    }
+}
+
+pub struct GammaControlManagerGlobalData {
+    filter: Box<dyn for<'a> Fn(&'a Client) -> bool + Send + Sync>,
+}
+
+pub struct GammaControlData {
+    output: WeakOutput,
+    size: u32,
+}
+
+impl<D> GlobalDispatch<ZwlrGammaControlManagerV1, GammaControlManagerGlobalData, D>
+    for GammaControlState
+where
+    D: GlobalDispatch<ZwlrGammaControlManagerV1, GammaControlManagerGlobalData>
+        + Dispatch<ZwlrGammaControlManagerV1, ()>
+        + 'static,
+{
+    fn bind(
+        _state: &mut D,
+        _dh: &DisplayHandle,
+        _client: &Client,
+        resource: New<ZwlrGammaControlManagerV1>,
+        _global_data: &GammaControlManagerGlobalData,
+        data_init: &mut DataInit<'_, D>,
+    ) {
+        data_init.init(resource, ());
+    }
+
+    fn can_view(client: Client, global_data: &GammaControlManagerGlobalData) -> bool {
+        (global_data.filter)(&client)
+    }
+}
+
+impl<D> Dispatch<ZwlrGammaControlManagerV1, (), D> for GammaControlState
+where
+    D: GlobalDispatch<ZwlrGammaControlManagerV1, GammaControlManagerGlobalData>
+        + Dispatch<ZwlrGammaControlManagerV1, ()>
+        + Dispatch<ZwlrGammaControlV1, GammaControlData>
+        + GammaControlHandler
+        + 'static,
+{
+    fn request(
+        state: &mut D,
+        _client: &Client,
+        _obj: &ZwlrGammaControlManagerV1,
+        request: zwlr_gamma_control_manager_v1::Request,
+        _data: &(),
+        _dh: &DisplayHandle,
+        data_init: &mut DataInit<'_, D>,
+    ) {
+        match request {
+            zwlr_gamma_control_manager_v1::Request::GetGammaControl { id, output } => {
+                let output = Output::from_resource(&output);
+                let in_use = output.as_ref().is_some_and(|output| {
+                    state.gamma_control_state().controls.iter().any(|control| {
+                        control.data::<GammaControlData>().unwrap().output == *output
+                    })
+                });
+                let size = output
+                    .as_ref()
+                    .filter(|_| !in_use)
+                    .and_then(|o| state.gamma_size(o));
+
+                let control = data_init.init(
+                    id,
+                    GammaControlData {
+                        output: output.as_ref().map(|o| o.downgrade()).unwrap_or_default(),
+                        size: size.unwrap_or(0),
+                    },
+                );
+                if let Some(size) = size {
+                    control.gamma_size(size);
+                    state.gamma_control_state().controls.push(control);
+                } else {
+                    control.failed();
+                }
+            }
+            zwlr_gamma_control_manager_v1::Request::Destroy => {}
+            _ => unreachable!(),
+        }
+    }
+}
+
+impl<D> Dispatch<ZwlrGammaControlV1, GammaControlData, D> for GammaControlState
+where
+    D: Dispatch<ZwlrGammaControlV1, GammaControlData> + GammaControlHandler + 'static,
+{
+    fn request(
+        state: &mut D,
+        _client: &Client,
+        obj: &ZwlrGammaControlV1,
+        request: zwlr_gamma_control_v1::Request,
+        data: &GammaControlData,
+        _dh: &DisplayHandle,
+        _data_init: &mut DataInit<'_, D>,
+    ) {
+        match request {
+            zwlr_gamma_control_v1::Request::SetGamma { fd } => {
+                if !state.gamma_control_state().controls.contains(obj) {
+                    return;
+                }
+                let Some(output) = data.output.upgrade() else {
+                    fail(state, obj);
+                    return;
+                };
+
+                let mut buf = vec![0u8; data.size as usize * 3 * 2];
+                if File::from(fd).read_exact_at(&mut buf, 0).is_err() {
+                    obj.post_error(
+                        zwlr_gamma_control_v1::Error::InvalidGamma,
+                        "Gamma ramps don't match the gamma size",
+                    );
+                    return;
+                }
+                let ramp = buf
+                    .chunks_exact(2)
+                    .map(|bytes| u16::from_ne_bytes([bytes[0], bytes[1]]))
+                    .collect();
+
+                if !state.set_gamma(&output, Some(ramp)) {
+                    state.set_gamma(&output, None);
+                    fail(state, obj);
+                }
+            }
+            zwlr_gamma_control_v1::Request::Destroy => {}
+            _ => unreachable!(),
+        }
+    }
+
+    fn destroyed(
+        state: &mut D,
+        _client: ClientId,
+        obj: &ZwlrGammaControlV1,
+        data: &GammaControlData,
+    ) {
+        let controls = &mut state.gamma_control_state().controls;
+        if let Some(pos) = controls.iter().position(|control| control == obj) {
+            controls.remove(pos);
+            if let Some(output) = data.output.upgrade() {
+                state.set_gamma(&output, None);
+            }
+        }
+    }
+}
+
+fn fail<D: GammaControlHandler>(state: &mut D, control: &ZwlrGammaControlV1) {
+    control.failed();
+    state
+        .gamma_control_state()
+        .controls
+        .retain(|other| other != control);
+}
+
+macro_rules! delegate_gamma_control {
+    ($(@<$( $lt:tt $( : $clt:tt $(+ $dlt:tt )* )? ),+>)? $ty: ty) => {
+        smithay::reexports::wayland_server::delegate_global_dispatch!($(@< $( $lt $( : $clt $(+ $dlt )* )? ),+ >)? $ty: [
+            smithay::reexports::wayland_protocols_wlr::gamma_control::v1::server::zwlr_gamma_control_manager_v1::ZwlrGammaControlManagerV1: $crate::wayland::protocols::gamma_control::GammaControlManagerGlobalData
+        ] => $crate::wayland::protocols::gamma_control::GammaControlState);
+        smithay::reexports::wayland_server::delegate_dispatch!($(@< $( $lt $( : $clt $(+ $dlt )* )? ),+ >)? $ty: [
+            smithay::reexports::wayland_protocols_wlr::gamma_control::v1::server::zwlr_gamma_control_manager_v1::ZwlrGammaControlManagerV1: ()
+        ] => $crate::wayland::protocols::gamma_control::GammaControlState);
+        smithay::reexports::wayland_server::delegate_dispatch!($(@< $( $lt $( : $clt $(+ $dlt )* )? ),+ >)? $ty: [
+            smithay::reexports::wayland_protocols_wlr::gamma_control::v1::server::zwlr_gamma_control_v1::ZwlrGammaControlV1: $crate::wayland::protocols::gamma_control::GammaControlData
+        ] => $crate::wayland::protocols::gamma_control::GammaControlState);
+    };
+}
+pub(crate) use delegate_gamma_control;
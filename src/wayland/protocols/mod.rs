@@ -1,8 +1,10 @@
 // SPDX-License-Identifier: GPL-3.0-only
 
 pub mod a11y;
+pub mod color_management;
 pub mod corner_radius;
 pub mod drm;
+pub mod gamma_control;
 pub mod image_capture_source;
 pub mod output_configuration;
 pub mod output_power;
@@ -297,6 +297,21 @@ pub fn toplevel_leave_workspace(toplevel: &impl Window, workspace: &WorkspaceHan
     }
 }
 
+/// Returns the `ext-foreign-toplevel-list` identifier of a toplevel, if it was registered
+pub fn toplevel_identifier(toplevel: &impl Window) -> Option<String> {
+    toplevel
+        .user_data()
+        .get::<ToplevelState>()
+        .and_then(|state| {
+            state
+                .lock()
+                .unwrap()
+                .foreign_handle
+                .as_ref()
+                .map(|handle| handle.identifier())
+        })
+}
+
 impl<D, W> ToplevelInfoState<D, W>
 where
     D: GlobalDispatch<ZcosmicToplevelInfoV1, ToplevelInfoGlobalData>
@@ -119,6 +119,13 @@ pub struct WorkspaceHandle {
     id: usize,
 }
 
+impl WorkspaceHandle {
+    /// Stable identifier of this workspace for the lifetime of the compositor
+    pub fn id(&self) -> usize {
+        self.id
+    }
+}
+
 pub trait WorkspaceHandler
 where
     Self: GlobalDispatch<ExtWorkspaceManagerV1, WorkspaceGlobalData>